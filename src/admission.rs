@@ -0,0 +1,282 @@
+//! What Pod Security admission and ValidatingAdmissionPolicies made of
+//! requests, from the annotations they leave on audit events, and which
+//! objects would be blocked by which.
+
+use crate::detail::Section;
+use crate::enrich::Enricher;
+use crate::kube::EventV1;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+const AUDIT_VIOLATIONS: &str = "pod-security.kubernetes.io/audit-violations";
+const POLICY_FAILURE: &str = "validation.policy.admission.k8s.io/validation_failure";
+
+/// A request that breaks a Pod Security Standard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PodSecurity {
+    /// The level and version broken, like `restricted:latest`.
+    pub policy: String,
+    /// The checks failed, like `runAsNonRoot != true`.
+    pub checks: Vec<String>,
+    /// Whether the request was denied for it, rather than only audited.
+    pub blocked: bool,
+}
+
+/// The Pod Security Standard a request breaks, from its audit annotation when
+/// the namespace audits a level, or its response when it enforces one.
+pub fn pod_security(event: &EventV1) -> Option<PodSecurity> {
+    let (text, blocked) = match event.annotations.get(AUDIT_VIOLATIONS) {
+        Some(violations) => (
+            violations.strip_prefix("would violate PodSecurity ")?,
+            false,
+        ),
+        None => {
+            let message = event.response_status.as_ref()?.message.as_deref()?;
+            let (_, rest) = message.split_once("violates PodSecurity ")?;
+            (rest, true)
+        }
+    };
+    let (policy, checks) = text.split_once(": ")?;
+    Some(PodSecurity {
+        policy: policy.trim_matches('"').to_string(),
+        checks: split_checks(checks),
+        blocked,
+    })
+}
+
+/// The names of the checks in a list like `runAsNonRoot != true (pod or
+/// container "app" must set ...), seccompProfile (...)`, splitting only on
+/// the commas outside the details in brackets and quotes.
+fn split_checks(text: &str) -> Vec<String> {
+    let mut checks = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' | '[' | '{' if !quoted => depth += 1,
+            ')' | ']' | '}' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                checks.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    checks.push(&text[start..]);
+    checks
+        .into_iter()
+        .map(|check| match check.split_once(" (") {
+            Some((name, _)) => name.trim().to_string(),
+            None => check.trim().to_string(),
+        })
+        .filter(|check| !check.is_empty())
+        .collect()
+}
+
+/// A ValidatingAdmissionPolicy that failed a request.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyFailure {
+    pub policy: String,
+    #[serde(default)]
+    pub binding: String,
+    #[serde(default)]
+    pub message: String,
+    /// What the binding does about failures: `Deny`, `Warn` or `Audit`.
+    #[serde(default)]
+    pub validation_actions: Vec<String>,
+}
+
+/// The ValidatingAdmissionPolicies that failed the request, if any.
+pub fn policy_failures(event: &EventV1) -> Vec<PolicyFailure> {
+    event
+        .annotations
+        .get(POLICY_FAILURE)
+        .and_then(|failures| serde_json::from_str(failures).ok())
+        .unwrap_or_default()
+}
+
+/// What a request was to, as `namespace/resource/name`, leaving out whatever
+/// it doesn't say.
+fn object(event: &EventV1) -> String {
+    let Some(object) = &event.object_ref else {
+        return event.path().to_string();
+    };
+    [&object.namespace, &object.resource, &object.name]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The Pod Security Standard broken, the checks failed and the policies
+/// failed by an event, as the `podsecurity`, `checks` and `policy` fields.
+pub struct Admission;
+
+impl Enricher for Admission {
+    fn keys(&self) -> Vec<String> {
+        ["podsecurity", "checks", "policy"].map(String::from).into()
+    }
+
+    fn enrich(&self, event: &mut EventV1) {
+        if let Some(security) = pod_security(event) {
+            let enriched = &mut event.enriched;
+            enriched.insert("podsecurity".to_string(), security.policy);
+            enriched.insert("checks".to_string(), security.checks.join(", "));
+        }
+        let failures = policy_failures(event);
+        if !failures.is_empty() {
+            let names = failures.into_iter().map(|failure| failure.policy);
+            let names = names.collect::<BTreeSet<_>>().into_iter();
+            let names = names.collect::<Vec<_>>().join(", ");
+            event.enriched.insert("policy".to_string(), names);
+        }
+    }
+}
+
+/// The objects each Pod Security Standard and policy would block among the
+/// events, with the checks or messages they fail and how many requests did,
+/// a section for each standard then each policy.
+pub fn sections<'a>(events: impl IntoIterator<Item = &'a EventV1>) -> Vec<Section> {
+    #[derive(Default)]
+    struct Failing {
+        reasons: BTreeSet<String>,
+        events: usize,
+        blocked: bool,
+    }
+
+    let mut standards = BTreeMap::<String, BTreeMap<String, Failing>>::new();
+    let mut policies = BTreeMap::<String, BTreeMap<String, Failing>>::new();
+    for event in events {
+        if let Some(security) = pod_security(event) {
+            let failing = standards.entry(security.policy).or_default();
+            let failing = failing.entry(object(event)).or_default();
+            failing.reasons.extend(security.checks);
+            failing.events += 1;
+            failing.blocked |= security.blocked;
+        }
+        for failure in policy_failures(event) {
+            let failing = policies.entry(failure.policy).or_default();
+            let failing = failing.entry(object(event)).or_default();
+            failing.reasons.insert(failure.message);
+            failing.events += 1;
+            failing.blocked |= failure.validation_actions.iter().any(|a| a == "Deny");
+        }
+    }
+
+    let section = |title: String, objects: BTreeMap<String, Failing>| {
+        let mut section = Section::new(title);
+        for (object, failing) in objects {
+            section.alert |= failing.blocked;
+            let reasons = failing.reasons.into_iter().collect::<Vec<_>>();
+            let blocked = if failing.blocked { ", blocked" } else { "" };
+            let events = match failing.events {
+                1 => "1 request".to_string(),
+                n => format!("{} requests", n),
+            };
+            section.row(
+                object,
+                format!("{} ({}{})", reasons.join(", "), events, blocked),
+            );
+        }
+        section
+    };
+    let standards = standards
+        .into_iter()
+        .map(|(policy, objects)| section(format!("Pod Security {}", policy), objects));
+    let policies = policies
+        .into_iter()
+        .map(|(policy, objects)| section(format!("Policy {}", policy), objects));
+    standards.chain(policies).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(name: &str, annotations: serde_json::Value, message: Option<&str>) -> EventV1 {
+        test_event()
+            .uri("/apis/apps/v1/namespaces/web/deployments")
+            .verb("create")
+            .object_ref(serde_json::json!({
+                "resource": "deployments",
+                "namespace": "web",
+                "name": name,
+            }))
+            .set(
+                "responseStatus",
+                serde_json::json!({
+                    "metadata": {},
+                    "code": if message.is_some() { 403 } else { 201 },
+                    "message": message,
+                }),
+            )
+            .annotations(annotations)
+            .build()
+    }
+
+    #[test]
+    fn admission() {
+        let audited = event(
+            "frontend",
+            serde_json::json!({
+                AUDIT_VIOLATIONS: "would violate PodSecurity \"restricted:latest\": allowPrivilegeEscalation != false (container \"app\" must set securityContext.allowPrivilegeEscalation=false), unrestricted capabilities (container \"app\" must set securityContext.capabilities.drop=[\"ALL\"]), runAsNonRoot != true (pod or container \"app\" must set securityContext.runAsNonRoot=true)",
+                POLICY_FAILURE: r#"[{"message":"replicas must be at most 5","policy":"max-replicas","binding":"max-replicas-web","expressionIndex":0,"validationActions":["Audit"]}]"#,
+            }),
+            None,
+        );
+        let security = pod_security(&audited).unwrap();
+        assert_eq!(security.policy, "restricted:latest");
+        assert_eq!(
+            security.checks,
+            [
+                "allowPrivilegeEscalation != false",
+                "unrestricted capabilities",
+                "runAsNonRoot != true"
+            ]
+        );
+        assert!(!security.blocked);
+        assert_eq!(policy_failures(&audited)[0].policy, "max-replicas");
+
+        let mut enriched = audited.clone();
+        Admission.enrich(&mut enriched);
+        assert_eq!(enriched.enriched["podsecurity"], "restricted:latest");
+        assert_eq!(enriched.enriched["policy"], "max-replicas");
+
+        let denied = event(
+            "debug",
+            serde_json::json!({}),
+            Some("pods \"debug\" is forbidden: violates PodSecurity \"baseline:v1.30\": host namespaces (hostNetwork=true, hostPID=true), privileged (container \"debug\" must not set securityContext.privileged=true)"),
+        );
+        let security = pod_security(&denied).unwrap();
+        assert_eq!(security.policy, "baseline:v1.30");
+        assert_eq!(security.checks, ["host namespaces", "privileged"]);
+        assert!(security.blocked);
+
+        let sections = sections([&audited, &audited, &denied]);
+        let titles = sections
+            .iter()
+            .map(|s| s.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            [
+                "Pod Security baseline:v1.30",
+                "Pod Security restricted:latest",
+                "Policy max-replicas"
+            ]
+        );
+        assert!(sections[0].alert);
+        assert_eq!(
+            sections[0].rows,
+            [(
+                "web/deployments/debug".to_string(),
+                "host namespaces, privileged (1 request, blocked)".to_string()
+            )]
+        );
+        assert!(sections[1].rows[0].1.ends_with("(2 requests)"));
+        assert!(!sections[2].alert);
+    }
+}
@@ -0,0 +1,473 @@
+//! What client binaries are calling the apiserver, from the user agents
+//! client-go and kubectl send, like
+//! `kubectl/v1.30.0 (linux/amd64) kubernetes/7c48c2b`, who runs them, and
+//! which are too far behind the apiserver.
+
+use crate::detail::Section;
+use crate::export::quote;
+use crate::kube::EventV1;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+/// How many users are listed for each client at most.
+const TOP: usize = 20;
+
+/// How many minor versions behind the apiserver a client can be before it's
+/// outdated, as kubectl is supported one minor version either side.
+pub const DEFAULT_SKEW: u32 = 1;
+
+/// A Kubernetes version, as far as the minor version, which is what skew is
+/// measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    /// Parse `v1.30.0`, `1.30` or `v1.29.5-eks-5e0fdde`.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut parts = s.trim().trim_start_matches('v').split(['.', '-', '+']);
+        let mut number = || parts.next().and_then(|part| part.parse::<u32>().ok());
+        match (number(), number()) {
+            (Some(major), Some(minor)) => Ok(Version { major, minor }),
+            _ => anyhow::bail!("{:?} isn't a version like v1.30", s),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}", self.major, self.minor)
+    }
+}
+
+/// A user agent taken apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Agent<'a> {
+    /// The binary, like `kubectl` or `kube-controller-manager`.
+    pub name: &'a str,
+    /// The version it was built as, like `v1.30.0`, which is `v0.0.0` for
+    /// client-go built without one.
+    pub version: Option<&'a str>,
+    /// The operating system and architecture, like `linux/amd64`.
+    pub platform: Option<&'a str>,
+    /// The Kubernetes commit it was built from.
+    pub commit: Option<&'a str>,
+}
+
+impl<'a> Agent<'a> {
+    /// Take apart a user agent in the form client-go gives them, getting
+    /// at least the name from any other.
+    pub fn parse(user_agent: &'a str) -> Option<Self> {
+        let user_agent = user_agent.trim();
+        let (product, rest) = user_agent
+            .split_once(char::is_whitespace)
+            .unwrap_or((user_agent, ""));
+        let (name, version) = match product.split_once('/') {
+            Some((name, version)) => (name, Some(version).filter(|v| !v.is_empty())),
+            None => (product, None),
+        };
+        if name.is_empty() {
+            return None;
+        }
+        let platform = rest
+            .split_once('(')
+            .and_then(|(_, rest)| rest.split_once(')'))
+            .map(|(platform, _)| platform.trim())
+            .filter(|platform| !platform.is_empty());
+        let commit = rest
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix("kubernetes/"))
+            .and_then(|commit| commit.split('/').next())
+            .filter(|commit| !commit.is_empty() && *commit != "$Format");
+        Some(Agent {
+            name,
+            version,
+            platform,
+            commit,
+        })
+    }
+
+    /// The event's user agent taken apart, if it sent one.
+    pub fn of(event: &'a EventV1) -> Option<Self> {
+        Self::parse(event.user_agent.as_deref()?)
+    }
+
+    /// The Kubernetes version it was built as, only for binaries built from
+    /// Kubernetes itself, which stamp the commit, as anything else's version
+    /// is its own.
+    pub fn kubernetes_version(&self) -> Option<Version> {
+        self.commit?;
+        self.version?
+            .parse()
+            .ok()
+            .filter(|version: &Version| version.major > 0)
+    }
+
+    /// The name and version, like `kubectl v1.30.0`.
+    pub fn binary(&self) -> String {
+        match self.version {
+            Some(version) => format!("{} {}", self.name, version),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    match count {
+        1 => format!("1 {}", one),
+        count => format!("{} {}", count, many),
+    }
+}
+
+/// The apiserver's version, going by the newest among its own requests'
+/// user agents and any `/version` responses logged.
+pub fn server_version(events: &[&EventV1]) -> Option<Version> {
+    events
+        .iter()
+        .filter_map(|event| {
+            let agent = Agent::of(event).filter(|agent| agent.name == "kube-apiserver");
+            if let Some(version) = agent.and_then(|agent| agent.kubernetes_version()) {
+                return Some(version);
+            }
+            if event.path() != "/version" {
+                return None;
+            }
+            let version = event.response_object.as_ref()?.get("gitVersion")?;
+            version.as_str()?.parse().ok()
+        })
+        .max()
+}
+
+/// A client binary more than the allowed skew behind the apiserver.
+#[derive(Debug, PartialEq)]
+pub struct Outdated {
+    /// The name and version, like `kubectl v1.26.3`.
+    pub binary: String,
+    /// How many minor versions behind the apiserver it is.
+    pub behind: u32,
+    pub requests: usize,
+    /// Who ran it, the most requests first.
+    pub users: Vec<String>,
+}
+
+/// The client binaries among the events more than `skew` minor versions
+/// behind `server`, the furthest behind first.
+pub fn outdated(events: &[&EventV1], server: Version, skew: u32) -> Vec<Outdated> {
+    let mut binaries = HashMap::<String, (u32, usize, HashMap<&str, usize>)>::new();
+    for event in events {
+        let Some(agent) = Agent::of(event) else {
+            continue;
+        };
+        let Some(version) = agent.kubernetes_version() else {
+            continue;
+        };
+        if version.major != server.major || version.minor.saturating_add(skew) >= server.minor {
+            continue;
+        }
+        let binary = binaries.entry(agent.binary()).or_insert((
+            server.minor - version.minor,
+            0,
+            HashMap::new(),
+        ));
+        binary.1 += 1;
+        *binary.2.entry(&event.user.username).or_default() += 1;
+    }
+
+    let mut outdated = binaries
+        .into_iter()
+        .map(|(binary, (behind, requests, users))| {
+            let mut users = users.into_iter().collect::<Vec<_>>();
+            users.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            Outdated {
+                binary,
+                behind,
+                requests,
+                users: users
+                    .into_iter()
+                    .map(|(user, _)| user.to_string())
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    outdated.sort_by(|a, b| {
+        (b.behind, b.requests)
+            .cmp(&(a.behind, a.requests))
+            .then(a.binary.cmp(&b.binary))
+    });
+    outdated
+}
+
+fn behind(outdated: &Outdated) -> String {
+    plural(
+        outdated.behind as usize,
+        "minor version behind",
+        "minor versions behind",
+    )
+}
+
+/// A section of the outdated clients, if any are.
+fn outdated_section(events: &[&EventV1], server: Version, skew: u32) -> Option<Section> {
+    let outdated = outdated(events, server, skew);
+    if outdated.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(format!("Outdated clients (apiserver {})", server));
+    section.alert = true;
+    for outdated in outdated {
+        let mut users = outdated.users.iter().take(3).cloned().collect::<Vec<_>>();
+        if outdated.users.len() > users.len() {
+            users.push(format!("{} more", outdated.users.len() - users.len()));
+        }
+        let text = format!("{}, run by {}", behind(&outdated), users.join(", "));
+        section.row(&outdated.binary, text);
+    }
+    Some(section)
+}
+
+/// The outdated clients as a table, then who runs the most of them, for
+/// fleet hygiene reports.
+pub fn text(outdated: &[Outdated], server: Version) -> String {
+    let mut text = String::new();
+    let _ = write_text(&mut text, outdated, server);
+    text
+}
+
+fn write_text(text: &mut String, outdated: &[Outdated], server: Version) -> fmt::Result {
+    if outdated.is_empty() {
+        return writeln!(text, "no clients are outdated against {}", server);
+    }
+    writeln!(text, "clients outdated against {}:", server)?;
+    let width = outdated.iter().map(|o| o.binary.len()).max().unwrap_or(0);
+    for outdated in outdated {
+        writeln!(
+            text,
+            "{:<width$}  {}, {}, run by {}",
+            outdated.binary,
+            behind(outdated),
+            plural(outdated.requests, "request", "requests"),
+            outdated.users.join(", "),
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+/// The outdated clients as CSV, a row for each user of each.
+pub fn csv(outdated: &[Outdated], server: Version) -> String {
+    let mut csv = "client,version,apiserver,behind,user\n".to_string();
+    for outdated in outdated {
+        let (name, version) = outdated
+            .binary
+            .split_once(' ')
+            .unwrap_or((&outdated.binary, ""));
+        for user in &outdated.users {
+            let cells = [
+                name,
+                version,
+                &server.to_string(),
+                &outdated.behind.to_string(),
+                user,
+            ]
+            .map(quote);
+            csv.push_str(&cells.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+/// Every client binary and version among the events, the busiest first,
+/// with how many users ran it and on what, then a section for each of who
+/// ran it and how much, after those more than `skew` minor versions behind
+/// the apiserver, if its version can be told from the events.
+pub fn sections(events: &[&EventV1], skew: u32) -> Vec<Section> {
+    #[derive(Default)]
+    struct Binary<'a> {
+        requests: usize,
+        platforms: BTreeSet<&'a str>,
+        users: HashMap<&'a str, usize>,
+    }
+
+    let mut binaries = HashMap::<String, Binary>::new();
+    for event in events {
+        let Some(agent) = Agent::of(event) else {
+            continue;
+        };
+        let binary = binaries.entry(agent.binary()).or_default();
+        binary.requests += 1;
+        binary.platforms.extend(agent.platform);
+        *binary.users.entry(&event.user.username).or_default() += 1;
+    }
+
+    let mut binaries = binaries.into_iter().collect::<Vec<_>>();
+    binaries.sort_by(|a, b| b.1.requests.cmp(&a.1.requests).then(a.0.cmp(&b.0)));
+    let mut clients = Section::new("Clients");
+    for (name, binary) in &binaries {
+        let mut text = format!(
+            "{} from {}",
+            plural(binary.requests, "request", "requests"),
+            plural(binary.users.len(), "user", "users")
+        );
+        if !binary.platforms.is_empty() {
+            let platforms = binary.platforms.iter().copied().collect::<Vec<_>>();
+            text.push_str(&format!(", on {}", platforms.join(", ")));
+        }
+        clients.row(name, text);
+    }
+
+    let outdated = server_version(events).and_then(|server| outdated_section(events, server, skew));
+    let mut sections = outdated.into_iter().chain([clients]).collect::<Vec<_>>();
+    for (name, binary) in binaries {
+        let mut users = binary.users.into_iter().collect::<Vec<_>>();
+        users.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let mut section = Section::new(name);
+        for (user, count) in users.into_iter().take(TOP) {
+            section.row(user, plural(count, "request", "requests"));
+        }
+        sections.push(section);
+    }
+    sections
+        .into_iter()
+        .filter(|section| !section.rows.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, agent: &str) -> EventV1 {
+        test_event().user(user).user_agent(agent).build()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            Agent::parse("kubectl/v1.30.0 (darwin/arm64) kubernetes/7c48c2b"),
+            Some(Agent {
+                name: "kubectl",
+                version: Some("v1.30.0"),
+                platform: Some("darwin/arm64"),
+                commit: Some("7c48c2b"),
+            })
+        );
+        let controller = "kube-controller-manager/v1.29.5 (linux/amd64) kubernetes/59755ff/system:serviceaccount:kube-system:replicaset-controller";
+        let controller = Agent::parse(controller).unwrap();
+        assert_eq!(controller.binary(), "kube-controller-manager v1.29.5");
+        assert_eq!(controller.commit, Some("59755ff"));
+        let unversioned = Agent::parse("manager/v0.0.0 (linux/amd64) kubernetes/$Format").unwrap();
+        assert_eq!(unversioned.commit, None);
+        let other = Agent::parse("Go-http-client/2.0").unwrap();
+        assert_eq!((other.name, other.platform), ("Go-http-client", None));
+        assert_eq!(Agent::parse(""), None);
+    }
+
+    #[test]
+    fn inventory() {
+        let kubectl = "kubectl/v1.30.0 (linux/amd64) kubernetes/7c48c2b";
+        let events = [
+            event("alice", kubectl),
+            event("alice", kubectl),
+            event("bob", "kubectl/v1.30.0 (darwin/arm64) kubernetes/7c48c2b"),
+            event("ci", "helm/v3.15.2 (linux/amd64)"),
+        ];
+        let events = events.iter().collect::<Vec<_>>();
+        let sections = sections(&events, DEFAULT_SKEW);
+        assert_eq!(
+            sections[0].rows,
+            [
+                (
+                    "kubectl v1.30.0".to_string(),
+                    "3 requests from 2 users, on darwin/arm64, linux/amd64".to_string()
+                ),
+                (
+                    "helm v3.15.2".to_string(),
+                    "1 request from 1 user, on linux/amd64".to_string()
+                ),
+            ]
+        );
+        assert_eq!(sections[1].title, "kubectl v1.30.0");
+        assert_eq!(
+            sections[1].rows,
+            [
+                ("alice".to_string(), "2 requests".to_string()),
+                ("bob".to_string(), "1 request".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn outdated_clients() {
+        assert_eq!(
+            "v1.29.5-eks-5e0fdde".parse::<Version>().unwrap(),
+            Version {
+                major: 1,
+                minor: 29
+            }
+        );
+        assert!("latest".parse::<Version>().is_err());
+
+        let events = [
+            event(
+                "system:apiserver",
+                "kube-apiserver/v1.30.2 (linux/amd64) kubernetes/3968350",
+            ),
+            event("alice", "kubectl/v1.26.3 (linux/amd64) kubernetes/9e64467"),
+            event("bob", "kubectl/v1.26.3 (darwin/arm64) kubernetes/9e64467"),
+            event("bob", "kubectl/v1.26.3 (darwin/arm64) kubernetes/9e64467"),
+            event("carol", "kubectl/v1.29.1 (linux/amd64) kubernetes/bc401b9"),
+            event("ci", "kubectl/v1.28.0 (linux/amd64) kubernetes/855e7c4"),
+            event("ci", "helm/v3.15.2 (linux/amd64)"),
+            event(
+                "operator",
+                "manager/v0.0.0 (linux/amd64) kubernetes/$Format",
+            ),
+        ];
+        let events = events.iter().collect::<Vec<_>>();
+        let server = server_version(&events).unwrap();
+        assert_eq!(server.to_string(), "v1.30");
+
+        let outdated = outdated(&events, server, DEFAULT_SKEW);
+        assert_eq!(
+            outdated,
+            [
+                Outdated {
+                    binary: "kubectl v1.26.3".to_string(),
+                    behind: 4,
+                    requests: 3,
+                    users: vec!["bob".to_string(), "alice".to_string()],
+                },
+                Outdated {
+                    binary: "kubectl v1.28.0".to_string(),
+                    behind: 2,
+                    requests: 1,
+                    users: vec!["ci".to_string()],
+                },
+            ]
+        );
+        assert_eq!(
+            text(&outdated, server),
+            "clients outdated against v1.30:\n\
+             kubectl v1.26.3  4 minor versions behind, 3 requests, run by bob, alice\n\
+             kubectl v1.28.0  2 minor versions behind, 1 request, run by ci\n"
+        );
+        assert_eq!(
+            csv(&outdated[1..], server),
+            "client,version,apiserver,behind,user\nkubectl,v1.28.0,v1.30,2,ci\n"
+        );
+        let sections = sections(&events, DEFAULT_SKEW);
+        assert_eq!(sections[0].title, "Outdated clients (apiserver v1.30)");
+        assert_eq!(
+            sections[0].rows[0].1,
+            "4 minor versions behind, run by bob, alice"
+        );
+        assert_eq!(super::sections(&events, 3)[0].rows.len(), 1);
+        assert!(super::outdated(&events, server, u32::MAX).is_empty());
+    }
+}
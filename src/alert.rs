@@ -0,0 +1,38 @@
+//! Rules from the config file for events to be alerted to as they arrive, by
+//! flagging their rows, flashing the status bar and perhaps ringing the bell.
+
+use crate::config::AlertRule;
+use crate::filter::Filter;
+use crate::kube::EventV1;
+use crate::script::Scripts;
+use anyhow::Context;
+use std::time::Duration;
+
+/// How long the status bar flashes for after an alert.
+pub const FLASH: Duration = Duration::from_secs(10);
+
+/// An alert rule with its filter parsed.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub name: String,
+    filter: Filter,
+    pub bell: bool,
+}
+
+impl Alert {
+    pub fn from_rule(rule: &AlertRule) -> anyhow::Result<Self> {
+        let filter = rule
+            .filter
+            .parse()
+            .with_context(|| format!("parsing the filter of alert {:?}", rule.name))?;
+        Ok(Self {
+            name: rule.name.clone(),
+            filter,
+            bell: rule.bell,
+        })
+    }
+
+    pub fn matches(&self, event: &EventV1, scripts: &Scripts) -> bool {
+        self.filter.eval(event, scripts)
+    }
+}
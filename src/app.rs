@@ -0,0 +1,3695 @@
+//! The TUI itself, tying the event store, view state and renderer to a
+//! terminal and its input.
+
+use crate::admission::Admission;
+use crate::alert::{self, Alert};
+use crate::break_glass::BreakGlass;
+use crate::config::Config;
+use crate::database::Database;
+use crate::dedupe::{self, Dedupe};
+use crate::diff;
+use crate::enrich::{self, ApiVersion, Enricher, ServiceAccount};
+use crate::export;
+use crate::filter::{Field, Filter, Op, Predicate};
+use crate::group::{self, Grouping};
+use crate::kube::EventV1;
+use crate::messages::MessageLog;
+use crate::metrics::Metrics;
+use crate::path::JsonPath;
+use crate::picker::{Checklist, ChecklistKind, Picker, PickerKind};
+use crate::pipe::Pipe;
+use crate::reproduce;
+use crate::runs::{self, RunRow, Runs};
+use crate::sample::Sampler;
+use crate::script::Scripts;
+use crate::secret;
+use crate::session::{self, EventId, Resuming, Session};
+use crate::sink::Forwarder;
+use crate::source::{Ingested, LoadProgress};
+use crate::stats::IngestStats;
+use crate::store::EventStore;
+use crate::theme::Theme;
+use crate::timestamp::Timestamps;
+use crate::tree::{self, Sidebar};
+use crate::ui;
+use crate::view::{Column, Overlay, Pane, ViewState};
+use anyhow::Context;
+use crossterm::{
+    self, cursor,
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    ExecutableCommand,
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
+use std::io::{stdout, Stdout};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The verbs the apiserver uses for resource requests, offered in the verb
+/// checklist even before any events with them have been seen.
+const VERBS: [&str; 8] = [
+    "get",
+    "list",
+    "watch",
+    "create",
+    "update",
+    "patch",
+    "delete",
+    "deletecollection",
+];
+
+/// The verbs that don't change anything, hidden together with `r`.
+const READ_ONLY_VERBS: [&str; 3] = ["get", "list", "watch"];
+
+/// What can be done with the marked events, offered with `a`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Export,
+    Copy,
+    Diff,
+    Hide,
+    Unhide,
+    ClearMarks,
+}
+
+impl Action {
+    const ALL: [Action; 6] = [
+        Action::Export,
+        Action::Copy,
+        Action::Diff,
+        Action::Hide,
+        Action::Unhide,
+        Action::ClearMarks,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Export => "export",
+            Action::Copy => "copy",
+            Action::Diff => "diff",
+            Action::Hide => "hide",
+            Action::Unhide => "unhide all",
+            Action::ClearMarks => "clear marks",
+        }
+    }
+}
+
+/// How far a view over the whole screen is scrolled after a key, or `None`
+/// if it closes it with `Esc`, `Enter` or `q`.
+fn scroll_overlay(scroll: u16, code: KeyCode) -> Option<u16> {
+    match code {
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => None,
+        KeyCode::Up => Some(scroll.saturating_sub(1)),
+        KeyCode::Down => Some(scroll + 1),
+        KeyCode::PageUp => Some(scroll.saturating_sub(10)),
+        KeyCode::PageDown => Some(scroll + 10),
+        _ => Some(scroll),
+    }
+}
+
+/// Everything that can be done with a key, offered by name in the command
+/// palette, where picking one presses its key.
+const COMMANDS: [(&str, KeyCode); 66] = [
+    ("edit the filter", KeyCode::Char('/')),
+    ("undo the last filter change", KeyCode::Char('z')),
+    ("save the filter as a search", KeyCode::Char('S')),
+    ("apply a saved search", KeyCode::Char('b')),
+    ("pick a namespace to filter to", KeyCode::Char('n')),
+    ("pick a user to filter to", KeyCode::Char('u')),
+    ("pick a resource to filter to", KeyCode::Char('k')),
+    ("pick an API group to filter to", KeyCode::Char('g')),
+    ("choose which verbs to show", KeyCode::Char('v')),
+    ("hide or show read only verbs", KeyCode::Char('r')),
+    ("cycle status filters", KeyCode::Char('s')),
+    ("cycle clusters", KeyCode::Char('c')),
+    ("filter to the selected user", KeyCode::Char('U')),
+    ("filter to the selected namespace", KeyCode::Char('N')),
+    ("filter to the selected object", KeyCode::Char('O')),
+    ("hide the selected user agent", KeyCode::Char('A')),
+    ("show every field of the event", KeyCode::Enter),
+    ("show what admission would block", KeyCode::Char('V')),
+    ("show certificates and tokens given", KeyCode::Char('I')),
+    ("show traffic by kind of client", KeyCode::Char('h')),
+    ("show who is being throttled", KeyCode::Char('l')),
+    ("show what a delete took down", KeyCode::Char('Z')),
+    ("show controllers fighting", KeyCode::Char('!')),
+    ("show the largest objects", KeyCode::Char('#')),
+    ("show lists without selectors", KeyCode::Char('*')),
+    ("show client binaries", KeyCode::Char('i')),
+    ("show who is over the rate limits", KeyCode::Char('%')),
+    ("find in the bodies", KeyCode::Char('f')),
+    ("find the next line", KeyCode::Char('F')),
+    ("jump to the top of the bodies", KeyCode::Home),
+    ("jump to the bottom of the bodies", KeyCode::End),
+    ("wrap or cut off long lines", KeyCode::Char('W')),
+    ("unfold managed fields and status", KeyCode::Char('M')),
+    ("reveal, decode or hide Secret data", KeyCode::Char('R')),
+    ("base64 decode the values at a path", KeyCode::Char('D')),
+    ("pin the event to compare to", KeyCode::Char('P')),
+    ("bookmark the event", KeyCode::Char('x')),
+    ("jump to the previous bookmark", KeyCode::Char('[')),
+    ("jump to the next bookmark", KeyCode::Char(']')),
+    ("show or hide the bookmark list", KeyCode::Char('B')),
+    ("show drift from the last kubectl apply", KeyCode::Char('~')),
+    ("export the bookmarks", KeyCode::Char('E')),
+    ("export the matching events as parquet", KeyCode::Char('e')),
+    ("pipe the events to a command", KeyCode::Char('|')),
+    ("copy a kubectl command for the event", KeyCode::Char('K')),
+    ("copy a curl command for the request", KeyCode::Char('y')),
+    ("copy a kubectl --raw command for it", KeyCode::Char('Y')),
+    ("mark the event", KeyCode::Char(' ')),
+    ("act on the marked events", KeyCode::Char('a')),
+    (
+        "browse namespaces, resources and objects",
+        KeyCode::Char('T'),
+    ),
+    (
+        "group by user, namespace, resource, verb or status",
+        KeyCode::Char('G'),
+    ),
+    ("collapse runs of repeated events", KeyCode::Char('C')),
+    ("hide exact duplicates of requests", KeyCode::Char('d')),
+    ("show or hide the timeline", KeyCode::Char('H')),
+    (
+        "show or hide outcomes under the timeline",
+        KeyCode::Char('='),
+    ),
+    ("jump back along the timeline", KeyCode::Char('{')),
+    ("jump on along the timeline", KeyCode::Char('}')),
+    ("jump to the busiest time", KeyCode::Char('J')),
+    ("jump to the next failures", KeyCode::Char('j')),
+    ("choose which columns to show", KeyCode::Char('o')),
+    ("add a column for a path", KeyCode::Char('p')),
+    ("cancel loading files", KeyCode::Char('X')),
+    ("open a new tab", KeyCode::Char('t')),
+    (
+        "watch the selected object's changes live",
+        KeyCode::Char('L'),
+    ),
+    ("close the tab", KeyCode::Char('w')),
+    ("show or hide the message log", KeyCode::Char('m')),
+];
+
+/// What a key is called in the command palette.
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+/// As many tabs as there are number keys to switch to them with.
+const MAX_TABS: usize = 9;
+
+/// How many characters of a tab's filter are shown in its title.
+const TAB_TITLE_LENGTH: usize = 20;
+
+/// How many lines of what a command events were piped to wrote are logged.
+const PIPE_OUTPUT_LINES: usize = 20;
+
+/// The status filters cycled through with `s`: failures, server errors and
+/// permission denied.
+const STATUS_PRESETS: [&str; 3] = ["status>=400", "status>=500", "status=403"];
+
+/// Why the TUI is to be left, as asked for by a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Quit,
+    /// Only until the process is continued, as with ctrl-z.
+    Suspend,
+}
+
+/// The TUI, drawing to the terminal on stdout by default, or to any other
+/// ratatui backend such as `TestBackend`.
+pub struct App<B: Backend = CrosstermBackend<Stdout>> {
+    terminal: Terminal<B>,
+    store: EventStore,
+    /// The view of the tab being shown.
+    view: ViewState,
+    /// The views of the other tabs, in order, with an empty placeholder at the
+    /// position of the one being shown.
+    tabs: Vec<ViewState>,
+    tab: usize,
+    stats: IngestStats,
+    messages: MessageLog,
+    /// Why the last frame couldn't be drawn, so a failure that persists is
+    /// only logged once.
+    draw_error: Option<String>,
+    config: Config,
+    /// Where to save the config, if anywhere.
+    config_path: Option<PathBuf>,
+    theme: Theme,
+    timestamps: Timestamps,
+    /// How far apart exact duplicates can be and still be hidden.
+    dedupe_window: chrono::Duration,
+    alerts: Vec<Alert>,
+    /// The scripts from the config file, for `script[name]`.
+    scripts: Scripts,
+    /// The name of the last alert raised, and when, for flashing the status
+    /// bar.
+    alerting: Option<(String, Instant)>,
+    /// Whether an alert wants the terminal bell rung.
+    bell: bool,
+    /// What decides which events to keep when they arrive too fast, if
+    /// anything.
+    sampler: Option<Sampler>,
+    /// Whether to keep events for non-resource URLs like `/healthz`, which
+    /// are dropped as they arrive otherwise.
+    non_resource: bool,
+    /// The files still being loaded.
+    loads: Vec<Arc<LoadProgress>>,
+    /// The SQLite file events are being saved to, if any.
+    database: Option<Database>,
+    /// What's left to restore of a session being resumed, while waiting for
+    /// the events it refers to.
+    resuming: Option<Resuming>,
+    /// The commands events have been piped to that are still running.
+    pipes: Vec<Pipe>,
+    /// The command events were last piped to, to offer again.
+    pipe_command: Option<String>,
+    /// The counters served to Prometheus, if they're being served.
+    metrics: Option<Metrics>,
+    /// Where else events are being sent on to.
+    sinks: Vec<Forwarder>,
+    /// Which writes to flag as made by hand where they shouldn't be.
+    break_glass: Option<BreakGlass>,
+    /// What works out more fields for events as they're taken in.
+    enrichers: Vec<Box<dyn Enricher>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::with_terminal(
+            Terminal::new(CrosstermBackend::new(stdout()))
+                .expect("failed to get stdout for terminal output"),
+        )
+    }
+
+    pub fn setup(&mut self) -> anyhow::Result<()> {
+        let result = stdout()
+            .execute(EnterAlternateScreen)
+            .and_then(|stdout| stdout.execute(EnableMouseCapture))
+            .context("failed to enter alternate screen")
+            .and_then(|_| enable_raw_mode().context("failed to enter raw mode"))
+            .and_then(|_| self.terminal.clear().context("failed to clear terminal"));
+        if result.is_err() {
+            restore_terminal();
+        }
+        result
+    }
+
+    pub fn tear_down(&mut self) {
+        restore_terminal();
+    }
+
+    /// Stop the process as ctrl-z would in a shell, with the terminal put back
+    /// for the shell while stopped, and take it over again once continued.
+    pub fn suspend(&mut self) -> anyhow::Result<()> {
+        self.tear_down();
+        // SIGSTOP, as SIGTSTP is handled to get here
+        signal_hook::low_level::raise(signal_hook::consts::SIGSTOP).context("failed to stop")?;
+        self.setup()
+    }
+}
+
+/// Put the terminal back how it was before [`App::setup`], as far as that
+/// can be done, so that it's usable after quitting or panicking.
+pub fn restore_terminal() {
+    // there's nothing more to be done if these fail
+    let _ = disable_raw_mode();
+    let _ = stdout().execute(DisableMouseCapture);
+    let _ = stdout().execute(LeaveAlternateScreen);
+    let _ = stdout().execute(cursor::Show);
+}
+
+/// Restore the terminal before a panic's message is printed, so that the
+/// message can be read and the shell isn't left in raw mode.
+pub fn install_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // panics in the sources' tasks and threads only end those, and the
+        // TUI carries on drawing on the main thread
+        if std::thread::current().name() == Some("main") {
+            restore_terminal();
+        }
+        hook(info);
+    }));
+}
+
+impl<B: Backend> App<B> {
+    /// Draw to an existing terminal, which is left for the caller to set up.
+    pub fn with_terminal(terminal: Terminal<B>) -> Self {
+        Self {
+            terminal,
+            store: EventStore::new(),
+            view: ViewState::new(),
+            tabs: vec![ViewState::new()],
+            tab: 0,
+            stats: IngestStats::new(),
+            messages: MessageLog::new(),
+            draw_error: None,
+            config: Config::default(),
+            config_path: None,
+            theme: Theme::default(),
+            timestamps: Timestamps::default(),
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            alerts: Vec::new(),
+            scripts: Scripts::new(),
+            alerting: None,
+            bell: false,
+            sampler: None,
+            non_resource: false,
+            loads: Vec::new(),
+            database: None,
+            resuming: None,
+            pipes: Vec::new(),
+            pipe_command: None,
+            metrics: None,
+            sinks: Vec::new(),
+            break_glass: None,
+            enrichers: vec![
+                Box::new(ApiVersion),
+                Box::new(ServiceAccount),
+                Box::new(Admission),
+            ],
+        }
+    }
+
+    /// The terminal being drawn to, for inspecting what was drawn.
+    pub fn terminal(&self) -> &Terminal<B> {
+        &self.terminal
+    }
+
+    pub fn store(&self) -> &EventStore {
+        &self.store
+    }
+
+    pub fn view(&self) -> &ViewState {
+        &self.view
+    }
+
+    pub fn messages(&self) -> &MessageLog {
+        &self.messages
+    }
+
+    pub fn handle_ingested(&mut self, ingested: Ingested) {
+        match ingested {
+            Ingested::Event(event) => self.handle_kube_event(*event),
+            Ingested::Skipped(reason) => {
+                self.stats.skipped += 1;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_skipped();
+                }
+                self.messages.warning(format!("skipped: {}", reason));
+            }
+            Ingested::Notice(text) => self.messages.info(text),
+            Ingested::Failed(err) => self.messages.error(err),
+        }
+    }
+
+    pub fn handle_kube_event(&mut self, mut event: EventV1) {
+        if !self.non_resource && !event.is_resource_request() {
+            return;
+        }
+        self.stats.record_event();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_event(&event);
+        }
+        if let Some(sampler) = &mut self.sampler {
+            if !sampler.keep(&event, self.stats.rate()) {
+                self.stats.sampled += 1;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_sampled();
+                }
+                return;
+            }
+        }
+        enrich::enrich(&self.enrichers, &mut event);
+        let index = self.store.insert(event);
+        if let Some(database) = &mut self.database {
+            if let Err(err) = database.insert(&self.store[index]) {
+                self.messages
+                    .error(format!("stopped saving events: {:#}", err));
+                self.database = None;
+            }
+        }
+        for sink in &self.sinks {
+            sink.forward(&self.store[index], &self.scripts);
+        }
+        self.check_alerts(index);
+        self.view.inserted(&self.store, &self.scripts, index);
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            if i != self.tab {
+                tab.inserted(&self.store, &self.scripts, index);
+            }
+        }
+        if self.resuming.is_some() {
+            self.resume_inserted(index);
+        }
+    }
+
+    /// Flag the event at `index` if it breaks glass or matches any of the
+    /// alert rules, telling of the first it matches.
+    fn check_alerts(&mut self, index: usize) {
+        let event = &self.store[index];
+        if let Some(glass) = self.break_glass.as_ref().filter(|g| g.matches(event)) {
+            self.messages.warning(format!(
+                "break glass: {} {} by {}",
+                event.verb, event.request_uri, event.user.username
+            ));
+            self.alerting = Some(("break glass".to_string(), Instant::now()));
+            self.bell |= glass.bell();
+            self.store.flag_alerted(index);
+            return;
+        }
+        let Some(alert) = self
+            .alerts
+            .iter()
+            .find(|alert| alert.matches(event, &self.scripts))
+        else {
+            return;
+        };
+        self.messages.warning(format!(
+            "alert {}: {} {} by {}",
+            alert.name, event.verb, event.request_uri, event.user.username
+        ));
+        self.alerting = Some((alert.name.clone(), Instant::now()));
+        self.bell |= alert.bell;
+        self.store.flag_alerted(index);
+    }
+
+    /// Whether an alert has asked for the terminal bell since last asked,
+    /// left to the caller to ring, so that it is rung once however many
+    /// events alert at a time.
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// Show only the events matching `filter`, or every event if `None`.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.view.set_filter(&self.store, &self.scripts, filter);
+    }
+
+    /// Narrow the current filter to events where `field` is `value`,
+    /// replacing any existing condition on the field.
+    pub fn refine_filter(&mut self, field: Field, value: impl Into<String>) {
+        let filter = self
+            .view
+            .filter()
+            .cloned()
+            .and_then(|f| f.without_field(&field));
+        self.set_filter(Some(Filter::refine(filter, Filter::field_eq(field, value))));
+    }
+
+    /// Show only events whose `field` isn't one of `excluded`, replacing any
+    /// existing condition on the field.
+    pub fn exclude_values<'a>(
+        &mut self,
+        field: Field,
+        excluded: impl IntoIterator<Item = &'a str>,
+    ) {
+        let mut filter = self
+            .view
+            .filter()
+            .cloned()
+            .and_then(|f| f.without_field(&field));
+        for value in excluded {
+            let term = Filter::Predicate(Predicate::Compare {
+                field: field.clone(),
+                op: Op::Ne,
+                value: value.to_string(),
+            });
+            filter = Some(Filter::refine(filter, term));
+        }
+        self.set_filter(filter);
+    }
+
+    /// Narrow the filter to the selected event's values of `fields`,
+    /// replacing any existing conditions on them.
+    fn filter_to_selected(&mut self, fields: &[Field]) {
+        let Some(event) = self.view.selected_event(&self.store) else {
+            return;
+        };
+
+        let mut filter = self.view.filter().cloned();
+        for field in fields {
+            filter = filter.and_then(|f| f.without_field(field));
+            if let Some(value) = field.values(event, &self.scripts).first() {
+                let term = Filter::field_eq(field.clone(), value.as_ref());
+                filter = Some(Filter::refine(filter, term));
+            }
+        }
+        self.set_filter(filter);
+    }
+
+    /// Narrow the filter to the namespace, resource and object of a node in
+    /// the sidebar, as far down as it goes.
+    fn filter_to_node(&mut self, path: &[String]) {
+        let fields = [Field::Namespace, Field::Resource, Field::Name];
+        let mut filter = self.view.filter().cloned();
+        for field in &fields {
+            filter = filter.and_then(|f| f.without_field(field));
+        }
+        for (field, value) in fields.into_iter().zip(path) {
+            if value != tree::CLUSTER_SCOPED {
+                filter = Some(Filter::refine(filter, Filter::field_eq(field, value)));
+            }
+        }
+        self.set_filter(filter);
+    }
+
+    fn toggle_sidebar(&mut self) {
+        self.view.sidebar = match self.view.sidebar {
+            Some(_) => None,
+            None => Some(Sidebar::new(&self.store)),
+        };
+    }
+
+    fn toggle_dedupe(&mut self) {
+        let dedupe = match self.view.dedupe() {
+            Some(_) => None,
+            None => Some(Dedupe::new(self.dedupe_window)),
+        };
+        self.view.set_dedupe(&self.store, &self.scripts, dedupe);
+    }
+
+    /// Cycle grouping the table by each of the fields it can be grouped by,
+    /// then back to not grouping.
+    fn cycle_grouping(&mut self) {
+        let next = match &self.view.grouping {
+            None => 0,
+            Some(grouping) => group::GROUP_FIELDS
+                .iter()
+                .position(|field| *field == grouping.field)
+                .map_or(group::GROUP_FIELDS.len(), |i| i + 1),
+        };
+        self.view.grouping = group::GROUP_FIELDS.get(next).cloned().map(Grouping::new);
+    }
+
+    /// Hide events sharing the selected event's values of `field`, as well as
+    /// any already hidden.
+    fn exclude_selected(&mut self, field: Field) {
+        let Some(event) = self.view.selected_event(&self.store) else {
+            return;
+        };
+
+        let mut excluded = self
+            .view
+            .filter()
+            .map(|filter| filter.excluded(&field))
+            .unwrap_or_default()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        for value in field.values(event, &self.scripts) {
+            if !excluded.iter().any(|e| *e == value) {
+                excluded.push(value.into_owned());
+            }
+        }
+        self.exclude_values(field, excluded.iter().map(String::as_str));
+    }
+
+    /// Open a new tab showing every event, and switch to it.
+    fn new_tab(&mut self) {
+        let mut view = ViewState::new();
+        view.refilter(&self.store, &self.scripts);
+        self.open_tab(view);
+    }
+
+    /// Open a tab with `view` in it, with the same columns as this one, and
+    /// switch to it.
+    fn open_tab(&mut self, mut view: ViewState) {
+        if self.tabs.len() == MAX_TABS {
+            self.messages
+                .warning(format!("can't have more than {} tabs", MAX_TABS));
+            return;
+        }
+        view.columns = self.view.columns.clone();
+        self.tabs.push(view);
+        self.switch_tab(self.tabs.len() - 1);
+    }
+
+    /// Show a summary of the visible events over the whole screen.
+    fn open_overlay(&mut self, overlay: Overlay) {
+        self.view.overlay = Some((overlay, 0));
+        self.view.overlay_sections = None;
+    }
+
+    /// Show what the selected delete took down with it, if it's a delete.
+    fn trace_cascade(&mut self) {
+        match self.view.selected_event(&self.store) {
+            Some(event) if event.verb == "delete" => self.open_overlay(Overlay::Cascade),
+            _ => self
+                .messages
+                .warning("select a delete to see what it took down"),
+        }
+    }
+
+    /// Open a tab following the changes to the selected event's object as
+    /// they arrive, each shown against the version before it.
+    fn watch_object(&mut self) {
+        let object = self
+            .view
+            .selected_event(&self.store)
+            .and_then(|event| event.object_ref.as_ref());
+        let Some((resource, name)) =
+            object.and_then(|object| Some((object.resource.as_ref()?, object.name.as_ref()?)))
+        else {
+            self.messages
+                .warning("the selected event isn't about a named object");
+            return;
+        };
+
+        let mut filter = Filter::refine(
+            Some(Filter::field_eq(Field::Resource, resource)),
+            Filter::field_eq(Field::Name, name),
+        );
+        if let Some(namespace) = object.and_then(|object| object.namespace.as_ref()) {
+            filter = Filter::refine(Some(filter), Filter::field_eq(Field::Namespace, namespace));
+        }
+        for verb in READ_ONLY_VERBS {
+            let term = Filter::Predicate(Predicate::Compare {
+                field: Field::Verb,
+                op: Op::Ne,
+                value: verb.to_string(),
+            });
+            filter = Filter::refine(Some(filter), term);
+        }
+
+        let mut view = ViewState::new();
+        view.watching = Some(format!("{}/{}", resource, name));
+        view.pane = Pane::Changes;
+        view.set_filter(&self.store, &self.scripts, Some(filter));
+        view.select(view.visible().len().saturating_sub(1));
+        self.open_tab(view);
+    }
+
+    /// Show the tab at `tab`, if there is one.
+    fn switch_tab(&mut self, tab: usize) {
+        if tab >= self.tabs.len() || tab == self.tab {
+            return;
+        }
+        std::mem::swap(&mut self.view, &mut self.tabs[self.tab]);
+        self.tab = tab;
+        std::mem::swap(&mut self.view, &mut self.tabs[self.tab]);
+    }
+
+    /// Close the tab being shown, unless it is the only one.
+    fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            return;
+        }
+        self.tabs.remove(self.tab);
+        self.tab = self.tab.min(self.tabs.len() - 1);
+        self.view = std::mem::take(&mut self.tabs[self.tab]);
+    }
+
+    /// A short title for each tab: its filter, or what it shows without one.
+    fn tab_titles(&self) -> Vec<String> {
+        let title = |view: &ViewState| {
+            let title = match (&view.watching, view.filter()) {
+                (Some(object), _) => format!("watch {}", object),
+                (None, Some(filter)) => filter.to_string(),
+                (None, None) => "all events".to_string(),
+            };
+            match title.char_indices().nth(TAB_TITLE_LENGTH) {
+                Some((end, _)) => format!("{}…", &title[..end]),
+                None => title,
+            }
+        };
+        (0..self.tabs.len())
+            .map(|i| match i == self.tab {
+                true => title(&self.view),
+                false => title(&self.tabs[i]),
+            })
+            .collect()
+    }
+
+    /// Mark the selected event and the one above or below it, moving on to it.
+    fn extend_marks(&mut self, forwards: bool) {
+        if let Some(index) = self.view.selected_index() {
+            self.view.mark(index);
+        }
+        match forwards {
+            true => self.view.next(),
+            false => self.view.previous(),
+        }
+        if let Some(index) = self.view.selected_index() {
+            self.view.mark(index);
+        }
+    }
+
+    /// The indices into the store of the events an action applies to: the
+    /// marked ones, or the selected one if none are marked.
+    fn action_targets(&self) -> Vec<usize> {
+        let marked = self.view.marked().collect::<Vec<_>>();
+        match marked.is_empty() {
+            true => self.view.selected_index().into_iter().collect(),
+            false => marked,
+        }
+    }
+
+    fn open_action_picker(&mut self) {
+        let targets = self.action_targets().len();
+        let items: Vec<(String, usize)> = Action::ALL
+            .iter()
+            .map(|action| {
+                let count = match action {
+                    Action::Unhide => self.view.hidden_count(),
+                    _ => targets,
+                };
+                (action.name().to_string(), count)
+            })
+            .collect();
+        self.view.picker = Some(Picker::new("Actions", PickerKind::Action, items));
+    }
+
+    fn run_action(&mut self, action: Action) {
+        let targets = self.action_targets();
+        match action {
+            Action::Export => {
+                let path = export::file_name("marked", "jsonl");
+                let events = targets.iter().map(|i| &self.store[*i]);
+                match export::to_file(&path, events) {
+                    Ok(count) => self.messages.info(format!(
+                        "exported {} events to {}",
+                        count,
+                        path.display()
+                    )),
+                    Err(err) => self.messages.error(format!("{:#}", err)),
+                }
+            }
+            Action::Copy => match export::to_clipboard(targets.iter().map(|i| &self.store[*i])) {
+                Ok(count) => self.messages.info(format!("copied {} events", count)),
+                Err(err) => self.messages.error(format!("{:#}", err)),
+            },
+            Action::Diff => self.diff_events(&targets),
+            Action::Hide => {
+                self.view
+                    .hide(&self.store, &self.scripts, targets.iter().copied());
+                self.view.clear_marks();
+            }
+            Action::Unhide => {
+                let count = self.view.unhide(&self.store, &self.scripts);
+                self.messages
+                    .info(format!("showing {} hidden events", count));
+            }
+            Action::ClearMarks => self.view.clear_marks(),
+        }
+    }
+
+    /// Show the differences between two events in the diff pane.
+    fn diff_events(&mut self, targets: &[usize]) {
+        let [old, new] = targets else {
+            self.messages.warning("mark exactly two events to diff");
+            return;
+        };
+        let (old, new) = (&self.store[*old], &self.store[*new]);
+        let (Ok(old_value), Ok(new_value)) = (serde_json::to_value(old), serde_json::to_value(new))
+        else {
+            self.messages.error("couldn't serialise the events to diff");
+            return;
+        };
+
+        let title = format!(
+            "{} {} -> {} {}",
+            self.timestamps.format(old.request_received_timestamp),
+            old.verb,
+            self.timestamps.format(new.request_received_timestamp),
+            new.verb
+        );
+        let mut changes = diff::diff(&old_value, &new_value);
+        if secret::is_secret(old) || secret::is_secret(new) {
+            // say what changed without saying what to
+            for change in &mut changes {
+                if secret::is_secret_path(change.path()) {
+                    change.hide_values();
+                }
+            }
+        }
+        self.view.diff = (title, changes);
+        self.view.pane = Pane::Diff;
+        self.view.scroll_position = 0;
+    }
+
+    fn toggle_bookmark(&mut self) {
+        if let Some(index) = self.view.selected_index() {
+            self.store.toggle_bookmark(index);
+        }
+    }
+
+    /// Select the next bookmarked event that passes the filters, or the
+    /// previous one.
+    fn jump_to_bookmark(&mut self, forwards: bool) {
+        let Some(selected) = self.view.selected() else {
+            return;
+        };
+        let visible = self.view.visible();
+        let bookmarked = |position: &usize| self.store.is_bookmarked(visible[*position]);
+        let found = match forwards {
+            true => (selected + 1..visible.len()).find(bookmarked),
+            false => (0..selected).rev().find(bookmarked),
+        };
+        match found {
+            Some(position) => self.view.select(position),
+            None => self.messages.info("no more bookmarks"),
+        }
+    }
+
+    /// Write the bookmarked events to a new file in the current directory.
+    fn export_bookmarks(&mut self) {
+        let path = export::file_name("bookmarks", "jsonl");
+        let events = self.store.bookmarks().map(|i| &self.store[i]);
+        match export::to_file(&path, events) {
+            Ok(count) => {
+                self.messages
+                    .info(format!("exported {} events to {}", count, path.display()))
+            }
+            Err(err) => self.messages.error(format!("{:#}", err)),
+        }
+    }
+
+    /// Write the events passing the filters to a new Parquet file in the
+    /// current directory.
+    fn export_parquet(&mut self) {
+        let path = export::file_name("events", "parquet");
+        let events = self.view.visible().iter().map(|i| &self.store[*i]);
+        match export::to_parquet(&path, events) {
+            Ok(count) => {
+                self.messages
+                    .info(format!("exported {} events to {}", count, path.display()))
+            }
+            Err(err) => self.messages.error(format!("{:#}", err)),
+        }
+    }
+
+    /// Copy a command making the selected event's request again, made by
+    /// one of the [`reproduce`] functions, showing the start of it.
+    fn copy_command(&mut self, command: fn(&EventV1) -> Result<String, String>) {
+        let Some(event) = self.view.selected_event(&self.store) else {
+            return;
+        };
+        let command = match command(event) {
+            Ok(command) => command,
+            Err(why) => return self.messages.warning(why),
+        };
+        match export::text_to_clipboard(&command) {
+            Ok(()) => {
+                let mut lines = command.lines();
+                let first = lines.next().unwrap_or_default();
+                let more = if lines.next().is_some() { " …" } else { "" };
+                self.messages.info(format!("copied {}{}", first, more));
+            }
+            Err(err) => self.messages.error(format!("{:#}", err)),
+        }
+    }
+
+    /// Start piping the marked events, or else every event passing the
+    /// filters, to `command`, which carries on in the background.
+    fn pipe_events(&mut self, command: String) {
+        let marked = self.view.marked().collect::<Vec<_>>();
+        let targets = match marked.is_empty() {
+            true => self.view.visible(),
+            false => &marked,
+        };
+        let events = targets.iter().map(|i| &self.store[*i]);
+        match Pipe::spawn(&command, events) {
+            Ok(pipe) => {
+                self.messages
+                    .info(format!("piping {} events to {}", pipe.count, pipe.command));
+                self.pipes.push(pipe);
+            }
+            Err(err) => self.messages.error(format!("{:#}", err)),
+        }
+        self.pipe_command = Some(command);
+    }
+
+    /// Tell of what the commands that have finished wrote, and how they
+    /// exited.
+    fn finish_pipes(&mut self) {
+        let (finished, running) = std::mem::take(&mut self.pipes)
+            .into_iter()
+            .partition::<Vec<_>, _>(Pipe::is_finished);
+        self.pipes = running;
+        for pipe in finished {
+            let command = pipe.command.clone();
+            let output = match pipe.output() {
+                Ok(output) => output,
+                Err(err) => {
+                    self.messages.error(format!("{:#}", err));
+                    continue;
+                }
+            };
+            let lines = String::from_utf8_lossy(&output.stdout);
+            let lines = lines.lines().collect::<Vec<_>>();
+            for line in lines.iter().take(PIPE_OUTPUT_LINES) {
+                self.messages.info(format!("{}: {}", command, line));
+            }
+            if lines.len() > PIPE_OUTPUT_LINES {
+                self.messages.info(format!(
+                    "{}: … and {} more lines",
+                    command,
+                    lines.len() - PIPE_OUTPUT_LINES
+                ));
+            }
+            for line in String::from_utf8_lossy(&output.stderr).lines() {
+                self.messages.warning(format!("{}: {}", command, line));
+            }
+            match output.status.success() {
+                true => self.messages.info(format!("{} finished", command)),
+                false => self
+                    .messages
+                    .error(format!("{} failed: {}", command, output.status)),
+            }
+        }
+    }
+
+    /// Go back to the filter before the last change.
+    fn undo_filter(&mut self) {
+        if !self.view.undo_filter(&self.store, &self.scripts) {
+            self.messages.info("nothing to undo");
+        }
+    }
+
+    /// Hide the read-only verbs, or show everything again if any are hidden.
+    fn toggle_read_only(&mut self) {
+        let hidden = self
+            .view
+            .filter()
+            .is_some_and(|filter| !filter.excluded(&Field::Verb).is_empty());
+        match hidden {
+            true => self.exclude_values(Field::Verb, []),
+            false => self.exclude_values(Field::Verb, READ_ONLY_VERBS),
+        }
+    }
+
+    /// Move on to the next status preset, or back to no status filter after
+    /// the last.
+    fn cycle_status_preset(&mut self) {
+        let filter = self.view.filter().cloned();
+        let current = filter.as_ref().and_then(|filter| {
+            filter.terms().into_iter().find_map(|term| {
+                let term = term.to_string();
+                STATUS_PRESETS.iter().position(|preset| *preset == term)
+            })
+        });
+        let next = match current {
+            Some(i) => STATUS_PRESETS.get(i + 1),
+            None => STATUS_PRESETS.first(),
+        };
+
+        let filter = filter.and_then(|filter| filter.without_field(&Field::Status));
+        let filter = match next {
+            Some(preset) => Some(Filter::refine(
+                filter,
+                preset.parse().expect("presets are valid filters"),
+            )),
+            None => filter,
+        };
+        self.set_filter(filter);
+    }
+
+    fn open_verb_checklist(&mut self) {
+        let excluded = self
+            .view
+            .filter()
+            .map(|filter| filter.excluded(&Field::Verb))
+            .unwrap_or_default();
+        let mut verbs = VERBS.map(String::from).to_vec();
+        for (verb, _) in self.store.value_counts(&Field::Verb, &self.scripts) {
+            if !verbs.contains(&verb) {
+                verbs.push(verb);
+            }
+        }
+        let items = verbs
+            .into_iter()
+            .map(|verb| {
+                let checked = !excluded.contains(&verb.as_str());
+                (verb, checked)
+            })
+            .collect();
+        self.view.checklist = Some(Checklist::new(
+            "Verbs",
+            ChecklistKind::Values(Field::Verb),
+            items,
+        ));
+    }
+
+    /// Show `column` in the table, after any already shown.
+    pub fn add_column(&mut self, column: Column) {
+        if !self.view.columns.contains(&column) {
+            self.view.columns.push(column);
+        }
+    }
+
+    /// The columns to choose from: the built in ones, the enriched fields,
+    /// the config file's scripts, then any paths added.
+    fn column_choices(&self) -> Vec<Column> {
+        let enriched = self
+            .enrichers
+            .iter()
+            .flat_map(|enricher| enricher.keys())
+            .map(Column::Enriched);
+        let scripts = self.scripts.names().into_iter().map(Column::Script);
+        let paths = self
+            .view
+            .columns
+            .iter()
+            .filter(|column| matches!(column, Column::Path(_)));
+        Column::ALL
+            .into_iter()
+            .chain(enriched)
+            .chain(scripts)
+            .chain(paths.cloned())
+            .collect()
+    }
+
+    fn open_column_checklist(&mut self) {
+        let items = self
+            .column_choices()
+            .into_iter()
+            .map(|column| {
+                let checked = self.view.columns.contains(&column);
+                (column.title(), checked)
+            })
+            .collect();
+        self.view.checklist = Some(Checklist::new("Columns", ChecklistKind::Columns, items));
+    }
+
+    fn open_picker(&mut self, title: &str, field: Field) {
+        let items = self.store.value_counts(&field, &self.scripts);
+        self.view.picker = Some(Picker::new(title, PickerKind::Value(field), items));
+    }
+
+    /// Use `config`, saving any changes to it to `path`.
+    pub fn set_config(&mut self, config: Config, path: Option<PathBuf>) {
+        self.config = config;
+        self.config_path = path;
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn set_timestamps(&mut self, timestamps: Timestamps) {
+        self.timestamps = timestamps;
+    }
+
+    pub fn set_sampler(&mut self, sampler: Option<Sampler>) {
+        self.sampler = sampler;
+    }
+
+    pub fn set_non_resource(&mut self, keep: bool) {
+        self.non_resource = keep;
+    }
+
+    /// Show how far through loading a file a source is in the status bar,
+    /// until it's done.
+    pub fn track_load(&mut self, progress: Arc<LoadProgress>) {
+        self.loads.push(progress);
+    }
+
+    /// Count every event taken in from now on towards `metrics`.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Work out more fields for every event taken in from now on with
+    /// `enricher`, after those already added.
+    pub fn add_enricher(&mut self, enricher: Box<dyn Enricher>) {
+        self.enrichers.push(enricher);
+    }
+
+    /// Send every event taken in from now on to `sink` too.
+    pub fn add_sink(&mut self, sink: Forwarder) {
+        self.sinks.push(sink);
+    }
+
+    /// Tell of sinks failing to send events on, or coming right again.
+    fn check_sinks(&mut self) {
+        for sink in &self.sinks {
+            for report in sink.reports() {
+                match report {
+                    Ok(()) => self
+                        .messages
+                        .info(format!("{}: sending events on again", sink.name)),
+                    Err(err) => self
+                        .messages
+                        .error(format!("{}: dropping events: {}", sink.name, err)),
+                }
+            }
+        }
+        let (stopped, running) = std::mem::take(&mut self.sinks)
+            .into_iter()
+            .partition::<Vec<_>, _>(Forwarder::is_finished);
+        self.sinks = running;
+        for sink in stopped {
+            self.messages
+                .error(format!("{}: stopped sending events on", sink.name));
+        }
+    }
+
+    /// Send on the events still waiting to go to the sinks, waiting for them
+    /// to be done.
+    pub fn finish_sinks(&mut self) {
+        for sink in self.sinks.drain(..) {
+            sink.finish();
+        }
+    }
+
+    /// Save every event taken in from now on to a SQLite file.
+    pub fn set_database(&mut self, database: Database) {
+        self.database = Some(database);
+    }
+
+    /// Write any events still waiting to be saved to the SQLite file.
+    pub fn save_events(&mut self) {
+        if let Some(database) = &mut self.database {
+            if let Err(err) = database.flush() {
+                self.messages
+                    .error(format!("stopped saving events: {:#}", err));
+                self.database = None;
+            }
+        }
+    }
+
+    /// What is being looked at, for picking up again another time.
+    pub fn session(&self) -> Session {
+        let tab = |view: &ViewState| session::Tab {
+            filter: view.filter().map(ToString::to_string),
+            source_filter: view.source_filter().map(str::to_string),
+            columns: view.columns.iter().map(Column::title).collect(),
+            selected: view.selected_event(&self.store).map(EventId::of),
+            watching: view.watching.clone(),
+            timeline_hidden: view.timeline_hidden,
+        };
+        let tabs = self.tabs.iter().enumerate();
+        Session {
+            tabs: tabs
+                .map(|(i, view)| tab(if i == self.tab { &self.view } else { view }))
+                .collect(),
+            tab: self.tab,
+            // including those of a session being resumed not read yet
+            bookmarks: self
+                .store
+                .bookmarks()
+                .map(|index| EventId::of(&self.store[index]))
+                .chain(
+                    self.resuming
+                        .iter()
+                        .flat_map(|r| r.bookmarks.iter().cloned()),
+                )
+                .collect(),
+        }
+    }
+
+    /// Pick up a session where it was left, with its tabs, and its bookmarks
+    /// and selections once the events they're of are read.
+    pub fn resume(&mut self, session: Session) {
+        let mut tabs = Vec::new();
+        for saved in session.tabs.iter().take(MAX_TABS) {
+            let mut view = ViewState::new();
+            match saved.filter.as_deref().map(str::parse).transpose() {
+                Ok(filter) => view.set_filter(&self.store, &self.scripts, filter),
+                Err(err) => self
+                    .messages
+                    .warning(format!("couldn't resume a tab's filter: {:#}", err)),
+            }
+            view.set_source_filter(&self.store, &self.scripts, saved.source_filter.clone());
+            for column in &saved.columns {
+                match session::column(column) {
+                    Ok(column) => view.columns.push(column),
+                    Err(err) => self
+                        .messages
+                        .warning(format!("couldn't resume column {}: {:#}", column, err)),
+                }
+            }
+            if saved.watching.is_some() {
+                view.watching = saved.watching.clone();
+                view.pane = Pane::Changes;
+            }
+            view.timeline_hidden = saved.timeline_hidden;
+            tabs.push(view);
+        }
+        if tabs.is_empty() {
+            return;
+        }
+
+        self.tabs = tabs;
+        self.tab = session.tab.min(self.tabs.len() - 1);
+        self.view = std::mem::take(&mut self.tabs[self.tab]);
+        self.resuming = Some(Resuming {
+            bookmarks: session.bookmarks.into_iter().collect(),
+            selections: session.tabs.into_iter().map(|tab| tab.selected).collect(),
+        });
+        self.messages.info("resumed the last session");
+    }
+
+    /// Bookmark or select the event inserted at `index` if the session being
+    /// resumed had it bookmarked or selected.
+    fn resume_inserted(&mut self, index: usize) {
+        let Some(resuming) = &mut self.resuming else {
+            return;
+        };
+        let id = EventId::of(&self.store[index]);
+        if resuming.bookmarks.remove(&id) {
+            self.store.toggle_bookmark(index);
+        }
+        for (i, selection) in resuming.selections.iter_mut().enumerate() {
+            if selection.as_ref() != Some(&id) {
+                continue;
+            }
+            let view = match i == self.tab {
+                true => &mut self.view,
+                false => &mut self.tabs[i],
+            };
+            if let Ok(position) = view.visible().binary_search(&index) {
+                view.select(position);
+                *selection = None;
+            }
+        }
+        if resuming.is_done() {
+            self.resuming = None;
+        }
+    }
+
+    /// Stop loading every file still being loaded.
+    fn cancel_loads(&mut self) {
+        self.loads.retain(|load| !load.is_finished());
+        if self.loads.is_empty() {
+            self.messages.info("no files are loading");
+            return;
+        }
+        for load in &self.loads {
+            load.cancel();
+        }
+        self.messages
+            .info(format!("cancelling loading {} files", self.loads.len()));
+    }
+
+    pub fn set_alerts(&mut self, alerts: Vec<Alert>) {
+        self.alerts = alerts;
+    }
+
+    /// Run `scripts` for `script[name]` in filters and columns.
+    pub fn set_scripts(&mut self, scripts: Scripts) {
+        self.scripts = scripts;
+    }
+
+    /// Flag the writes `break_glass` matches from now on.
+    pub fn set_break_glass(&mut self, break_glass: BreakGlass) {
+        self.break_glass = Some(break_glass);
+    }
+
+    pub fn set_dedupe_window(&mut self, window: chrono::Duration) {
+        self.dedupe_window = window;
+    }
+
+    /// Save the current filter under `name` in the config file.
+    fn save_search(&mut self, name: &str) {
+        let Some(filter) = self.view.filter() else {
+            self.messages.warning("no filter to save");
+            return;
+        };
+        self.config.save_search(name, filter.to_string());
+        let Some(path) = &self.config_path else {
+            self.messages
+                .warning(format!("saved search {:?} for this session only", name));
+            return;
+        };
+        match self.config.save(path) {
+            Ok(()) => self.messages.info(format!("saved search {:?}", name)),
+            Err(err) => self.messages.error(format!("{:#}", err)),
+        }
+    }
+
+    /// Replace the filter with the saved search called `name`.
+    fn apply_search(&mut self, name: &str) {
+        let Some(search) = self.config.search(name) else {
+            return;
+        };
+        match search.filter.parse() {
+            Ok(filter) => self.set_filter(Some(filter)),
+            Err(err) => self
+                .messages
+                .error(format!("saved search {:?}: {:#}", name, err)),
+        }
+    }
+
+    /// Pick a saved search, showing how many events each matches.
+    fn open_search_picker(&mut self) {
+        let items: Vec<(String, usize)> = self
+            .config
+            .searches
+            .iter()
+            .map(|search| {
+                let count = match search.filter.parse::<Filter>() {
+                    Ok(filter) => self
+                        .store
+                        .iter()
+                        .filter(|e| filter.eval(e, &self.scripts))
+                        .count(),
+                    Err(_) => 0,
+                };
+                (search.name.clone(), count)
+            })
+            .collect();
+        self.view.picker = Some(Picker::new("Saved searches", PickerKind::Search, items));
+    }
+
+    fn open_command_palette(&mut self) {
+        let items = COMMANDS
+            .iter()
+            .map(|(name, key)| (name.to_string(), key_name(*key)));
+        self.view.picker = Some(Picker::new("Commands", PickerKind::Command, items));
+    }
+
+    pub fn handle_terminal_event(&mut self, event: std::io::Result<Event>) -> Option<Exit> {
+        match event {
+            Ok(Event::Resize(..)) => {
+                // redraw everything from scratch rather than diffing against
+                // a buffer of the old size
+                if let Err(err) = self.terminal.autoresize() {
+                    self.messages.error(format!("terminal: {}", err));
+                }
+                None
+            }
+            Ok(Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                ..
+            })) => {
+                self.view.click_timeline(&self.store, column, row);
+                None
+            }
+            Ok(event) => {
+                if let Event::Key(KeyEvent {
+                    code, modifiers, ..
+                }) = event
+                {
+                    // once moving about, the session's selections would only
+                    // get in the way
+                    if let Some(resuming) = &mut self.resuming {
+                        resuming.selections.clear();
+                    }
+                    // raw mode keeps ctrl-c and ctrl-z from sending signals, so
+                    // they're keys
+                    if modifiers.contains(KeyModifiers::CONTROL) {
+                        match code {
+                            KeyCode::Char('c') => return Some(Exit::Quit),
+                            KeyCode::Char('z') => return Some(Exit::Suspend),
+                            _ => {}
+                        }
+                    }
+                    if self.view.filter_input.is_some() {
+                        self.handle_filter_key(code);
+                        return None;
+                    }
+                    if self.view.column_input.is_some() {
+                        self.handle_column_key(code);
+                        return None;
+                    }
+                    if self.view.decode_input.is_some() {
+                        self.handle_decode_key(code);
+                        return None;
+                    }
+                    if self.view.find_input.is_some() {
+                        self.handle_find_key(code);
+                        return None;
+                    }
+                    if self.view.query_input.is_some() {
+                        self.handle_query_key(code);
+                        return None;
+                    }
+                    if self.view.search_name_input.is_some() {
+                        self.handle_search_name_key(code);
+                        return None;
+                    }
+                    if self.view.pipe_input.is_some() {
+                        self.handle_pipe_key(code);
+                        return None;
+                    }
+                    if self.view.picker.is_some() {
+                        self.handle_picker_key(code);
+                        return None;
+                    }
+                    if self.view.checklist.is_some() {
+                        self.handle_checklist_key(code);
+                        return None;
+                    }
+                    if let Some(detail) = &mut self.view.detail {
+                        match code {
+                            KeyCode::Char('.') => self.view.query_input = Some(".".to_string()),
+                            KeyCode::Char('+') => self.pin_query(),
+                            code => match scroll_overlay(detail.scroll, code) {
+                                Some(scroll) => detail.scroll = scroll,
+                                None => self.view.detail = None,
+                            },
+                        }
+                        return None;
+                    }
+                    if let Some((overlay, scroll)) = self.view.overlay {
+                        self.view.overlay =
+                            scroll_overlay(scroll, code).map(|scroll| (overlay, scroll));
+                        return None;
+                    }
+                    if self.view.sidebar.as_ref().is_some_and(|s| s.focused)
+                        && self.handle_sidebar_key(code)
+                    {
+                        return None;
+                    }
+                    if self.handle_group_key(code) || self.handle_run_key(code) {
+                        return None;
+                    }
+
+                    match code {
+                        KeyCode::Esc | KeyCode::Char('q') => return Some(Exit::Quit),
+                        KeyCode::Up if modifiers.contains(KeyModifiers::SHIFT) => {
+                            self.extend_marks(false)
+                        }
+                        KeyCode::Down if modifiers.contains(KeyModifiers::SHIFT) => {
+                            self.extend_marks(true)
+                        }
+                        KeyCode::Up => self.view.previous(),
+                        KeyCode::Down => self.view.next(),
+                        KeyCode::Char(' ') => {
+                            if let Some(index) = self.view.selected_index() {
+                                self.view.toggle_mark(index);
+                            }
+                            self.view.next();
+                        }
+                        KeyCode::Enter if self.view.selected().is_some() => {
+                            self.view.open_detail(&self.store)
+                        }
+                        KeyCode::Char('V') => self.open_overlay(Overlay::Admission),
+                        KeyCode::Char('I') => self.open_overlay(Overlay::Credentials),
+                        KeyCode::Char('h') => self.open_overlay(Overlay::Clients),
+                        KeyCode::Char('l') => self.open_overlay(Overlay::Throttling),
+                        KeyCode::Char('Z') => self.trace_cascade(),
+                        KeyCode::Char('!') => self.open_overlay(Overlay::Fights),
+                        KeyCode::Char('#') => self.open_overlay(Overlay::Sizes),
+                        KeyCode::Char('*') => self.open_overlay(Overlay::Selectors),
+                        KeyCode::Char('i') => self.open_overlay(Overlay::Agents),
+                        KeyCode::Char('%') => self.open_overlay(Overlay::Rates),
+                        KeyCode::Char('a') => self.open_action_picker(),
+                        KeyCode::Char('P') => self.view.toggle_pin(),
+                        KeyCode::Char('t') => self.new_tab(),
+                        KeyCode::Char('X') => self.cancel_loads(),
+                        KeyCode::Char('L') => self.watch_object(),
+                        KeyCode::Char('w') => self.close_tab(),
+                        KeyCode::Char(c @ '1'..='9') => self.switch_tab(c as usize - '1' as usize),
+                        KeyCode::PageUp if self.view.pane == Pane::Messages => {
+                            self.view.message_scroll = (self.view.message_scroll + 3)
+                                .min(self.messages.messages().len().saturating_sub(1))
+                        }
+                        KeyCode::PageDown if self.view.pane == Pane::Messages => {
+                            self.view.message_scroll = self.view.message_scroll.saturating_sub(3)
+                        }
+                        KeyCode::PageUp => self.view.scroll_up(),
+                        KeyCode::PageDown => self.view.scroll_down(),
+                        KeyCode::Left => self.view.scroll_left(),
+                        KeyCode::Right => self.view.scroll_right(),
+                        KeyCode::Char('W') => self.view.toggle_wrap(),
+                        KeyCode::Char('M') => self.view.unfolded = !self.view.unfolded,
+                        KeyCode::Char('R') => self.view.cycle_reveal(),
+                        KeyCode::Char('D') => {
+                            self.view.decode_input = Some(".requestObject.".to_string())
+                        }
+                        KeyCode::Char('f') => {
+                            self.view.find_input = Some(self.view.find.clone().unwrap_or_default())
+                        }
+                        KeyCode::Char('F') => self.view.find_next(),
+                        KeyCode::Home => self.view.scroll_to_top(),
+                        KeyCode::End => self.view.scroll_to_bottom(),
+                        KeyCode::Char('n') => self.open_picker("Namespaces", Field::Namespace),
+                        KeyCode::Char('u') => self.open_picker("Users", Field::User),
+                        KeyCode::Char('k') => self.open_picker("Resources", Field::Resource),
+                        KeyCode::Char('g') => self.open_picker("API groups", Field::ApiGroup),
+                        KeyCode::Char('v') => self.open_verb_checklist(),
+                        KeyCode::Char('o') => self.open_column_checklist(),
+                        KeyCode::Char('p') if modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.open_command_palette()
+                        }
+                        KeyCode::Char(':') => self.open_command_palette(),
+                        KeyCode::Char('p') => self.view.column_input = Some(".".to_string()),
+                        KeyCode::Char('r') => self.toggle_read_only(),
+                        KeyCode::Char('s') => self.cycle_status_preset(),
+                        KeyCode::Char('U') => self.filter_to_selected(&[Field::User]),
+                        KeyCode::Char('N') => self.filter_to_selected(&[Field::Namespace]),
+                        KeyCode::Char('O') => self.filter_to_selected(&[
+                            Field::Resource,
+                            Field::Namespace,
+                            Field::Name,
+                        ]),
+                        KeyCode::Char('A') => self.exclude_selected(Field::UserAgent),
+                        KeyCode::Char('m') => {
+                            self.view.toggle_pane(Pane::Messages);
+                            self.view.message_scroll = 0;
+                        }
+                        KeyCode::Char('x') => self.toggle_bookmark(),
+                        KeyCode::Char(']') => self.jump_to_bookmark(true),
+                        KeyCode::Char('[') => self.jump_to_bookmark(false),
+                        KeyCode::Char('B') => self.view.toggle_pane(Pane::Bookmarks),
+                        KeyCode::Char('~') => {
+                            self.view.toggle_pane(Pane::Drift);
+                            self.view.scroll_position = 0;
+                        }
+                        KeyCode::Char('T') => self.toggle_sidebar(),
+                        KeyCode::Char('G') => self.cycle_grouping(),
+                        KeyCode::Char('d') => self.toggle_dedupe(),
+                        KeyCode::Char('C') => {
+                            self.view.runs = match self.view.runs {
+                                Some(_) => None,
+                                None => Some(Runs::new()),
+                            }
+                        }
+                        KeyCode::Char('H') => {
+                            self.view.timeline_hidden = !self.view.timeline_hidden
+                        }
+                        KeyCode::Char('=') => {
+                            self.view.timeline_outcomes = !self.view.timeline_outcomes
+                        }
+                        KeyCode::Char('{') => self.view.jump_slice(&self.store, false),
+                        KeyCode::Char('}') => self.view.jump_slice(&self.store, true),
+                        KeyCode::Char('J') => self.view.jump_busiest(&self.store),
+                        KeyCode::Char('j') => self.view.jump_failing(&self.store),
+                        KeyCode::Tab => {
+                            if let Some(sidebar) = &mut self.view.sidebar {
+                                sidebar.focused = true;
+                            }
+                        }
+                        KeyCode::Char('E') => self.export_bookmarks(),
+                        KeyCode::Char('e') => self.export_parquet(),
+                        KeyCode::Char('K') => self.copy_command(reproduce::kubectl),
+                        KeyCode::Char('y') => self.copy_command(reproduce::curl),
+                        KeyCode::Char('Y') => self.copy_command(reproduce::kubectl_raw),
+                        KeyCode::Char('|') => {
+                            let command = self.pipe_command.as_ref();
+                            let command = command.or(self.config.pipe_command.as_ref());
+                            self.view.pipe_input = Some(command.cloned().unwrap_or_default());
+                        }
+                        KeyCode::Char('c') => {
+                            self.view.cycle_source_filter(&self.store, &self.scripts)
+                        }
+                        KeyCode::Char('z') => self.undo_filter(),
+                        KeyCode::Char('S') => self.view.search_name_input = Some(String::new()),
+                        KeyCode::Char('b') => self.open_search_picker(),
+                        KeyCode::Char('/') => {
+                            self.view.filter_history.reset();
+                            self.view.filter_input = Some(
+                                self.view
+                                    .filter()
+                                    .map(Filter::to_string)
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        _ => {}
+                    };
+                }
+
+                None
+            }
+            Err(err) => {
+                self.messages.error(format!("terminal: {}", err));
+                None
+            }
+        }
+    }
+
+    fn handle_filter_key(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.view.filter_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.view.filter_input = None;
+                self.view.prompt_error = None;
+            }
+            KeyCode::Up => {
+                if let Some(entry) = self.view.filter_history.older(input) {
+                    *input = entry.to_string();
+                }
+            }
+            KeyCode::Down => {
+                if let Some(entry) = self.view.filter_history.newer() {
+                    *input = entry.to_string();
+                }
+            }
+            KeyCode::Enter if input.trim().is_empty() => {
+                self.view.filter_input = None;
+                self.view.prompt_error = None;
+                self.set_filter(None);
+            }
+            KeyCode::Enter => match input.parse() {
+                Ok(filter) => {
+                    self.view.filter_history.push(input.trim().to_string());
+                    self.view.filter_input = None;
+                    self.view.prompt_error = None;
+                    self.set_filter(Some(filter));
+                }
+                Err(err) => self.view.prompt_error = Some(err.to_string()),
+            },
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_column_key(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.view.column_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.view.column_input = None;
+                self.view.prompt_error = None;
+            }
+            KeyCode::Enter => match input.trim().parse() {
+                Ok(path) => {
+                    self.view.column_input = None;
+                    self.view.prompt_error = None;
+                    self.add_column(Column::Path(path));
+                }
+                Err(err) => self.view.prompt_error = Some(err.to_string()),
+            },
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_decode_key(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.view.decode_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.view.decode_input = None;
+                self.view.prompt_error = None;
+            }
+            KeyCode::Enter => match input.trim().parse::<JsonPath>() {
+                Ok(path) if path.is_in_body() => {
+                    self.view.decode_input = None;
+                    self.view.prompt_error = None;
+                    self.view.toggle_decoded(path);
+                }
+                Ok(_) => {
+                    self.view.prompt_error =
+                        Some("only values in .requestObject or .responseObject".to_string())
+                }
+                Err(err) => self.view.prompt_error = Some(err.to_string()),
+            },
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    /// Show the path queried in the detail view as a column of the table.
+    fn pin_query(&mut self) {
+        let Some(path) = self.view.query.clone() else {
+            self.messages
+                .info("query a path with . to pin it as a column");
+            return;
+        };
+        self.messages.info(format!("pinned {} as a column", path));
+        self.add_column(Column::Path(path));
+    }
+
+    fn handle_query_key(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.view.query_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.view.query_input = None;
+                self.view.prompt_error = None;
+            }
+            // an empty path stops querying
+            KeyCode::Enter if input.trim().is_empty() => {
+                self.view.query_input = None;
+                self.view.prompt_error = None;
+                self.view.query = None;
+            }
+            KeyCode::Enter => match input.trim().parse() {
+                Ok(path) => {
+                    self.view.query_input = None;
+                    self.view.prompt_error = None;
+                    self.view.query = Some(path);
+                    if let Some(detail) = &mut self.view.detail {
+                        detail.scroll = 0;
+                    }
+                }
+                Err(err) => self.view.prompt_error = Some(err.to_string()),
+            },
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_find_key(&mut self, code: KeyCode) {
+        let Some(text) = &mut self.view.find_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => self.view.find_input = None,
+            KeyCode::Enter => {
+                let text = std::mem::take(text);
+                self.view.find_input = None;
+                self.view.set_find(text);
+            }
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) => text.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_search_name_key(&mut self, code: KeyCode) {
+        let Some(name) = &mut self.view.search_name_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => self.view.search_name_input = None,
+            KeyCode::Enter if name.trim().is_empty() => {}
+            KeyCode::Enter => {
+                let name = name.trim().to_string();
+                self.view.search_name_input = None;
+                self.save_search(&name);
+            }
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Char(c) => name.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_pipe_key(&mut self, code: KeyCode) {
+        let Some(command) = &mut self.view.pipe_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => self.view.pipe_input = None,
+            KeyCode::Enter if command.trim().is_empty() => {}
+            KeyCode::Enter => {
+                let command = command.trim().to_string();
+                self.view.pipe_input = None;
+                self.pipe_events(command);
+            }
+            KeyCode::Backspace => {
+                command.pop();
+            }
+            KeyCode::Char(c) => command.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = &mut self.view.picker else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => self.view.picker = None,
+            KeyCode::Enter => {
+                let kind = picker.kind.clone();
+                let value = picker.selected_value().map(str::to_string);
+                self.view.picker = None;
+                match (kind, value) {
+                    (PickerKind::Value(field), Some(value)) => self.refine_filter(field, value),
+                    (PickerKind::Search, Some(name)) => self.apply_search(&name),
+                    (PickerKind::Action, Some(name)) => {
+                        if let Some(action) = Action::ALL.into_iter().find(|a| a.name() == name) {
+                            self.run_action(action);
+                        }
+                    }
+                    (PickerKind::Command, Some(name)) => {
+                        if let Some((_, key)) = COMMANDS.iter().find(|(n, _)| *n == name) {
+                            let key = KeyEvent::new(*key, KeyModifiers::NONE);
+                            self.handle_terminal_event(Ok(Event::Key(key)));
+                        }
+                    }
+                    (_, None) => {}
+                }
+            }
+            KeyCode::Up => picker.previous(),
+            KeyCode::Down => picker.next(),
+            KeyCode::Backspace => picker.pop(),
+            KeyCode::Char(c) => picker.push(c),
+            _ => {}
+        }
+    }
+
+    /// Move about the sidebar's tree, returning whether the key was for it
+    /// rather than the table.
+    fn handle_sidebar_key(&mut self, code: KeyCode) -> bool {
+        let Some(sidebar) = &mut self.view.sidebar else {
+            return false;
+        };
+
+        match code {
+            KeyCode::Esc => self.view.sidebar = None,
+            KeyCode::Tab => sidebar.focused = false,
+            KeyCode::Up => sidebar.previous(),
+            KeyCode::Down => sidebar.next(),
+            KeyCode::Right => sidebar.expand(),
+            KeyCode::Left => sidebar.collapse(),
+            KeyCode::Enter => {
+                if let Some(node) = sidebar.selected_node() {
+                    self.filter_to_node(&node.path);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Move about the groups while the table is grouped, selecting the event
+    /// under the selected row, and returning whether the key was for them.
+    fn handle_group_key(&mut self, code: KeyCode) -> bool {
+        if !matches!(
+            code,
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+        ) {
+            return false;
+        }
+        let Some(mut grouping) = self.view.grouping.take() else {
+            return false;
+        };
+
+        let rows = grouping.rows(&self.store, &self.scripts, self.view.visible());
+        match code {
+            KeyCode::Up => grouping.previous(),
+            KeyCode::Down => grouping.next(&rows),
+            KeyCode::Right => grouping.expand(&rows),
+            _ => grouping.collapse(&rows),
+        }
+        let rows = grouping.rows(&self.store, &self.scripts, self.view.visible());
+        if let Some(selected) = grouping.selected(&rows) {
+            self.view.select(rows[selected].position());
+        }
+        self.view.grouping = Some(grouping);
+        true
+    }
+
+    /// Move about the rows while collapsing runs, passing over the events in
+    /// collapsed runs, and expand and collapse runs, returning whether the key
+    /// was for them rather than the bodies.
+    fn handle_run_key(&mut self, code: KeyCode) -> bool {
+        let (Some(runs), Some(selected)) = (&self.view.runs, self.view.selected()) else {
+            return false;
+        };
+        let rows = runs.rows(&self.store, self.view.visible());
+        let Some(row) = rows.iter().position(|row| row.contains(selected)) else {
+            return false;
+        };
+
+        let position = match code {
+            KeyCode::Up => row.checked_sub(1).map(|row| rows[row].position()),
+            KeyCode::Down => rows.get(row + 1).map(RunRow::position),
+            KeyCode::Right => match rows[row] {
+                RunRow::Run {
+                    first,
+                    expanded: false,
+                    ..
+                } => {
+                    let index = self.view.visible()[first];
+                    if let Some(runs) = &mut self.view.runs {
+                        runs.expand(index);
+                    }
+                    Some(first)
+                }
+                _ => return false,
+            },
+            KeyCode::Left => match runs::run_of(&rows, selected).map(|run| rows[run]) {
+                Some(RunRow::Run {
+                    first,
+                    expanded: true,
+                    ..
+                }) => {
+                    let index = self.view.visible()[first];
+                    if let Some(runs) = &mut self.view.runs {
+                        runs.collapse(index);
+                    }
+                    Some(first)
+                }
+                _ => return false,
+            },
+            _ => return false,
+        };
+        if let Some(position) = position {
+            self.view.select(position);
+        }
+        true
+    }
+
+    fn handle_checklist_key(&mut self, code: KeyCode) {
+        let Some(checklist) = &mut self.view.checklist else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => self.view.checklist = None,
+            KeyCode::Enter => {
+                if let Some(checklist) = self.view.checklist.take() {
+                    match &checklist.kind {
+                        ChecklistKind::Values(field) => {
+                            self.exclude_values(field.clone(), checklist.excluded())
+                        }
+                        ChecklistKind::Columns => {
+                            let checked = checklist.checked().collect::<Vec<_>>();
+                            self.view.columns = self
+                                .column_choices()
+                                .into_iter()
+                                .filter(|column| checked.contains(&column.title().as_str()))
+                                .collect();
+                        }
+                    }
+                }
+            }
+            KeyCode::Up => checklist.previous(),
+            KeyCode::Down => checklist.next(),
+            KeyCode::Char(' ') => checklist.toggle(),
+            _ => {}
+        }
+    }
+
+    pub fn draw(&mut self) {
+        let tabs = self.tab_titles();
+        self.loads.retain(|load| !load.is_finished());
+        self.finish_pipes();
+        self.check_sinks();
+        // on and off each second until the flash is over
+        let alert = self
+            .alerting
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < alert::FLASH && at.elapsed().as_secs() % 2 == 0)
+            .map(|(name, _)| name.as_str());
+        let drawn = self.terminal.draw(|frame| {
+            ui::draw(
+                frame,
+                &self.store,
+                &self.scripts,
+                &mut self.view,
+                &tabs,
+                self.tab,
+                &mut self.stats,
+                &self.messages,
+                &self.theme,
+                &self.timestamps,
+                alert,
+                &self.loads,
+                &self.config,
+            )
+        });
+        let error = drawn.err().map(|err| format!("failed to draw: {}", err));
+        if error.is_some() && error != self.draw_error {
+            self.messages.error(error.clone().unwrap_or_default());
+        }
+        self.draw_error = error;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drift;
+    use crate::kube::test_event;
+    use crate::timestamp::TimeFormat;
+    use crossterm::event::KeyModifiers;
+    use ratatui::backend::TestBackend;
+    use ratatui::style::{Color, Modifier};
+
+    fn event(verb: &str, uri: &str, timestamp: &str) -> EventV1 {
+        test_event()
+            .uri(uri)
+            .verb(verb)
+            .object_ref(object_ref(uri))
+            .at(timestamp)
+            .build()
+    }
+
+    /// The object reference the apiserver gives a request to `uri`, none for
+    /// non-resource URLs.
+    fn object_ref(uri: &str) -> serde_json::Value {
+        let path = uri.split('?').next().unwrap();
+        let mut segments = path.split('/').skip(1);
+        let (group, version) = match segments.next() {
+            Some("api") => (None, segments.next()),
+            Some("apis") => (segments.next(), segments.next()),
+            _ => return serde_json::Value::Null,
+        };
+        let mut rest = segments.collect::<Vec<_>>();
+        let mut namespace = None;
+        if rest.len() > 2 && rest[0] == "namespaces" {
+            namespace = Some(rest[1]);
+            rest.drain(..2);
+        }
+        match rest.as_slice() {
+            [] => serde_json::Value::Null,
+            [resource, rest @ ..] => serde_json::json!({
+                "resource": resource,
+                "namespace": namespace,
+                "name": rest.first(),
+                "subresource": rest.get(1),
+                "apiGroup": group,
+                "apiVersion": version,
+            }),
+        }
+    }
+
+    fn app() -> App<TestBackend> {
+        let mut app = App::with_terminal(Terminal::new(TestBackend::new(100, 40)).unwrap());
+        app.handle_kube_event(event(
+            "list",
+            "/api/v1/pods?limit=500",
+            "2024-07-01T10:00:01Z",
+        ));
+        app.handle_kube_event(event("get", "/api/v1/namespaces", "2024-07-01T10:00:00Z"));
+        app
+    }
+
+    fn screen(app: &App<TestBackend>) -> String {
+        let buffer = app.terminal().backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn press(app: &mut App<TestBackend>, code: KeyCode) {
+        let key = KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_terminal_event(Ok(Event::Key(key)));
+    }
+
+    #[test]
+    fn draws_events_in_time_order() {
+        let mut app = app();
+        app.draw();
+
+        let screen = screen(&app);
+        let get = screen
+            .find("get    /api/v1/namespaces")
+            .expect("get row drawn");
+        let list = screen.find("list   /api/v1/pods ").expect("list row drawn");
+        assert!(get < list);
+        // The selection follows the first event as the earlier one is inserted
+        assert!(screen.contains("Request URI:       /api/v1/pods?limit=500"));
+    }
+
+    #[test]
+    fn filter_prompt() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('/'));
+        for c in "verb=list".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.draw();
+        assert!(screen(&app).contains("/verb=list"));
+
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        let screen = screen(&app);
+        assert!(screen.contains("[verb=list]"));
+        assert!(screen.contains("2 events | 1 matching | 0 skipped"));
+        assert!(!screen.contains("/api/v1/namespaces"));
+    }
+
+    #[test]
+    fn filter_history_and_undo() {
+        let mut app = app();
+        for filter in ["verb=list", "verb=get"] {
+            press(&mut app, KeyCode::Char('/'));
+            for _ in 0..20 {
+                press(&mut app, KeyCode::Backspace);
+            }
+            for c in filter.chars() {
+                press(&mut app, KeyCode::Char(c));
+            }
+            press(&mut app, KeyCode::Enter);
+        }
+
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.view().filter_input.as_deref(), Some("verb=list"));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().filter_input.as_deref(), Some("verb=get "));
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char('z'));
+        assert_eq!(app.view().filter().unwrap().to_string(), "verb=list");
+        press(&mut app, KeyCode::Char('z'));
+        assert!(app.view().filter().is_none());
+        press(&mut app, KeyCode::Char('z'));
+        assert_eq!(app.messages().last().unwrap().text, "nothing to undo");
+    }
+
+    #[test]
+    fn message_log() {
+        let mut app = app();
+        app.handle_ingested(Ingested::Skipped("unknown field `foo`".to_string()));
+        app.handle_ingested(Ingested::Failed("stdin is not valid JSON".to_string()));
+        press(&mut app, KeyCode::Char('m'));
+        app.draw();
+
+        let screen = screen(&app);
+        assert!(screen.contains("Messages (2)"));
+        assert!(screen.contains("warn  skipped: unknown field `foo`"));
+        assert!(screen.contains("error stdin is not valid JSON"));
+        assert!(screen.contains("1 skipped"));
+    }
+
+    /// A terminal that can be made to fail to draw.
+    struct Failing {
+        inner: TestBackend,
+        failing: bool,
+    }
+
+    impl Backend for Failing {
+        fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
+        where
+            I: Iterator<Item = (u16, u16, &'a ratatui::buffer::Cell)>,
+        {
+            match self.failing {
+                true => Err(std::io::Error::other("terminal went away")),
+                false => self.inner.draw(content),
+            }
+        }
+
+        fn hide_cursor(&mut self) -> std::io::Result<()> {
+            self.inner.hide_cursor()
+        }
+
+        fn show_cursor(&mut self) -> std::io::Result<()> {
+            self.inner.show_cursor()
+        }
+
+        fn get_cursor(&mut self) -> std::io::Result<(u16, u16)> {
+            self.inner.get_cursor()
+        }
+
+        fn set_cursor(&mut self, x: u16, y: u16) -> std::io::Result<()> {
+            self.inner.set_cursor(x, y)
+        }
+
+        fn clear(&mut self) -> std::io::Result<()> {
+            self.inner.clear()
+        }
+
+        fn size(&self) -> std::io::Result<ratatui::layout::Rect> {
+            self.inner.size()
+        }
+
+        fn window_size(&mut self) -> std::io::Result<ratatui::backend::WindowSize> {
+            self.inner.window_size()
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    #[test]
+    fn draw_failures_logged_once() {
+        let backend = Failing {
+            inner: TestBackend::new(100, 40),
+            failing: true,
+        };
+        let mut app = App::with_terminal(Terminal::new(backend).unwrap());
+        let errors = |app: &App<Failing>| app.messages().messages().len();
+        for _ in 0..3 {
+            app.draw();
+        }
+        assert_eq!(errors(&app), 1);
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "failed to draw: terminal went away"
+        );
+
+        // failing again after drawing is logged again
+        app.terminal.backend_mut().failing = false;
+        app.draw();
+        app.terminal.backend_mut().failing = true;
+        app.draw();
+        assert_eq!(errors(&app), 2);
+    }
+
+    #[test]
+    fn namespace_picker() {
+        let mut app = app();
+        let mut event = event(
+            "get",
+            "/api/v1/namespaces/kube-system/pods",
+            "2024-07-01T10:00:02Z",
+        );
+        event.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "pods",
+            "namespace": "kube-system",
+        }))
+        .unwrap();
+        app.handle_kube_event(event);
+
+        press(&mut app, KeyCode::Char('n'));
+        for c in "ksys".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.draw();
+        assert!(screen(&app).contains("kube-system (1)"));
+
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        let screen = screen(&app);
+        assert!(screen.contains("[namespace=kube-system]"));
+        assert!(screen.contains("3 events | 1 matching"));
+    }
+
+    #[test]
+    fn verb_toggles() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('r'));
+        assert_eq!(
+            app.view().filter().unwrap().to_string(),
+            "verb!=get and verb!=list and verb!=watch"
+        );
+        assert!(app.view().visible().is_empty());
+
+        press(&mut app, KeyCode::Char('v'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        app.draw();
+        assert!(screen(&app).contains("[x] list"));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.view().filter().unwrap().to_string(),
+            "verb!=get and verb!=watch"
+        );
+        assert_eq!(app.view().visible().len(), 1);
+
+        press(&mut app, KeyCode::Char('r'));
+        assert!(app.view().filter().is_none());
+    }
+
+    #[test]
+    fn status_presets() {
+        let mut app = app();
+        app.set_filter(Some("verb=get".parse().unwrap()));
+        let mut filters = Vec::new();
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Char('s'));
+            filters.push(app.view().filter().unwrap().to_string());
+        }
+        assert_eq!(
+            filters,
+            [
+                "verb=get and status>=400",
+                "verb=get and status>=500",
+                "verb=get and status=403",
+                "verb=get",
+            ]
+        );
+    }
+
+    #[test]
+    fn optional_columns() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('o'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view().columns, [Column::Stage]);
+        app.draw();
+        let screen = screen(&app);
+        assert!(screen.contains("stage"));
+        assert!(screen.contains("ResponseComplete"));
+    }
+
+    #[test]
+    fn sidebar() {
+        let mut app = app();
+        app.handle_kube_event(event(
+            "get",
+            "/api/v1/namespaces/default/pods/web",
+            "2024-07-01T10:00:02Z",
+        ));
+
+        // past the cluster-scoped objects to the namespace
+        press(&mut app, KeyCode::Char('T'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Objects"));
+        assert!(drawn.contains("▾ default (1)"));
+        assert!(drawn.contains("  ▸ pods (1)"));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.view().filter().unwrap().to_string(),
+            "namespace=default and resource=pods"
+        );
+        assert_eq!(app.view().visible().len(), 1);
+
+        // with the focus back on the table, keys move the selection again
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Down);
+        assert!(app
+            .view()
+            .sidebar
+            .as_ref()
+            .is_some_and(|s| s.selected() == 2));
+        press(&mut app, KeyCode::Char('T'));
+        assert!(app.view().sidebar.is_none());
+    }
+
+    #[test]
+    fn grouping() {
+        let mut app = app();
+        app.handle_kube_event(event("get", "/api/v1/nodes", "2024-07-01T10:00:02Z"));
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Char('G'));
+        }
+        assert_eq!(app.view().grouping.as_ref().unwrap().field, Field::Verb);
+
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().selected(), Some(1));
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().selected(), Some(2));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("verb"));
+        assert!(drawn.contains("▾ get (2)"));
+        assert!(drawn.contains("▸ list (1)"));
+        assert!(drawn.contains("/api/v1/nodes"));
+        assert!(!drawn.contains("/api/v1/pods"));
+
+        // closing the group goes back to its row
+        press(&mut app, KeyCode::Left);
+        assert_eq!(app.view().selected(), Some(0));
+        app.draw();
+        assert!(!screen(&app).contains("/api/v1/nodes"));
+
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('G'));
+        assert!(app.view().grouping.is_none());
+    }
+
+    #[test]
+    fn collapsed_runs() {
+        let mut app = app();
+        for second in 2..5 {
+            let timestamp = format!("2024-07-01T10:00:0{}Z", second);
+            app.handle_kube_event(event("update", "/api/v1/nodes/node-1", &timestamp));
+        }
+        press(&mut app, KeyCode::Char('C'));
+
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().selected(), Some(2));
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().selected(), Some(2));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("▸ /api/v1/nodes/node-1 (3 times in 2.000s)"));
+        // once in the table and twice in the request info
+        assert_eq!(drawn.matches("node-1").count(), 3);
+
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().selected(), Some(3));
+        app.draw();
+        assert_eq!(screen(&app).matches("node-1").count(), 5);
+
+        // closing the run goes back to its first event
+        press(&mut app, KeyCode::Left);
+        assert_eq!(app.view().selected(), Some(2));
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.view().selected(), Some(1));
+
+        press(&mut app, KeyCode::Char('C'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.view().selected(), Some(3));
+    }
+
+    #[test]
+    fn deduping() {
+        let mut app = app();
+        for second in [2, 3] {
+            let timestamp = format!("2024-07-01T10:00:0{}Z", second);
+            app.handle_kube_event(event("list", "/api/v1/pods?limit=500", &timestamp));
+        }
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.view().visible(), [0, 1]);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("/api/v1/pods (+2 duplicates)"));
+        assert!(drawn.contains("2 duplicates hidden"));
+
+        // those arriving while hiding them are counted too, until the window
+        // has passed
+        let uri = "/api/v1/pods?limit=500";
+        app.handle_kube_event(event("list", uri, "2024-07-01T10:00:04Z"));
+        app.handle_kube_event(event("list", uri, "2024-07-01T10:00:30Z"));
+        assert_eq!(app.view().visible(), [0, 1, 5]);
+        assert_eq!(app.view().dedupe().unwrap().hidden(), 3);
+
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.view().visible().len(), 6);
+    }
+
+    #[test]
+    fn cluster_column() {
+        let mut app = App::with_terminal(Terminal::new(TestBackend::new(100, 40)).unwrap());
+        for (source, uri, timestamp) in [
+            (
+                "prod",
+                "/api/v1/namespaces/web/pods",
+                "2024-07-01T10:00:02Z",
+            ),
+            (
+                "staging",
+                "/api/v1/namespaces/web/secrets",
+                "2024-07-01T10:00:01Z",
+            ),
+        ] {
+            let mut event = event("list", uri, timestamp);
+            event.source = Some(source.to_string());
+            app.handle_kube_event(event);
+        }
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("cluster"));
+        let staging = drawn.find("staging").expect("staging row");
+        let prod = drawn.find("prod ").expect("prod row");
+        assert!(staging < prod);
+
+        app.set_filter(Some("cluster=prod".parse().unwrap()));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("/api/v1/namespaces/web/pods"));
+        assert!(!drawn.contains("/api/v1/namespaces/web/secrets"));
+    }
+
+    #[test]
+    fn alerts() {
+        let mut app = app();
+        let rule = crate::config::AlertRule {
+            name: "namespace deleted".to_string(),
+            filter: "verb=delete resource=namespaces".to_string(),
+            bell: true,
+        };
+        app.set_alerts(vec![Alert::from_rule(&rule).unwrap()]);
+
+        let mut deleted = event("delete", "/api/v1/namespaces/prod", "2024-07-01T10:00:02Z");
+        deleted.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "namespaces",
+            "name": "prod",
+        }))
+        .unwrap();
+        app.handle_kube_event(deleted);
+        app.handle_kube_event(event("get", "/api/v1/nodes", "2024-07-01T10:00:03Z"));
+        assert!(app.store().is_alerted(2));
+        assert!(!app.store().is_alerted(3));
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "alert namespace deleted: delete /api/v1/namespaces/prod by alice"
+        );
+        assert!(app.take_bell());
+        assert!(!app.take_bell());
+
+        app.set_theme(Theme::plain());
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("! alert: namespace deleted |"));
+        assert!(drawn.contains("! /api/v1/namespaces/prod"));
+
+        let bad = crate::config::AlertRule {
+            filter: "verb=".to_string(),
+            ..rule
+        };
+        assert!(Alert::from_rule(&bad).is_err());
+    }
+
+    #[test]
+    fn break_glass() {
+        let mut app = app();
+        app.set_break_glass(BreakGlass::new(crate::config::BreakGlassRule {
+            namespaces: vec!["prod".to_string()],
+            allowed: Vec::new(),
+            bell: false,
+        }));
+        let mut patched = event(
+            "patch",
+            "/apis/apps/v1/namespaces/prod/deployments/web",
+            "2024-07-01T10:00:02Z",
+        );
+        patched.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "deployments",
+            "namespace": "prod",
+            "name": "web",
+        }))
+        .unwrap();
+        app.handle_kube_event(patched);
+        assert!(app.store().is_alerted(2));
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "break glass: patch /apis/apps/v1/namespaces/prod/deployments/web by alice"
+        );
+        assert!(!app.take_bell());
+    }
+
+    #[test]
+    fn watching_objects() {
+        let mut app = app();
+        let web = |verb: &str, timestamp: &str, replicas: u32| {
+            let mut event = event(
+                verb,
+                "/apis/apps/v1/namespaces/default/deployments/web",
+                timestamp,
+            );
+            event.object_ref = serde_json::from_value(serde_json::json!({
+                "resource": "deployments",
+                "namespace": "default",
+                "name": "web",
+                "apiGroup": "apps",
+            }))
+            .unwrap();
+            event.response_object = Some(serde_json::json!({
+                "kind": "Deployment",
+                "spec": {"replicas": replicas},
+            }));
+            event
+        };
+        app.handle_kube_event(web("create", "2024-07-01T10:00:02Z", 1));
+        app.handle_kube_event(web("update", "2024-07-01T10:00:03Z", 2));
+        app.handle_kube_event(web("get", "2024-07-01T10:00:04Z", 2));
+        app.view.select(2);
+
+        press(&mut app, KeyCode::Char('L'));
+        assert_eq!(app.tab_titles()[1], "watch deployments/we…");
+        assert_eq!(app.view().visible().len(), 2);
+        assert_eq!(app.view().selected(), Some(1));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("create -> 2024-07-01 10:00:03 UTC update (1 changes)"));
+        assert!(drawn.contains("~ .spec.replicas: 1 -> 2"));
+
+        // changes arriving are followed
+        press(&mut app, KeyCode::Up);
+        app.handle_kube_event(web("patch", "2024-07-01T10:00:05Z", 3));
+        assert_eq!(app.view().selected(), Some(2));
+        app.draw();
+        assert!(screen(&app).contains("~ .spec.replicas: 2 -> 3"));
+
+        press(&mut app, KeyCode::Char('w'));
+        app.view.select(0);
+        press(&mut app, KeyCode::Char('L'));
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "the selected event isn't about a named object"
+        );
+    }
+
+    #[test]
+    fn loading_files() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('X'));
+        assert_eq!(app.messages().last().unwrap().text, "no files are loading");
+
+        let source = crate::source::FileSource::new("audit.log");
+        app.track_load(source.progress());
+        app.draw();
+        assert!(screen(&app).contains("loading audit.log [░░░░░░░░░░] 0 B of 0 B, 0 events"));
+
+        press(&mut app, KeyCode::Char('X'));
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "cancelling loading 1 files"
+        );
+    }
+
+    #[test]
+    fn timeline() {
+        let mut app = app();
+        for second in [40, 40, 40, 59] {
+            let timestamp = format!("2024-07-01T10:00:{}Z", second);
+            app.handle_kube_event(event("get", "/api/v1/nodes", &timestamp));
+        }
+        app.draw();
+        assert!(screen(&app).contains('█'));
+
+        press(&mut app, KeyCode::Char('J'));
+        assert_eq!(app.view().selected(), Some(2));
+        press(&mut app, KeyCode::Char('}'));
+        assert_eq!(app.view().selected(), Some(5));
+        press(&mut app, KeyCode::Char('{'));
+        assert_eq!(app.view().selected(), Some(2));
+        press(&mut app, KeyCode::Char('{'));
+        assert_eq!(app.view().selected(), Some(1));
+        press(&mut app, KeyCode::Char('{'));
+        assert_eq!(app.view().selected(), Some(0));
+
+        press(&mut app, KeyCode::Char('H'));
+        app.draw();
+        assert!(!screen(&app).contains('█'));
+    }
+
+    #[test]
+    fn timeline_failures() {
+        let mut app = app();
+        for (second, code) in [(20, 200), (30, 500), (40, 429), (59, 200)] {
+            let timestamp = format!("2024-07-01T10:00:{}Z", second);
+            let mut event = event("get", "/api/v1/nodes", &timestamp);
+            event.response_status =
+                serde_json::from_value(serde_json::json!({"metadata": {}, "code": code})).ok();
+            app.handle_kube_event(event);
+        }
+        app.draw();
+        let area = app.view().timeline_area;
+        let timeline = app.view().timeline(&app.store).unwrap();
+        let x = |second: u32| {
+            let time = format!("2024-07-01T10:00:{}Z", second).parse().unwrap();
+            area.x + timeline.slice(time) as u16
+        };
+        let buffer = app.terminal().backend().buffer();
+        let colour = |second: u32| buffer.get(x(second), area.y).fg;
+        assert_eq!(colour(30), Color::Red);
+        assert_eq!(colour(40), Color::Magenta);
+        assert_eq!(colour(20), Color::White);
+
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.view().selected(), Some(3));
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.view().selected(), Some(4));
+
+        let click = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: x(20),
+            row: area.y,
+            modifiers: KeyModifiers::NONE,
+        };
+        app.handle_terminal_event(Ok(Event::Mouse(click)));
+        assert_eq!(app.view().selected(), Some(2));
+    }
+
+    #[test]
+    fn timeline_outcomes() {
+        let mut app = app();
+        // one slice of a 500 and three 200s, then one of a 429
+        for (second, code) in [(58, 500), (58, 200), (58, 200), (58, 200), (59, 429)] {
+            let timestamp = format!("2024-07-01T10:00:{}Z", second);
+            let mut event = event("get", "/api/v1/nodes", &timestamp);
+            event.response_status =
+                serde_json::from_value(serde_json::json!({"metadata": {}, "code": code})).ok();
+            app.handle_kube_event(event);
+        }
+        press(&mut app, KeyCode::Char('='));
+        app.draw();
+        let area = app.view().timeline_area;
+        let timeline = app.view().timeline(&app.store).unwrap();
+        let x = |second: u32| {
+            let time = format!("2024-07-01T10:00:{}Z", second).parse().unwrap();
+            area.x + timeline.slice(time) as u16
+        };
+        let buffer = app.terminal().backend().buffer();
+        let cell = |second: u32, row: u16| {
+            let cell = buffer.get(x(second), area.y + 1 + row);
+            (cell.symbol().to_string(), cell.fg)
+        };
+        // server errors, throttled, client errors and the rest, top down
+        assert_eq!(cell(58, 0), ("░".to_string(), Color::Red));
+        assert_eq!(cell(58, 1).0, " ");
+        assert_eq!(cell(58, 3), ("▓".to_string(), Color::White));
+        assert_eq!(cell(59, 1), ("█".to_string(), Color::Magenta));
+        assert_eq!(cell(59, 3).0, " ");
+
+        press(&mut app, KeyCode::Char('='));
+        app.draw();
+        assert_eq!(app.view().timeline_area.y, area.y + 4);
+    }
+
+    #[test]
+    fn command_palette() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char(':'));
+        for c in "mesglog".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.draw();
+        assert!(screen(&app).contains("show or hide the message log (m)"));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.view().picker.is_none());
+        assert_eq!(app.view().pane, Pane::Messages);
+
+        let key = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+        app.handle_terminal_event(Ok(Event::Key(key)));
+        for c in "wrap".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert!(app.view().unwrapped);
+    }
+
+    #[test]
+    fn ctrl_keys() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('/'));
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(
+            app.handle_terminal_event(Ok(Event::Key(key))),
+            Some(Exit::Quit)
+        );
+        let key = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(
+            app.handle_terminal_event(Ok(Event::Key(key))),
+            Some(Exit::Suspend)
+        );
+    }
+
+    #[test]
+    fn small_terminals() {
+        let mut app = app();
+        app.terminal.backend_mut().resize(40, 10);
+        app.handle_terminal_event(Ok(Event::Resize(40, 10)));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("terminal too small (need 50x12"));
+        assert!(drawn.contains("40x10)"));
+
+        for (width, height) in [(1, 1), (50, 12), (60, 16), (80, 24), (100, 40)] {
+            app.terminal.backend_mut().resize(width, height);
+            app.draw();
+            press(&mut app, KeyCode::Enter);
+            app.draw();
+            press(&mut app, KeyCode::Esc);
+            press(&mut app, KeyCode::Char('o'));
+            app.draw();
+            press(&mut app, KeyCode::Esc);
+        }
+        assert!(screen(&app).contains("Request Info"));
+
+        app.terminal.backend_mut().resize(80, 24);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(!drawn.contains("Request Info"));
+        assert!(drawn.contains("list   /api/v1/pods"));
+        assert!(drawn.contains("2 of 2 | 2 events"));
+    }
+
+    #[test]
+    fn non_resource_urls() {
+        let mut app = app();
+        app.handle_kube_event(event("get", "/healthz", "2024-07-01T10:00:02Z"));
+        assert_eq!(app.store.len(), 2);
+
+        app.set_non_resource(true);
+        app.handle_kube_event(event("get", "/healthz", "2024-07-01T10:00:02Z"));
+        assert_eq!(app.store.len(), 3);
+        app.set_filter(Some("scope=nonresource".parse().unwrap()));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("/healthz"));
+        assert!(drawn.contains("1 of 1 | 3 events"));
+    }
+
+    #[test]
+    fn since_previous_column() {
+        let mut app = app();
+        app.handle_kube_event(event("get", "/api/v1/nodes", "2024-07-01T10:03:13.5Z"));
+        press(&mut app, KeyCode::Char('o'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view().columns, [Column::SincePrevious]);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("since previous"));
+        assert!(drawn.contains("list   +1.000s"));
+        assert!(drawn.contains("get    +3m12s"));
+    }
+
+    #[test]
+    fn contextual_filters() {
+        let mut app = App::with_terminal(Terminal::new(TestBackend::new(100, 40)).unwrap());
+        let mut event = event(
+            "delete",
+            "/api/v1/namespaces/default/secrets/token",
+            "2024-07-01T09:00:00Z",
+        );
+        event.user_agent = Some("kubectl/v1.30.0".into());
+        event.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "secrets",
+            "namespace": "default",
+            "name": "token",
+        }))
+        .unwrap();
+        app.handle_kube_event(event);
+
+        press(&mut app, KeyCode::Char('O'));
+        assert_eq!(
+            app.view().filter().unwrap().to_string(),
+            "resource=secrets and namespace=default and name=token"
+        );
+        press(&mut app, KeyCode::Char('A'));
+        assert_eq!(
+            app.view().filter().unwrap().to_string(),
+            "resource=secrets and namespace=default and name=token and useragent!=kubectl/v1.30.0"
+        );
+        assert!(app.view().visible().is_empty());
+    }
+
+    #[test]
+    fn saved_searches() {
+        let path = std::env::temp_dir()
+            .join(format!("kale-test-{}", std::process::id()))
+            .join("config.toml");
+        let mut app = app();
+        app.set_config(Config::default(), Some(path.clone()));
+        app.set_filter(Some("verb=list".parse().unwrap()));
+
+        press(&mut app, KeyCode::Char('S'));
+        for c in "pod lists".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        let config = Config::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(config.search("pod lists").unwrap().filter, "verb=list");
+
+        app.set_filter(None);
+        press(&mut app, KeyCode::Char('b'));
+        app.draw();
+        assert!(screen(&app).contains("pod lists (1)"));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view().filter().unwrap().to_string(), "verb=list");
+    }
+
+    #[test]
+    fn path_columns() {
+        let mut app = app();
+        let mut event = event(
+            "patch",
+            "/apis/apps/v1/namespaces/default/deployments/web",
+            "2024-07-01T10:00:02Z",
+        );
+        event.request_object = Some(serde_json::json!({"spec": {"replicas": 12}}));
+        app.handle_kube_event(event);
+
+        press(&mut app, KeyCode::Char('p'));
+        for c in "requestObject.spec.replicas".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        app.set_filter(Some(".requestObject.spec.replicas>10".parse().unwrap()));
+        app.draw();
+        let screen = screen(&app);
+        assert!(screen.contains(".requestObject.spec."));
+        assert!(screen.contains("12"));
+        assert!(screen.contains("3 events | 1 matching"));
+    }
+
+    #[test]
+    fn drift_pane() {
+        let mut app = app();
+        let mut event = event(
+            "update",
+            "/apis/apps/v1/namespaces/default/deployments/web",
+            "2024-07-01T10:00:02Z",
+        );
+        let applied = serde_json::json!({"spec": {"replicas": 3}}).to_string();
+        event.request_object = Some(serde_json::json!({
+            "metadata": {
+                "annotations": {drift::ANNOTATION: applied},
+                "managedFields": [{
+                    "manager": "horizontal-pod-autoscaler",
+                    "operation": "Update",
+                    "fieldsType": "FieldsV1",
+                    "fieldsV1": {"f:spec": {"f:replicas": {}}},
+                }],
+            },
+            "spec": {"replicas": 12},
+        }));
+        app.handle_kube_event(event);
+
+        press(&mut app, KeyCode::Char('~'));
+        assert_eq!(app.view().pane, Pane::Drift);
+        app.draw();
+        assert!(screen(&app).contains("Drift: not a write of an object applied"));
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        let screen = screen(&app);
+        assert!(screen.contains("Drift: last applied -> update by alice (1 changes)"));
+        assert!(screen.contains("~ .spec.replicas: 3 -> 12  (horizontal-pod-autoscaler)"));
+        press(&mut app, KeyCode::Char('~'));
+        assert_eq!(app.view().pane, Pane::Bodies);
+    }
+
+    #[test]
+    fn bookmarks() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.store().bookmarks().collect::<Vec<_>>(), [1]);
+
+        // earlier events shift the bookmark along with the event
+        app.handle_kube_event(event("get", "/api/v1/nodes", "2024-07-01T09:00:00Z"));
+        assert_eq!(app.store().bookmarks().collect::<Vec<_>>(), [2]);
+
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.view().selected(), Some(0));
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(app.view().selected(), Some(2));
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(app.messages().last().unwrap().text, "no more bookmarks");
+
+        press(&mut app, KeyCode::Char('B'));
+        app.draw();
+        let screen = screen(&app);
+        assert!(screen.contains("Bookmarks (1)"));
+        assert!(screen.contains("list   /api/v1/pods"));
+    }
+
+    #[test]
+    fn batch_actions() {
+        let mut app = app();
+        let pick = |app: &mut App<TestBackend>, action: &str| {
+            press(app, KeyCode::Char('a'));
+            for c in action.chars() {
+                press(app, KeyCode::Char(c));
+            }
+            press(app, KeyCode::Enter);
+        };
+
+        press(&mut app, KeyCode::Up);
+        let shift_down = KeyEvent::new(KeyCode::Down, KeyModifiers::SHIFT);
+        app.handle_terminal_event(Ok(Event::Key(shift_down)));
+        assert_eq!(app.view().marked().collect::<Vec<_>>(), [0, 1]);
+
+        pick(&mut app, "diff");
+        app.draw();
+        let screen = screen(&app);
+        assert!(
+            screen.contains("~ .requestURI: \"/api/v1/namespaces\" -> \"/api/v1/pods?limit=500\"")
+        );
+        assert!(screen.contains("~ .verb: \"get\" -> \"list\""));
+
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Char(' '));
+        pick(&mut app, "clear");
+        assert_eq!(app.view().marked().count(), 0);
+
+        // with nothing marked, actions apply to the selected event
+        pick(&mut app, "hide");
+        assert_eq!(app.view().visible(), [0]);
+        pick(&mut app, "unhide");
+        assert_eq!(app.view().visible(), [0, 1]);
+    }
+
+    #[test]
+    fn resuming_sessions() {
+        let mut app = app();
+        app.set_filter(Some("verb=get".parse().unwrap()));
+        press(&mut app, KeyCode::Char('t'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('x'));
+        app.add_column(Column::Stage);
+        let session = app.session();
+        assert_eq!(session.tab, 1);
+        assert_eq!(session.tabs[0].filter.as_deref(), Some("verb=get"));
+        assert_eq!(session.tabs[1].columns, ["stage"]);
+        assert_eq!(session.bookmarks.len(), 1);
+
+        // the events arrive after resuming, as when reading them again
+        let mut app = App::with_terminal(Terminal::new(TestBackend::new(120, 30)).unwrap());
+        app.resume(session.clone());
+        assert_eq!(app.tab_titles(), ["verb=get", "all events"]);
+        assert_eq!(app.view().columns, [Column::Stage]);
+        app.handle_kube_event(event("get", "/api/v1/namespaces", "2024-07-01T10:00:00Z"));
+        app.handle_kube_event(event(
+            "list",
+            "/api/v1/pods?limit=500",
+            "2024-07-01T10:00:01Z",
+        ));
+        app.handle_kube_event(event("get", "/api/v1/nodes", "2024-07-01T10:00:02Z"));
+        assert_eq!(app.view().selected(), Some(1));
+        assert!(app.store.is_bookmarked(1));
+        assert_eq!(app.session(), session);
+    }
+
+    #[test]
+    fn piping() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('|'));
+        for c in "grep -c pods".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.draw();
+        assert!(screen(&app).contains("pipe events to: grep -c pods"));
+        press(&mut app, KeyCode::Enter);
+        while !app.pipes.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            app.draw();
+        }
+        let messages = app
+            .messages()
+            .messages()
+            .iter()
+            .map(|m| m.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages[messages.len() - 3..],
+            [
+                "piping 2 events to grep -c pods",
+                "grep -c pods: 1",
+                "grep -c pods finished"
+            ]
+        );
+
+        // offered again, and only given the marked events
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char('|'));
+        assert_eq!(app.view().pipe_input.as_deref(), Some("grep -c pods"));
+        press(&mut app, KeyCode::Enter);
+        let last = &app.messages().last().unwrap().text;
+        assert_eq!(last, "piping 1 events to grep -c pods");
+    }
+
+    #[test]
+    fn tabs() {
+        let mut app = app();
+        app.set_filter(Some("verb=get".parse().unwrap()));
+        press(&mut app, KeyCode::Char('t'));
+        assert!(app.view().filter().is_none());
+        app.handle_kube_event(event("get", "/api/v1/nodes", "2024-07-01T10:00:03Z"));
+        app.draw();
+        assert!(screen(&app).contains(" 1:verb=get  2:all events "));
+        assert!(screen(&app).contains("3 events | 3 matching"));
+
+        press(&mut app, KeyCode::Char('1'));
+        assert_eq!(app.view().filter().unwrap().to_string(), "verb=get");
+        assert_eq!(app.view().visible(), [0, 2]);
+
+        press(&mut app, KeyCode::Char('w'));
+        assert!(app.view().filter().is_none());
+        app.draw();
+        assert!(!screen(&app).contains("1:"));
+    }
+
+    #[test]
+    fn pinned_comparison() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('P'));
+        press(&mut app, KeyCode::Up);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Pinned: 2024-07-01 10:00:01 UTC list"));
+        assert!(drawn.contains("Selected: 2024-07-01 10:00:00 UTC get"));
+        assert!(drawn.contains("\"resource\": \"pods\""));
+        assert!(drawn.contains("\"resource\": \"namespaces\""));
+
+        press(&mut app, KeyCode::Char('P'));
+        app.draw();
+        assert!(!screen(&app).contains("Pinned:"));
+    }
+
+    #[test]
+    fn detail_view() {
+        let mut app = app();
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("(esc to close)"));
+        assert!(drawn.contains("Level        Metadata"));
+        assert!(drawn.contains("/api/v1/pods?limit=500"));
+
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.view.detail.is_none());
+        app.draw();
+        assert!(!screen(&app).contains("(esc to close)"));
+    }
+
+    #[test]
+    fn detail_view_keeps_its_event() {
+        let mut app = App::with_terminal(Terminal::new(TestBackend::new(100, 40)).unwrap());
+        let request = || {
+            test_event()
+                .audit_id("a")
+                .object_ref(object_ref("/api/v1/pods"))
+                .at("2024-07-01T10:00:01Z")
+        };
+        app.handle_kube_event(request().set("stage", "RequestReceived").build());
+        press(&mut app, KeyCode::Enter);
+
+        // an earlier event shifts it along, and the rest of its request arrives
+        let mut earlier = event("get", "/api/v1/namespaces", "2024-07-01T10:00:00Z");
+        earlier.audit_id = "b".to_string();
+        app.handle_kube_event(earlier);
+        let complete = request()
+            .set("stage", "ResponseComplete")
+            .set("stageTimestamp", "2024-07-01T10:00:01.250Z");
+        app.handle_kube_event(complete.build());
+
+        let detail = app.view.detail.as_ref().unwrap();
+        assert_eq!(app.store[detail.index].audit_id, "a");
+        assert_eq!(detail.stages.len(), 2);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Event a (esc to close)"));
+        assert!(drawn.contains("ResponseComplete"));
+        assert!(drawn.contains("+250ms"));
+    }
+
+    #[test]
+    fn querying_the_detail_view() {
+        let mut app = app();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('.'));
+        for c in "user[".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert!(app.view.prompt_error.is_some());
+        press(&mut app, KeyCode::Backspace);
+        for c in ".username".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.draw();
+        assert!(screen(&app).contains("query the event: .user.username"));
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Query .user.username"));
+        assert!(drawn.contains("[0]          alice"));
+        assert!(app.view.detail.is_some());
+    }
+
+    #[test]
+    fn pinning_a_query() {
+        let mut app = app();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('+'));
+        assert!(app.view().columns.is_empty());
+        press(&mut app, KeyCode::Char('.'));
+        for c in "user.username".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('+'));
+        press(&mut app, KeyCode::Char('+'));
+        let path = ".user.username".parse().unwrap();
+        assert_eq!(app.view().columns, [Column::Path(path)]);
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "pinned .user.username as a column"
+        );
+        press(&mut app, KeyCode::Esc);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains(".user.username"));
+        assert!(drawn.contains("alice"));
+    }
+
+    #[test]
+    fn admission_view() {
+        let mut app = app();
+        let mut event = event(
+            "create",
+            "/api/v1/namespaces/web/pods",
+            "2024-07-01T10:00:02Z",
+        );
+        event.annotations.insert(
+            "pod-security.kubernetes.io/audit-violations".to_string(),
+            "would violate PodSecurity \"restricted:latest\": runAsNonRoot != true (pod must set securityContext.runAsNonRoot=true)".to_string(),
+        );
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Char('V'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Admission (esc to close)"));
+        assert!(drawn.contains("Pod Security restricted:latest"));
+        assert!(drawn.contains("runAsNonRoot != true (1 request)"));
+
+        press(&mut app, KeyCode::Esc);
+        assert!(app.view.overlay.is_none());
+    }
+
+    #[test]
+    fn client_and_user_agent_views() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('i'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("User agents (esc to close)"));
+        assert!(!drawn.contains("Clients (esc to close)"));
+
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('h'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Clients (esc to close)"));
+        assert!(!drawn.contains("User agents (esc to close)"));
+    }
+
+    #[test]
+    fn credentials_view() {
+        let mut app = app();
+        let mut event = event(
+            "create",
+            "/api/v1/namespaces/ci/serviceaccounts/deployer/token",
+            "2024-07-01T10:00:02Z",
+        );
+        event.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "serviceaccounts",
+            "subresource": "token",
+            "namespace": "ci",
+            "name": "deployer",
+        }))
+        .unwrap();
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Char('I'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Credentials (esc to close)"));
+        assert!(drawn.contains("Service account tokens"));
+        assert!(drawn.contains("got a token for ci/deployer"));
+
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.view.overlay.is_none());
+    }
+
+    #[test]
+    fn fights_view() {
+        let mut app = app();
+        for (second, user) in ["argocd", "hpa", "argocd", "hpa", "argocd"]
+            .into_iter()
+            .enumerate()
+        {
+            let timestamp = format!("2024-07-01T10:00:1{}Z", second);
+            let mut event = event(
+                "patch",
+                "/apis/apps/v1/namespaces/web/deployments/web",
+                &timestamp,
+            );
+            event.user.username = user.to_string();
+            event.object_ref = serde_json::from_value(serde_json::json!({
+                "resource": "deployments",
+                "namespace": "web",
+                "name": "web",
+            }))
+            .unwrap();
+            app.handle_kube_event(event);
+        }
+        press(&mut app, KeyCode::Char('!'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Fight over web/deployments/web"));
+        assert!(drawn.contains("5 in 4s, changing hands 4 times"));
+
+        press(&mut app, KeyCode::Esc);
+        assert!(app.view.overlay.is_none());
+    }
+
+    #[test]
+    fn cascade_view() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('Z'));
+        assert!(app.view.overlay.is_none());
+        assert_eq!(
+            app.messages().last().unwrap().text,
+            "select a delete to see what it took down"
+        );
+
+        let mut event = event(
+            "delete",
+            "/apis/apps/v1/namespaces/web/deployments/web",
+            "2024-07-01T10:00:02Z",
+        );
+        event.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "deployments",
+            "namespace": "web",
+            "name": "web",
+        }))
+        .unwrap();
+        app.handle_kube_event(event);
+        app.view.select(2);
+        press(&mut app, KeyCode::Char('Z'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Cascade (esc to close)"));
+        assert!(drawn.contains("Deleting web/deployments/web"));
+        assert!(drawn.contains("nothing it owned was deleted after it"));
+    }
+
+    #[test]
+    fn throttling_view() {
+        let mut app = app();
+        let mut event = event("list", "/api/v1/pods", "2024-07-01T10:00:02Z");
+        event.response_status =
+            serde_json::from_value(serde_json::json!({"metadata": {}, "code": 429})).ok();
+        event
+            .annotations
+            .insert("apf.prioritylevel".to_string(), "workload-low".to_string());
+        app.handle_kube_event(event.clone());
+        press(&mut app, KeyCode::Char('l'));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Throttling (esc to close)"));
+        assert!(drawn.contains("1 of 1 request throttled (100%)"));
+        assert!(drawn.contains("Throttled clients"));
+
+        // worked out again once more events arrive
+        app.handle_kube_event(event);
+        app.draw();
+        assert!(screen(&app).contains("2 of 2 requests throttled (100%)"));
+
+        press(&mut app, KeyCode::Esc);
+        assert!(app.view.overlay.is_none());
+    }
+
+    #[test]
+    fn groups_in_request_info() {
+        let mut app = app();
+        let mut event = event("get", "/api/v1/nodes", "2024-07-01T10:00:02Z");
+        event.user.groups = (0..20).map(|i| format!("group-{}", i)).collect();
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("Groups:            group-0, group-1,"));
+        assert!(drawn.contains("more (enter for all)"));
+        assert!(!drawn.contains("group-19"));
+
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        assert!(screen(&app).contains("group-19"));
+    }
+
+    #[test]
+    fn failed_response_status() {
+        let mut app = app();
+        app.draw();
+        assert!(screen(&app).contains("Response Status:   N/A"));
+
+        let mut event = event("get", "/api/v1/secrets", "2024-07-01T10:00:02Z");
+        event.response_status = serde_json::from_value(serde_json::json!({
+            "code": 403,
+            "status": "Failure",
+            "reason": "Forbidden",
+            "message": "secrets is forbidden",
+        }))
+        .unwrap();
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        assert!(screen(&app).contains("Response Status:   403 Forbidden: secrets is forbidden"));
+
+        app.set_theme(Theme::plain());
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("│> 2024-07-01 10:00:02 UTC"));
+        assert!(drawn.contains("Response Status:   ! 403 Forbidden"));
+    }
+
+    #[test]
+    fn position_and_scrollbar() {
+        let mut app = app();
+        app.draw();
+        assert!(screen(&app).contains("2 of 2 | 2 events"));
+        assert!(!screen(&app).contains('║'));
+
+        for second in 2..30 {
+            let timestamp = format!("2024-07-01T10:00:{:02}Z", second);
+            app.handle_kube_event(event("get", "/api/v1/nodes", &timestamp));
+        }
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("3 of 30 | 30 events"));
+        assert!(drawn.contains('║'));
+    }
+
+    #[test]
+    fn themes() {
+        fn selected(app: &App<TestBackend>) -> ratatui::style::Style {
+            let drawn = screen(app);
+            let y = drawn
+                .lines()
+                .position(|line| line.contains("10:00:01 UTC"))
+                .unwrap();
+            app.terminal().backend().buffer().get(2, y as u16).style()
+        }
+
+        let mut app = app();
+        app.draw();
+        assert_eq!(selected(&app).bg, Some(Color::Gray));
+
+        app.set_theme(Theme::plain());
+        app.draw();
+        let style = selected(&app);
+        assert_eq!(style.fg, Some(Color::Reset));
+        assert!(style.add_modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn timestamp_formats() {
+        let mut app = app();
+        app.set_timestamps(Timestamps::new("+02:00".parse().unwrap(), TimeFormat::Time));
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("│12:00:01.000 list   /api/v1/pods"));
+        assert!(!drawn.contains("10:00:01"));
+    }
+
+    #[test]
+    fn body_scrolling() {
+        let mut app = app();
+        let mut event = event("create", "/api/v1/configmaps", "2024-07-01T10:00:02Z");
+        let data = (0..50)
+            .map(|i| (format!("key-{:02}", i), serde_json::json!(i)))
+            .collect::<serde_json::Map<_, _>>();
+        event.request_object = Some(serde_json::json!({ "data": data }));
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        assert!(screen(&app).contains(" 0% "));
+
+        for _ in 0..100 {
+            press(&mut app, KeyCode::PageDown);
+        }
+        app.draw();
+        let limit = app.view.scroll_limit;
+        assert!(limit > 0);
+        assert_eq!(app.view.scroll_position, limit);
+        assert!(screen(&app).contains(" 100% "));
+        assert!(screen(&app).contains("\"key-49\": 49"));
+
+        press(&mut app, KeyCode::Home);
+        assert_eq!(app.view.scroll_position, 0);
+        press(&mut app, KeyCode::End);
+        assert_eq!(app.view.scroll_position, limit);
+    }
+
+    #[test]
+    fn unwrapped_bodies() {
+        let mut app = app();
+        let mut event = event("create", "/api/v1/secrets", "2024-07-01T10:00:02Z");
+        let blob = format!("{}END", "x".repeat(150));
+        event.request_object = Some(serde_json::json!({ "data": blob }));
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        assert!(screen(&app).contains("END"));
+
+        press(&mut app, KeyCode::Char('W'));
+        app.draw();
+        assert!(!screen(&app).contains("END"));
+
+        for _ in 0..50 {
+            press(&mut app, KeyCode::Right);
+        }
+        app.draw();
+        assert_eq!(app.view.horizontal_scroll, app.view.horizontal_limit);
+        assert!(screen(&app).contains("END\""));
+
+        press(&mut app, KeyCode::Char('W'));
+        assert_eq!(app.view.horizontal_scroll, 0);
+    }
+
+    #[test]
+    fn find_in_bodies() {
+        let mut app = app();
+        let mut event = event("create", "/api/v1/configmaps", "2024-07-01T10:00:02Z");
+        let data = (0..50)
+            .map(|i| (format!("key-{:02}", i), serde_json::json!(i)))
+            .collect::<serde_json::Map<_, _>>();
+        event.request_object = Some(serde_json::json!({ "data": data }));
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('f'));
+        for c in "KEY-4".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.draw();
+        assert!(screen(&app).contains("find in bodies: KEY-4"));
+
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        let drawn = screen(&app);
+        assert!(drawn.contains("\"KEY-4\" 1 of 10"));
+        assert!(drawn.contains("\"key-40\": 40"));
+        assert_eq!(app.view.scroll_position, app.view.scroll_limit);
+
+        press(&mut app, KeyCode::Char('F'));
+        assert_eq!(app.view.scroll_position, app.view.find_lines[1]);
+        app.draw();
+        assert!(screen(&app).contains("\"KEY-4\" 2 of 10"));
+
+        press(&mut app, KeyCode::Char('f'));
+        for _ in 0..5 {
+            press(&mut app, KeyCode::Backspace);
+        }
+        press(&mut app, KeyCode::Enter);
+        assert!(app.view.find.is_none());
+    }
+
+    #[test]
+    fn folded_bodies() {
+        let mut app = app();
+        let mut event = event(
+            "get",
+            "/api/v1/namespaces/default/pods/web",
+            "2024-07-01T10:00:02Z",
+        );
+        event.response_object = Some(serde_json::json!({
+            "metadata": {"name": "web", "managedFields": [{"manager": "kubectl"}]},
+        }));
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        assert!(screen(&app).contains("\"<1 managed"));
+        assert!(!screen(&app).contains("kubectl"));
+
+        press(&mut app, KeyCode::Char('M'));
+        app.draw();
+        assert!(screen(&app).contains("\"manager\": \"kubectl\""));
+    }
+
+    #[test]
+    fn secret_values() {
+        let mut app = app();
+        let mut event = event(
+            "create",
+            "/api/v1/namespaces/default/secrets",
+            "2024-07-01T10:00:02Z",
+        );
+        event.object_ref =
+            serde_json::from_value(serde_json::json!({"resource": "secrets"})).unwrap();
+        event.request_object = Some(serde_json::json!({"data": {"password": "aHVudGVyMg=="}}));
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        app.draw();
+        assert!(screen(&app).contains("<hidden, R to reveal>"));
+        assert!(!screen(&app).contains("aHVudGVyMg=="));
+
+        press(&mut app, KeyCode::Char('R'));
+        app.draw();
+        assert!(screen(&app).contains("\"password\": \"aHVudGVyMg==\""));
+
+        press(&mut app, KeyCode::Char('R'));
+        app.draw();
+        assert!(screen(&app).contains("\"password\": \"hunter2\""));
+
+        press(&mut app, KeyCode::Char('R'));
+        app.draw();
+        assert!(screen(&app).contains("<hidden, R to reveal>"));
+    }
+
+    #[test]
+    fn decoding_values() {
+        let mut app = app();
+        let mut event = event(
+            "create",
+            "/apis/certificates.k8s.io/v1/certificatesigningrequests",
+            "2024-07-01T10:00:02Z",
+        );
+        event.request_object = Some(serde_json::json!({"spec": {"request": "aGVsbG8gd29ybGQ="}}));
+        app.handle_kube_event(event);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('D'));
+        for c in "spec.request".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        assert!(screen(&app).contains("\"request\": \"hello world\""));
+
+        press(&mut app, KeyCode::Char('D'));
+        for _ in 0..".requestObject.".len() {
+            press(&mut app, KeyCode::Backspace);
+        }
+        for c in ".user".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        assert!(screen(&app).contains("only values in .requestObject or .responseObject"));
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char('D'));
+        for c in "spec.request".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        app.draw();
+        assert!(screen(&app).contains("aGVsbG8gd29ybGQ="));
+    }
+}
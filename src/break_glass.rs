@@ -0,0 +1,199 @@
+//! Writes by people, rather than automation, to the namespaces the config
+//! file says should only change through it, for change management to follow
+//! up on.
+
+use crate::clients::Client;
+use crate::config::BreakGlassRule;
+use crate::export::quote;
+use crate::kube::EventV1;
+use chrono::SecondsFormat;
+use std::fmt::Write;
+
+/// The verbs that change things.
+const WRITES: [&str; 5] = ["create", "update", "patch", "delete", "deletecollection"];
+
+/// Which events break glass, from the config file's `[break_glass]`.
+#[derive(Debug, Clone)]
+pub struct BreakGlass {
+    rule: BreakGlassRule,
+}
+
+impl BreakGlass {
+    pub fn new(rule: BreakGlassRule) -> Self {
+        Self { rule }
+    }
+
+    /// Whether to ring the terminal bell when glass is broken.
+    pub fn bell(&self) -> bool {
+        self.rule.bell
+    }
+
+    /// Whether the namespace is one of the rule's, each of which ends in `*`
+    /// to match any starting with the rest.
+    fn guarded(&self, namespace: &str) -> bool {
+        self.rule
+            .namespaces
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => namespace.starts_with(prefix),
+                None => namespace == pattern,
+            })
+    }
+
+    /// Whether a person, other than those allowed, changed something in one
+    /// of the guarded namespaces.
+    pub fn matches(&self, event: &EventV1) -> bool {
+        let namespace = event
+            .object_ref
+            .as_ref()
+            .and_then(|object| object.namespace.as_deref());
+        WRITES.contains(&event.verb.as_str())
+            && namespace.is_some_and(|namespace| self.guarded(namespace))
+            && Client::of(event) == Client::Human
+            && !self.rule.allowed.contains(&event.user.username)
+    }
+}
+
+fn fields(event: &EventV1) -> [String; 6] {
+    let object = event.object_ref.as_ref();
+    let object = object.map(|object| {
+        [&object.namespace, &object.resource, &object.name]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("/")
+    });
+    [
+        event
+            .request_received_timestamp
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        event.user.username.clone(),
+        event.verb.clone(),
+        object.unwrap_or_else(|| event.path().to_string()),
+        event
+            .response_status
+            .as_ref()
+            .map(|status| status.code.to_string())
+            .unwrap_or_default(),
+        event.user_agent.clone().unwrap_or_default(),
+    ]
+}
+
+const HEADINGS: [&str; 6] = ["time", "user", "verb", "object", "code", "user agent"];
+
+/// The events as a table to read, with a count of each person's at the end.
+pub fn text(events: &[&EventV1]) -> String {
+    let mut text = String::new();
+    let _ = write_text(&mut text, events);
+    text
+}
+
+fn write_text(text: &mut String, events: &[&EventV1]) -> std::fmt::Result {
+    if events.is_empty() {
+        return writeln!(text, "no one broke glass");
+    }
+    let rows = events.iter().map(|event| fields(event)).collect::<Vec<_>>();
+    let mut widths = HEADINGS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut line = |cells: &[&str]| {
+        let cells = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>();
+        writeln!(text, "{}", cells.join("  ").trim_end())
+    };
+    line(&HEADINGS)?;
+    for row in &rows {
+        line(&row.each_ref().map(String::as_str))?;
+    }
+
+    let mut people = Vec::<(&str, usize)>::new();
+    for event in events {
+        let user = event.user.username.as_str();
+        match people.iter_mut().find(|(person, _)| *person == user) {
+            Some((_, count)) => *count += 1,
+            None => people.push((user, 1)),
+        }
+    }
+    people.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    writeln!(text)?;
+    for (person, count) in people {
+        match count {
+            1 => writeln!(text, "{} made 1 change", person)?,
+            count => writeln!(text, "{} made {} changes", person, count)?,
+        }
+    }
+    Ok(())
+}
+
+/// The events as CSV, for change management's spreadsheets and tickets.
+pub fn csv(events: &[&EventV1]) -> String {
+    let mut csv = HEADINGS.join(",") + "\n";
+    for event in events {
+        let cells = fields(event).map(|cell| quote(&cell));
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, verb: &str, namespace: &str) -> EventV1 {
+        test_event()
+            .uri(format!(
+                "/apis/apps/v1/namespaces/{}/deployments/web",
+                namespace
+            ))
+            .verb(verb)
+            .user(user)
+            .groups(&["system:authenticated"])
+            .user_agent("kubectl/v1.30.0, edited")
+            .object_ref(serde_json::json!({
+                "resource": "deployments",
+                "namespace": namespace,
+                "name": "web",
+            }))
+            .code(200)
+            .build()
+    }
+
+    #[test]
+    fn breaking_glass() {
+        let glass = BreakGlass::new(BreakGlassRule {
+            namespaces: vec!["prod".to_string(), "payments-*".to_string()],
+            allowed: vec!["release-manager".to_string()],
+            bell: false,
+        });
+        let patch = event("alice", "patch", "prod");
+        assert!(glass.matches(&patch));
+        assert!(glass.matches(&event("bob", "delete", "payments-eu")));
+        assert!(!glass.matches(&event("alice", "get", "prod")));
+        assert!(!glass.matches(&event("alice", "patch", "staging")));
+        assert!(!glass.matches(&event("alice", "patch", "production")));
+        assert!(!glass.matches(&event(
+            "system:serviceaccount:argocd:controller",
+            "patch",
+            "prod"
+        )));
+        assert!(!glass.matches(&event("release-manager", "patch", "prod")));
+
+        let text = text(&[&patch, &patch]);
+        assert!(text.starts_with("time                  user   verb   object"));
+        assert!(text.contains("\n2024-07-01T10:00:00Z  alice  patch  prod/deployments/web  200"));
+        assert!(text.ends_with("\nalice made 2 changes\n"));
+        assert_eq!(
+            csv(&[&patch]),
+            "time,user,verb,object,code,user agent\n2024-07-01T10:00:00Z,alice,patch,prod/deployments/web,200,\"kubectl/v1.30.0, edited\"\n"
+        );
+    }
+}
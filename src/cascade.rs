@@ -0,0 +1,305 @@
+//! What deleting an object took down with it: the deletes after it of the
+//! objects whose ownerReferences chain back to it, as the garbage collector
+//! follows them.
+
+use crate::detail::Section;
+use crate::kube::{EventV1, Stage};
+use crate::store::EventStore;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// An object by its namespace, resource and name.
+type Key<'a> = (Option<&'a str>, Option<&'a str>, Option<&'a str>);
+
+/// A delete and the deletes of what it owned.
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    /// Where the delete is in the store.
+    pub index: usize,
+    pub children: Vec<Node>,
+}
+
+fn key(event: &EventV1) -> Option<Key<'_>> {
+    let object = event.object_ref.as_ref()?;
+    Some((
+        object.namespace.as_deref(),
+        object.resource.as_deref(),
+        object.name.as_deref(),
+    ))
+}
+
+/// The uid of the object in the event's bodies, or its reference.
+fn uid(event: &EventV1) -> Option<String> {
+    let bodies = [&event.response_object, &event.request_object];
+    let body = bodies.into_iter().flatten().find_map(|body| {
+        let uid = body.pointer("/metadata/uid")?.as_str()?;
+        Some(uid.to_string())
+    });
+    let reference = event.object_ref.as_ref().and_then(|object| object.uid);
+    body.or_else(|| reference.map(|uid| uid.to_string()))
+}
+
+/// The uids of the owners of the object in the event's bodies.
+fn owners(event: &EventV1) -> Vec<String> {
+    let bodies = [&event.response_object, &event.request_object];
+    let references = bodies
+        .into_iter()
+        .flatten()
+        .find_map(|body| body.pointer("/metadata/ownerReferences")?.as_array());
+    references
+        .into_iter()
+        .flatten()
+        .filter_map(|reference| Some(reference.get("uid")?.as_str()?.to_string()))
+        .collect()
+}
+
+/// The deletes after the delete at `root` in the store of anything it owned,
+/// and anything they owned, and so on, going by the uids and owners of
+/// objects in any event's bodies, if `root` is a delete.
+pub fn trace(store: &EventStore, root: usize) -> Option<Node> {
+    let deleted = store.get(root).filter(|event| event.verb == "delete")?;
+
+    // what's known of each object, as a delete's own bodies may not say
+    let mut uids = HashMap::<Key, String>::new();
+    let mut owned_by = HashMap::<Key, Vec<String>>::new();
+    for event in store.iter() {
+        let Some(key) = key(event) else {
+            continue;
+        };
+        if let Some(uid) = uid(event) {
+            uids.insert(key, uid);
+        }
+        let owners = owners(event);
+        if !owners.is_empty() {
+            owned_by.insert(key, owners);
+        }
+    }
+
+    let deletes = (0..store.len())
+        .filter(|i| *i != root)
+        .filter(|i| {
+            let event = &store[*i];
+            event.verb == "delete"
+                && event.stage == Stage::ResponseComplete
+                && event.request_received_timestamp >= deleted.request_received_timestamp
+                && !event
+                    .response_status
+                    .as_ref()
+                    .is_some_and(|status| status.is_failure())
+        })
+        .collect::<Vec<_>>();
+
+    let uid_of = |index: usize| {
+        let event = &store[index];
+        uid(event).or_else(|| uids.get(&key(event)?).cloned())
+    };
+    let owners_of = |index: usize| {
+        let event = &store[index];
+        let owners = owners(event);
+        match owners.is_empty() {
+            true => key(event)
+                .and_then(|key| owned_by.get(&key).cloned())
+                .unwrap_or_default(),
+            false => owners,
+        }
+    };
+    let owners = deletes
+        .iter()
+        .map(|index| (*index, owners_of(*index)))
+        .collect::<Vec<_>>();
+
+    let mut seen = HashSet::from([root]);
+    Some(Node {
+        index: root,
+        children: children(root, &owners, &mut seen, &uid_of),
+    })
+}
+
+/// The deletes among `deletes`, with the owners of what they deleted, of
+/// what the delete at `index` owned, and what they owned, leaving out any
+/// already `seen`.
+fn children(
+    index: usize,
+    deletes: &[(usize, Vec<String>)],
+    seen: &mut HashSet<usize>,
+    uid_of: &dyn Fn(usize) -> Option<String>,
+) -> Vec<Node> {
+    let Some(uid) = uid_of(index) else {
+        return Vec::new();
+    };
+    let owned = deletes
+        .iter()
+        .filter(|(child, owners)| !seen.contains(child) && owners.contains(&uid))
+        .map(|(child, _)| *child)
+        .collect::<Vec<_>>();
+    seen.extend(&owned);
+    owned
+        .into_iter()
+        .map(|child| Node {
+            index: child,
+            children: children(child, deletes, seen, uid_of),
+        })
+        .collect()
+}
+
+/// What a delete was of, as `resource/name`, with the namespace too if
+/// asked for.
+fn object(event: &EventV1, namespace: bool) -> String {
+    let Some(object) = &event.object_ref else {
+        return event.path().to_string();
+    };
+    let namespace = object.namespace.as_ref().filter(|_| namespace);
+    [namespace, object.resource.as_ref(), object.name.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// How long after the first delete another was, like `+1.5s`.
+fn after(elapsed: Duration) -> String {
+    let ms = elapsed.num_milliseconds();
+    match ms < 1000 {
+        true => format!("+{}ms", ms),
+        false => format!("+{:.1}s", ms as f64 / 1000.0),
+    }
+}
+
+/// The cascade as a tree, a row for each delete with when and by whom.
+pub fn sections(store: &EventStore, root: &Node) -> Vec<Section> {
+    let deleted = &store[root.index];
+    let mut section = Section::new(format!("Deleting {}", object(deleted, true)));
+    section.row(
+        object(deleted, true),
+        format!("by {}", deleted.user.username),
+    );
+    if root.children.is_empty() {
+        section.row("", "nothing it owned was deleted after it");
+    }
+    let start = deleted.request_received_timestamp;
+    rows(store, &root.children, "", start, &mut section);
+    vec![section]
+}
+
+/// A row for each of the nodes and their children, drawn as branches of a
+/// tree under `prefix`.
+fn rows(
+    store: &EventStore,
+    nodes: &[Node],
+    prefix: &str,
+    start: DateTime<Utc>,
+    section: &mut Section,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let event = &store[node.index];
+        let branch = if last { "└─ " } else { "├─ " };
+        section.row(
+            format!("{}{}{}", prefix, branch, object(event, false)),
+            format!(
+                "{} by {}",
+                after(event.request_received_timestamp - start),
+                event.user.username
+            ),
+        );
+        let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        rows(store, &node.children, &prefix, start, section);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use serde_json::{json, Value};
+
+    fn event(verb: &str, resource: &str, name: &str, second: u32, body: Value) -> EventV1 {
+        let time = format!("2024-07-01T10:00:{:02}Z", second);
+        let user = match verb {
+            "delete" if second > 0 => "system:serviceaccount:kube-system:generic-garbage-collector",
+            _ => "alice",
+        };
+        test_event()
+            .level("RequestResponse")
+            .uri(format!(
+                "/apis/apps/v1/namespaces/web/{}/{}",
+                resource, name
+            ))
+            .verb(verb)
+            .user(user)
+            .object_ref(json!({"resource": resource, "namespace": "web", "name": name}))
+            .code(200)
+            .response_object(body)
+            .at(time)
+            .build()
+    }
+
+    fn owned(uid: &str, owner: &str) -> Value {
+        json!({"metadata": {"uid": uid, "ownerReferences": [{"uid": owner, "kind": "Owner", "name": "owner"}]}})
+    }
+
+    #[test]
+    fn cascade() {
+        let mut store = EventStore::new();
+        let pod = store.insert(event("create", "pods", "web-1-a", 0, owned("p1", "r1")));
+        let deployment = store.insert(event(
+            "delete",
+            "deployments",
+            "web",
+            0,
+            json!({"metadata": {"uid": "d1"}}),
+        ));
+        let replicaset = store.insert(event(
+            "delete",
+            "replicasets",
+            "web-1",
+            1,
+            owned("r1", "d1"),
+        ));
+        let mut gone = event("delete", "replicasets", "gone", 1, owned("r2", "d1"));
+        gone.response_status = serde_json::from_value(json!({"metadata": {}, "code": 404})).ok();
+        store.insert(gone);
+        store.insert(event(
+            "delete",
+            "configmaps",
+            "settings",
+            2,
+            owned("c1", "x1"),
+        ));
+        let pod_deleted = store.insert(event("delete", "pods", "web-1-a", 2, Value::Null));
+
+        assert_eq!(trace(&store, pod), None);
+        let root = trace(&store, deployment).unwrap();
+        assert_eq!(
+            root,
+            Node {
+                index: deployment,
+                children: vec![Node {
+                    index: replicaset,
+                    children: vec![Node {
+                        index: pod_deleted,
+                        children: Vec::new()
+                    }],
+                }],
+            }
+        );
+        let sections = sections(&store, &root);
+        assert_eq!(sections[0].title, "Deleting web/deployments/web");
+        assert_eq!(
+            sections[0].rows[1..],
+            [
+                (
+                    "└─ replicasets/web-1".to_string(),
+                    "+1.0s by system:serviceaccount:kube-system:generic-garbage-collector"
+                        .to_string()
+                ),
+                (
+                    "   └─ pods/web-1-a".to_string(),
+                    "+2.0s by system:serviceaccount:kube-system:generic-garbage-collector"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+}
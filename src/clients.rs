@@ -0,0 +1,188 @@
+//! Who the apiserver's traffic comes from: nodes' kubelets, controllers or
+//! people, and how hard each node is leaning on it, to spot a misbehaving
+//! node.
+
+use crate::detail::Section;
+use crate::kube::EventV1;
+use std::collections::HashMap;
+
+/// How many times the typical node's rate a node has to reach to be marked.
+const OUTLIER: f64 = 3.0;
+
+/// The kinds of client that make requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Client {
+    /// A node's kubelet, or anything else with a node's credentials.
+    Node,
+    /// Service accounts and the control plane's own components.
+    Controller,
+    /// Anyone else, people and their scripts.
+    Human,
+}
+
+impl Client {
+    pub fn of(event: &EventV1) -> Self {
+        let user = &event.user;
+        if user.groups.iter().any(|group| group == "system:nodes")
+            || user.username.starts_with("system:node:")
+        {
+            return Client::Node;
+        }
+        match user.username.starts_with("system:") {
+            true => Client::Controller,
+            false => Client::Human,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Client::Node => "nodes",
+            Client::Controller => "controllers",
+            Client::Human => "humans",
+        }
+    }
+}
+
+/// The events per minute of `count` of the events over the `minutes` they
+/// span.
+fn rate(count: usize, minutes: f64) -> String {
+    let rate = count as f64 / minutes;
+    match rate < 10.0 {
+        true => format!("{:.1}/min", rate),
+        false => format!("{:.0}/min", rate),
+    }
+}
+
+/// What the clients of each kind did among the events, in time order, and
+/// then each node by how many requests it made, with what it made most of,
+/// marking those far busier than the typical node.
+pub fn sections(events: &[&EventV1]) -> Vec<Section> {
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        return Vec::new();
+    };
+    let span = last.request_received_timestamp - first.request_received_timestamp;
+    let minutes = (span.num_milliseconds() as f64 / 60_000.0).max(1.0 / 60.0);
+
+    let mut kinds = HashMap::<Client, usize>::new();
+    let mut nodes = HashMap::<&str, HashMap<String, usize>>::new();
+    for event in events {
+        let client = Client::of(event);
+        *kinds.entry(client).or_default() += 1;
+        if client == Client::Node {
+            let user = &event.user.username;
+            let node = user.strip_prefix("system:node:").unwrap_or(user);
+            let resource = event
+                .object_ref
+                .as_ref()
+                .and_then(|object| object.resource.as_deref())
+                .unwrap_or_else(|| event.path());
+            let request = format!("{} {}", event.verb, resource);
+            *nodes.entry(node).or_default().entry(request).or_default() += 1;
+        }
+    }
+
+    let mut clients = Section::new("Clients");
+    for client in [Client::Node, Client::Controller, Client::Human] {
+        let count = kinds.get(&client).copied().unwrap_or_default();
+        let share = count as f64 * 100.0 / events.len() as f64;
+        let requests = match count {
+            1 => "1 request".to_string(),
+            count => format!("{} requests", count),
+        };
+        clients.row(
+            client.title(),
+            format!("{} ({}, {:.0}%)", rate(count, minutes), requests, share),
+        );
+    }
+
+    let mut nodes = nodes
+        .into_iter()
+        .map(|(node, requests)| (node, requests.values().sum::<usize>(), requests))
+        .collect::<Vec<_>>();
+    nodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let mut counts = nodes.iter().map(|(_, count, _)| *count).collect::<Vec<_>>();
+    counts.sort_unstable();
+    let typical = counts.get(counts.len() / 2).copied().unwrap_or_default();
+
+    let mut by_node = Section::new("Nodes");
+    for (node, count, requests) in nodes {
+        let busiest = requests
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        let mut text = rate(count, minutes);
+        if let Some((request, times)) = busiest {
+            text.push_str(&format!(", mostly {} ({})", request, times));
+        }
+        if count as f64 > typical as f64 * OUTLIER {
+            by_node.alert = true;
+            text.push_str(&format!(
+                ", {:.0}x the typical node",
+                count as f64 / typical as f64
+            ));
+        }
+        by_node.row(node, text);
+    }
+
+    let mut sections = vec![clients];
+    if !by_node.rows.is_empty() {
+        sections.push(by_node);
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, groups: &[&str], verb: &str, second: u32) -> EventV1 {
+        let time = format!("2024-07-01T10:{:02}:{:02}Z", second / 60, second % 60);
+        test_event()
+            .uri("/api/v1/namespaces/default/pods")
+            .verb(verb)
+            .user(user)
+            .groups(groups)
+            .object_ref(serde_json::json!({"resource": "pods", "namespace": "default"}))
+            .at(time)
+            .build()
+    }
+
+    #[test]
+    fn clients() {
+        let nodes = ["system:nodes", "system:authenticated"];
+        let mut events = vec![
+            event("alice", &["system:authenticated"], "get", 60),
+            event("system:serviceaccount:kube-system:coredns", &[], "watch", 0),
+            event("system:kube-scheduler", &[], "create", 30),
+        ];
+        events.extend((0..2).map(|s| event("system:node:worker-1", &nodes, "get", s)));
+        events.extend((0..2).map(|s| event("system:node:worker-2", &nodes, "get", s)));
+        events.extend((0..20).map(|s| event("system:node:worker-3", &nodes, "list", s * 3)));
+        events.sort_by_key(|event| event.request_received_timestamp);
+        let events = events.iter().collect::<Vec<_>>();
+
+        assert_eq!(Client::of(events[26]), Client::Human);
+        let sections = sections(&events);
+        assert_eq!(
+            sections[0].rows,
+            [
+                ("nodes".to_string(), "24/min (24 requests, 89%)".to_string()),
+                (
+                    "controllers".to_string(),
+                    "2.0/min (2 requests, 7%)".to_string()
+                ),
+                ("humans".to_string(), "1.0/min (1 request, 4%)".to_string()),
+            ]
+        );
+        let nodes = &sections[1];
+        assert!(nodes.alert);
+        assert_eq!(
+            nodes.rows[0],
+            (
+                "worker-3".to_string(),
+                "20/min, mostly list pods (20), 10x the typical node".to_string()
+            )
+        );
+        assert_eq!(nodes.rows[1].0, "worker-1");
+    }
+}
@@ -0,0 +1,252 @@
+//! Comparing two captures, like before and after an upgrade, by what the
+//! users, verbs, resources and errors in them were doing and how often.
+
+use crate::kube::EventV1;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// How many changes each table has at most.
+const TOP: usize = 20;
+
+/// How many times more or less often something has to happen to count as a
+/// change.
+const FACTOR: f64 = 2.0;
+
+/// How many events something needs in at least one of the captures for a
+/// change in its rate to be worth telling of, rather than noise.
+const MIN_EVENTS: usize = 10;
+
+/// How something differs between the captures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// Only in the second capture.
+    New,
+    /// Only in the first capture.
+    Gone,
+    /// In both, but this many times as often in the second, per minute.
+    Rate(f64),
+}
+
+/// Something in the captures, with its events per minute in each.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub key: String,
+    pub change: Change,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// The changes in one thing about the events.
+#[derive(Debug, PartialEq)]
+pub struct Comparison {
+    pub title: &'static str,
+    pub rows: Vec<Row>,
+}
+
+/// Events per minute over the span of a capture, at least a second long so
+/// that a single event doesn't count as infinitely many.
+fn per_minute(events: &[&EventV1]) -> f64 {
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        return 0.0;
+    };
+    let span = last.request_received_timestamp - first.request_received_timestamp;
+    let seconds = (span.num_milliseconds() as f64 / 1000.0).max(1.0);
+    60.0 / seconds
+}
+
+fn compare(
+    title: &'static str,
+    before: &[&EventV1],
+    after: &[&EventV1],
+    key: impl Fn(&EventV1) -> Option<String>,
+) -> Comparison {
+    let count = |events: &[&EventV1]| {
+        let mut counts = HashMap::<String, usize>::new();
+        for key in events.iter().filter_map(|event| key(event)) {
+            *counts.entry(key).or_default() += 1;
+        }
+        counts
+    };
+    let (before_counts, after_counts) = (count(before), count(after));
+    let (before_rate, after_rate) = (per_minute(before), per_minute(after));
+    let keys = before_counts.keys().chain(after_counts.keys());
+    let mut rows = keys
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|key| {
+            let before_count = before_counts.get(key).copied().unwrap_or_default();
+            let after_count = after_counts.get(key).copied().unwrap_or_default();
+            let before = before_count as f64 * before_rate;
+            let after = after_count as f64 * after_rate;
+            let change = match (before_count, after_count) {
+                (0, _) => Change::New,
+                (_, 0) => Change::Gone,
+                _ if before_count.max(after_count) < MIN_EVENTS => return None,
+                _ => Change::Rate(after / before),
+            };
+            if let Change::Rate(ratio) = change {
+                if ratio < FACTOR && ratio > 1.0 / FACTOR {
+                    return None;
+                }
+            }
+            Some(Row {
+                key: key.clone(),
+                change,
+                before,
+                after,
+            })
+        })
+        .collect::<Vec<_>>();
+    // what appeared or went away first, then the biggest changes in rate
+    let rank = |row: &Row| match row.change {
+        Change::New | Change::Gone => f64::INFINITY,
+        Change::Rate(ratio) => ratio.ln().abs(),
+    };
+    rows.sort_by(|a, b| {
+        rank(b)
+            .total_cmp(&rank(a))
+            .then_with(|| b.before.max(b.after).total_cmp(&a.before.max(a.after)))
+    });
+    rows.truncate(TOP);
+    Comparison { title, rows }
+}
+
+fn resource(event: &EventV1) -> Option<String> {
+    let object = event.object_ref.as_ref()?;
+    let resource = object.resource.as_deref()?;
+    Some(match object.subresource.as_deref() {
+        Some(subresource) => format!("{}/{}", resource, subresource),
+        None => resource.to_string(),
+    })
+}
+
+/// How the events in `after` differ from those in `before`, each in time
+/// order.
+pub fn comparisons(before: &[&EventV1], after: &[&EventV1]) -> Vec<Comparison> {
+    vec![
+        compare("Users", before, after, |event| {
+            Some(event.user.username.clone())
+        }),
+        compare("Verbs", before, after, |event| Some(event.verb.clone())),
+        compare("Resources", before, after, resource),
+        compare("Errors", before, after, |event| {
+            let status = event.response_status.as_ref()?;
+            if status.code < 400 {
+                return None;
+            }
+            let resource = resource(event).unwrap_or_else(|| event.path().to_string());
+            Some(format!("{} {} {}", status.code, event.verb, resource))
+        }),
+    ]
+}
+
+fn rate(per_minute: f64) -> String {
+    match per_minute {
+        0.0 => "0".to_string(),
+        rate if rate < 10.0 => format!("{:.2}", rate),
+        rate => format!("{:.0}", rate),
+    }
+}
+
+/// The comparison as text tables, for the terminal.
+pub fn text(before: &[&EventV1], after: &[&EventV1]) -> String {
+    let mut text = String::new();
+    let _ = write_text(&mut text, before, after);
+    text
+}
+
+fn write_text(text: &mut String, before: &[&EventV1], after: &[&EventV1]) -> std::fmt::Result {
+    writeln!(
+        text,
+        "{} events before and {} after, in events per minute:",
+        before.len(),
+        after.len()
+    )?;
+    for comparison in comparisons(before, after) {
+        writeln!(text)?;
+        writeln!(text, "{}", comparison.title)?;
+        if comparison.rows.is_empty() {
+            writeln!(text, "  no changes")?;
+            continue;
+        }
+        let width = comparison.rows.iter().map(|row| row.key.len()).max();
+        let width = width.unwrap_or_default();
+        for row in &comparison.rows {
+            let change = match row.change {
+                Change::New => "new".to_string(),
+                Change::Gone => "gone".to_string(),
+                Change::Rate(ratio) if ratio >= 1.0 => format!("{:.1}x", ratio),
+                Change::Rate(ratio) => format!("1/{:.1}x", 1.0 / ratio),
+            };
+            writeln!(
+                text,
+                "  {:<width$}  {:<7} {:>8} -> {}",
+                row.key,
+                change,
+                rate(row.before),
+                rate(row.after),
+                width = width
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, verb: &str, code: i32, second: u32) -> EventV1 {
+        let time = format!("2024-07-01T10:{:02}:{:02}Z", second / 60, second % 60);
+        test_event()
+            .uri("/api/v1/namespaces/default/pods")
+            .verb(verb)
+            .user(user)
+            .object_ref(serde_json::json!({"resource": "pods", "namespace": "default"}))
+            .code(code)
+            .at(time)
+            .build()
+    }
+
+    #[test]
+    fn comparing() {
+        // a minute of alice listing once a second, and bob once
+        let mut before = (0..60)
+            .map(|second| event("alice", "list", 200, second))
+            .collect::<Vec<_>>();
+        before.push(event("bob", "get", 200, 30));
+        before.sort_by_key(|event| event.request_received_timestamp);
+        // half a minute of alice listing four times a second, and carol
+        // getting forbidden
+        let mut after = (0..120)
+            .map(|i| event("alice", "list", 200, i / 4))
+            .collect::<Vec<_>>();
+        after.push(event("carol", "get", 403, 30));
+        after.sort_by_key(|event| event.request_received_timestamp);
+        let before = before.iter().collect::<Vec<_>>();
+        let after = after.iter().collect::<Vec<_>>();
+
+        let comparisons = comparisons(&before, &after);
+        let users = &comparisons[0].rows;
+        let keys = users.iter().map(|row| row.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["carol", "bob", "alice"]);
+        assert_eq!(users[0].change, Change::New);
+        assert_eq!(users[1].change, Change::Gone);
+        let Change::Rate(ratio) = users[2].change else {
+            panic!("expected alice's rate to change");
+        };
+        assert!((ratio - 4.0).abs() < 0.2, "{}", ratio);
+        // too few gets in either to tell
+        let verbs = &comparisons[1].rows;
+        assert_eq!(
+            verbs.iter().map(|row| &row.key).collect::<Vec<_>>(),
+            ["list"]
+        );
+        assert_eq!(comparisons[3].rows[0].key, "403 get pods");
+
+        let text = text(&before, &after);
+        assert!(text.contains("61 events before and 121 after"));
+        assert!(text.contains("\nErrors\n  403 get pods  "));
+    }
+}
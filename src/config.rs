@@ -0,0 +1,196 @@
+//! The user's configuration file, `kale/config.toml` in the platform's config
+//! directory, which also holds anything saved from the TUI.
+
+use crate::theme::ThemeName;
+use crate::timestamp::{TimeFormat, Zone};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// The theme to draw with, unless `--theme` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeName>,
+    /// The time zone to show timestamps in, unless `--timezone` says
+    /// otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Zone>,
+    /// The format to show timestamps in, unless `--time-format` says
+    /// otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_format: Option<TimeFormat>,
+    /// How many seconds apart exact duplicates can be and still be hidden,
+    /// unless `--dedupe-window` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedupe_window: Option<u32>,
+    /// How many events a second to start sampling above, unless
+    /// `--sample-above` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_above: Option<u32>,
+    /// Keep one in how many of each noisy read while sampling, unless
+    /// `--sample-one-in` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_one_in: Option<u32>,
+    /// How many minor versions behind the apiserver clients can be before
+    /// they're outdated, unless `kale outdated --skew` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skew: Option<u32>,
+    /// The command to offer to pipe events to with `|`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipe_command: Option<String>,
+    /// The MaxMind database to look up the countries of external source IPs
+    /// in, unless `--geoip` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geoip: Option<PathBuf>,
+    /// Networks to tag source IPs with by name, each with its CIDRs.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub networks: BTreeMap<String, Vec<String>>,
+    /// Namespaces people shouldn't change by hand, to flag it when they do.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_glass: Option<BreakGlassRule>,
+    /// Filters to alert on events matching as they arrive.
+    #[serde(rename = "alert", skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<AlertRule>,
+    /// How many requests a user can make in how long before they're listed
+    /// as a candidate for rate limiting.
+    #[serde(rename = "rate_limit", skip_serializing_if = "Vec::is_empty")]
+    pub rate_limits: Vec<RateLimit>,
+    /// Functions over events to show as columns and filter on.
+    #[serde(rename = "script", skip_serializing_if = "Vec::is_empty")]
+    pub scripts: Vec<NamedScript>,
+    /// Filters saved by name, in the order they were saved.
+    #[serde(rename = "search", skip_serializing_if = "Vec::is_empty")]
+    pub searches: Vec<SavedSearch>,
+}
+
+/// A filter expression saved under a name.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub filter: String,
+}
+
+/// A filter expression to alert on, with a name to tell of it by.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub filter: String,
+    /// Whether to ring the terminal bell too.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bell: bool,
+}
+
+/// A request rate, as how many requests in a window of how many seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct RateLimit {
+    pub seconds: u32,
+    pub requests: usize,
+}
+
+/// Namespaces only automation should change, and the people allowed to all
+/// the same.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BreakGlassRule {
+    /// The namespaces, each of which can end in `*` to match any starting
+    /// with the rest.
+    pub namespaces: Vec<String>,
+    /// The users who can change them without it being flagged.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+    /// Whether to ring the terminal bell too.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bell: bool,
+}
+
+/// A Rhai script, to be shown and filtered on as `script[name]`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NamedScript {
+    pub name: String,
+    pub script: String,
+}
+
+impl Config {
+    /// Where the config file lives by default, if the platform has a config
+    /// directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("kale").join("config.toml"))
+    }
+
+    /// Read the config file, or the defaults if it doesn't exist yet.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+        };
+        let config =
+            toml::from_str::<Self>(&text).with_context(|| format!("parsing {}", path.display()))?;
+        config
+            .check()
+            .with_context(|| format!("checking {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Check what parsing alone can't, like rate limits that could never be
+    /// met.
+    fn check(&self) -> anyhow::Result<()> {
+        for limit in &self.rate_limits {
+            anyhow::ensure!(
+                limit.seconds > 0 && limit.requests > 0,
+                "rate limits need at least one request in at least one second, not {} in {}s",
+                limit.requests,
+                limit.seconds
+            );
+        }
+        Ok(())
+    }
+
+    /// Write the config file, creating its directory if need be.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let text = toml::to_string_pretty(self)?;
+        std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Save a filter under `name`, replacing any search already called that.
+    pub fn save_search(&mut self, name: impl Into<String>, filter: impl Into<String>) {
+        let search = SavedSearch {
+            name: name.into(),
+            filter: filter.into(),
+        };
+        match self.searches.iter_mut().find(|s| s.name == search.name) {
+            Some(existing) => *existing = search,
+            None => self.searches.push(search),
+        }
+    }
+
+    pub fn search(&self, name: &str) -> Option<&SavedSearch> {
+        self.searches.iter().find(|s| s.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_rate_limits() {
+        let dir = std::env::temp_dir().join(format!("kale-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        for (limit, valid) in [
+            ("seconds = 10\nrequests = 100", true),
+            ("seconds = 0\nrequests = 100", false),
+            ("seconds = 10\nrequests = 0", false),
+        ] {
+            std::fs::write(&path, format!("[[rate_limit]]\n{}\n", limit)).unwrap();
+            assert_eq!(Config::load(&path).is_ok(), valid, "{}", limit);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,329 @@
+//! Requests that hand out credentials: certificate signing requests and their
+//! approvals, service account tokens and token reviews, described from their
+//! bodies for reviewing who got access to what.
+
+use crate::detail::Section;
+use crate::kube::{EventV1, Stage};
+use crate::timestamp::Timestamps;
+use base64::Engine;
+use serde_json::Value;
+use std::net::IpAddr;
+use x509_parser::certification_request::X509CertificationRequest;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::pem::parse_x509_pem;
+use x509_parser::prelude::FromDer;
+
+/// The kinds of request that hand out credentials, in the order they're shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    CertificateRequest,
+    Approval,
+    Token,
+    TokenReview,
+}
+
+impl Kind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Kind::CertificateRequest => "Certificate signing requests",
+            Kind::Approval => "Certificate approvals",
+            Kind::Token => "Service account tokens",
+            Kind::TokenReview => "Token reviews",
+        }
+    }
+}
+
+/// Which kind of credential request the event is, if any.
+pub fn kind(event: &EventV1) -> Option<Kind> {
+    let object = event.object_ref.as_ref()?;
+    let resource = object.resource.as_deref()?;
+    let subresource = object.subresource.as_deref();
+    Some(match (resource, subresource, event.verb.as_str()) {
+        ("certificatesigningrequests", None, "create") => Kind::CertificateRequest,
+        ("certificatesigningrequests", Some("approval"), "update" | "patch") => Kind::Approval,
+        ("serviceaccounts", Some("token"), "create") => Kind::Token,
+        ("tokenreviews", None, "create") => Kind::TokenReview,
+        _ => return None,
+    })
+}
+
+/// The subject and subject alternative names of a certificate signing
+/// request, from the base64 PEM of `spec.request`.
+pub fn subject(request: &str) -> Option<String> {
+    let pem = base64::engine::general_purpose::STANDARD
+        .decode(request)
+        .ok()?;
+    let (_, pem) = parse_x509_pem(&pem).ok()?;
+    let (_, csr) = X509CertificationRequest::from_der(&pem.contents).ok()?;
+    let mut subject = csr.certification_request_info.subject.to_string();
+    let names = csr
+        .requested_extensions()
+        .into_iter()
+        .flatten()
+        .filter_map(|extension| match extension {
+            ParsedExtension::SubjectAlternativeName(names) => Some(&names.general_names),
+            _ => None,
+        })
+        .flatten()
+        .map(|name| match name {
+            GeneralName::DNSName(name) => name.to_string(),
+            GeneralName::IPAddress(&[a, b, c, d]) => IpAddr::from([a, b, c, d]).to_string(),
+            GeneralName::IPAddress(bytes) => <[u8; 16]>::try_from(*bytes)
+                .map(|bytes| IpAddr::from(bytes).to_string())
+                .unwrap_or_else(|_| name.to_string()),
+            name => name.to_string(),
+        })
+        .collect::<Vec<_>>();
+    if !names.is_empty() {
+        subject.push_str(&format!(" (also {})", names.join(", ")));
+    }
+    Some(subject)
+}
+
+fn strings(value: &Value) -> Vec<&str> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+/// What the request asked for and who asked, in a line.
+pub fn describe(event: &EventV1) -> Option<(Kind, String)> {
+    let kind = kind(event)?;
+    let user = &event.user.username;
+    let name = event.object_ref.as_ref().and_then(|o| o.name.as_deref());
+    let request = event.request_object.as_ref().unwrap_or(&Value::Null);
+    let response = event.response_object.as_ref().unwrap_or(&Value::Null);
+    let spec = &request["spec"];
+    let mut text = match kind {
+        Kind::CertificateRequest => {
+            let name = name.or(response["metadata"]["name"].as_str());
+            let mut text = format!("{} asked for {}", user, name.unwrap_or("a certificate"));
+            if let Some(subject) = spec["request"].as_str().and_then(subject) {
+                text.push_str(&format!(" as {}", subject));
+            }
+            if let Some(signer) = spec["signerName"].as_str() {
+                text.push_str(&format!(" from {}", signer));
+            }
+            text
+        }
+        Kind::Approval => {
+            let conditions = request["status"]["conditions"].as_array();
+            let decisions = conditions
+                .into_iter()
+                .flatten()
+                .filter_map(|condition| {
+                    let decision = match condition["type"].as_str()? {
+                        "Approved" => "approved",
+                        "Denied" => "denied",
+                        _ => return None,
+                    };
+                    Some(match condition["reason"].as_str() {
+                        Some(reason) => format!("{} ({})", decision, reason),
+                        None => decision.to_string(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let decision = match decisions.is_empty() {
+                true => "decided on".to_string(),
+                false => decisions.join(" and "),
+            };
+            format!("{} {} {}", user, decision, name.unwrap_or("a certificate"))
+        }
+        Kind::Token => {
+            let account = event.object_ref.as_ref().map(|object| {
+                let namespace = object.namespace.as_deref().unwrap_or_default();
+                format!("{}/{}", namespace, name.unwrap_or_default())
+            });
+            let mut text = format!(
+                "{} got a token for {}",
+                user,
+                account.as_deref().unwrap_or("a service account")
+            );
+            let audiences = strings(&spec["audiences"]);
+            if !audiences.is_empty() {
+                text.push_str(&format!(" for {}", audiences.join(", ")));
+            }
+            let bound = &spec["boundObjectRef"];
+            if let (Some(kind), Some(name)) = (bound["kind"].as_str(), bound["name"].as_str()) {
+                text.push_str(&format!(" bound to {}/{}", kind, name));
+            }
+            if let Some(seconds) = spec["expirationSeconds"].as_u64() {
+                text.push_str(&format!(" for {}s", seconds));
+            }
+            text
+        }
+        Kind::TokenReview => {
+            let mut text = format!("{} reviewed a token", user);
+            let audiences = strings(&spec["audiences"]);
+            if !audiences.is_empty() {
+                text.push_str(&format!(" for {}", audiences.join(", ")));
+            }
+            let status = &response["status"];
+            match (
+                status["authenticated"].as_bool(),
+                status["user"]["username"].as_str(),
+            ) {
+                (Some(true), Some(username)) => text.push_str(&format!(": {}", username)),
+                (Some(false), _) => text.push_str(": not authenticated"),
+                _ => {}
+            }
+            text
+        }
+    };
+    if event.request_object.is_none() {
+        text.push_str(", the body wasn't logged");
+    }
+    if let Some(status) = event.response_status.as_ref().filter(|s| s.is_failure()) {
+        text.push_str(&format!(", failed with {}", status.code));
+    }
+    Some((kind, text))
+}
+
+/// The credential requests among the events, a section for each kind, each
+/// row the time of a request and what it did, marking those with any that
+/// failed. Only the last stage of each
+/// request is told of, leaving out those that were only received.
+pub fn sections<'a>(
+    events: impl IntoIterator<Item = &'a EventV1>,
+    timestamps: &Timestamps,
+) -> Vec<Section> {
+    let mut described = events
+        .into_iter()
+        .filter(|event| event.stage != Stage::RequestReceived)
+        .filter_map(|event| {
+            let (kind, text) = describe(event)?;
+            let failed = event
+                .response_status
+                .as_ref()
+                .is_some_and(|s| s.is_failure());
+            let time = timestamps.format(event.request_received_timestamp);
+            Some((kind, time, text, failed))
+        })
+        .collect::<Vec<_>>();
+    described.sort_by_key(|(kind, _, _, _)| *kind);
+
+    let mut sections: Vec<(Kind, Section)> = Vec::new();
+    for (kind, time, text, failed) in described {
+        if sections.last().map(|(last, _)| *last) != Some(kind) {
+            sections.push((kind, Section::new(kind.title())));
+        }
+        if let Some((_, section)) = sections.last_mut() {
+            section.alert |= failed;
+            section.row(time, text);
+        }
+    }
+    sections.into_iter().map(|(_, section)| section).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    /// A request for `O=system:nodes, CN=system:node:worker-1` with the names
+    /// `worker-1` and `10.0.0.5`.
+    const CSR: &str = "LS0tLS1CRUdJTiBDRVJUSUZJQ0FURSBSRVFVRVNULS0tLS0KTUlJQkhqQ0J4QUlCQURBMk1SVXdFd1lEVlFRS0RBeHplWE4wWlcwNmJtOWtaWE14SFRBYkJnTlZCQU1NRkhONQpjM1JsYlRwdWIyUmxPbmR2Y210bGNpMHhNRmt3RXdZSEtvWkl6ajBDQVFZSUtvWkl6ajBEQVFjRFFnQUVQMW5ZCnV6dTkzNkQwYkptMzdqbHovaW5ORmhLSktNSE0zQitBTzI1VEk3S2RKUE1LeUdKeklYZUxqZ016UUVCcjFwbXcKdWUyMWR4UGVrUkVYbzF4ZldhQXNNQ29HQ1NxR1NJYjNEUUVKRGpFZE1Cc3dHUVlEVlIwUkJCSXdFSUlJZDI5eQphMlZ5TFRHSEJBb0FBQVV3Q2dZSUtvWkl6ajBFQXdJRFNRQXdSZ0loQUtlbVh6N1BGUk9aVVVhdllWMGJKVmtsCm83b3hjaXo1NUliS1lqMzkyeDRmQWlFQXhvSUR2eEVoTEpDNURjMUJicnZzelVjbU1ta2l1YUVqZ0cyNkl5ZXYKSmRjPQotLS0tLUVORCBDRVJUSUZJQ0FURSBSRVFVRVNULS0tLS0K";
+
+    fn event(
+        verb: &str,
+        uri: &str,
+        object_ref: Value,
+        request: Option<Value>,
+        response: Option<Value>,
+    ) -> EventV1 {
+        test_event()
+            .level("RequestResponse")
+            .uri(uri)
+            .verb(verb)
+            .object_ref(object_ref)
+            .code(201)
+            .request_object(request)
+            .response_object(response)
+            .build()
+    }
+
+    #[test]
+    fn describing() {
+        let csr = event(
+            "create",
+            "/apis/certificates.k8s.io/v1/certificatesigningrequests",
+            serde_json::json!({"resource": "certificatesigningrequests", "apiGroup": "certificates.k8s.io"}),
+            Some(
+                serde_json::json!({"spec": {"request": CSR, "signerName": "kubernetes.io/kubelet-serving"}}),
+            ),
+            Some(serde_json::json!({"metadata": {"name": "csr-x7k2p"}})),
+        );
+        assert_eq!(
+            describe(&csr),
+            Some((
+                Kind::CertificateRequest,
+                "alice asked for csr-x7k2p as O=system:nodes, CN=system:node:worker-1 (also worker-1, 10.0.0.5) from kubernetes.io/kubelet-serving".to_string()
+            ))
+        );
+
+        let approval = event(
+            "update",
+            "/apis/certificates.k8s.io/v1/certificatesigningrequests/csr-x7k2p/approval",
+            serde_json::json!({"resource": "certificatesigningrequests", "subresource": "approval", "name": "csr-x7k2p"}),
+            Some(
+                serde_json::json!({"status": {"conditions": [{"type": "Approved", "reason": "AutoApproved"}]}}),
+            ),
+            None,
+        );
+        assert_eq!(
+            describe(&approval).unwrap().1,
+            "alice approved (AutoApproved) csr-x7k2p"
+        );
+
+        let token = event(
+            "create",
+            "/api/v1/namespaces/ci/serviceaccounts/deployer/token",
+            serde_json::json!({"resource": "serviceaccounts", "subresource": "token", "namespace": "ci", "name": "deployer"}),
+            Some(
+                serde_json::json!({"spec": {"audiences": ["vault"], "expirationSeconds": 3600, "boundObjectRef": {"kind": "Pod", "name": "runner-0"}}}),
+            ),
+            None,
+        );
+        assert_eq!(
+            describe(&token).unwrap().1,
+            "alice got a token for ci/deployer for vault bound to Pod/runner-0 for 3600s"
+        );
+
+        let review = event(
+            "create",
+            "/apis/authentication.k8s.io/v1/tokenreviews",
+            serde_json::json!({"resource": "tokenreviews", "apiGroup": "authentication.k8s.io"}),
+            None,
+            None,
+        );
+        assert_eq!(
+            describe(&review).unwrap().1,
+            "alice reviewed a token, the body wasn't logged"
+        );
+        let pods = event(
+            "create",
+            "/api/v1/namespaces/ci/pods",
+            serde_json::json!({"resource": "pods"}),
+            None,
+            None,
+        );
+        assert!(describe(&pods).is_none());
+
+        let sections = sections([&review, &token, &csr], &Timestamps::default());
+        let titles = sections
+            .iter()
+            .map(|s| s.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            [
+                "Certificate signing requests",
+                "Service account tokens",
+                "Token reviews"
+            ]
+        );
+    }
+}
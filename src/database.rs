@@ -0,0 +1,186 @@
+//! Saving the events read to a SQLite file, and loading them back from it, so
+//! that reading a huge capture only has to be done once.
+
+use crate::kube::EventV1;
+use anyhow::Context;
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+
+/// How many events to wait for before writing them all in one transaction.
+const SAVE_BATCH: usize = 1000;
+
+/// The events are kept whole as JSON, with the fields most worth querying
+/// alongside for using the file with other tools.
+const SCHEMA: &str = "
+    CREATE TABLE events (
+        id INTEGER PRIMARY KEY,
+        -- microseconds since the epoch
+        received INTEGER NOT NULL,
+        user TEXT NOT NULL,
+        verb TEXT NOT NULL,
+        namespace TEXT,
+        resource TEXT,
+        -- the label of the input the event was read from
+        source TEXT,
+        event TEXT NOT NULL
+    );
+    CREATE INDEX events_received ON events (received);
+    CREATE INDEX events_user ON events (user);
+    CREATE INDEX events_resource ON events (resource);
+";
+
+/// A SQLite file events are being saved to.
+pub struct Database {
+    path: PathBuf,
+    connection: Connection,
+    /// Events waiting to be written, with when each was received.
+    pending: Vec<(i64, EventV1)>,
+}
+
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("path", &self.path)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+impl Database {
+    /// Create a new SQLite file at `path` to save events to, refusing to
+    /// overwrite one that's already there.
+    pub fn create(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            anyhow::bail!("{} already exists", path.display());
+        }
+        let flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE;
+        let connection = Connection::open_with_flags(&path, flags)
+            .and_then(|connection| connection.execute_batch(SCHEMA).map(|_| connection))
+            .with_context(|| format!("creating {}", path.display()))?;
+        Ok(Self {
+            path,
+            connection,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Save an event, once enough are waiting to be worth a transaction or
+    /// [`Database::flush`] is called.
+    pub fn insert(&mut self, event: &EventV1) -> anyhow::Result<()> {
+        let received = event.request_received_timestamp.timestamp_micros();
+        self.pending.push((received, event.clone()));
+        if self.pending.len() >= SAVE_BATCH {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write every event waiting to be saved.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO events (received, user, verb, namespace, resource, source, event)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (received, event) in &self.pending {
+                let object = event.object_ref.as_ref();
+                insert.execute(params![
+                    received,
+                    event.user.username,
+                    event.verb,
+                    object.and_then(|object| object.namespace.as_deref()),
+                    object.and_then(|object| object.resource.as_deref()),
+                    event.source,
+                    serde_json::to_string(event)?,
+                ])?;
+            }
+        }
+        transaction
+            .commit()
+            .with_context(|| format!("writing {}", self.path.display()))?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// Read every event saved in the SQLite file at `path` in the order they were
+/// received, handing each to `each`, and returning how many there were.
+pub fn read(
+    path: &Path,
+    mut each: impl FnMut(EventV1) -> anyhow::Result<()>,
+) -> anyhow::Result<usize> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let mut select = connection
+        .prepare("SELECT event, source FROM events ORDER BY received, id")
+        .with_context(|| format!("{} is not a saved session", path.display()))?;
+    let mut rows = select.query([])?;
+    let mut count = 0;
+    while let Some(row) = rows.next()? {
+        let json = row.get_ref(0)?.as_str()?;
+        let mut event: EventV1 = serde_json::from_str(json)
+            .with_context(|| format!("reading event {} of {}", count + 1, path.display()))?;
+        event.source = row.get(1)?;
+        each(event)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(uri: &str, timestamp: &str, source: Option<&str>) -> EventV1 {
+        let mut event = test_event()
+            .uri(uri)
+            .verb("get")
+            .object_ref(serde_json::json!({"resource": "pods", "namespace": "default"}))
+            .at(timestamp)
+            .build();
+        event.source = source.map(str::to_string);
+        event
+    }
+
+    #[test]
+    fn saving_and_reading() {
+        let dir = std::env::temp_dir().join(format!("kale-database-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.db");
+        let _ = std::fs::remove_file(&path);
+
+        let mut database = Database::create(&path).unwrap();
+        let web = "/api/v1/namespaces/default/pods/web";
+        database
+            .insert(&event(web, "2024-07-01T10:00:01Z", Some("prod")))
+            .unwrap();
+        database
+            .insert(&event("/api/v1/pods", "2024-07-01T10:00:00Z", None))
+            .unwrap();
+        database.flush().unwrap();
+        assert!(Database::create(&path).is_err());
+
+        let mut events = Vec::new();
+        let count = read(&path, |event| {
+            events.push((event.request_uri, event.source));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            events,
+            [
+                ("/api/v1/pods".to_string(), None),
+                (web.to_string(), Some("prod".to_string()))
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+//! Hiding requests repeated exactly, like those of a client retrying over and
+//! over, while counting how many each shown request stands for.
+
+use crate::kube::EventV1;
+use crate::store::EventStore;
+use chrono::Duration;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+/// How far apart repeats can be and still be hidden, unless configured
+/// otherwise.
+pub const DEFAULT_WINDOW: Duration = Duration::seconds(10);
+
+/// What has to be the same for two events to be duplicates.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Key<'a> {
+    user: &'a str,
+    verb: &'a str,
+    uri: &'a str,
+    /// A hash of the request body, rather than the body itself, to keep the
+    /// keys small.
+    body: u64,
+}
+
+impl<'a> Key<'a> {
+    fn of(event: &'a EventV1) -> Self {
+        let mut hasher = DefaultHasher::new();
+        if let Some(body) = &event.request_object {
+            body.to_string().hash(&mut hasher);
+        }
+        Self {
+            user: &event.user.username,
+            verb: &event.verb,
+            uri: &event.request_uri,
+            body: hasher.finish(),
+        }
+    }
+}
+
+/// Which events are shown in place of their duplicates, and how many of them.
+#[derive(Debug)]
+pub struct Dedupe {
+    window: Duration,
+    /// How many duplicates are hidden, by the index into the store of the
+    /// event shown in their place.
+    counts: BTreeMap<usize, usize>,
+}
+
+impl Dedupe {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Drop from `indices`, which are in time order, the events duplicating
+    /// one kept within the window before them, counting them against it.
+    pub fn dedupe(&mut self, store: &EventStore, indices: Vec<usize>) -> Vec<usize> {
+        self.counts.clear();
+        let mut kept = HashMap::new();
+        indices
+            .into_iter()
+            .filter(|i| {
+                let event = &store[*i];
+                let key = Key::of(event);
+                match kept.get(&key) {
+                    Some(original)
+                        if event.request_received_timestamp
+                            - store[*original].request_received_timestamp
+                            <= self.window =>
+                    {
+                        *self.counts.entry(*original).or_default() += 1;
+                        false
+                    }
+                    _ => {
+                        kept.insert(key, *i);
+                        true
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the event inserted into the store at `index` duplicates one
+    /// of the `visible` events within the window before it, counting it if so.
+    pub fn duplicate(&mut self, store: &EventStore, visible: &[usize], index: usize) -> bool {
+        let event = &store[index];
+        let key = Key::of(event);
+        let position = visible.partition_point(|i| *i < index);
+        let original = visible[..position]
+            .iter()
+            .rev()
+            .take_while(|i| {
+                event.request_received_timestamp - store[**i].request_received_timestamp
+                    <= self.window
+            })
+            .find(|i| Key::of(&store[**i]) == key);
+        match original {
+            Some(original) => {
+                *self.counts.entry(*original).or_default() += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Account for an event having been inserted into the store at `index`.
+    pub fn inserted(&mut self, index: usize) {
+        if self.counts.range(index..).next().is_some() {
+            self.counts = std::mem::take(&mut self.counts)
+                .into_iter()
+                .map(|(i, count)| (if i >= index { i + 1 } else { i }, count))
+                .collect();
+        }
+    }
+
+    /// How many duplicates of the event at `index` in the store are hidden.
+    pub fn count(&self, index: usize) -> usize {
+        self.counts.get(&index).copied().unwrap_or_default()
+    }
+
+    /// How many duplicates are hidden altogether.
+    pub fn hidden(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(uri: &str, timestamp: &str, body: Option<serde_json::Value>) -> EventV1 {
+        test_event()
+            .level("Request")
+            .uri(uri)
+            .verb("create")
+            .request_object(body)
+            .at(timestamp)
+            .build()
+    }
+
+    #[test]
+    fn deduping() {
+        let pods = "/api/v1/namespaces/default/pods";
+        let web = serde_json::json!({"metadata": {"name": "web"}});
+        let db = serde_json::json!({"metadata": {"name": "db"}});
+        let mut store = EventStore::new();
+        store.insert(event(pods, "2024-07-01T10:00:00Z", Some(web.clone())));
+        store.insert(event(pods, "2024-07-01T10:00:01Z", Some(web.clone())));
+        store.insert(event(pods, "2024-07-01T10:00:02Z", Some(db)));
+        store.insert(event(pods, "2024-07-01T10:00:05Z", Some(web.clone())));
+        store.insert(event(pods, "2024-07-01T10:00:30Z", Some(web.clone())));
+
+        let mut dedupe = Dedupe::new(Duration::seconds(10));
+        let visible = dedupe.dedupe(&store, (0..store.len()).collect());
+        assert_eq!(visible, [0, 2, 4]);
+        assert_eq!(dedupe.count(0), 2);
+        assert_eq!(dedupe.count(4), 0);
+        assert_eq!(dedupe.hidden(), 2);
+
+        let index = store.insert(event(pods, "2024-07-01T10:00:35Z", Some(web)));
+        dedupe.inserted(index);
+        let visible = [0, 2, 4];
+        assert!(dedupe.duplicate(&store, &visible, index));
+        assert_eq!(dedupe.count(4), 1);
+        let index = store.insert(event("/api/v1/pods", "2024-07-01T09:00:00Z", None));
+        dedupe.inserted(index);
+        let visible = [1, 3, 5];
+        assert!(!dedupe.duplicate(&store, &visible, index));
+        assert_eq!(dedupe.count(5), 1);
+        assert_eq!(dedupe.hidden(), 3);
+    }
+}
@@ -0,0 +1,572 @@
+//! Everything known about a single event, laid out as labelled sections for
+//! the detail view.
+
+use crate::kube::{EventV1, Scope, UserInfo};
+use crate::path::JsonPath;
+use crate::timestamp::Timestamps;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Labels for the annotations set by Kubernetes itself, in the order they are
+/// shown in. Any others are shown after these under their keys.
+const ANNOTATION_LABELS: &[(&str, &str)] = &[
+    ("authorization.k8s.io/decision", "Authorization"),
+    ("authorization.k8s.io/reason", "Authorization Reason"),
+    (
+        "pod-security.kubernetes.io/enforce-policy",
+        "Pod Security Policy",
+    ),
+    (
+        "pod-security.kubernetes.io/exempt",
+        "Pod Security Exemption",
+    ),
+    (
+        "pod-security.kubernetes.io/audit-violations",
+        "Pod Security Violations",
+    ),
+    (
+        "validation.policy.admission.k8s.io/validation_failure",
+        "Admission Policy Failure",
+    ),
+    ("k8s.io/deprecated", "Deprecated API"),
+    ("k8s.io/removed-release", "Removed In Release"),
+    ("apiserver.latency.k8s.io/total", "Total Latency"),
+    ("apiserver.latency.k8s.io/etcd", "etcd Latency"),
+    (
+        "apiserver.latency.k8s.io/serialize-response-object",
+        "Serialize Latency",
+    ),
+    (
+        "apiserver.latency.k8s.io/response-write",
+        "Response Write Latency",
+    ),
+];
+
+/// The prefix of the annotations the apiserver leaves on how long parts of a
+/// request took.
+const LATENCY: &str = "apiserver.latency.k8s.io/";
+
+/// How many characters wide the bars of the timing section are at most.
+const BAR_WIDTH: usize = 30;
+
+/// What the apiserver's non-resource URLs are for, by the start of their
+/// path, and whether reaching one is worth a closer look.
+const NON_RESOURCE: &[(&str, &str, bool)] = &[
+    ("/healthz", "health check", false),
+    ("/livez", "liveness check", false),
+    ("/readyz", "readiness check", false),
+    ("/version", "server version", false),
+    ("/openid/", "service account issuer keys", false),
+    (
+        "/.well-known/openid-configuration",
+        "service account issuer discovery",
+        false,
+    ),
+    ("/openapi/", "OpenAPI schema", false),
+    ("/api", "API discovery", false),
+    ("/metrics", "apiserver metrics", true),
+    ("/logs", "control plane node logs", true),
+    ("/debug/", "debugging and profiling", true),
+];
+
+/// A titled group of label and value pairs.
+#[derive(Debug, PartialEq)]
+pub struct Section {
+    pub title: String,
+    pub rows: Vec<(String, String)>,
+    /// Whether the section tells of something going wrong.
+    pub alert: bool,
+}
+
+impl Section {
+    pub(crate) fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            rows: Vec::new(),
+            alert: false,
+        }
+    }
+
+    pub(crate) fn row(&mut self, label: impl Into<String>, value: impl ToString) {
+        self.rows.push((label.into(), value.to_string()));
+    }
+
+    /// Add a row only if there is a value for it.
+    fn optional(&mut self, label: impl Into<String>, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            self.row(label, value);
+        }
+    }
+}
+
+/// Every field of the event, in sections, leaving out the request and
+/// response objects and any section with nothing in it. `stages` are the
+/// events of every stage of the same request, including this one, for how
+/// long it took to get to each.
+pub fn sections(event: &EventV1, stages: &[&EventV1], timestamps: &Timestamps) -> Vec<Section> {
+    let mut sections = Vec::new();
+
+    let mut request = Section::new("Request");
+    request.row("Audit ID", &event.audit_id);
+    request.optional("Cluster", event.source.as_ref());
+    request.row("Level", event.level);
+    request.row("Stage", event.stage);
+    request.row("Verb", &event.verb);
+    if event.is_dry_run() {
+        request.row("Dry Run", "yes, nothing was persisted");
+    }
+    request.optional("Applied By", event.applied_by());
+    request.row("Request URI", &event.request_uri);
+    request.row(
+        "Received",
+        timestamps.format(event.request_received_timestamp),
+    );
+    request.row(
+        "Stage Time",
+        format!(
+            "{} (+{}ms)",
+            timestamps.format(event.stage_timestamp),
+            (event.stage_timestamp - event.request_received_timestamp).num_milliseconds()
+        ),
+    );
+    sections.push(request);
+    sections.push(timing(stages));
+
+    let mut query = Section::new("Query");
+    for (key, value) in event.query() {
+        query.row(key, value);
+    }
+    sections.push(query);
+
+    sections.push(user("User", &event.user));
+    if let Some(impersonated) = &event.impersonated_user {
+        sections.push(user("Impersonated User", impersonated));
+    }
+
+    let mut client = Section::new("Client");
+    client.optional(
+        "Source IPs",
+        event.source_ips.as_ref().map(|ips| {
+            ips.iter()
+                .map(|ip| ip.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }),
+    );
+    client.optional("User Agent", event.user_agent.as_ref());
+    sections.push(client);
+
+    if event.scope() == Scope::NonResource {
+        sections.push(non_resource(event));
+    }
+    let mut object = Section::new("Object");
+    if let Some(object_ref) = &event.object_ref {
+        object.optional("Resource", object_ref.resource.as_ref());
+        object.optional("Subresource", object_ref.subresource.as_ref());
+        object.optional("Namespace", object_ref.namespace.as_ref());
+        object.optional("Name", object_ref.name.as_ref());
+        object.optional("API Group", object_ref.api_group.as_ref());
+        object.optional("API Version", object_ref.api_version.as_ref());
+        object.optional("UID", object_ref.uid.as_ref());
+        object.optional("Resource Version", object_ref.resource_version.as_ref());
+    }
+    sections.push(object);
+
+    let mut status = Section::new("Response Status");
+    if let Some(response) = &event.response_status {
+        status.alert = response.is_failure();
+        status.row("Code", response.code);
+        status.optional("Status", response.status.as_ref());
+        status.optional("Reason", response.reason.as_ref());
+        status.optional("Message", response.message.as_ref());
+        if let Some(details) = &response.details {
+            status.optional("Details Kind", details.kind.as_ref());
+            status.optional("Details Group", details.group.as_ref());
+            status.optional("Details Name", details.name.as_ref());
+            status.optional("Details UID", details.uid.as_ref());
+            status.optional(
+                "Retry After",
+                details.retry_after_seconds.map(|s| format!("{}s", s)),
+            );
+            for cause in details.causes.iter().flatten() {
+                let text = [&cause.reason, &cause.field, &cause.message]
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(": ");
+                status.row("Cause", text);
+            }
+        }
+    }
+    sections.push(status);
+
+    let mut annotations = Section::new("Annotations");
+    for (key, label) in ANNOTATION_LABELS {
+        annotations.optional(*label, event.annotations.get(*key));
+    }
+    let mut keys = event
+        .annotations
+        .keys()
+        .filter(|key| !ANNOTATION_LABELS.iter().any(|(known, _)| known == key))
+        .collect::<Vec<_>>();
+    keys.sort();
+    for key in keys {
+        annotations.row(key, &event.annotations[key]);
+    }
+    sections.push(annotations);
+
+    let mut enriched = Section::new("Enriched");
+    for (key, value) in &event.enriched {
+        enriched.row(key, value);
+    }
+    sections.push(enriched);
+
+    sections.retain(|section| !section.rows.is_empty());
+    sections
+}
+
+/// How many milliseconds a Go duration like `1m2.5s` or `532.1ms` is.
+fn milliseconds(duration: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut rest = duration;
+    while !rest.is_empty() {
+        let number = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|end| *end > 0)?;
+        let value = rest[..number].parse::<f64>().ok()?;
+        rest = &rest[number..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit] {
+            "h" => 3_600_000.0,
+            "m" => 60_000.0,
+            "s" => 1_000.0,
+            "ms" => 1.0,
+            "us" | "µs" | "μs" => 0.001,
+            "ns" => 0.000_001,
+            _ => return None,
+        };
+        total += value * scale;
+        rest = &rest[unit..];
+    }
+    Some(total)
+}
+
+/// A waterfall of when each stage of the request was reached after it was
+/// received, then how long the parts of it the apiserver times took, longest
+/// first, each with a bar to scale.
+fn timing(stages: &[&EventV1]) -> Section {
+    let mut section = Section::new("Timing");
+    let mut reached = stages
+        .iter()
+        .map(|event| {
+            let elapsed = event.stage_timestamp - event.request_received_timestamp;
+            (
+                event.stage,
+                elapsed.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0,
+            )
+        })
+        .collect::<Vec<_>>();
+    reached.sort_by(|a, b| a.1.total_cmp(&b.1));
+    reached.dedup_by_key(|(stage, _)| *stage);
+
+    let mut latencies = BTreeMap::<&str, (&str, f64)>::new();
+    for event in stages {
+        for (key, value) in &event.annotations {
+            if let (Some(part), Some(ms)) = (key.strip_prefix(LATENCY), milliseconds(value)) {
+                latencies.insert(part, (value, ms));
+            }
+        }
+    }
+    let mut latencies = latencies.into_iter().collect::<Vec<_>>();
+    latencies.sort_by(|a, b| b.1 .1.total_cmp(&a.1 .1));
+    if reached.len() < 2 && latencies.is_empty() {
+        return section;
+    }
+
+    let longest = reached
+        .iter()
+        .map(|(_, ms)| *ms)
+        .chain(latencies.iter().map(|(_, (_, ms))| *ms))
+        .fold(0.0, f64::max);
+    let bar = |ms: f64| {
+        let width = match longest > 0.0 {
+            true => (ms / longest * BAR_WIDTH as f64).round() as usize,
+            false => 0,
+        };
+        let width = if ms > 0.0 { width.max(1) } else { width };
+        format!("{:<width$}", "█".repeat(width), width = BAR_WIDTH)
+    };
+    for (stage, ms) in reached {
+        section.row(stage.to_string(), format!("{} +{:.0}ms", bar(ms), ms));
+    }
+    for (part, (text, ms)) in latencies {
+        section.row(part, format!("{} {}", bar(ms), text));
+    }
+    section
+}
+
+fn user(title: &'static str, user: &UserInfo) -> Section {
+    let mut section = Section::new(title);
+    section.row("Username", &user.username);
+    section.optional("UID", user.uid.as_ref());
+    if !user.groups.is_empty() {
+        section.row("Groups", user.groups.join(", "));
+    }
+    if let Some(Value::Object(extra)) = &user.extra {
+        for (key, value) in extra {
+            let value = match value {
+                Value::Array(values) => values
+                    .iter()
+                    .map(|value| match value {
+                        Value::String(s) => s.clone(),
+                        value => value.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Value::String(s) => s.clone(),
+                value => value.to_string(),
+            };
+            section.row(key, value);
+        }
+    }
+    section
+}
+
+/// The values at a path in the event, asked for in the detail view.
+pub fn query(event: &EventV1, path: &JsonPath) -> Section {
+    let mut section = Section::new(format!("Query {} (+ to pin as a column)", path));
+    let values = path.values(event);
+    if values.is_empty() {
+        section.row("", "nothing at this path");
+    }
+    for (i, value) in values.into_iter().enumerate() {
+        section.row(format!("[{}]", i), value);
+    }
+    section
+}
+
+/// What a non-resource URL is for, if it's one the apiserver serves, alerting
+/// to those that expose more than health.
+fn non_resource(event: &EventV1) -> Section {
+    let mut section = Section::new("Non-Resource URL");
+    let path = event.path();
+    section.row("Path", path);
+    let known = NON_RESOURCE
+        .iter()
+        .find(|(prefix, _, _)| path.starts_with(prefix));
+    match known {
+        Some((_, purpose, sensitive)) => {
+            section.row("For", purpose);
+            section.alert = *sensitive;
+        }
+        None => section.row("For", "not one the apiserver serves itself"),
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::kube::Stage;
+
+    fn event() -> EventV1 {
+        test_event()
+            .uri("/api/v1/namespaces/default/secrets/token?fieldSelector=type%3Dopaque&limit=1")
+            .verb("get")
+            .set(
+                "user",
+                serde_json::json!({
+                    "username": "alice",
+                    "groups": ["dev", "system:authenticated"],
+                    "extra": {"authentication.kubernetes.io/pod-name": ["runner-0"]},
+                }),
+            )
+            .object_ref(serde_json::json!({
+                "resource": "secrets",
+                "namespace": "default",
+                "name": "token",
+                "apiVersion": "v1",
+            }))
+            .set(
+                "responseStatus",
+                serde_json::json!({
+                    "metadata": {},
+                    "status": "Failure",
+                    "reason": "Forbidden",
+                    "message": "secrets \"token\" is forbidden",
+                    "code": 403,
+                }),
+            )
+            .set("requestReceivedTimestamp", "2024-07-01T10:00:00.000000Z")
+            .set("stageTimestamp", "2024-07-01T10:00:00.250000Z")
+            .annotations(serde_json::json!({
+                "mutation.webhook.admission.k8s.io/round_0_index_0": "{}",
+                "authorization.k8s.io/reason": "",
+                "authorization.k8s.io/decision": "forbid",
+            }))
+            .build()
+    }
+
+    fn rows<'a>(sections: &'a [Section], title: &str) -> Vec<(&'a str, &'a str)> {
+        sections
+            .iter()
+            .find(|section| section.title == title)
+            .map(|section| {
+                section
+                    .rows
+                    .iter()
+                    .map(|(label, value)| (label.as_str(), value.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn sections_of_an_event() {
+        let event = event();
+        let sections = sections(&event, &[&event], &Timestamps::default());
+        let titles = sections
+            .iter()
+            .map(|s| s.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            [
+                "Request",
+                "Query",
+                "User",
+                "Object",
+                "Response Status",
+                "Annotations"
+            ]
+        );
+
+        let request = rows(&sections, "Request");
+        assert!(request.contains(&("Level", "Metadata")));
+        assert_eq!(
+            rows(&sections, "Query"),
+            [("fieldSelector", "type=opaque"), ("limit", "1")]
+        );
+        assert!(request.contains(&("Stage Time", "2024-07-01 10:00:00.250 UTC (+250ms)")));
+        assert_eq!(
+            rows(&sections, "User"),
+            [
+                ("Username", "alice"),
+                ("Groups", "dev, system:authenticated"),
+                ("authentication.kubernetes.io/pod-name", "runner-0"),
+            ]
+        );
+        assert_eq!(
+            rows(&sections, "Response Status"),
+            [
+                ("Code", "403"),
+                ("Status", "Failure"),
+                ("Reason", "Forbidden"),
+                ("Message", "secrets \"token\" is forbidden"),
+            ]
+        );
+        assert_eq!(
+            rows(&sections, "Annotations"),
+            [
+                ("Authorization", "forbid"),
+                ("Authorization Reason", ""),
+                ("mutation.webhook.admission.k8s.io/round_0_index_0", "{}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn queries() {
+        let event = event();
+        let path = ".objectRef.name".parse().unwrap();
+        assert_eq!(
+            query(&event, &path).rows,
+            [("[0]".to_string(), "token".to_string())]
+        );
+        let path = ".user.groups[]".parse().unwrap();
+        let section = query(&event, &path);
+        assert_eq!(section.title, "Query .user.groups[] (+ to pin as a column)");
+        assert_eq!(
+            section.rows[1],
+            ("[1]".to_string(), "system:authenticated".to_string())
+        );
+        let path = ".requestObject.spec".parse().unwrap();
+        assert_eq!(query(&event, &path).rows[0].1, "nothing at this path");
+    }
+
+    #[test]
+    fn non_resource_urls() {
+        let mut event = event();
+        event.object_ref = None;
+        event.request_uri = "/logs/kube-apiserver.log".to_string();
+        let logs = sections(&event, &[&event], &Timestamps::default());
+        let section = logs
+            .iter()
+            .find(|section| section.title == "Non-Resource URL")
+            .expect("a non-resource URL section");
+        assert!(section.alert);
+        assert_eq!(
+            rows(&logs, "Non-Resource URL"),
+            [
+                ("Path", "/logs/kube-apiserver.log"),
+                ("For", "control plane node logs")
+            ]
+        );
+
+        event.request_uri = "/readyz?verbose".to_string();
+        let health = sections(&event, &[&event], &Timestamps::default());
+        assert!(rows(&health, "Non-Resource URL").contains(&("For", "readiness check")));
+        assert!(!rows(&health, "Request").is_empty());
+    }
+
+    #[test]
+    fn timing() {
+        assert_eq!(milliseconds("532.1ms"), Some(532.1));
+        assert_eq!(milliseconds("1m2.5s"), Some(62_500.0));
+        assert_eq!(milliseconds("15µs"), Some(0.015));
+        assert_eq!(milliseconds("soon"), None);
+
+        let complete = event();
+        let mut received = complete.clone();
+        received.stage = Stage::RequestReceived;
+        received.stage_timestamp = received.request_received_timestamp;
+        let mut started = complete.clone();
+        started.stage = Stage::ResponseStarted;
+        started.stage_timestamp = "2024-07-01T10:00:00.100000Z".parse().unwrap();
+        let mut complete = complete;
+        let latencies = [
+            ("total", "250ms"),
+            ("etcd", "200ms"),
+            ("response-write", "1µs"),
+        ];
+        for (part, latency) in latencies {
+            let key = format!("{}{}", LATENCY, part);
+            complete.annotations.insert(key, latency.to_string());
+        }
+
+        let waterfall = sections(
+            &complete,
+            &[&complete, &started, &received],
+            &Timestamps::default(),
+        );
+        let bar = |width: usize| format!("{:<30}", "█".repeat(width));
+        assert_eq!(
+            rows(&waterfall, "Timing"),
+            [
+                ("RequestReceived", format!("{} +0ms", bar(0)).as_str()),
+                ("ResponseStarted", &format!("{} +100ms", bar(12))),
+                ("ResponseComplete", &format!("{} +250ms", bar(30))),
+                ("total", &format!("{} 250ms", bar(30))),
+                ("etcd", &format!("{} 200ms", bar(24))),
+                ("response-write", &format!("{} 1µs", bar(1))),
+            ]
+        );
+
+        let alone = sections(&received, &[&received], &Timestamps::default());
+        assert!(rows(&alone, "Timing").is_empty());
+    }
+}
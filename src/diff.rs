@@ -0,0 +1,141 @@
+//! Structural differences between two JSON documents, addressed by jq style
+//! paths like those in [`crate::path`].
+
+use serde_json::Value;
+use std::fmt;
+
+/// One difference between two documents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } => path,
+            Change::Changed { path, .. } => path,
+        }
+    }
+
+    /// Replace the values with a placeholder, keeping the path.
+    pub fn hide_values(&mut self) {
+        let hidden = || Value::String("<hidden>".to_string());
+        match self {
+            Change::Added { value, .. } | Change::Removed { value, .. } => *value = hidden(),
+            Change::Changed { old, new, .. } => {
+                *old = hidden();
+                *new = hidden();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, value),
+            Change::Changed { path, old, new } => write!(f, "~ {}: {} -> {}", path, old, new),
+        }
+    }
+}
+
+/// Every difference from `old` to `new`, objects compared key by key and
+/// arrays element by element.
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    walk(String::new(), old, new, &mut changes);
+    changes
+}
+
+fn walk(path: String, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let path = format!("{}.{}", path, key);
+                match new.get(key) {
+                    Some(new_value) => walk(path, old_value, new_value, changes),
+                    None => changes.push(Change::Removed {
+                        path,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    changes.push(Change::Added {
+                        path: format!("{}.{}", path, key),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let path = format!("{}[{}]", path, i);
+                match (old.get(i), new.get(i)) {
+                    (Some(old), Some(new)) => walk(path, old, new, changes),
+                    (Some(old), None) => changes.push(Change::Removed {
+                        path,
+                        value: old.clone(),
+                    }),
+                    (None, Some(new)) => changes.push(Change::Added {
+                        path,
+                        value: new.clone(),
+                    }),
+                    (None, None) => unreachable!("index is within one of the arrays"),
+                }
+            }
+        }
+        (old, new) if old == new => {}
+        (old, new) => changes.push(Change::Changed {
+            path: if path.is_empty() {
+                ".".to_string()
+            } else {
+                path
+            },
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn changes() {
+        let old = json!({"spec": {"replicas": 1, "paused": true, "ports": [80, 443]}});
+        let new = json!({"spec": {"replicas": 3, "ports": [80], "strategy": "Recreate"}});
+        let changes = diff(&old, &new)
+            .iter()
+            .map(Change::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                "- .spec.paused: true",
+                "- .spec.ports[1]: 443",
+                "~ .spec.replicas: 1 -> 3",
+                "+ .spec.strategy: \"Recreate\"",
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+        assert_eq!(diff(&json!(1), &json!("1"))[0].path(), ".");
+    }
+}
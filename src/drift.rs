@@ -0,0 +1,137 @@
+//! Drift from what `kubectl apply` last applied: where the object an update or
+//! patch wrote differs from its `kubectl.kubernetes.io/last-applied-configuration`
+//! annotation, which is out-of-band changes being kept or overwritten.
+
+use crate::diff::{self, Change};
+use crate::kube::EventV1;
+use crate::secret;
+use serde_json::Value;
+
+/// The annotation `kubectl apply` keeps the configuration it applied in.
+pub const ANNOTATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// The verbs that write an object someone may have applied.
+const WRITES: [&str; 2] = ["update", "patch"];
+
+/// Parts of an object that aren't configuration, and so don't drift.
+const IGNORED: [&str; 2] = [".metadata", ".status"];
+
+/// The object an event's bodies tell of, the one returned unless that was
+/// only a Status, or else the one sent.
+fn object(event: &EventV1) -> Option<&Value> {
+    event
+        .response_object
+        .as_ref()
+        .filter(|object| object["kind"] != "Status")
+        .or(event.request_object.as_ref())
+}
+
+/// The configuration last applied to the object an event's bodies tell of,
+/// if it was ever applied with `kubectl apply`.
+pub fn last_applied(event: &EventV1) -> Option<Value> {
+    let annotation = object(event)?
+        .pointer("/metadata/annotations")?
+        .get(ANNOTATION)?
+        .as_str()?;
+    serde_json::from_str(annotation).ok()
+}
+
+/// Where the object an update or patch wrote differs from the configuration
+/// last applied to it, going by the object sent with an update, as that's
+/// what the writer wants, or else the one returned. Only fields the
+/// configuration sets are compared, and the values in Secrets are hidden.
+/// `None` if the event isn't a write of an applied object.
+pub fn drift(event: &EventV1) -> Option<Vec<Change>> {
+    if !WRITES.contains(&event.verb.as_str()) {
+        return None;
+    }
+    let applied = last_applied(event)?;
+    let written = match event.verb.as_str() {
+        "update" => event.request_object.as_ref().or(object(event))?,
+        _ => object(event)?,
+    };
+    let mut changes = diff::diff(&applied, written)
+        .into_iter()
+        .filter(|change| !matches!(change, Change::Added { .. }))
+        .filter(|change| !IGNORED.iter().any(|path| change.path().starts_with(path)))
+        .collect::<Vec<_>>();
+    if secret::is_secret(event) {
+        for change in &mut changes {
+            if secret::is_secret_object_path(change.path()) {
+                change.hide_values();
+            }
+        }
+    }
+    Some(changes)
+}
+
+/// Whether an update or patch wrote its object differently to how it was
+/// last applied.
+pub fn drifted(event: &EventV1) -> bool {
+    drift(event).is_some_and(|changes| !changes.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use serde_json::json;
+
+    fn event(verb: &str, replicas: u32) -> EventV1 {
+        let applied = json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {"name": "web", "namespace": "web"},
+            "spec": {"replicas": 3, "template": {"spec": {"containers": [{"name": "web", "image": "web:1.2"}]}}},
+        });
+        let object = json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "name": "web",
+                "namespace": "web",
+                "resourceVersion": "42",
+                "annotations": {ANNOTATION: applied.to_string()},
+            },
+            "spec": {
+                "replicas": replicas,
+                "strategy": {"type": "RollingUpdate"},
+                "template": {"spec": {"containers": [{"name": "web", "image": "web:1.2"}]}},
+            },
+            "status": {"replicas": replicas},
+        });
+        test_event()
+            .level("RequestResponse")
+            .uri("/apis/apps/v1/namespaces/web/deployments/web")
+            .verb(verb)
+            .object_ref(json!({"resource": "deployments", "namespace": "web", "name": "web"}))
+            .request_object(object.clone())
+            .response_object(object)
+            .build()
+    }
+
+    #[test]
+    fn drifting() {
+        let scaled = event("update", 8);
+        assert_eq!(last_applied(&scaled).unwrap()["spec"]["replicas"], 3);
+        assert_eq!(
+            drift(&scaled).unwrap(),
+            [Change::Changed {
+                path: ".spec.replicas".to_string(),
+                old: json!(3),
+                new: json!(8),
+            }]
+        );
+        assert!(drifted(&scaled));
+
+        let applied = event("patch", 3);
+        assert_eq!(drift(&applied), Some(Vec::new()));
+        assert!(!drifted(&applied));
+        assert_eq!(drift(&event("get", 8)), None);
+
+        let mut unapplied = event("update", 8);
+        unapplied.request_object = Some(json!({"spec": {"replicas": 8}}));
+        unapplied.response_object = None;
+        assert_eq!(drift(&unapplied), None);
+    }
+}
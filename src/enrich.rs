@@ -0,0 +1,465 @@
+//! Deriving more fields for events as they're read, like who owns the
+//! workload that made a request, shown as columns and filtered on as
+//! `enriched[key]`.
+
+use crate::filter::{in_network, parse_network};
+use crate::kube::EventV1;
+use anyhow::Context;
+use maxminddb::{geoip2, Reader};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Something that works out fields of events from what's in them, or from
+/// what it knows of the cluster.
+pub trait Enricher: Send {
+    /// The keys of the fields this can set, to offer as columns.
+    fn keys(&self) -> Vec<String>;
+
+    /// Set whatever fields this can work out for the event in
+    /// [`EventV1::enriched`].
+    fn enrich(&self, event: &mut EventV1);
+}
+
+/// Run each of the enrichers over the event, in order, so that later ones can
+/// see what earlier ones set.
+pub fn enrich(enrichers: &[Box<dyn Enricher>], event: &mut EventV1) {
+    for enricher in enrichers {
+        enricher.enrich(event);
+    }
+}
+
+/// The group and version of the API an event's request went to, as the
+/// `apiversion` field, like `apps/v1`, or `v1` for the core group.
+pub struct ApiVersion;
+
+impl ApiVersion {
+    fn of(event: &EventV1) -> Option<String> {
+        let from_ref = event.object_ref.as_ref().and_then(|object| {
+            let version = object.api_version.as_deref().filter(|v| !v.is_empty())?;
+            Some(
+                match object.api_group.as_deref().filter(|g| !g.is_empty()) {
+                    Some(group) => format!("{}/{}", group, version),
+                    None => version.to_string(),
+                },
+            )
+        });
+        if from_ref.is_some() {
+            return from_ref;
+        }
+
+        match event.group_version()? {
+            ("core", version) => version.map(str::to_string),
+            (group, version) => Some(format!("{}/{}", group, version?)),
+        }
+    }
+}
+
+impl Enricher for ApiVersion {
+    fn keys(&self) -> Vec<String> {
+        vec!["apiversion".to_string()]
+    }
+
+    fn enrich(&self, event: &mut EventV1) {
+        if let Some(version) = Self::of(event) {
+            event.enriched.insert("apiversion".to_string(), version);
+        }
+    }
+}
+
+/// The namespace and name of the service account in a username like
+/// `system:serviceaccount:<namespace>:<name>`.
+fn service_account(username: &str) -> Option<(&str, &str)> {
+    username
+        .strip_prefix("system:serviceaccount:")?
+        .split_once(':')
+}
+
+/// The service account a request was made with, as the `serviceaccount`
+/// field, like `kube-system/coredns`.
+pub struct ServiceAccount;
+
+impl Enricher for ServiceAccount {
+    fn keys(&self) -> Vec<String> {
+        vec!["serviceaccount".to_string()]
+    }
+
+    fn enrich(&self, event: &mut EventV1) {
+        if let Some((namespace, name)) = service_account(&event.user.username) {
+            let account = format!("{}/{}", namespace, name);
+            event.enriched.insert("serviceaccount".to_string(), account);
+        }
+    }
+}
+
+/// The workloads whose pods run as the service account a request was made
+/// with, as the `workload` field, like `Deployment/coredns`, from a `List` of
+/// them as `kubectl get deployments,daemonsets,statefulsets,cronjobs,jobs
+/// --all-namespaces --output=json` gives.
+pub struct Workloads {
+    /// The workloads by the namespace and name of their service account.
+    accounts: HashMap<(String, String), BTreeSet<String>>,
+}
+
+impl Workloads {
+    pub fn new(list: &Value) -> Self {
+        let mut accounts = HashMap::<_, BTreeSet<_>>::new();
+        for item in list["items"].as_array().into_iter().flatten() {
+            let kind = item["kind"].as_str().unwrap_or_default();
+            let metadata = &item["metadata"];
+            // jobs made by cronjobs are told of as their cronjob
+            let owned = metadata["ownerReferences"]
+                .as_array()
+                .is_some_and(|owners| !owners.is_empty());
+            let spec = match kind {
+                "Deployment" | "DaemonSet" | "StatefulSet" => &item["spec"],
+                "Job" if !owned => &item["spec"],
+                "CronJob" => &item["spec"]["jobTemplate"]["spec"],
+                _ => continue,
+            };
+            let (Some(namespace), Some(name)) =
+                (metadata["namespace"].as_str(), metadata["name"].as_str())
+            else {
+                continue;
+            };
+            let pod = &spec["template"]["spec"];
+            let account = pod["serviceAccountName"]
+                .as_str()
+                .or(pod["serviceAccount"].as_str())
+                .unwrap_or("default");
+            accounts
+                .entry((namespace.to_string(), account.to_string()))
+                .or_default()
+                .insert(format!("{}/{}", kind, name));
+        }
+        Self { accounts }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let list =
+            serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        Ok(Self::new(&list))
+    }
+}
+
+impl Enricher for Workloads {
+    fn keys(&self) -> Vec<String> {
+        vec!["workload".to_string()]
+    }
+
+    fn enrich(&self, event: &mut EventV1) {
+        let Some((namespace, name)) = service_account(&event.user.username) else {
+            return;
+        };
+        let key = (namespace.to_string(), name.to_string());
+        if let Some(workloads) = self.accounts.get(&key) {
+            let workloads = workloads.iter().cloned().collect::<Vec<_>>();
+            event
+                .enriched
+                .insert("workload".to_string(), workloads.join(", "));
+        }
+    }
+}
+
+/// Who owns the workload behind a request, as the `owner` field, from a
+/// mapping file of users and namespaces to owners like:
+///
+/// ```toml
+/// [users]
+/// "system:serviceaccount:argocd:*" = "platform"
+///
+/// [namespaces]
+/// payments = "team-payments"
+/// ```
+///
+/// Users are tried before namespaces, and a pattern ending in `*` matches
+/// anything starting with the rest of it, the longest such pattern winning
+/// where there's no exact match.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Owners {
+    users: BTreeMap<String, String>,
+    namespaces: BTreeMap<String, String>,
+}
+
+impl Owners {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    fn lookup<'a>(map: &'a BTreeMap<String, String>, key: &str) -> Option<&'a String> {
+        if let Some(owner) = map.get(key) {
+            return Some(owner);
+        }
+        map.iter()
+            .filter_map(|(pattern, owner)| {
+                let prefix = pattern.strip_suffix('*')?;
+                key.starts_with(prefix).then_some((prefix.len(), owner))
+            })
+            .max_by_key(|(length, _)| *length)
+            .map(|(_, owner)| owner)
+    }
+
+    fn of(&self, event: &EventV1) -> Option<&String> {
+        let user = Self::lookup(&self.users, &event.user.username);
+        user.or_else(|| {
+            let object = event.object_ref.as_ref()?;
+            Self::lookup(&self.namespaces, object.namespace.as_deref()?)
+        })
+    }
+}
+
+impl Enricher for Owners {
+    fn keys(&self) -> Vec<String> {
+        vec!["owner".to_string()]
+    }
+
+    fn enrich(&self, event: &mut EventV1) {
+        if let Some(owner) = self.of(event) {
+            event.enriched.insert("owner".to_string(), owner.clone());
+        }
+    }
+}
+
+/// Where the client of a request was, by the first of its source IPs, as the
+/// `network` field: the name of the narrowest of the config file's networks
+/// it's in, like `pods` or `vpn`, or failing that `private` for loopback,
+/// link local and private addresses, or `external` for anything else.
+///
+/// With a MaxMind GeoIP2 or GeoLite2 country or city database, external
+/// addresses get the code of the country they're in as the `country` field
+/// too.
+pub struct Networks {
+    networks: Vec<(String, (IpAddr, u32))>,
+    geoip: Option<Reader<Vec<u8>>>,
+}
+
+impl Networks {
+    /// The networks named in the config file, each with its CIDRs, like
+    /// `pods = ["10.244.0.0/16"]`, and the GeoIP database to read, if any.
+    pub fn new(
+        networks: &BTreeMap<String, Vec<String>>,
+        geoip: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let mut parsed = Vec::new();
+        for (name, cidrs) in networks {
+            for cidr in cidrs {
+                let network = parse_network(cidr).ok_or_else(|| {
+                    anyhow::anyhow!("network {:?} has {:?}, which isn't a CIDR", name, cidr)
+                })?;
+                parsed.push((name.clone(), network));
+            }
+        }
+        let geoip = geoip
+            .map(|path| {
+                Reader::open_readfile(path)
+                    .with_context(|| format!("reading the GeoIP database {}", path.display()))
+            })
+            .transpose()?;
+        Ok(Self {
+            networks: parsed,
+            geoip,
+        })
+    }
+
+    fn network(&self, ip: IpAddr) -> &str {
+        let named = self
+            .networks
+            .iter()
+            .filter(|(_, network)| in_network(ip, *network))
+            .max_by_key(|(_, (_, prefix))| *prefix);
+        if let Some((name, _)) = named {
+            return name;
+        }
+        let private = match ip {
+            IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+            IpAddr::V6(ip) => {
+                ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local()
+            }
+        };
+        match private {
+            true => "private",
+            false => "external",
+        }
+    }
+
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        let country = self.geoip.as_ref()?.lookup::<geoip2::Country>(ip).ok()?;
+        Some(country.country?.iso_code?.to_string())
+    }
+}
+
+impl Enricher for Networks {
+    fn keys(&self) -> Vec<String> {
+        let mut keys = vec!["network".to_string()];
+        if self.geoip.is_some() {
+            keys.push("country".to_string());
+        }
+        keys
+    }
+
+    fn enrich(&self, event: &mut EventV1) {
+        let Some(&ip) = event.source_ips.iter().flatten().next() else {
+            return;
+        };
+        let network = self.network(ip);
+        if network == "external" {
+            if let Some(country) = self.country(ip) {
+                event.enriched.insert("country".to_string(), country);
+            }
+        }
+        event
+            .enriched
+            .insert("network".to_string(), network.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, uri: &str, object_ref: serde_json::Value) -> EventV1 {
+        test_event()
+            .uri(uri)
+            .user(user)
+            .object_ref(object_ref)
+            .build()
+    }
+
+    #[test]
+    fn enriching() {
+        let owners: Owners = toml::from_str(
+            r#"
+            [users]
+            "system:serviceaccount:*" = "someone"
+            "system:serviceaccount:argocd:*" = "platform"
+            "alice" = "team-alice"
+
+            [namespaces]
+            payments = "team-payments"
+            "#,
+        )
+        .unwrap();
+        let enrichers: Vec<Box<dyn Enricher>> = vec![Box::new(ApiVersion), Box::new(owners)];
+
+        let mut deployments = event(
+            "system:serviceaccount:argocd:controller",
+            "/apis/apps/v1/namespaces/payments/deployments",
+            serde_json::json!({"resource": "deployments", "namespace": "payments"}),
+        );
+        enrich(&enrichers, &mut deployments);
+        assert_eq!(deployments.enriched["apiversion"], "apps/v1");
+        assert_eq!(deployments.enriched["owner"], "platform");
+
+        let mut pods = event(
+            "bob",
+            "/api/v1/namespaces/payments/pods",
+            serde_json::json!({"resource": "pods", "namespace": "payments", "apiVersion": "v1"}),
+        );
+        enrich(&enrichers, &mut pods);
+        assert_eq!(pods.enriched["apiversion"], "v1");
+        assert_eq!(pods.enriched["owner"], "team-payments");
+
+        let mut nodes = event(
+            "carol",
+            "/api/v1/nodes",
+            serde_json::json!({"resource": "nodes"}),
+        );
+        enrich(&enrichers, &mut nodes);
+        assert!(!nodes.enriched.contains_key("owner"));
+    }
+
+    #[test]
+    fn workloads() {
+        let list = serde_json::json!({
+            "kind": "List",
+            "items": [
+                {
+                    "kind": "Deployment",
+                    "metadata": {"namespace": "kube-system", "name": "coredns"},
+                    "spec": {"template": {"spec": {"serviceAccountName": "coredns"}}},
+                },
+                {
+                    "kind": "CronJob",
+                    "metadata": {"namespace": "ci", "name": "cleanup"},
+                    "spec": {"jobTemplate": {"spec": {"template": {"spec": {"serviceAccountName": "deployer"}}}}},
+                },
+                {
+                    "kind": "Job",
+                    "metadata": {"namespace": "ci", "name": "cleanup-28700000", "ownerReferences": [{"kind": "CronJob"}]},
+                    "spec": {"template": {"spec": {"serviceAccountName": "deployer"}}},
+                },
+                {
+                    "kind": "StatefulSet",
+                    "metadata": {"namespace": "ci", "name": "runner"},
+                    "spec": {"template": {"spec": {"serviceAccountName": "deployer"}}},
+                },
+                {
+                    "kind": "DaemonSet",
+                    "metadata": {"namespace": "ci", "name": "agent"},
+                    "spec": {"template": {"spec": {}}},
+                },
+            ],
+        });
+        let enrichers: Vec<Box<dyn Enricher>> =
+            vec![Box::new(ServiceAccount), Box::new(Workloads::new(&list))];
+        let workload = |user: &str| {
+            let mut event = event(user, "/api/v1/pods", serde_json::json!({}));
+            enrich(&enrichers, &mut event);
+            event.enriched
+        };
+
+        let coredns = workload("system:serviceaccount:kube-system:coredns");
+        assert_eq!(coredns["serviceaccount"], "kube-system/coredns");
+        assert_eq!(coredns["workload"], "Deployment/coredns");
+        let deployer = workload("system:serviceaccount:ci:deployer");
+        assert_eq!(deployer["workload"], "CronJob/cleanup, StatefulSet/runner");
+        assert_eq!(
+            workload("system:serviceaccount:ci:default")["workload"],
+            "DaemonSet/agent"
+        );
+        assert!(!workload("system:serviceaccount:ci:unused").contains_key("workload"));
+        assert!(workload("alice").is_empty());
+    }
+
+    #[test]
+    fn networks() {
+        let config: BTreeMap<String, Vec<String>> = toml::from_str(
+            r#"
+            cluster = ["10.0.0.0/8"]
+            nodes = ["10.0.0.0/16"]
+            pods = ["10.244.0.0/16"]
+            vpn = ["100.64.0.0/10", "fd7a:115c:a1e0::/48"]
+            "#,
+        )
+        .unwrap();
+        let networks = Networks::new(&config, None).unwrap();
+        let network = |ip: &str| {
+            let mut event = event("alice", "/api/v1/pods", serde_json::json!({}));
+            event.source_ips = Some(vec![ip.parse().unwrap(), "10.0.0.1".parse().unwrap()]);
+            networks.enrich(&mut event);
+            event.enriched["network"].clone()
+        };
+        assert_eq!(network("10.0.3.4"), "nodes");
+        assert_eq!(network("10.1.0.1"), "cluster");
+        assert_eq!(network("100.100.1.1"), "vpn");
+        assert_eq!(network("fd7a:115c:a1e0::1"), "vpn");
+        assert_eq!(network("192.168.1.1"), "private");
+        assert_eq!(network("::1"), "private");
+        assert_eq!(network("203.0.113.9"), "external");
+
+        let mut nowhere = event("alice", "/api/v1/pods", serde_json::json!({}));
+        networks.enrich(&mut nowhere);
+        assert!(nowhere.enriched.is_empty());
+
+        let bad = BTreeMap::from([("pods".to_string(), vec!["10.244.0.0".to_string()])]);
+        assert!(Networks::new(&bad, None).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Writing events back out, for picking up with other tools.
+
+use crate::kube::EventV1;
+use anyhow::Context;
+use arrow_array::builder::{Int32Builder, StringBuilder, TimestampMicrosecondBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::{stdout, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How many events to write to Parquet in each row group.
+const PARQUET_BATCH: usize = 8192;
+
+/// Write the events as newline delimited JSON, as the apiserver would,
+/// returning how many there were.
+pub fn write_json_lines<'a>(
+    writer: impl Write,
+    events: impl IntoIterator<Item = &'a EventV1>,
+) -> anyhow::Result<usize> {
+    let mut writer = BufWriter::new(writer);
+    let mut count = 0;
+    for event in events {
+        serde_json::to_writer(&mut writer, event)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Write the events to a new newline delimited JSON file at `path`.
+pub fn to_file<'a>(
+    path: &Path,
+    events: impl IntoIterator<Item = &'a EventV1>,
+) -> anyhow::Result<usize> {
+    let file =
+        std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    write_json_lines(file, events).with_context(|| format!("writing {}", path.display()))
+}
+
+/// Copy the events to the clipboard as newline delimited JSON, returning how
+/// many there were.
+///
+/// This asks the terminal to do it with an OSC 52 escape sequence, so it also
+/// works over SSH, but only in terminals that support it.
+pub fn to_clipboard<'a>(events: impl IntoIterator<Item = &'a EventV1>) -> anyhow::Result<usize> {
+    let mut text = Vec::new();
+    let count = write_json_lines(&mut text, events)?;
+    text_to_clipboard(&text)?;
+    Ok(count)
+}
+
+/// Copy some text to the clipboard, the same way as [`to_clipboard`].
+pub fn text_to_clipboard(text: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The Parquet columns: the fields most worth querying, flattened, then the
+/// whole event as JSON for anything else.
+fn parquet_schema() -> SchemaRef {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+    let text = |name| Field::new(name, DataType::Utf8, true);
+    Arc::new(Schema::new(vec![
+        Field::new("received", timestamp.clone(), false),
+        Field::new("stage_timestamp", timestamp, false),
+        Field::new("audit_id", DataType::Utf8, false),
+        Field::new("stage", DataType::Utf8, false),
+        Field::new("level", DataType::Utf8, false),
+        Field::new("verb", DataType::Utf8, false),
+        Field::new("user", DataType::Utf8, false),
+        text("user_agent"),
+        text("source_ip"),
+        text("cluster"),
+        text("namespace"),
+        text("resource"),
+        text("subresource"),
+        text("name"),
+        text("api_group"),
+        text("api_version"),
+        Field::new("request_uri", DataType::Utf8, false),
+        Field::new("code", DataType::Int32, true),
+        Field::new("event", DataType::Utf8, false),
+    ]))
+}
+
+/// The events as a batch of Parquet rows.
+fn parquet_batch(schema: &SchemaRef, events: &[&EventV1]) -> anyhow::Result<RecordBatch> {
+    let timestamps = || TimestampMicrosecondBuilder::new().with_timezone("UTC");
+    let (mut received, mut stage_timestamp) = (timestamps(), timestamps());
+    let mut text = (0..16).map(|_| StringBuilder::new()).collect::<Vec<_>>();
+    let mut code = Int32Builder::new();
+    for event in events {
+        received.append_value(event.request_received_timestamp.timestamp_micros());
+        stage_timestamp.append_value(event.stage_timestamp.timestamp_micros());
+        let object = event.object_ref.as_ref();
+        let object_field = |field: fn(&crate::kube::ObjectReference) -> &Option<String>| {
+            object.and_then(|object| field(object).clone())
+        };
+        let values = [
+            Some(event.audit_id.clone()),
+            Some(event.stage.to_string()),
+            Some(event.level.to_string()),
+            Some(event.verb.clone()),
+            Some(event.user.username.clone()),
+            event.user_agent.clone(),
+            event
+                .source_ips
+                .as_ref()
+                .and_then(|ips| ips.first())
+                .map(ToString::to_string),
+            event.source.clone(),
+            object_field(|object| &object.namespace),
+            object_field(|object| &object.resource),
+            object_field(|object| &object.subresource),
+            object_field(|object| &object.name),
+            object_field(|object| &object.api_group),
+            object_field(|object| &object.api_version),
+            Some(event.request_uri.clone()),
+            Some(serde_json::to_string(event)?),
+        ];
+        for (builder, value) in text.iter_mut().zip(values) {
+            builder.append_option(value);
+        }
+        code.append_option(event.response_status.as_ref().map(|status| status.code));
+    }
+
+    let mut text = text
+        .into_iter()
+        .map(|mut builder| Arc::new(builder.finish()) as ArrayRef);
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(received.finish()),
+        Arc::new(stage_timestamp.finish()),
+    ];
+    // the text columns either side of the status code, in the schema's order
+    columns.extend(text.by_ref().take(15));
+    columns.push(Arc::new(code.finish()));
+    columns.extend(text);
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Write the events to a new Parquet file at `path`, for analysing with the
+/// likes of DuckDB or Spark, returning how many there were.
+pub fn to_parquet<'a>(
+    path: &Path,
+    events: impl IntoIterator<Item = &'a EventV1>,
+) -> anyhow::Result<usize> {
+    let write = || {
+        let file = std::fs::File::create(path)?;
+        let schema = parquet_schema();
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+        let mut count = 0;
+        let mut events = events.into_iter().peekable();
+        while events.peek().is_some() {
+            let batch = events.by_ref().take(PARQUET_BATCH).collect::<Vec<_>>();
+            writer.write(&parquet_batch(&schema, &batch)?)?;
+            count += batch.len();
+        }
+        writer.close()?;
+        anyhow::Ok(count)
+    };
+    write().with_context(|| format!("writing {}", path.display()))
+}
+
+/// A name for an export of `what` in the current directory that won't clash
+/// with earlier ones, like `kale-bookmarks-20240701-100000.jsonl`.
+pub fn file_name(what: &str, extension: &str) -> PathBuf {
+    let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    PathBuf::from(format!("kale-{}-{}.{}", what, time, extension))
+}
+
+/// Quote a CSV cell if it needs it.
+pub(crate) fn quote(cell: &str) -> String {
+    match cell.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int32Type;
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn event(verb: &str, code: Option<i32>) -> EventV1 {
+        let event = test_event()
+            .uri("/api/v1/namespaces/default/pods/web")
+            .verb(verb)
+            .object_ref(
+                serde_json::json!({"resource": "pods", "namespace": "default", "name": "web"}),
+            )
+            .set("stageTimestamp", "2024-07-01T10:00:00.5Z");
+        match code {
+            Some(code) => event.code(code),
+            None => event,
+        }
+        .build()
+    }
+
+    #[test]
+    fn parquet() {
+        let path = std::env::temp_dir().join(format!("kale-export-{}.parquet", std::process::id()));
+        let events = [event("get", Some(200)), event("delete", None)];
+        assert_eq!(to_parquet(&path, &events).unwrap(), 2);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let text = |name| batch.column_by_name(name).unwrap().as_string::<i32>();
+        assert_eq!(text("verb").value(1), "delete");
+        assert_eq!(text("name").value(0), "web");
+        assert!(text("cluster").is_null(0));
+        let code = batch.column_by_name("code").unwrap();
+        assert_eq!(code.as_primitive::<Int32Type>().value(0), 200);
+        assert!(code.is_null(1));
+        let event: EventV1 = serde_json::from_str(text("event").value(0)).unwrap();
+        assert_eq!(event.verb, "get");
+    }
+}
@@ -0,0 +1,237 @@
+//! Objects that users keep taking turns to change, usually two controllers
+//! each reconciling them to what they want, and what each keeps changing.
+
+use crate::detail::Section;
+use crate::diff::{self, Change};
+use crate::kube::{EventV1, Stage};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// How many times the writes to an object have to change hands between users
+/// for them to be fighting over it.
+const TURNS: usize = 4;
+
+/// The verbs that change an object in place.
+const WRITES: [&str; 2] = ["update", "patch"];
+
+/// Fields that change with every write, whoever makes it.
+const BOOKKEEPING: [&str; 3] = [
+    ".metadata.resourceVersion",
+    ".metadata.generation",
+    ".metadata.managedFields",
+];
+
+/// Users taking turns to change an object.
+#[derive(Debug, PartialEq)]
+pub struct Fight {
+    /// The object, as `namespace/resource/name` and any subresource.
+    pub object: String,
+    /// The users, in the order they first wrote to it.
+    pub users: Vec<String>,
+    pub writes: usize,
+    /// How many times a write was by a different user than the one before.
+    pub turns: usize,
+    /// How long from the first write to the last, in seconds.
+    pub seconds: i64,
+    /// What each user changed last time they changed each field, going by
+    /// the objects in the bodies, for the fields more than one user changed,
+    /// or every field if none were.
+    pub changes: BTreeMap<String, Vec<Change>>,
+}
+
+fn object(event: &EventV1) -> Option<String> {
+    let object = event.object_ref.as_ref()?;
+    let name = object.name.as_ref()?;
+    let mut text = [&object.namespace, &object.resource]
+        .into_iter()
+        .flatten()
+        .chain([name])
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("/");
+    if let Some(subresource) = &object.subresource {
+        text.push('/');
+        text.push_str(subresource);
+    }
+    Some(text)
+}
+
+/// The object in the event's bodies.
+fn body(event: &EventV1) -> Option<&Value> {
+    event
+        .response_object
+        .as_ref()
+        .filter(|object| object["kind"] != "Status")
+        .or(event.request_object.as_ref())
+}
+
+/// The fights among the events, which are in time order, the most turns
+/// first.
+pub fn fights(events: &[&EventV1]) -> Vec<Fight> {
+    let mut writes = HashMap::<String, Vec<&EventV1>>::new();
+    for event in events {
+        let succeeded = !event
+            .response_status
+            .as_ref()
+            .is_some_and(|status| status.is_failure());
+        if WRITES.contains(&event.verb.as_str())
+            && event.stage == Stage::ResponseComplete
+            && succeeded
+        {
+            if let Some(object) = object(event) {
+                writes.entry(object).or_default().push(event);
+            }
+        }
+    }
+
+    let mut fights = writes
+        .into_iter()
+        .filter_map(|(object, writes)| fight(object, &writes))
+        .collect::<Vec<_>>();
+    fights.sort_by(|a, b| b.turns.cmp(&a.turns).then(a.object.cmp(&b.object)));
+    fights
+}
+
+fn fight(object: String, writes: &[&EventV1]) -> Option<Fight> {
+    let turns = writes
+        .windows(2)
+        .filter(|pair| pair[0].user.username != pair[1].user.username)
+        .count();
+    if turns < TURNS {
+        return None;
+    }
+
+    let mut users = Vec::<String>::new();
+    for write in writes {
+        if !users.contains(&write.user.username) {
+            users.push(write.user.username.clone());
+        }
+    }
+
+    // the latest change by each user to each field, by field
+    let mut fields = BTreeMap::<String, BTreeMap<&str, Change>>::new();
+    for pair in writes.windows(2) {
+        let (Some(old), Some(new)) = (body(pair[0]), body(pair[1])) else {
+            continue;
+        };
+        for change in diff::diff(old, new) {
+            if BOOKKEEPING
+                .iter()
+                .any(|path| change.path().starts_with(path))
+            {
+                continue;
+            }
+            let by = fields.entry(change.path().to_string()).or_default();
+            by.insert(&pair[1].user.username, change);
+        }
+    }
+    let contested = fields.values().any(|by| by.len() > 1);
+    let mut changes = BTreeMap::<String, Vec<Change>>::new();
+    for by in fields.into_values().filter(|by| by.len() > 1 || !contested) {
+        for (user, change) in by {
+            changes.entry(user.to_string()).or_default().push(change);
+        }
+    }
+
+    let (first, last) = (writes.first()?, writes.last()?);
+    let span = last.request_received_timestamp - first.request_received_timestamp;
+    Some(Fight {
+        object,
+        users,
+        writes: writes.len(),
+        turns,
+        seconds: span.num_seconds(),
+        changes,
+    })
+}
+
+/// A section for each fight among the events, with who is fighting, how
+/// hard, and what each keeps changing.
+pub fn sections(events: &[&EventV1]) -> Vec<Section> {
+    fights(events)
+        .into_iter()
+        .map(|fight| {
+            let mut section = Section::new(format!("Fight over {}", fight.object));
+            section.alert = true;
+            section.row("Users", fight.users.join(", "));
+            section.row(
+                "Writes",
+                format!(
+                    "{} in {}s, changing hands {} times",
+                    fight.writes, fight.seconds, fight.turns
+                ),
+            );
+            for (user, changes) in fight.changes {
+                for change in changes {
+                    section.row(&user, change);
+                }
+            }
+            section
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use serde_json::json;
+
+    fn event(user: &str, second: u32, replicas: u32, version: u32) -> EventV1 {
+        let time = format!("2024-07-01T10:00:{:02}Z", second);
+        test_event()
+            .level("RequestResponse")
+            .uri("/apis/apps/v1/namespaces/web/deployments/web")
+            .verb("update")
+            .user(user)
+            .object_ref(json!({"resource": "deployments", "namespace": "web", "name": "web"}))
+            .code(200)
+            .response_object(json!({
+                "kind": "Deployment",
+                "metadata": {"name": "web", "resourceVersion": version.to_string()},
+                "spec": {"replicas": replicas, "paused": false},
+            }))
+            .at(time)
+            .build()
+    }
+
+    #[test]
+    fn fighting() {
+        let (argo, hpa) = (
+            "system:serviceaccount:argocd:argocd",
+            "system:serviceaccount:kube-system:horizontal-pod-autoscaler",
+        );
+        let mut events = (0..6)
+            .map(|i| match i % 2 {
+                0 => event(argo, i * 5, 3, i),
+                _ => event(hpa, i * 5, 8, i),
+            })
+            .collect::<Vec<_>>();
+        let mut calm = event("alice", 40, 3, 10);
+        calm.object_ref.as_mut().unwrap().name = Some("api".to_string());
+        events.push(calm);
+        let events = events.iter().collect::<Vec<_>>();
+
+        let fights = fights(&events);
+        assert_eq!(fights.len(), 1);
+        let fight = &fights[0];
+        assert_eq!(fight.object, "web/deployments/web");
+        assert_eq!(fight.users, [argo, hpa]);
+        assert_eq!((fight.writes, fight.turns, fight.seconds), (6, 5, 25));
+        assert_eq!(
+            fight.changes[argo],
+            [Change::Changed {
+                path: ".spec.replicas".to_string(),
+                old: json!(8),
+                new: json!(3),
+            }]
+        );
+        assert_eq!(
+            fight.changes[hpa][0].to_string(),
+            "~ .spec.replicas: 3 -> 8"
+        );
+
+        let sections = sections(&events[..4]);
+        assert!(sections.is_empty());
+    }
+}
@@ -0,0 +1,1155 @@
+//! The filter language, shared by the TUI and anything else built on the
+//! library.
+//!
+//! A filter is a space separated list of terms, all of which must match:
+//!
+//! ```text
+//! verb=delete namespace!=kube-system
+//! user="system:admin" or group=system:masters
+//! not (verb=get or verb=list or verb=watch)
+//! secrets
+//! ```
+//!
+//! A term is either a comparison `field op value`, where `op` is one of `=`,
+//! `!=`, `>`, `>=`, `<` and `<=`, or a bare word which matches any event
+//! containing it (case insensitively) in its URI, user, verb, object or user
+//! agent. Terms combine with `and` (or just a space), `or` and `not`, and can be
+//! grouped with parentheses. Values containing spaces, parentheses or operator
+//! characters can be double quoted.
+//!
+//! Regular expressions match with `field~pattern` and `field!~pattern`, or
+//! `~pattern` to search the same fields as a bare word. Each is compiled once,
+//! when the filter is parsed.
+//!
+//! Any other part of the event can be compared by its jq style path, as in
+//! `.requestObject.spec.replicas>=10`; see [`crate::path`]. What a script from
+//! the config file makes of the event is compared as `script[name]`; see
+//! [`crate::script`], and fields worked out as it was read as `enriched[key]`;
+//! see [`crate::enrich`].
+
+use crate::agent::Agent;
+use crate::drift;
+use crate::kube::EventV1;
+use crate::path::JsonPath;
+use crate::script::Scripts;
+use crate::size;
+use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// A parsed filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Predicate(Predicate),
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+impl Filter {
+    /// Whether the event passes this filter, running any `script[name]` it
+    /// compares with `scripts`.
+    pub fn eval(&self, event: &EventV1, scripts: &Scripts) -> bool {
+        match self {
+            Filter::Predicate(predicate) => predicate.eval(event, scripts),
+            Filter::Not(filter) => !filter.eval(event, scripts),
+            Filter::And(filters) => filters.iter().all(|filter| filter.eval(event, scripts)),
+            Filter::Or(filters) => filters.iter().any(|filter| filter.eval(event, scripts)),
+        }
+    }
+
+    /// A filter matching events where `field` equals `value`.
+    pub fn field_eq(field: Field, value: impl Into<String>) -> Self {
+        Filter::Predicate(Predicate::Compare {
+            field,
+            op: Op::Eq,
+            value: value.into(),
+        })
+    }
+
+    /// Both this filter and `other`, flattening nested `and`s.
+    pub fn and(self, other: Filter) -> Self {
+        let mut filters = match self {
+            Filter::And(filters) => filters,
+            filter => vec![filter],
+        };
+        match other {
+            Filter::And(others) => filters.extend(others),
+            other => filters.push(other),
+        }
+        Filter::And(filters)
+    }
+
+    /// Refine an optional filter with another term, or start a new filter.
+    pub fn refine(filter: Option<Filter>, other: Filter) -> Self {
+        match filter {
+            Some(filter) => filter.and(other),
+            None => other,
+        }
+    }
+
+    /// The top level terms, all of which must match.
+    pub fn terms(&self) -> Vec<&Filter> {
+        match self {
+            Filter::And(filters) => filters.iter().collect(),
+            filter => vec![filter],
+        }
+    }
+
+    /// The values that top level `field!=value` terms exclude.
+    pub fn excluded(&self, field: &Field) -> Vec<&str> {
+        self.terms()
+            .into_iter()
+            .filter_map(|filter| match filter {
+                Filter::Predicate(Predicate::Compare {
+                    field: f,
+                    op: Op::Ne,
+                    value,
+                }) if f == field => Some(value.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// This filter without any top level comparisons on `field`, so that a
+    /// new value for the field can replace the old one. Returns `None` if
+    /// nothing is left.
+    pub fn without_field(self, field: &Field) -> Option<Self> {
+        let on_field = |filter: &Filter| match filter {
+            Filter::Predicate(Predicate::Compare { field: f, .. }) => f == field,
+            Filter::Predicate(Predicate::Match { field: f, .. }) => f.as_ref() == Some(field),
+            _ => false,
+        };
+
+        match self {
+            Filter::And(filters) => {
+                let mut filters = filters
+                    .into_iter()
+                    .filter(|filter| !on_field(filter))
+                    .collect::<Vec<_>>();
+                match filters.len() {
+                    0 => None,
+                    1 => filters.pop(),
+                    _ => Some(Filter::And(filters)),
+                }
+            }
+            filter if on_field(&filter) => None,
+            filter => Some(filter),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let filter = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => anyhow::bail!("unexpected {}", token),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Predicate(predicate) => write!(f, "{}", predicate),
+            Filter::Not(filter) => match **filter {
+                Filter::Predicate(_) | Filter::Not(_) => write!(f, "not {}", filter),
+                _ => write!(f, "not ({})", filter),
+            },
+            Filter::And(filters) => {
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+                    match filter {
+                        Filter::Or(_) => write!(f, "({})", filter)?,
+                        _ => write!(f, "{}", filter)?,
+                    }
+                }
+                Ok(())
+            }
+            Filter::Or(filters) => {
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    write!(f, "{}", filter)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A single test against an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// Compare a field of the event against a value.
+    Compare { field: Field, op: Op, value: String },
+    /// Match events containing the text anywhere in their summary fields.
+    Text(String),
+    /// Match a field against a regex, or the summary fields if there is none.
+    Match {
+        field: Option<Field>,
+        pattern: Pattern,
+        negated: bool,
+    },
+}
+
+/// The fields searched by bare words and field-less regexes.
+const TEXT_FIELDS: [Field; 5] = [
+    Field::Uri,
+    Field::User,
+    Field::Verb,
+    Field::Object,
+    Field::UserAgent,
+];
+
+/// A compiled regex, compared by its source.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> anyhow::Result<Self> {
+        Ok(Self(Regex::new(pattern)?))
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Predicate {
+    /// Whether the event satisfies this predicate.
+    ///
+    /// Fields with several values, like groups, match if any of the values
+    /// do, except for `!=` which requires that none are equal.
+    pub fn eval(&self, event: &EventV1, scripts: &Scripts) -> bool {
+        match self {
+            Predicate::Compare { field, op, value } => {
+                let values = field.values(event, scripts);
+                let equal = |v: &str| match (field, parse_network(value)) {
+                    (Field::Ip, Some(network)) => v.parse().is_ok_and(|ip| in_network(ip, network)),
+                    _ => compare(v, value) == Ordering::Equal,
+                };
+                match op {
+                    Op::Eq => values.iter().any(|v| equal(v)),
+                    Op::Ne => !values.iter().any(|v| equal(v)),
+                    op => values.iter().any(|v| op.accepts(compare(v, value))),
+                }
+            }
+            Predicate::Text(text) => {
+                let text = text.to_lowercase();
+                TEXT_FIELDS
+                    .iter()
+                    .flat_map(|field| field.values(event, scripts))
+                    .any(|value| value.to_lowercase().contains(&text))
+            }
+            Predicate::Match {
+                field,
+                pattern,
+                negated,
+            } => {
+                let fields = match field {
+                    Some(field) => std::slice::from_ref(field),
+                    None => &TEXT_FIELDS,
+                };
+                let matched = fields
+                    .iter()
+                    .flat_map(|field| field.values(event, scripts))
+                    .any(|value| pattern.is_match(&value));
+                matched != *negated
+            }
+        }
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::Compare { field, op, value } => {
+                write!(f, "{}{}{}", field, op, Quoted(value))
+            }
+            Predicate::Text(text) => write!(f, "{}", Quoted(text)),
+            Predicate::Match {
+                field,
+                pattern,
+                negated,
+            } => {
+                if let Some(field) = field {
+                    write!(f, "{}", field)?;
+                }
+                let op = if *negated { "!~" } else { "~" };
+                write!(f, "{}{}", op, Quoted(pattern.as_str()))
+            }
+        }
+    }
+}
+
+/// Compare numerically when both sides are numbers, otherwise as strings.
+fn compare(left: &str, right: &str) -> Ordering {
+    match (left.parse::<f64>(), right.parse::<f64>()) {
+        (Ok(left), Ok(right)) => left.partial_cmp(&right).unwrap_or(Ordering::Equal),
+        _ => left.cmp(right),
+    }
+}
+
+/// Parse a network written `address/prefix`, like `10.0.0.0/8`.
+pub(crate) fn parse_network(s: &str) -> Option<(IpAddr, u32)> {
+    let (address, prefix) = s.split_once('/')?;
+    let address = address.parse::<IpAddr>().ok()?;
+    let prefix = prefix.parse::<u32>().ok()?;
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    (prefix <= bits).then_some((address, prefix))
+}
+
+/// Whether `ip` is in the network, never if they are different families.
+pub(crate) fn in_network(ip: IpAddr, (address, prefix): (IpAddr, u32)) -> bool {
+    match (ip, address) {
+        (IpAddr::V4(ip), IpAddr::V4(address)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(address) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(address)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(address) & mask
+        }
+        _ => false,
+    }
+}
+
+/// The parts of an event that can be filtered on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// The value of an annotation, written `annotation[key]`.
+    Annotation(String),
+    /// The values of a query string parameter, written `query[name]`.
+    Query(String),
+    /// The values at a jq style path into the event.
+    Path(JsonPath),
+    /// The values of the script with this name, written `script[name]`.
+    Script(String),
+    /// A field worked out for the event as it was read, written
+    /// `enriched[key]`.
+    Enriched(String),
+    /// The API group of the object, `core` for the legacy `/api` group.
+    ApiGroup,
+    /// The version of the object's API, like `v1` or `v1beta1`, without
+    /// the group the `apiversion` enriched field has.
+    Version,
+    AuditId,
+    Cluster,
+    /// The HTTP status code of the response.
+    Status,
+    Group,
+    /// The source IPs of the request, which `=` also matches against
+    /// networks like `10.0.0.0/8`.
+    Ip,
+    Level,
+    Name,
+    Namespace,
+    /// The resource without any subresource, so `pods` for `pods/status`.
+    Resource,
+    /// Whether the request was `namespaced`, `cluster` wide, or for a
+    /// `nonresource` URL.
+    Scope,
+    /// The subresource, like `status`, `scale`, `exec`, `log` or `token`,
+    /// none for the resource itself.
+    Subresource,
+    Stage,
+    Uri,
+    User,
+    UserAgent,
+    /// The client binary from the user agent, like `kubectl`.
+    Client,
+    /// The version of the client binary, like `v1.30.0`.
+    ClientVersion,
+    /// The operating system and architecture of the client, like
+    /// `linux/amd64`.
+    Platform,
+    Verb,
+    /// The whole object reference, as displayed.
+    Object,
+    /// Whether the request was a dry run, `true` or `false`.
+    DryRun,
+    /// The field manager of a server-side apply, none for anything else.
+    Apply,
+    /// Whether an update or patch wrote its object differently to how it was
+    /// last applied with `kubectl apply`, `true` or `false`.
+    Drift,
+    /// How many bytes the request body is, serialised.
+    RequestSize,
+    /// How many bytes the response body is, serialised.
+    ResponseSize,
+}
+
+impl Field {
+    /// The values of this field on the event, empty when it has none, with
+    /// `script[name]` run by `scripts`.
+    pub fn values<'a>(&self, event: &'a EventV1, scripts: &Scripts) -> Vec<Cow<'a, str>> {
+        let object_ref = event.object_ref.as_ref();
+        match self {
+            Field::Annotation(key) => event
+                .annotations
+                .get(key)
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Query(name) => event
+                .query()
+                .into_iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| Cow::from(value.into_owned()))
+                .collect(),
+            Field::Path(path) => path.values(event),
+            // a script going wrong is shown in its column instead
+            Field::Script(name) => scripts
+                .values(name, event)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Cow::from)
+                .collect(),
+            Field::Enriched(key) => event.enriched.get(key).map(Cow::from).into_iter().collect(),
+            Field::ApiGroup => api_group(event).into_iter().collect(),
+            Field::Version => version(event).into_iter().collect(),
+            Field::AuditId => vec![Cow::from(&event.audit_id)],
+            Field::Cluster => event.source.iter().map(Cow::from).collect(),
+            Field::Status => event
+                .response_status
+                .iter()
+                .map(|status| Cow::from(status.code.to_string()))
+                .collect(),
+            Field::Group => event.user.groups.iter().map(Cow::from).collect(),
+            Field::Ip => event
+                .source_ips
+                .iter()
+                .flatten()
+                .map(|ip| Cow::from(ip.to_string()))
+                .collect(),
+            Field::Level => vec![Cow::from(event.level.to_string())],
+            Field::Name => object_ref
+                .and_then(|o| o.name.as_ref())
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Namespace => object_ref
+                .and_then(|o| o.namespace.as_ref())
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Resource => object_ref
+                .and_then(|o| o.resource.as_ref())
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Scope => vec![Cow::from(event.scope().to_string())],
+            Field::Subresource => object_ref
+                .and_then(|o| o.subresource.as_ref())
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Stage => vec![Cow::from(event.stage.to_string())],
+            Field::Uri => vec![Cow::from(&event.request_uri)],
+            Field::User => vec![Cow::from(&event.user.username)],
+            Field::UserAgent => event.user_agent.iter().map(Cow::from).collect(),
+            Field::Client => Agent::of(event)
+                .map(|agent| Cow::from(agent.name))
+                .into_iter()
+                .collect(),
+            Field::ClientVersion => Agent::of(event)
+                .and_then(|agent| agent.version)
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Platform => Agent::of(event)
+                .and_then(|agent| agent.platform)
+                .map(Cow::from)
+                .into_iter()
+                .collect(),
+            Field::Verb => vec![Cow::from(&event.verb)],
+            Field::Object => object_ref
+                .map(|o| Cow::from(o.to_string()))
+                .into_iter()
+                .collect(),
+            Field::DryRun => vec![Cow::from(event.is_dry_run().to_string())],
+            Field::Apply => event.applied_by().map(Cow::from).into_iter().collect(),
+            Field::Drift => vec![Cow::from(drift::drifted(event).to_string())],
+            Field::RequestSize => size::request_bytes(event)
+                .map(|bytes| Cow::from(bytes.to_string()))
+                .into_iter()
+                .collect(),
+            Field::ResponseSize => size::response_bytes(event)
+                .map(|bytes| Cow::from(bytes.to_string()))
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// The API group from the object reference, or failing that from the URI.
+fn api_group(event: &EventV1) -> Option<Cow<'_, str>> {
+    let from_ref = event
+        .object_ref
+        .as_ref()
+        .and_then(|o| o.api_group.as_deref())
+        .filter(|group| !group.is_empty());
+    if let Some(group) = from_ref {
+        return Some(Cow::from(group));
+    }
+    event.group_version().map(|(group, _)| Cow::from(group))
+}
+
+/// The version of the API from the object reference, or failing that from
+/// the URI.
+fn version(event: &EventV1) -> Option<Cow<'_, str>> {
+    let from_ref = event
+        .object_ref
+        .as_ref()
+        .and_then(|o| o.api_version.as_deref())
+        .filter(|version| !version.is_empty());
+    from_ref.or_else(|| event.group_version()?.1).map(Cow::from)
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let annotation = s
+            .strip_prefix("annotation[")
+            .and_then(|rest| rest.strip_suffix(']'));
+        if let Some(key) = annotation {
+            return Ok(Field::Annotation(key.to_string()));
+        }
+        let query = s
+            .strip_prefix("query[")
+            .and_then(|rest| rest.strip_suffix(']'));
+        if let Some(name) = query {
+            return Ok(Field::Query(name.to_string()));
+        }
+        let script = s
+            .strip_prefix("script[")
+            .and_then(|rest| rest.strip_suffix(']'));
+        if let Some(name) = script {
+            return Ok(Field::Script(name.to_string()));
+        }
+        let enriched = s
+            .strip_prefix("enriched[")
+            .and_then(|rest| rest.strip_suffix(']'));
+        if let Some(key) = enriched {
+            return Ok(Field::Enriched(key.to_string()));
+        }
+        if s.starts_with('.') {
+            return Ok(Field::Path(s.parse()?));
+        }
+
+        Ok(match s.to_lowercase().as_str() {
+            "apigroup" => Field::ApiGroup,
+            "version" => Field::Version,
+            "auditid" | "id" => Field::AuditId,
+            "cluster" | "source" => Field::Cluster,
+            "status" | "code" => Field::Status,
+            "group" => Field::Group,
+            "ip" | "sourceip" => Field::Ip,
+            "level" => Field::Level,
+            "name" => Field::Name,
+            "namespace" | "ns" => Field::Namespace,
+            "resource" => Field::Resource,
+            "scope" => Field::Scope,
+            "subresource" => Field::Subresource,
+            "stage" => Field::Stage,
+            "uri" => Field::Uri,
+            "user" => Field::User,
+            "useragent" | "ua" => Field::UserAgent,
+            "client" => Field::Client,
+            "clientversion" => Field::ClientVersion,
+            "platform" => Field::Platform,
+            "verb" => Field::Verb,
+            "object" => Field::Object,
+            "dryrun" => Field::DryRun,
+            "apply" => Field::Apply,
+            "drift" => Field::Drift,
+            "requestsize" => Field::RequestSize,
+            "responsesize" => Field::ResponseSize,
+            _ => anyhow::bail!("unknown field {:?}", s),
+        })
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Field::Annotation(key) => return write!(f, "annotation[{}]", key),
+            Field::Query(name) => return write!(f, "query[{}]", name),
+            Field::Path(path) => return write!(f, "{}", path),
+            Field::Script(name) => return write!(f, "script[{}]", name),
+            Field::Enriched(key) => return write!(f, "enriched[{}]", key),
+            Field::ApiGroup => "apigroup",
+            Field::Version => "version",
+            Field::AuditId => "auditid",
+            Field::Cluster => "cluster",
+            Field::Status => "status",
+            Field::Group => "group",
+            Field::Ip => "ip",
+            Field::Level => "level",
+            Field::Name => "name",
+            Field::Namespace => "namespace",
+            Field::Resource => "resource",
+            Field::Scope => "scope",
+            Field::Subresource => "subresource",
+            Field::Stage => "stage",
+            Field::Uri => "uri",
+            Field::User => "user",
+            Field::UserAgent => "useragent",
+            Field::Client => "client",
+            Field::ClientVersion => "clientversion",
+            Field::Platform => "platform",
+            Field::Verb => "verb",
+            Field::Object => "object",
+            Field::DryRun => "dryrun",
+            Field::Apply => "apply",
+            Field::Drift => "drift",
+            Field::RequestSize => "requestsize",
+            Field::ResponseSize => "responsesize",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    fn accepts(&self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// Displays a value, quoting it if it wouldn't survive being parsed again.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bare = !self.0.is_empty()
+            && !self.0.chars().any(|c| c.is_whitespace() || is_special(c))
+            && !is_keyword(self.0);
+        if bare {
+            write!(f, "{}", self.0)
+        } else {
+            write!(
+                f,
+                "\"{}\"",
+                self.0.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        }
+    }
+}
+
+fn is_special(c: char) -> bool {
+    matches!(c, '(' | ')' | '"' | '=' | '!' | '~' | '<' | '>')
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(word.to_lowercase().as_str(), "and" | "or" | "not")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Not,
+    Op(Op),
+    /// `~`, or `!~` when negated.
+    Match {
+        negated: bool,
+    },
+    Word(String),
+    Quoted(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::Match { negated: false } => write!(f, "'~'"),
+            Token::Match { negated: true } => write!(f, "'!~'"),
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Quoted(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '=' => Token::Op(Op::Eq),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ne),
+            '!' if chars.next_if_eq(&'~').is_some() => Token::Match { negated: true },
+            '~' => Token::Match { negated: false },
+            '!' => Token::Not,
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => text.push(c),
+                        None => anyhow::bail!("unterminated quote"),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c if is_special(c) => anyhow::bail!("unexpected '{}'", c),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !is_special(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<Filter> {
+        let mut filters = vec![self.parse_and()?];
+        while self.peek_keyword("or") {
+            self.next();
+            filters.push(self.parse_and()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::Or(filters),
+        })
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<Filter> {
+        let mut filters = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                None | Some(Token::RParen) => break,
+                _ if self.peek_keyword("or") => break,
+                _ if self.peek_keyword("and") => {
+                    self.next();
+                }
+                _ => {}
+            }
+            filters.push(self.parse_unary()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::And(filters),
+        })
+    }
+
+    /// The pattern after a `~` or `!~`.
+    fn parse_match(&mut self, field: Option<Field>, negated: bool) -> anyhow::Result<Filter> {
+        let pattern = match self.next() {
+            Some(Token::Word(pattern) | Token::Quoted(pattern)) => pattern,
+            _ => anyhow::bail!("missing pattern after '~'"),
+        };
+        let pattern = Pattern::new(&pattern)?;
+        Ok(Filter::Predicate(Predicate::Match {
+            field,
+            pattern,
+            negated,
+        }))
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<Filter> {
+        if self.peek_keyword("not") {
+            self.next();
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+
+        match self.next() {
+            Some(Token::Not) => Ok(Filter::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let filter = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(filter),
+                    _ => anyhow::bail!("missing ')'"),
+                }
+            }
+            Some(Token::Word(word)) => match self.peek() {
+                Some(Token::Op(op)) => {
+                    let op = *op;
+                    self.next();
+                    let field = word.parse()?;
+                    let value = match self.next() {
+                        Some(Token::Word(value) | Token::Quoted(value)) => value,
+                        _ => anyhow::bail!("missing value after {}{}", word, op),
+                    };
+                    if field == Field::Ip && value.contains('/') && parse_network(&value).is_none()
+                    {
+                        anyhow::bail!("invalid network {:?}", value);
+                    }
+                    Ok(Filter::Predicate(Predicate::Compare { field, op, value }))
+                }
+                Some(Token::Match { negated }) => {
+                    let negated = *negated;
+                    self.next();
+                    let field = word.parse()?;
+                    self.parse_match(Some(field), negated)
+                }
+                _ => Ok(Filter::Predicate(Predicate::Text(word))),
+            },
+            Some(Token::Match { negated }) => self.parse_match(None, negated),
+            Some(Token::Quoted(text)) => Ok(Filter::Predicate(Predicate::Text(text))),
+            Some(token) => anyhow::bail!("unexpected {}", token),
+            None => anyhow::bail!("unexpected end of filter"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event() -> EventV1 {
+        test_event()
+            .uri("/api/v1/namespaces/kube-system/secrets/token")
+            .verb("get")
+            .groups(&["dev", "system:authenticated"])
+            .object_ref(serde_json::json!({
+                "resource": "secrets",
+                "namespace": "kube-system",
+                "name": "token",
+                "apiVersion": "v1",
+            }))
+            .code(403)
+            .set("sourceIPs", serde_json::json!(["10.1.2.3", "fd00::1"]))
+            .annotations(serde_json::json!({"authorization.k8s.io/decision": "forbid"}))
+            .build()
+    }
+
+    fn eval(filter: &str) -> bool {
+        filter
+            .parse::<Filter>()
+            .expect("valid filter")
+            .eval(&event(), &Scripts::new())
+    }
+
+    #[test]
+    fn scripts() {
+        let mut scripts = Scripts::new();
+        scripts
+            .register("kind", r#"event.objectRef.resource + "/" + event.verb"#)
+            .unwrap();
+        let filter = "script[kind]=secrets/get".parse::<Filter>().unwrap();
+        assert!(filter.eval(&event(), &scripts));
+        assert!(!filter.eval(&event(), &Scripts::new()));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(eval("verb=get"));
+        assert!(eval("verb != list"));
+        assert!(eval("group=dev"));
+        assert!(!eval("group!=dev"));
+        assert!(!eval("namespace=default"));
+        assert!(eval("verb=get ns=kube-system"));
+        assert!(eval("status=403"));
+        assert!(eval("status>=400 status<500"));
+        assert!(!eval("status!=403"));
+        assert!(eval("level=Metadata stage!=RequestReceived"));
+        assert!(eval("annotation[authorization.k8s.io/decision]=forbid"));
+        assert!(!eval("annotation[authorization.k8s.io/reason]=forbid"));
+    }
+
+    #[test]
+    fn regexes() {
+        assert!(eval(r#"uri~"^/api/v1/namespaces/[^/]+/secrets/""#));
+        assert!(!eval(r#"uri!~"/secrets/""#));
+        assert!(eval("user~^al"));
+        assert!(!eval("~TOKEN"));
+        assert!(eval(r#"~"(?i)TOKEN""#));
+        assert!(!eval("not ~token"));
+    }
+
+    #[test]
+    fn versions() {
+        // the version alone is a field, the group and version enriched
+        assert_eq!("version".parse::<Field>().unwrap(), Field::Version);
+        assert!("apiversion".parse::<Field>().is_err());
+        let mut event = event();
+        event.request_uri = "/apis/apps/v1/namespaces/web/deployments".into();
+        event.object_ref = None;
+        event
+            .enriched
+            .insert("apiversion".to_string(), "apps/v1".to_string());
+        let filter = |text: &str| {
+            text.parse::<Filter>()
+                .unwrap()
+                .eval(&event, &Scripts::new())
+        };
+        assert!(filter("version=v1 and enriched[apiversion]=apps/v1"));
+    }
+
+    #[test]
+    fn api_groups() {
+        assert!(eval("apigroup=core"));
+        let mut event = event();
+        event.request_uri = "/apis/apiextensions.k8s.io/v1/customresourcedefinitions".into();
+        event.object_ref = None;
+        assert!("apigroup=apiextensions.k8s.io"
+            .parse::<Filter>()
+            .unwrap()
+            .eval(&event, &Scripts::new()));
+        assert!("version=v1"
+            .parse::<Filter>()
+            .unwrap()
+            .eval(&event, &Scripts::new()));
+
+        event.request_uri =
+            "/apis/autoscaling/v2beta2/namespaces/web/horizontalpodautoscalers".into();
+        let filter = |text: &str| {
+            text.parse::<Filter>()
+                .unwrap()
+                .eval(&event, &Scripts::new())
+        };
+        assert!(filter("apigroup=autoscaling and version~beta"));
+        event.request_uri = "/apis/autoscaling".into();
+        assert!("apigroup=autoscaling and not version~."
+            .parse::<Filter>()
+            .unwrap()
+            .eval(&event, &Scripts::new()));
+    }
+
+    #[test]
+    fn subresources() {
+        assert!(eval("resource=secrets and subresource!=status"));
+        let mut event = event();
+        event.verb = "update".into();
+        let object = event.object_ref.as_mut().unwrap();
+        object.resource = Some("pods".into());
+        object.subresource = Some("status".into());
+        let filter = |text: &str| {
+            text.parse::<Filter>()
+                .unwrap()
+                .eval(&event, &Scripts::new())
+        };
+        assert!(filter("resource=pods"));
+        assert!(!filter("resource=\"pods/status\""));
+        assert!(filter("subresource=status"));
+        assert!(!filter("resource=pods and subresource!=status"));
+    }
+
+    #[test]
+    fn scopes() {
+        assert!(eval("scope=namespaced"));
+        let mut event = event();
+        event.request_uri = "/api/v1/nodes/worker-1".into();
+        event.object_ref =
+            serde_json::from_value(serde_json::json!({"resource": "nodes", "name": "worker-1"}))
+                .ok();
+        let scope = |event: &EventV1| {
+            let filter = |text: &str| text.parse::<Filter>().unwrap().eval(event, &Scripts::new());
+            ["namespaced", "cluster", "nonresource"]
+                .into_iter()
+                .find(|scope| filter(&format!("scope={}", scope)))
+        };
+        assert_eq!(scope(&event), Some("cluster"));
+        event.request_uri = "/api/v1/namespaces/web".into();
+        event.object_ref = serde_json::from_value(
+            serde_json::json!({"resource": "namespaces", "namespace": "web", "name": "web"}),
+        )
+        .ok();
+        assert_eq!(scope(&event), Some("cluster"));
+        event.request_uri = "/openid/v1/jwks".into();
+        event.object_ref = None;
+        assert_eq!(scope(&event), Some("nonresource"));
+
+        // discovery is under /apis but the apiserver gives it no reference
+        event.request_uri = "/apis/metrics.k8s.io".into();
+        assert!(!event.is_resource_request());
+        assert_eq!(scope(&event), Some("nonresource"));
+
+        // proxying through a service is a request for its proxy subresource
+        event.request_uri = "/api/v1/namespaces/web/services/api:8080/proxy/metrics".into();
+        event.object_ref = serde_json::from_value(serde_json::json!({
+            "resource": "services",
+            "namespace": "web",
+            "name": "api:8080",
+            "subresource": "proxy",
+        }))
+        .ok();
+        assert!(event.is_resource_request());
+        assert_eq!(scope(&event), Some("namespaced"));
+    }
+
+    #[test]
+    fn networks() {
+        assert!(eval("ip=10.1.2.3"));
+        assert!(eval("ip=10.0.0.0/8"));
+        assert!(eval("ip=10.1.2.3/32"));
+        assert!(!eval("ip=192.168.0.0/16"));
+        assert!(eval("ip=fd00::/8"));
+        assert!(eval("ip=0.0.0.0/0"));
+        assert!(!eval("ip!=10.0.0.0/8"));
+        assert!("ip=10.0.0.0/33".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn combinators() {
+        assert!(eval("verb=list or resource=secrets"));
+        assert!(!eval("not (verb=get or verb=list)"));
+        assert!(eval("!verb=watch and user=alice"));
+        assert!(eval("SECRETS"));
+        assert!(!eval("\"config maps\""));
+    }
+
+    #[test]
+    fn query_parameters() {
+        let mut event = event();
+        event.request_uri =
+            "/api/v1/pods?labelSelector=app%3Dweb&watch=true&command=sh&command=-c".to_string();
+        let eval = |filter: &str| {
+            filter
+                .parse::<Filter>()
+                .unwrap()
+                .eval(&event, &Scripts::new())
+        };
+        assert!(eval("query[watch]=true"));
+        assert!(eval("query[labelSelector]=\"app=web\""));
+        assert!(eval("query[command]=-c"));
+        assert!(!eval("query[fieldSelector]~."));
+        assert_eq!(api_group(&event).as_deref(), Some("core"));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for filter in [
+            "verb=get and (user=alice or user=\"system:admin bob\")",
+            "not (verb=get or verb=list) and secrets",
+            "annotation[mutation.webhook.admission.k8s.io/round_0]!=x",
+            "query[labelSelector]=\"app=web\"",
+            "enriched[owner]=platform and script[bot]=true",
+            "responsesize>1000000 or requestsize>=512",
+            "dryrun=false and apply!=kubectl and drift=true",
+            "resource=pods and subresource!=status",
+            "apigroup=apps and version!=v1",
+            "scope=cluster or scope=nonresource",
+            "client=kubectl and clientversion!=v0.0.0 and platform~darwin",
+            "uri~/apis/apps/v1/namespaces/.*/deployments and ~\"^(alice|bob)$\"",
+        ] {
+            let parsed = filter.parse::<Filter>().expect("valid filter");
+            assert_eq!(parsed.to_string(), filter);
+            assert_eq!(parsed.to_string().parse::<Filter>().unwrap(), parsed);
+        }
+    }
+
+    #[test]
+    fn replacing_a_field() {
+        let filter = "verb=get ns=default secrets".parse::<Filter>().unwrap();
+        let filter = Filter::refine(
+            filter.without_field(&Field::Namespace),
+            Filter::field_eq(Field::Namespace, "kube-system"),
+        );
+        assert_eq!(
+            filter.to_string(),
+            "verb=get and secrets and namespace=kube-system"
+        );
+        assert_eq!(
+            Filter::refine(None, Filter::field_eq(Field::Verb, "get")).to_string(),
+            "verb=get"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!("colour=red".parse::<Filter>().is_err());
+        assert!("(verb=get".parse::<Filter>().is_err());
+        assert!("verb=".parse::<Filter>().is_err());
+        assert!("\"open".parse::<Filter>().is_err());
+        assert!("uri~\"(\"".parse::<Filter>().is_err());
+        assert!("uri~".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! Folding away the parts of request and response objects that are rarely
+//! what's being looked for, so that the spec is the first thing seen.
+
+use serde_json::Value;
+
+const LAST_APPLIED: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// A copy of an object with its `metadata.managedFields`, last applied
+/// configuration and, unless `keep_status`, its `status` replaced by notes
+/// saying what was there. The items of lists are folded the same way.
+pub fn fold(value: &Value, keep_status: bool) -> Value {
+    let mut value = value.clone();
+    fold_object(&mut value, keep_status);
+    if let Some(Value::Array(items)) = value.get_mut("items") {
+        for item in items {
+            fold_object(item, keep_status);
+        }
+    }
+    value
+}
+
+fn fold_object(value: &mut Value, keep_status: bool) {
+    let Value::Object(object) = value else {
+        return;
+    };
+
+    if let Some(Value::Object(metadata)) = object.get_mut("metadata") {
+        if let Some(Value::Array(fields)) = metadata.get("managedFields") {
+            let note = format!("<{} managed fields folded>", fields.len());
+            metadata.insert("managedFields".to_string(), Value::String(note));
+        }
+        if let Some(Value::Object(annotations)) = metadata.get_mut("annotations") {
+            if let Some(Value::String(applied)) = annotations.get(LAST_APPLIED) {
+                let note = format!("<{} bytes folded>", applied.len());
+                annotations.insert(LAST_APPLIED.to_string(), Value::String(note));
+            }
+        }
+    }
+
+    // a Status response's status is just "Success" or "Failure"
+    if !keep_status {
+        if let Some(Value::Object(status)) = object.get("status") {
+            let note = format!("<{} fields folded>", status.len());
+            object.insert("status".to_string(), Value::String(note));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn folding() {
+        let pod = json!({
+            "metadata": {
+                "name": "web",
+                "managedFields": [{"manager": "kubectl"}, {"manager": "kubelet"}],
+                "annotations": {LAST_APPLIED: "{}\n", "team": "web"},
+            },
+            "spec": {"nodeName": "node-1"},
+            "status": {"phase": "Running", "podIP": "10.0.0.1"},
+        });
+        assert_eq!(
+            fold(&pod, false),
+            json!({
+                "metadata": {
+                    "name": "web",
+                    "managedFields": "<2 managed fields folded>",
+                    "annotations": {LAST_APPLIED: "<3 bytes folded>", "team": "web"},
+                },
+                "spec": {"nodeName": "node-1"},
+                "status": "<2 fields folded>",
+            })
+        );
+        assert_eq!(fold(&pod, true)["status"]["phase"], "Running");
+
+        let list = json!({"kind": "PodList", "items": [pod]});
+        assert_eq!(
+            fold(&list, false)["items"][0]["metadata"]["managedFields"],
+            "<2 managed fields folded>"
+        );
+
+        let status = json!({"kind": "Status", "status": "Failure", "code": 404});
+        assert_eq!(fold(&status, false), status);
+    }
+}
@@ -0,0 +1,188 @@
+//! Grouping the events in the table by a field, with a row for each of its
+//! values that opens out into the events with it.
+
+use crate::filter::Field;
+use crate::script::Scripts;
+use crate::store::EventStore;
+use std::collections::{BTreeSet, HashMap};
+
+/// The fields that can be grouped by, in the order `G` cycles through them.
+pub const GROUP_FIELDS: [Field; 5] = [
+    Field::User,
+    Field::Namespace,
+    Field::Resource,
+    Field::Verb,
+    Field::Status,
+];
+
+/// What events without a value for the field are grouped under.
+pub const NO_VALUE: &str = "(none)";
+
+/// A row of the grouped table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupRow {
+    /// A value of the field, with how many events have it and the position
+    /// among the visible events of the first of them.
+    Group {
+        value: String,
+        count: usize,
+        expanded: bool,
+        first: usize,
+    },
+    /// An event in an expanded group, by its position among the visible
+    /// events.
+    Event(usize),
+}
+
+impl GroupRow {
+    /// The position among the visible events of the event the row stands for.
+    pub fn position(&self) -> usize {
+        match self {
+            GroupRow::Group { first, .. } => *first,
+            GroupRow::Event(position) => *position,
+        }
+    }
+}
+
+/// The visible events grouped by a field, with which groups are expanded and
+/// which row is selected.
+#[derive(Debug)]
+pub struct Grouping {
+    pub field: Field,
+    expanded: BTreeSet<String>,
+    selected: usize,
+    /// The first row drawn in the table.
+    pub(crate) offset: usize,
+}
+
+impl Grouping {
+    pub fn new(field: Field) -> Self {
+        Self {
+            field,
+            expanded: BTreeSet::new(),
+            selected: 0,
+            offset: 0,
+        }
+    }
+
+    /// A row for each group, largest first, followed by the events in it if
+    /// it's expanded.
+    pub fn rows(&self, store: &EventStore, scripts: &Scripts, visible: &[usize]) -> Vec<GroupRow> {
+        let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, index) in visible.iter().enumerate() {
+            let value = match self.field.values(&store[*index], scripts).first() {
+                Some(value) => value.to_string(),
+                None => NO_VALUE.to_string(),
+            };
+            groups.entry(value).or_default().push(position);
+        }
+        let mut groups = groups.into_iter().collect::<Vec<_>>();
+        groups.sort_by(|(a, a_positions), (b, b_positions)| {
+            b_positions.len().cmp(&a_positions.len()).then(a.cmp(b))
+        });
+
+        let mut rows = Vec::new();
+        for (value, positions) in groups {
+            let expanded = self.expanded.contains(&value);
+            rows.push(GroupRow::Group {
+                count: positions.len(),
+                first: positions[0],
+                expanded,
+                value,
+            });
+            if expanded {
+                rows.extend(positions.into_iter().map(GroupRow::Event));
+            }
+        }
+        rows
+    }
+
+    /// The selected row, kept among `rows`.
+    pub fn selected(&mut self, rows: &[GroupRow]) -> Option<usize> {
+        self.selected = self.selected.min(rows.len().checked_sub(1)?);
+        Some(self.selected)
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn next(&mut self, rows: &[GroupRow]) {
+        self.selected = (self.selected + 1).min(rows.len().saturating_sub(1));
+    }
+
+    /// Open out the selected group into its events.
+    pub fn expand(&mut self, rows: &[GroupRow]) {
+        if let Some(GroupRow::Group { value, .. }) = rows.get(self.selected) {
+            self.expanded.insert(value.clone());
+        }
+    }
+
+    /// Close the group the selected row is in, selecting its row.
+    pub fn collapse(&mut self, rows: &[GroupRow]) {
+        let group = rows[..rows.len().min(self.selected + 1)]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, row)| match row {
+                GroupRow::Group { value, .. } => Some((i, value)),
+                GroupRow::Event(_) => None,
+            });
+        if let Some((i, value)) = group {
+            self.expanded.remove(value);
+            self.selected = i;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::kube::EventV1;
+
+    fn event(user: &str, timestamp: &str) -> EventV1 {
+        test_event().user(user).at(timestamp).build()
+    }
+
+    #[test]
+    fn grouping() {
+        let mut store = EventStore::new();
+        store.insert(event("alice", "2024-07-01T10:00:00Z"));
+        store.insert(event("bob", "2024-07-01T10:00:01Z"));
+        store.insert(event("bob", "2024-07-01T10:00:02Z"));
+        let visible = [0, 1, 2];
+
+        let mut grouping = Grouping::new(Field::User);
+        let rows = grouping.rows(&store, &Scripts::new(), &visible);
+        assert_eq!(
+            rows,
+            [
+                GroupRow::Group {
+                    value: "bob".to_string(),
+                    count: 2,
+                    expanded: false,
+                    first: 1
+                },
+                GroupRow::Group {
+                    value: "alice".to_string(),
+                    count: 1,
+                    expanded: false,
+                    first: 0
+                },
+            ]
+        );
+
+        grouping.expand(&rows);
+        let rows = grouping.rows(&store, &Scripts::new(), &visible);
+        assert_eq!(rows[1..3], [GroupRow::Event(1), GroupRow::Event(2)]);
+        grouping.next(&rows);
+        grouping.next(&rows);
+        assert_eq!(grouping.selected(&rows), Some(2));
+
+        grouping.collapse(&rows);
+        let rows = grouping.rows(&store, &Scripts::new(), &visible);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(grouping.selected(&rows), Some(0));
+    }
+}
@@ -1,37 +1,277 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct EventV1 {
-    _kind: String,
-    _api_version: String,
+    pub kind: String,
+    pub api_version: String,
     pub level: Level,
+    /// Usually a UUID, but some distributions and older versions use other
+    /// formats, or take it from a client-supplied `Audit-ID` header.
     #[serde(rename = "auditID")]
-    pub audit_id: Uuid,
+    pub audit_id: String,
     pub stage: Stage,
     #[serde(rename = "requestURI")]
     pub request_uri: String,
     pub verb: String,
     pub user: UserInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub impersonated_user: Option<UserInfo>,
-    #[serde(rename = "sourceIPs")]
+    #[serde(rename = "sourceIPs", skip_serializing_if = "Option::is_none")]
     pub source_ips: Option<Vec<std::net::IpAddr>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub object_ref: Option<ObjectReference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub response_status: Option<ResponseStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub request_object: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub response_object: Option<Value>,
     pub request_received_timestamp: DateTime<Utc>,
     pub stage_timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub annotations: HashMap<String, String>,
+    /// The label of the input this event was read from, when reading from
+    /// several clusters at once.
+    #[serde(skip)]
+    pub source: Option<String>,
+    /// Fields worked out for the event as it was read, by key, see
+    /// [`crate::enrich`].
+    #[serde(skip)]
+    pub enriched: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// How much of the cluster a request was about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Things in one namespace.
+    Namespaced,
+    /// Cluster-scoped things, or namespaced things across every namespace.
+    Cluster,
+    /// Not things in the cluster, like `/healthz`, `/version` or
+    /// `/openid/v1/jwks`.
+    NonResource,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Scope::Namespaced => "namespaced",
+            Scope::Cluster => "cluster",
+            Scope::NonResource => "nonresource",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl EventV1 {
+    /// Whether the event refers to things in the cluster, as opposed to
+    /// non-resource URLs like `/healthz` or `/version`, going by whether the
+    /// apiserver gave it an object reference, as it does for every resource
+    /// request, proxied ones included, and no non-resource one, discovery
+    /// like `/apis/apps` included.
+    pub fn is_resource_request(&self) -> bool {
+        self.object_ref.is_some()
+    }
+
+    /// Whether the request was about things in one namespace, across the
+    /// cluster, or not about things in the cluster at all.
+    pub fn scope(&self) -> Scope {
+        let Some(object) = &self.object_ref else {
+            return Scope::NonResource;
+        };
+        // a namespace, and its subresources, are in its own namespace too
+        if object.resource.as_deref() == Some("namespaces") {
+            return Scope::Cluster;
+        }
+        let mut segments = self.path().split('/').skip(1);
+        let under_version = match segments.next() {
+            Some("api") => segments.nth(1),
+            Some("apis") => segments.nth(2),
+            _ => None,
+        };
+        let namespaced = match under_version {
+            // `namespaces/<namespace>/<resource>...`
+            Some("namespaces") => segments.nth(1).is_some(),
+            Some(_) => false,
+            // not a URI the apiserver routes by, so go by the reference
+            None => object
+                .namespace
+                .as_deref()
+                .is_some_and(|namespace| !namespace.is_empty()),
+        };
+        match namespaced {
+            true => Scope::Namespaced,
+            false => Scope::Cluster,
+        }
+    }
+
+    /// The request URI without its query string.
+    pub fn path(&self) -> &str {
+        self.request_uri.split('?').next().unwrap_or_default()
+    }
+
+    /// The API group and version the request URI is under, the group being
+    /// `core` for the legacy `/api` group, like `("apps", Some("v1"))` for
+    /// `/apis/apps/v1/deployments`, and the version missing for discovery of
+    /// a group's versions.
+    pub fn group_version(&self) -> Option<(&str, Option<&str>)> {
+        let mut segments = self.path().split('/').skip(1);
+        let (group, version) = match segments.next()? {
+            "api" => ("core", segments.next()),
+            "apis" => (segments.next()?, segments.next()),
+            _ => return None,
+        };
+        Some((group, version.filter(|version| !version.is_empty())))
+    }
+
+    /// The decoded parameters of the query string, in order, with repeated
+    /// ones like exec's `command` given once for each value.
+    pub fn query(&self) -> Vec<(Cow<'_, str>, Cow<'_, str>)> {
+        match self.request_uri.split_once('?') {
+            Some((_, query)) => form_urlencoded::parse(query.as_bytes()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether the request was a dry run, checked and admitted but not
+    /// persisted.
+    pub fn is_dry_run(&self) -> bool {
+        self.query()
+            .iter()
+            .any(|(key, value)| key == "dryRun" && !value.is_empty())
+    }
+
+    /// The field manager of a server-side apply: a patch by a field manager
+    /// sending a whole object, as the content type that says so isn't logged.
+    pub fn applied_by(&self) -> Option<String> {
+        if self.verb != "patch" {
+            return None;
+        }
+        let body = self.request_object.as_ref()?;
+        if body.get("apiVersion").is_none() || body.get("kind").is_none() {
+            return None;
+        }
+        self.query()
+            .into_iter()
+            .find(|(key, _)| key == "fieldManager")
+            .map(|(_, manager)| manager.into_owned())
+    }
+}
+
+/// Deserialise a single JSON document into the audit events it contains.
+///
+/// A document is either a single `Event`, or an `EventList` as sent by the
+/// webhook backend and some exporters, which is flattened into its items.
+/// Events from `audit.k8s.io/v1alpha1` and `audit.k8s.io/v1beta1` are
+/// normalised into the v1 schema, and log shipper envelopes are unwrapped
+/// first, see [`unwrap_envelope`].
+pub fn events_from_value(value: Value) -> serde_json::Result<Vec<EventV1>> {
+    let mut value = unwrap_envelope(value)?;
+    if value.get("kind").and_then(Value::as_str) == Some("EventList") {
+        let items = match value.get_mut("items").map(Value::take) {
+            Some(Value::Array(items)) => items,
+            _ => Vec::new(),
+        };
+        return items.into_iter().map(event_from_value).collect();
+    }
+
+    event_from_value(value).map(|event| vec![event])
+}
+
+/// The fields that log collectors wrap the original log line in: `log` for
+/// containerd/docker and Fluent Bit, `MESSAGE` for `journalctl -o json`, and
+/// `message` for CloudWatch and friends.
+const ENVELOPE_FIELDS: [&str; 3] = ["log", "MESSAGE", "message"];
+
+/// Strip any number of log collector envelopes from around an audit document.
+///
+/// The wrapped document may be embedded as an object or as a string holding
+/// (possibly escaped) JSON. Anything with a `kind` is taken to be the audit
+/// document itself and returned untouched.
+pub fn unwrap_envelope(mut value: Value) -> serde_json::Result<Value> {
+    loop {
+        let Value::Object(object) = &mut value else {
+            return Ok(value);
+        };
+        if object.contains_key("kind") {
+            return Ok(value);
+        }
+        let Some(inner) = ENVELOPE_FIELDS
+            .iter()
+            .find_map(|field| object.remove(*field))
+        else {
+            return Ok(value);
+        };
+
+        value = match inner {
+            Value::String(inner) => serde_json::from_str(inner.trim())?,
+            inner => inner,
+        };
+    }
+}
+
+fn event_from_value(mut value: Value) -> serde_json::Result<EventV1> {
+    let api_version = value.get("apiVersion").and_then(Value::as_str);
+    if matches!(
+        api_version,
+        Some("audit.k8s.io/v1alpha1" | "audit.k8s.io/v1beta1")
+    ) {
+        normalise_legacy(&mut value);
+    }
+
+    serde_json::from_value(value)
+}
+
+/// Rewrite a v1alpha1 or v1beta1 event in place into the shape of a v1 event.
+///
+/// The older versions carry an `ObjectMeta` and a single `timestamp` rather
+/// than the received and stage timestamps, and v1alpha1 object references hold
+/// the group and version together in `apiVersion`.
+fn normalise_legacy(value: &mut Value) {
+    let Some(event) = value.as_object_mut() else {
+        return;
+    };
+
+    let metadata = event.remove("metadata");
+    let timestamp = event.remove("timestamp").or_else(|| {
+        metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("creationTimestamp"))
+            .cloned()
+    });
+    if let Some(timestamp) = timestamp {
+        event
+            .entry("requestReceivedTimestamp")
+            .or_insert_with(|| timestamp.clone());
+        event.entry("stageTimestamp").or_insert(timestamp);
+    }
+    event.insert("apiVersion".to_string(), "audit.k8s.io/v1".into());
+    event
+        .entry("stage")
+        .or_insert_with(|| "ResponseComplete".into());
+
+    if let Some(Value::Object(object_ref)) = event.get_mut("objectRef") {
+        if !object_ref.contains_key("apiGroup") {
+            let group_version = object_ref.get("apiVersion").and_then(Value::as_str);
+            if let Some((group, version)) = group_version.and_then(|gv| gv.split_once('/')) {
+                let (group, version) = (group.to_string(), version.to_string());
+                object_ref.insert("apiGroup".to_string(), group.into());
+                object_ref.insert("apiVersion".to_string(), version.into());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 pub enum Level {
     None,
     Metadata,
@@ -39,7 +279,7 @@ pub enum Level {
     RequestResponse,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 pub enum Stage {
     RequestReceived,
     ResponseStarted,
@@ -47,26 +287,60 @@ pub enum Stage {
     Panic,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::None => "None",
+            Level::Metadata => "Metadata",
+            Level::Request => "Request",
+            Level::RequestResponse => "RequestResponse",
+        };
+        f.pad(name)
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::RequestReceived => "RequestReceived",
+            Stage::ResponseStarted => "ResponseStarted",
+            Stage::ResponseComplete => "ResponseComplete",
+            Stage::Panic => "Panic",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct UserInfo {
     pub groups: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub uid: Option<String>,
     pub username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub extra: Option<Value>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ObjectReference {
-    resource: Option<String>,
-    namespace: Option<String>,
-    name: Option<String>,
-    uid: Option<Uuid>,
-    _api_group: Option<String>,
-    _api_version: Option<String>,
-    _resource_version: Option<String>,
-    subresource: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subresource: Option<String>,
 }
 
 impl fmt::Display for ObjectReference {
@@ -95,43 +369,349 @@ impl fmt::Display for ObjectReference {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ResponseStatus {
-    _api_version: Option<String>,
-    _code: i32,
-    _details: Option<StatusDetails>,
-    _kind: Option<String>,
-    _message: Option<String>,
-    _metadata: Option<ListMeta>,
-    _reason: Option<String>,
-    _status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    pub code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<StatusDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ListMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl ResponseStatus {
+    /// Whether the request was refused or went wrong.
+    pub fn is_failure(&self) -> bool {
+        self.code >= 400 || self.status.as_deref() == Some("Failure")
+    }
+}
+
+impl fmt::Display for ResponseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)?;
+
+        if let Some(reason) = &self.reason {
+            write!(f, " {}", reason)?;
+        }
+
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct StatusDetails {
-    _causes: Option<Vec<StatusCause>>,
-    _group: Option<String>,
-    _kind: Option<String>,
-    _name: Option<String>,
-    _retry_after_seconds: Option<isize>,
-    _uid: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub causes: Option<Vec<StatusCause>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<isize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct StatusCause {
-    _field: String,
-    _message: String,
-    _reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ListMeta {
-    #[serde(rename = "continue")]
-    _cont: Option<String>,
-    _remaining_item_count: Option<isize>,
-    _resource_version: Option<String>,
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none")]
+    pub cont: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_item_count: Option<isize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_version: Option<String>,
+}
+
+/// An event to build on in tests: a list of every pod by alice, with no
+/// object reference, received and completed at 10:00:00 on 2024-07-01.
+#[cfg(test)]
+pub(crate) fn test_event() -> TestEvent {
+    TestEvent(serde_json::json!({
+        "kind": "Event",
+        "apiVersion": "audit.k8s.io/v1",
+        "level": "Metadata",
+        "auditID": "ec95c2ca-00d4-40b9-93b4-78a6eb1242c7",
+        "stage": "ResponseComplete",
+        "requestURI": "/api/v1/pods",
+        "verb": "list",
+        "user": {"username": "alice", "groups": []},
+        "requestReceivedTimestamp": "2024-07-01T10:00:00Z",
+        "stageTimestamp": "2024-07-01T10:00:00Z",
+    }))
+}
+
+/// An event being built for a test, kept as the JSON it'll be read from so
+/// that it's parsed just as one from an audit log would be.
+#[cfg(test)]
+pub(crate) struct TestEvent(Value);
+
+#[cfg(test)]
+impl TestEvent {
+    /// Set any field of the event, by its name in the JSON.
+    pub(crate) fn set(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.0[key] = value.into();
+        self
+    }
+
+    pub(crate) fn level(self, level: &str) -> Self {
+        self.set("level", level)
+    }
+
+    pub(crate) fn audit_id(self, audit_id: &str) -> Self {
+        self.set("auditID", audit_id)
+    }
+
+    pub(crate) fn uri(self, uri: impl Into<Value>) -> Self {
+        self.set("requestURI", uri)
+    }
+
+    pub(crate) fn verb(self, verb: &str) -> Self {
+        self.set("verb", verb)
+    }
+
+    pub(crate) fn user(mut self, username: &str) -> Self {
+        self.0["user"]["username"] = username.into();
+        self
+    }
+
+    pub(crate) fn groups(mut self, groups: &[&str]) -> Self {
+        self.0["user"]["groups"] = groups.into();
+        self
+    }
+
+    pub(crate) fn user_agent(self, agent: &str) -> Self {
+        self.set("userAgent", agent)
+    }
+
+    pub(crate) fn object_ref(self, object_ref: impl Into<Value>) -> Self {
+        self.set("objectRef", object_ref)
+    }
+
+    /// A response status with just a code.
+    pub(crate) fn code(self, code: i32) -> Self {
+        self.set(
+            "responseStatus",
+            serde_json::json!({"metadata": {}, "code": code}),
+        )
+    }
+
+    pub(crate) fn request_object(self, object: impl Into<Value>) -> Self {
+        self.set("requestObject", object)
+    }
+
+    pub(crate) fn response_object(self, object: impl Into<Value>) -> Self {
+        self.set("responseObject", object)
+    }
+
+    pub(crate) fn annotations(self, annotations: impl Into<Value>) -> Self {
+        self.set("annotations", annotations)
+    }
+
+    /// Received and completed at `timestamp`.
+    pub(crate) fn at(self, timestamp: impl Into<Value> + Clone) -> Self {
+        self.set("requestReceivedTimestamp", timestamp.clone())
+            .set("stageTimestamp", timestamp)
+    }
+
+    /// The event as it would be written in an audit log.
+    pub(crate) fn json(self) -> Value {
+        self.0
+    }
+
+    pub(crate) fn build(self) -> EventV1 {
+        serde_json::from_value(self.0).expect("valid event")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From an OpenShift 4 cluster: the auditID comes from the router's
+    // X-Request-Id and there are no annotations on the event.
+    const OPENSHIFT: &str = r#"{"kind":"Event","apiVersion":"audit.k8s.io/v1","level":"Metadata","auditID":"a2b5c1f7e6d94b0b8c3f","stage":"ResponseComplete","requestURI":"/apis/route.openshift.io/v1/namespaces/openshift-console/routes/console","verb":"get","user":{"username":"system:serviceaccount:openshift-console-operator:console-operator","uid":"8e3c7d0b-1b7a-4f0e-9c52-1d7f0a1c2b3d","groups":["system:serviceaccounts","system:serviceaccounts:openshift-console-operator","system:authenticated"],"extra":{"authentication.kubernetes.io/pod-name":["console-operator-5d4b9c7f8-abcde"]}},"sourceIPs":["10.128.0.42"],"userAgent":"console/v0.0.0 (linux/amd64) kubernetes/$Format","objectRef":{"resource":"routes","namespace":"openshift-console","name":"console","apiGroup":"route.openshift.io","apiVersion":"v1"},"responseStatus":{"metadata":{},"code":200},"requestReceivedTimestamp":"2024-06-12T03:14:15.926535Z","stageTimestamp":"2024-06-12T03:14:15.931000Z"}"#;
+
+    // From k3s, with a client-supplied Audit-ID header
+    const K3S: &str = r#"{"kind":"Event","apiVersion":"audit.k8s.io/v1","level":"Metadata","auditID":"k3s-agent-1718162055","stage":"ResponseComplete","requestURI":"/api/v1/nodes/k3s-agent-1?timeout=10s","verb":"get","user":{"username":"system:node:k3s-agent-1","groups":["system:nodes","system:authenticated"]},"sourceIPs":["192.168.1.21"],"userAgent":"k3s/v1.29.5+k3s1 (linux/arm64) kubernetes/4e53a32","objectRef":{"resource":"nodes","name":"k3s-agent-1","apiVersion":"v1"},"responseStatus":{"metadata":{},"code":200},"requestReceivedTimestamp":"2024-06-12T03:14:15.000000Z","stageTimestamp":"2024-06-12T03:14:15.002000Z","annotations":{"authorization.k8s.io/decision":"allow","authorization.k8s.io/reason":""}}"#;
+
+    // From a 1.11 cluster still logging v1beta1, with the timestamp the
+    // older versions carried beside the received and stage ones
+    const V1BETA1: &str = r#"{"kind":"Event","apiVersion":"audit.k8s.io/v1beta1","metadata":{"creationTimestamp":"2019-03-14T12:00:00Z"},"level":"Metadata","timestamp":"2019-03-14T12:00:00Z","auditID":"5b4c9e0a-7f0e-4c0e-9d2c-3a6b1e2f4d5c","stage":"ResponseComplete","requestURI":"/apis/apps/v1/namespaces/default/deployments/web","verb":"get","user":{"username":"alice","groups":["system:authenticated"]},"sourceIPs":["10.0.0.1"],"userAgent":"kubectl/v1.11.3 (linux/amd64) kubernetes/a452946","objectRef":{"resource":"deployments","namespace":"default","name":"web","apiGroup":"apps","apiVersion":"v1"},"responseStatus":{"metadata":{},"code":200},"requestReceivedTimestamp":"2019-03-14T12:00:00.100000Z","stageTimestamp":"2019-03-14T12:00:00.200000Z"}"#;
+
+    // From a 1.7 cluster logging v1alpha1: only the one timestamp, no user
+    // agent, and the group and version together in the object reference
+    const V1ALPHA1: &str = r#"{"kind":"Event","apiVersion":"audit.k8s.io/v1alpha1","metadata":{"creationTimestamp":"2017-09-01T08:30:00Z"},"level":"Metadata","timestamp":"2017-09-01T08:30:00Z","auditID":"0c5e2a1b-9d8f-4e7a-b6c5-d4e3f2a1b0c9","stage":"ResponseComplete","requestURI":"/apis/extensions/v1beta1/namespaces/default/deployments/web","verb":"update","user":{"username":"bob","groups":["system:authenticated"]},"sourceIPs":["10.0.0.2"],"objectRef":{"resource":"deployments","namespace":"default","name":"web","apiVersion":"extensions/v1beta1"},"responseStatus":{"metadata":{},"code":200}}"#;
+
+    fn parse(json: &str) -> EventV1 {
+        let value = serde_json::from_str(json).expect("valid json");
+        let mut events = events_from_value(value).expect("valid event");
+        assert_eq!(events.len(), 1);
+        events.remove(0)
+    }
+
+    #[test]
+    fn non_uuid_audit_id_without_annotations() {
+        let event = parse(OPENSHIFT);
+        assert_eq!(event.audit_id, "a2b5c1f7e6d94b0b8c3f");
+        assert!(event.annotations.is_empty());
+    }
+
+    #[test]
+    fn client_supplied_audit_id() {
+        let event = parse(K3S);
+        assert_eq!(event.audit_id, "k3s-agent-1718162055");
+        assert_eq!(
+            event.annotations["authorization.k8s.io/decision"],
+            "allow".to_string()
+        );
+    }
+
+    #[test]
+    fn dry_runs_and_applies() {
+        let mut event = parse(K3S);
+        assert!(!event.is_dry_run());
+        assert_eq!(event.applied_by(), None);
+
+        event.verb = "patch".to_string();
+        event.request_uri =
+            "/api/v1/nodes/k3s-agent-1?dryRun=All&fieldManager=kubectl&force=true".to_string();
+        event.request_object = Some(serde_json::json!({"apiVersion": "v1", "kind": "Node"}));
+        assert!(event.is_dry_run());
+        assert_eq!(event.applied_by().as_deref(), Some("kubectl"));
+
+        // a merge patch by a field manager is no apply
+        event.request_object = Some(serde_json::json!({"metadata": {"labels": {}}}));
+        assert_eq!(event.applied_by(), None);
+    }
+
+    #[test]
+    fn round_trips() {
+        for json in [OPENSHIFT, K3S] {
+            let event = parse(json);
+            let serialised = serde_json::to_value(&event).expect("serialisable");
+            let reparsed = parse(&serialised.to_string());
+            assert_eq!(serde_json::to_value(&reparsed).unwrap(), serialised);
+
+            // Nothing is lost apart from the precision of the timestamps
+            let mut original: Value = serde_json::from_str(json).unwrap();
+            let mut serialised = serialised;
+            for value in [&mut original, &mut serialised] {
+                value["requestReceivedTimestamp"].take();
+                value["stageTimestamp"].take();
+            }
+            assert_eq!(serialised, original);
+        }
+    }
+
+    #[test]
+    fn event_lists() {
+        let event = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+        let list = serde_json::json!({
+            "kind": "EventList",
+            "apiVersion": "audit.k8s.io/v1",
+            "metadata": {},
+            "items": [event(OPENSHIFT), event(K3S)],
+        });
+        let events = events_from_value(list).expect("valid list");
+        let ids = events.iter().map(|event| event.audit_id.as_str());
+        assert_eq!(
+            ids.collect::<Vec<_>>(),
+            ["a2b5c1f7e6d94b0b8c3f", "k3s-agent-1718162055"]
+        );
+
+        let single = events_from_value(event(K3S)).expect("valid event");
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].audit_id, "k3s-agent-1718162055");
+
+        // a list without an array of items has no events in it
+        let empty = serde_json::json!({"kind": "EventList", "items": {"0": event(K3S)}});
+        assert!(events_from_value(empty).expect("valid list").is_empty());
+        let items = serde_json::json!({"kind": "EventList", "items": [{"kind": "Event"}]});
+        assert!(events_from_value(items).is_err());
+    }
+
+    #[test]
+    fn legacy_versions() {
+        let beta = parse(V1BETA1);
+        assert_eq!(beta.api_version, "audit.k8s.io/v1");
+        let received = DateTime::parse_from_rfc3339("2019-03-14T12:00:00.100Z").unwrap();
+        assert_eq!(beta.request_received_timestamp, received);
+        assert_eq!(
+            beta.stage_timestamp,
+            DateTime::parse_from_rfc3339("2019-03-14T12:00:00.200Z").unwrap()
+        );
+        assert_eq!(
+            beta.user_agent.as_deref(),
+            Some("kubectl/v1.11.3 (linux/amd64) kubernetes/a452946")
+        );
+        let object = beta.object_ref.as_ref().unwrap();
+        assert_eq!(object.api_group.as_deref(), Some("apps"));
+        assert_eq!(object.api_version.as_deref(), Some("v1"));
+
+        let alpha = parse(V1ALPHA1);
+        assert_eq!(alpha.api_version, "audit.k8s.io/v1");
+        let timestamp = DateTime::parse_from_rfc3339("2017-09-01T08:30:00Z").unwrap();
+        assert_eq!(alpha.request_received_timestamp, timestamp);
+        assert_eq!(alpha.stage_timestamp, timestamp);
+        assert_eq!(alpha.user_agent, None);
+        let object = alpha.object_ref.as_ref().unwrap();
+        assert_eq!(object.api_group.as_deref(), Some("extensions"));
+        assert_eq!(object.api_version.as_deref(), Some("v1beta1"));
+    }
+
+    #[test]
+    fn envelopes() {
+        let event = serde_json::from_str::<Value>(K3S).unwrap();
+
+        // Fluent Bit with the JSON parser, the event as an object
+        let wrapped = serde_json::json!({"log": event, "stream": "stdout"});
+        assert_eq!(unwrap_envelope(wrapped).unwrap(), event);
+
+        // journalctl -o json around CloudWatch, the event as a string
+        let inner = serde_json::json!({"message": format!("  {}\n", K3S)});
+        let wrapped = serde_json::json!({"MESSAGE": inner.to_string(), "_PID": "1234"});
+        assert_eq!(unwrap_envelope(wrapped).unwrap(), event);
+        let journal = serde_json::json!({"MESSAGE": K3S});
+        assert_eq!(
+            events_from_value(journal).unwrap()[0].audit_id,
+            "k3s-agent-1718162055"
+        );
+
+        // anything with a kind, or no envelope field, is left alone
+        let mut kind = event.clone();
+        kind["message"] = "not an envelope".into();
+        assert_eq!(unwrap_envelope(kind.clone()).unwrap(), kind);
+        let other = serde_json::json!({"level": "info", "msg": "starting"});
+        assert_eq!(unwrap_envelope(other.clone()).unwrap(), other);
+        assert!(unwrap_envelope(serde_json::json!({"log": "{not json"})).is_err());
+    }
 }
@@ -1,20 +1,410 @@
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
 use crossterm::{self, event::EventStream};
 use futures::stream::StreamExt;
-use kubernetes_audit_log_explorer::{kube::EventV1, App};
-use std::io::stdin;
-use tokio::sync::mpsc;
+use kubernetes_audit_log_explorer::{
+    agent::{self, Version},
+    alert::Alert,
+    app::{self, Exit},
+    break_glass::{self, BreakGlass},
+    compare,
+    config::Config,
+    database::Database,
+    enrich::{Networks, Owners, Workloads},
+    export,
+    filter::Filter,
+    kube::EventV1,
+    metrics::{self, Metrics},
+    path::JsonPath,
+    report,
+    sample::{self, Sampler},
+    script::Scripts,
+    session::Session,
+    sink::{ElasticsearchSink, Forwarder, OtlpSink},
+    source::{
+        self, cluster::ClusterOptions, Checkpoints, ClusterSource, DatabaseSource, EventSource,
+        FileSource, FollowSource, ForwardSource, Ingested, Labelled, LoadProgress, ReaderSource,
+        ReplaySource, Speed, WebhookSource,
+    },
+    store::EventStore,
+    theme::{Theme, ThemeName},
+    timestamp::{TimeFormat, Timestamps, Zone},
+    view::Column,
+    App,
+};
+use std::{
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::signal::unix::{signal, SignalKind};
+
+/// How many events to take in at most between drawing and answering keys.
+const INGEST_BATCH: usize = 5000;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Where to read audit events from
+    #[arg(long, value_enum, default_value_t = Source::Stdin, global = true)]
+    source: Source,
+
+    /// Address to accept Fluentd/Fluent Bit forward connections or audit
+    /// webhook requests on
+    #[arg(long, default_value = "0.0.0.0:24224", global = true)]
+    listen: SocketAddr,
+
+    /// Read audit events from a file instead, labelled with the cluster they
+    /// came from as NAME=PATH; may be given several times
+    #[arg(long, value_name = "NAME=PATH", global = true)]
+    input: Vec<String>,
+
+    /// Keep reading the --input files as they grow, like `tail -F`
+    #[arg(long)]
+    follow: bool,
+
+    /// Remember how far each --follow file has been read in this state file,
+    /// and carry on from there when following them again
+    #[arg(long, value_name = "FILE", requires = "follow")]
+    checkpoints: Option<PathBuf>,
+
+    /// Read the events saved to a SQLite file with --save instead
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["source", "input"], global = true)]
+    open: Option<PathBuf>,
+
+    /// Serve counters of the events read at /metrics on this address, for
+    /// Prometheus to scrape, like 0.0.0.0:9090
+    #[arg(long, value_name = "ADDR")]
+    metrics: Option<SocketAddr>,
+
+    /// Send every event read on to an OpenTelemetry collector as log records,
+    /// over OTLP/HTTP at this endpoint, like http://localhost:4318
+    #[arg(long, value_name = "URL")]
+    otlp: Option<String>,
+
+    /// Index every event read into Elasticsearch or OpenSearch too, given as
+    /// es://host:port/index, or es+https:// for a cluster behind TLS
+    #[arg(long, value_name = "URL")]
+    sink: Vec<ElasticsearchSink>,
+
+    /// Only send on the events matching this filter with --otlp and --sink
+    #[arg(long, value_name = "FILTER")]
+    sink_filter: Option<Filter>,
+
+    /// Tag events with who owns the workload behind them, as enriched[owner],
+    /// from a TOML file mapping users and namespaces to owners
+    #[arg(long, value_name = "FILE")]
+    owners: Option<PathBuf>,
+
+    /// Tag service accounts' requests with the workloads running as them, as
+    /// enriched[workload], from a file of what `kubectl get
+    /// deployments,daemonsets,statefulsets,cronjobs,jobs -A -o json` gives, or
+    /// `cluster` to list them with kubectl now
+    #[arg(long, value_name = "FILE")]
+    workloads: Option<String>,
+
+    /// Look up the countries of external source IPs in this MaxMind GeoIP2 or
+    /// GeoLite2 database, as enriched[country], by default the config file's
+    /// geoip
+    #[arg(long, value_name = "FILE")]
+    geoip: Option<PathBuf>,
+
+    /// Save every event read to a new SQLite file, for reading back quickly
+    /// with --open
+    #[arg(long, value_name = "FILE")]
+    save: Option<PathBuf>,
+
+    /// Only show events matching this filter, which can be changed with `/`
+    #[arg(long, global = true)]
+    filter: Option<Filter>,
+
+    /// Show the values at a jq style path as an extra column, like
+    /// .requestObject.spec.replicas; may be given several times
+    #[arg(long, value_name = "PATH")]
+    column: Vec<JsonPath>,
+
+    /// Config file to read saved searches from and save them to, by default
+    /// kale/config.toml in the user's config directory
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Pick up the tabs, bookmarks and selections of the last session where
+    /// they were left
+    #[arg(long, conflicts_with = "filter")]
+    resume: bool,
+
+    /// File to save the session to on quitting, and to resume it from, by
+    /// default kale/session.json in the user's state directory
+    #[arg(long, value_name = "FILE")]
+    session: Option<PathBuf>,
+
+    /// Colours to draw with, by default the config file's theme, or plain if
+    /// NO_COLOR is set, or dark
+    #[arg(long, value_enum)]
+    theme: Option<ThemeName>,
+
+    /// Time zone to show timestamps in: utc (the default), local (which
+    /// follows TZ) or an offset like +02:00
+    #[arg(long, value_name = "ZONE")]
+    timezone: Option<Zone>,
+
+    /// Format to show timestamps in
+    #[arg(long, value_enum)]
+    time_format: Option<TimeFormat>,
+
+    /// How many seconds apart exact duplicates can be and still be hidden
+    /// with `d`, by default the config file's dedupe_window, or 10
+    #[arg(long, value_name = "SECONDS")]
+    dedupe_window: Option<u32>,
+
+    /// Once more than this many events a second arrive, keep only some of
+    /// the reads, by default the config file's sample_above, or never
+    #[arg(long, value_name = "EVENTS")]
+    sample_above: Option<u32>,
+
+    /// Keep one in how many of the same user's reads of a resource while
+    /// sampling, by default the config file's sample_one_in, or 10
+    #[arg(long, value_name = "N")]
+    sample_one_in: Option<u32>,
+
+    /// Keep events for non-resource URLs like /healthz, /version and
+    /// /openid/v1/jwks, which are left out otherwise
+    #[arg(long, global = true)]
+    include_non_resource: bool,
+
+    /// Kubeconfig context to read audit logs from, see --source cluster
+    #[arg(long, global = true)]
+    context: Option<String>,
+
+    /// Path of the audit log on the control plane nodes, for clusters that
+    /// don't write audit events to the apiserver pod logs
+    #[arg(long, global = true)]
+    audit_log_path: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Read the events and write a report of them to share, rather than
+    /// exploring them; stops reading at Ctrl-C for sources that don't end
+    Report {
+        /// What to write the report as
+        #[arg(long, value_enum, default_value_t = ReportFormat::Html)]
+        format: ReportFormat,
+
+        /// File to write the report to, by default a new one in the current
+        /// directory
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Compare two captures, like before and after an upgrade, printing the
+    /// users, verbs, resources and errors that appear in only one of them or
+    /// whose rates changed the most
+    Compare {
+        /// The capture to compare against
+        before: PathBuf,
+
+        /// The capture to compare
+        after: PathBuf,
+    },
+    /// List the changes people made by hand to the namespaces in the config
+    /// file's [break_glass], rather than through automation, for change
+    /// management to follow up on
+    BreakGlass {
+        /// Write the changes as CSV rather than a table
+        #[arg(long)]
+        csv: bool,
+
+        /// File to write the changes to, rather than printing them
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// List the kubectl and other Kubernetes client binaries too many minor
+    /// versions behind the apiserver, and who runs them, for fleet hygiene
+    /// reports
+    Outdated {
+        /// The apiserver's version, like v1.30, by default the newest the
+        /// apiserver's own requests and any /version responses tell of
+        #[arg(long, value_name = "VERSION")]
+        server_version: Option<Version>,
+
+        /// How many minor versions behind the apiserver clients can be, 1
+        /// unless the config file's skew says otherwise
+        #[arg(long, value_name = "MINORS")]
+        skew: Option<u32>,
+
+        /// Write the clients as CSV, a row for each user, rather than a table
+        #[arg(long)]
+        csv: bool,
+
+        /// File to write the clients to, rather than printing them
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Explore the events as they happened, sending them in at a pace in
+    /// proportion to their timestamps rather than all at once
+    Replay {
+        /// How much faster than they happened to replay the events, like 10x,
+        /// from 0.001x to 1000000x
+        #[arg(long, default_value = "1x")]
+        speed: Speed,
+
+        /// Files to replay, labelled with the cluster they came from as
+        /// NAME=PATH like --input
+        #[arg(value_name = "FILE")]
+        files: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// A standalone page of summary tables and the events to filter
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Source {
+    /// Newline delimited JSON events on stdin
+    Stdin,
+    /// Fluentd/Fluent Bit forward protocol, see --listen
+    Forward,
+    /// The apiserver's audit webhook backend, see --listen
+    Webhook,
+    /// The apiserver pod logs or node audit log files, via kubectl
+    Cluster,
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    match &args.command {
+        Some(Command::Report { format, output }) => {
+            return write_report(&args, *format, output.clone()).await
+        }
+        Some(Command::Compare { before, after }) => {
+            return print_comparison(&args, before, after).await
+        }
+        Some(Command::BreakGlass { csv, output }) => {
+            return write_break_glass(&args, *csv, output.as_deref()).await
+        }
+        Some(Command::Outdated {
+            server_version,
+            skew,
+            csv,
+            output,
+        }) => {
+            let output = output.as_deref();
+            return write_outdated(&args, *server_version, *skew, *csv, output).await;
+        }
+        _ => {}
+    }
+
+    let (config, config_path) = load_config(&args)?;
+    let session_path = args.session.clone().or_else(Session::default_path);
+    let session = match (args.resume, &session_path) {
+        (true, Some(path)) => Some(Session::load(path)?),
+        (true, None) => anyhow::bail!("there's nowhere to resume a session from, see --session"),
+        (false, _) => None,
+    };
+
+    // read and process log events from the chosen sources
+    let (sources, loads, checkpoints) = sources(&args)?;
+    // create the file to save to before reading anything, to fail early if
+    // it's already there
+    let database = args.save.clone().map(Database::create).transpose()?;
+    let mut recv = source::spawn(sources);
+
     let mut app = App::new();
-    app.setup();
+    let theme = args.theme.or(config.theme).unwrap_or_else(|| {
+        match std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            true => ThemeName::Plain,
+            false => ThemeName::Dark,
+        }
+    });
+    app.set_theme(Theme::named(theme));
+    app.set_timestamps(Timestamps::new(
+        args.timezone.or(config.timezone).unwrap_or_default(),
+        args.time_format.or(config.time_format).unwrap_or_default(),
+    ));
+    if let Some(seconds) = args.dedupe_window.or(config.dedupe_window) {
+        app.set_dedupe_window(chrono::Duration::seconds(seconds.into()));
+    }
+    if let Some(threshold) = args.sample_above.or(config.sample_above) {
+        let one_in = args.sample_one_in.or(config.sample_one_in);
+        let one_in = one_in.unwrap_or(sample::DEFAULT_ONE_IN);
+        app.set_sampler(Some(Sampler::new(threshold, one_in)));
+    }
+    app.set_non_resource(args.include_non_resource);
+    if let Some(database) = database {
+        app.set_database(database);
+    }
+    if let Some(addr) = args.metrics {
+        let counters = Metrics::new();
+        metrics::serve(addr, counters.clone())?;
+        app.set_metrics(counters);
+    }
+    if let Some(endpoint) = &args.otlp {
+        let filter = args.sink_filter.clone();
+        app.add_sink(Forwarder::spawn("otlp", OtlpSink::new(endpoint), filter));
+    }
+    for sink in &args.sink {
+        let name = sink.to_string();
+        let filter = args.sink_filter.clone();
+        app.add_sink(Forwarder::spawn(name, sink.clone(), filter));
+    }
+    if let Some(path) = &args.owners {
+        app.add_enricher(Box::new(Owners::load(path)?));
+    }
+    if let Some(workloads) = &args.workloads {
+        let workloads = match workloads.as_str() {
+            "cluster" => Workloads::new(&source::cluster::workloads(&ClusterOptions {
+                context: args.context.clone(),
+                audit_log_path: None,
+            })?),
+            path => Workloads::load(Path::new(path))?,
+        };
+        app.add_enricher(Box::new(workloads));
+    }
+    let geoip = args.geoip.as_deref().or(config.geoip.as_deref());
+    if !config.networks.is_empty() || geoip.is_some() {
+        app.add_enricher(Box::new(Networks::new(&config.networks, geoip)?));
+    }
+    for load in loads {
+        app.track_load(load);
+    }
+    if let Some(rule) = &config.break_glass {
+        app.set_break_glass(BreakGlass::new(rule.clone()));
+    }
+    app.set_scripts(Scripts::load(&config.scripts)?);
+    let alerts = config.alerts.iter().map(Alert::from_rule);
+    app.set_alerts(alerts.collect::<anyhow::Result<_>>()?);
+    app.set_config(config, config_path);
+    app.set_filter(args.filter);
+    if let Some(session) = session {
+        app.resume(session);
+    }
+    for path in args.column {
+        app.add_column(Column::Path(path));
+    }
+    // quit cleanly when asked to, rather than leaving the terminal in raw mode
+    let mut interrupt = signal(SignalKind::interrupt()).context("failed to handle SIGINT")?;
+    let mut terminate = signal(SignalKind::terminate()).context("failed to handle SIGTERM")?;
+    let mut stop = signal(SignalKind::from_raw(signal_hook::consts::SIGTSTP))
+        .context("failed to handle SIGTSTP")?;
+
+    app::install_panic_hook();
+    app.setup()?;
 
-    // read and process log events from /dev/stdin
-    let (send, mut recv) = mpsc::unbounded_channel();
-    tokio::task::spawn_blocking(|| stdin_processor(send));
     // read and process terminal events from /dev/tty
     let mut terminal_events = EventStream::new();
 
+    // redraw every now and then, so the status bar stays current when idle
+    let mut redraw = tokio::time::interval(Duration::from_secs(1));
+
     app.draw();
 
     loop {
@@ -22,47 +412,275 @@ async fn main() -> anyhow::Result<()> {
         let term_event = terminal_events.next();
 
         tokio::select! {
-            Some(kube_event) = stdin_event => {
-                app.handle_kube_event(kube_event);
+            Some(ingested) = stdin_event => {
+                app.handle_ingested(ingested);
+                // take in whatever else has arrived too, to draw once for the
+                // lot, but not so much that keys go unanswered while loading
+                for _ in 0..INGEST_BATCH {
+                    match recv.try_recv() {
+                        Ok(ingested) => app.handle_ingested(ingested),
+                        Err(_) => break,
+                    }
+                }
+                app.save_events();
             },
+            _ = redraw.tick() => {},
+            _ = interrupt.recv() => break,
+            _ = terminate.recv() => break,
+            _ = stop.recv() => app.suspend()?,
             maybe_event = term_event => {
                 match maybe_event {
-                    Some(event) => {
-                        if app.handle_terminal_event(event).is_some() {
-                            break;
-                        }
-                    }
+                    Some(event) => match app.handle_terminal_event(event) {
+                        Some(Exit::Quit) => break,
+                        Some(Exit::Suspend) => app.suspend()?,
+                        None => {}
+                    },
                     None => break,
                 }
             }
         };
 
         app.draw();
+        if app.take_bell() {
+            // BEL, which terminals ring or flash for; not worth quitting over
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
     }
 
+    app.save_events();
     app.tear_down();
-    Ok(())
+    app.finish_sinks();
+    if let Some(path) = session_path {
+        app.session().save(&path)?;
+    }
+    // the followed files' sources save as they stop, but may not get the chance
+    match checkpoints {
+        Some(checkpoints) => checkpoints.save(),
+        None => Ok(()),
+    }
 }
 
-fn stdin_processor(send: mpsc::UnboundedSender<EventV1>) -> anyhow::Result<()> {
-    let stdin = stdin();
-    let stream = serde_json::Deserializer::from_reader(stdin).into_iter::<EventV1>();
-
-    for maybe_event in stream {
-        match maybe_event {
-            Ok(event) => {
-                // Drop events that don't refer to things in the cluster
-                if !(event.request_uri.starts_with("/api/")
-                    || event.request_uri.starts_with("/apis/"))
-                {
-                    continue;
+type Sources = Vec<Box<dyn EventSource>>;
+
+/// The sources of events the arguments ask for, with the progress of those
+/// loading files and how far those following files have read.
+fn sources(args: &Args) -> anyhow::Result<(Sources, Vec<Arc<LoadProgress>>, Option<Checkpoints>)> {
+    let mut sources: Sources = Vec::new();
+    let mut loads = Vec::new();
+    let checkpoints = args
+        .checkpoints
+        .clone()
+        .map(Checkpoints::load)
+        .transpose()?;
+    let replaying = match &args.command {
+        Some(Command::Replay { speed, files }) => Some((*speed, files.as_slice())),
+        _ => None,
+    };
+    let replayed = replaying.map(|(_, files)| files).unwrap_or_default();
+    if !args.input.is_empty() || !replayed.is_empty() {
+        for input in args.input.iter().chain(replayed) {
+            let (label, path) = match input.split_once('=') {
+                Some((label, path)) => (label.to_string(), path.to_string()),
+                None => (input.clone(), input.clone()),
+            };
+            // Fail before taking over the terminal if the file isn't there
+            std::fs::metadata(&path).with_context(|| format!("failed to open {}", path))?;
+            let inner: Box<dyn EventSource> = match args.follow {
+                true => match &checkpoints {
+                    Some(checkpoints) => {
+                        Box::new(FollowSource::new(path).checkpointed(checkpoints.clone()))
+                    }
+                    None => Box::new(FollowSource::new(path)),
+                },
+                false => {
+                    let source = FileSource::new(path);
+                    loads.push(source.progress());
+                    Box::new(source)
                 }
+            };
+            sources.push(Box::new(Labelled::new(label, inner)));
+        }
+    } else if let Some(path) = &args.open {
+        std::fs::metadata(path).with_context(|| format!("failed to open {}", path.display()))?;
+        sources.push(Box::new(DatabaseSource::new(path)));
+    } else {
+        sources.push(match args.source {
+            Source::Stdin => Box::new(ReaderSource::stdin()),
+            Source::Forward => Box::new(ForwardSource::new(args.listen)),
+            Source::Webhook => Box::new(WebhookSource::new(args.listen)),
+            Source::Cluster => Box::new(ClusterSource::new(ClusterOptions {
+                context: args.context.clone(),
+                audit_log_path: args.audit_log_path.clone(),
+            })),
+        });
+    }
+    if let Some((speed, _)) = replaying {
+        sources = vec![Box::new(ReplaySource::new(speed, sources))];
+    }
+    Ok((sources, loads, checkpoints))
+}
 
-                send.send(event)?;
-            }
-            Err(_) => anyhow::bail!("stdin failed to deserialise"),
+/// The config file, or the defaults if there isn't one, and where it lives.
+fn load_config(args: &Args) -> anyhow::Result<(Config, Option<PathBuf>)> {
+    let config_path = args.config.clone().or_else(Config::default_path);
+    let config = match &config_path {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    Ok((config, config_path))
+}
+
+/// The scripts from the config file, for filters that use them.
+fn load_scripts(args: &Args) -> anyhow::Result<Scripts> {
+    let (config, _) = load_config(args)?;
+    Scripts::load(&config.scripts)
+}
+
+/// Read every event from the sources, or until interrupted, leaving out those
+/// for non-resource URLs unless asked not to.
+async fn read_all(sources: Sources, non_resource: bool) -> anyhow::Result<EventStore> {
+    let mut recv = source::spawn(sources);
+    let mut store = EventStore::new();
+    loop {
+        tokio::select! {
+            ingested = recv.recv() => match ingested {
+                Some(Ingested::Event(event)) => {
+                    if non_resource || event.is_resource_request() {
+                        store.insert(*event);
+                    }
+                }
+                Some(Ingested::Skipped(reason)) => eprintln!("skipped: {}", reason),
+                Some(Ingested::Notice(text)) => eprintln!("{}", text),
+                Some(Ingested::Failed(err)) => anyhow::bail!(err),
+                None => break,
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    Ok(store)
+}
+
+/// Read every event from the sources, or until interrupted, and write a
+/// report of those matching the filter.
+async fn write_report(
+    args: &Args,
+    format: ReportFormat,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let scripts = load_scripts(args)?;
+    let (sources, _, _) = sources(args)?;
+    let store = read_all(sources, args.include_non_resource).await?;
+    let filter = args.filter.as_ref();
+    let events = store
+        .iter()
+        .filter(|event| filter.is_none_or(|filter| filter.eval(event, &scripts)))
+        .collect::<Vec<_>>();
+    let (text, extension) = match format {
+        ReportFormat::Html => (report::html(&events), "html"),
+    };
+    let path = output.unwrap_or_else(|| export::file_name("report", extension));
+    std::fs::write(&path, text).with_context(|| format!("writing {}", path.display()))?;
+    eprintln!(
+        "wrote a report of {} events to {}",
+        events.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Read every event from the sources, or until interrupted, and write out
+/// those matching the filter that break glass.
+async fn write_break_glass(args: &Args, csv: bool, output: Option<&Path>) -> anyhow::Result<()> {
+    let (config, _) = load_config(args)?;
+    let Some(rule) = config.break_glass else {
+        anyhow::bail!("the config file has no [break_glass] namespaces to check");
+    };
+    let glass = BreakGlass::new(rule);
+    let scripts = Scripts::load(&config.scripts)?;
+    let (sources, _, _) = sources(args)?;
+    let store = read_all(sources, args.include_non_resource).await?;
+    let filter = args.filter.as_ref();
+    let events = store
+        .iter()
+        .filter(|event| filter.is_none_or(|filter| filter.eval(event, &scripts)))
+        .filter(|event| glass.matches(event))
+        .collect::<Vec<_>>();
+    let text = match csv {
+        true => break_glass::csv(&events),
+        false => break_glass::text(&events),
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))?;
+            eprintln!("wrote {} changes to {}", events.len(), path.display());
         }
+        None => print!("{}", text),
     }
+    Ok(())
+}
 
+/// Read every event from the sources, or until interrupted, and write out
+/// the clients among those matching the filter too far behind the apiserver.
+async fn write_outdated(
+    args: &Args,
+    server: Option<Version>,
+    skew: Option<u32>,
+    csv: bool,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let (config, _) = load_config(args)?;
+    let scripts = Scripts::load(&config.scripts)?;
+    let skew = skew.or(config.skew).unwrap_or(agent::DEFAULT_SKEW);
+    let (sources, _, _) = sources(args)?;
+    let store = read_all(sources, args.include_non_resource).await?;
+    let filter = args.filter.as_ref();
+    let events = store
+        .iter()
+        .filter(|event| filter.is_none_or(|filter| filter.eval(event, &scripts)))
+        .collect::<Vec<_>>();
+    let Some(server) = server.or_else(|| agent::server_version(&store.iter().collect::<Vec<_>>()))
+    else {
+        anyhow::bail!("the events don't say what version the apiserver is, see --server-version");
+    };
+    let outdated = agent::outdated(&events, server, skew);
+    let text = match csv {
+        true => agent::csv(&outdated, server),
+        false => agent::text(&outdated, server),
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))?;
+            eprintln!(
+                "wrote {} outdated clients to {}",
+                outdated.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Print how the events matching the filter in one capture differ from
+/// those in another.
+async fn print_comparison(args: &Args, before: &Path, after: &Path) -> anyhow::Result<()> {
+    let scripts = load_scripts(args)?;
+    let mut stores = Vec::new();
+    for path in [before, after] {
+        std::fs::metadata(path).with_context(|| format!("failed to open {}", path.display()))?;
+        stores.push(
+            read_all(
+                vec![Box::new(FileSource::new(path))],
+                args.include_non_resource,
+            )
+            .await?,
+        );
+    }
+    let filter = args.filter.as_ref();
+    let matching = |event: &&EventV1| filter.is_none_or(|filter| filter.eval(event, &scripts));
+    let before = stores[0].iter().filter(matching).collect::<Vec<_>>();
+    let after = stores[1].iter().filter(matching).collect::<Vec<_>>();
+    print!("{}", compare::text(&before, &after));
     Ok(())
 }
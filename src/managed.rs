@@ -0,0 +1,200 @@
+//! Which field managers own which fields of an object, going by the
+//! `metadata.managedFields` the apiserver keeps on it, to say who set what a
+//! diff shows changing.
+
+use crate::diff::Change;
+use serde_json::{Map, Value};
+
+/// The field managers whose fields in `object` include the one at `path`, a
+/// jq style path as given by [`crate::diff`], or any field under it. A
+/// manager owning a field as a whole owns everything under it too.
+pub fn managers<'a>(object: &'a Value, path: &str) -> Vec<&'a str> {
+    let Some(entries) = object
+        .pointer("/metadata/managedFields")
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+    let mut managers = Vec::new();
+    for entry in entries {
+        let (Some(manager), Some(fields)) = (
+            entry.get("manager").and_then(Value::as_str),
+            entry.get("fieldsV1").and_then(Value::as_object),
+        ) else {
+            continue;
+        };
+        if owns(object, fields, path) && !managers.contains(&manager) {
+            managers.push(manager);
+        }
+    }
+    managers
+}
+
+/// The managers of each change, going by who owns the field in the new
+/// object, or in the old one for fields that were removed.
+pub fn attribute(old: Option<&Value>, new: Option<&Value>, changes: &[Change]) -> Vec<Vec<String>> {
+    changes
+        .iter()
+        .map(|change| {
+            let object = match change {
+                Change::Removed { .. } => old,
+                _ => new,
+            };
+            object
+                .map(|object| managers(object, change.path()))
+                .unwrap_or_default()
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether the set of fields covers `path`, following it through `value`
+/// to match list items to the keys they're listed under.
+fn owns(value: &Value, fields: &Map<String, Value>, path: &str) -> bool {
+    if path.is_empty() || path == "." {
+        return true;
+    }
+    let (value, key, rest) = match (value, path.as_bytes()[0]) {
+        (Value::Object(object), b'.') => {
+            let path = &path[1..];
+            // keys may have dots in them, so take the longest that fits
+            let Some(key) = object
+                .keys()
+                .filter(|key| {
+                    path.strip_prefix(key.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+                })
+                .max_by_key(|key| key.len())
+            else {
+                return false;
+            };
+            (&object[key], format!("f:{}", key), &path[key.len()..])
+        }
+        (Value::Array(items), b'[') => {
+            let Some((index, rest)) = path[1..].split_once(']') else {
+                return false;
+            };
+            let Some((index, item)) = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| Some((index, items.get(index)?)))
+            else {
+                return false;
+            };
+            let Some(key) = fields.keys().find(|key| lists(key, index, item)) else {
+                return false;
+            };
+            (item, key.clone(), rest)
+        }
+        _ => return false,
+    };
+    match fields.get(&key).and_then(Value::as_object) {
+        // a field owned as a whole, with nothing listed under it
+        Some(fields) if fields.is_empty() || (fields.len() == 1 && fields.contains_key(".")) => {
+            true
+        }
+        Some(fields) => owns(value, fields, rest),
+        None => false,
+    }
+}
+
+/// Whether the key a list item is under in managedFields, `k:` with the
+/// fields that identify it, `v:` with its value or `i:` with its index, is
+/// for `item` at `index`.
+fn lists(key: &str, index: usize, item: &Value) -> bool {
+    if let Some(identity) = key.strip_prefix("k:") {
+        let Ok(Value::Object(identity)) = serde_json::from_str::<Value>(identity) else {
+            return false;
+        };
+        identity
+            .iter()
+            .all(|(field, value)| item.get(field) == Some(value))
+    } else if let Some(value) = key.strip_prefix("v:") {
+        serde_json::from_str::<Value>(value).is_ok_and(|value| &value == item)
+    } else if let Some(i) = key.strip_prefix("i:") {
+        i.parse() == Ok(index)
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn deployment(replicas: u32, image: &str) -> Value {
+        json!({
+            "metadata": {
+                "name": "web",
+                "labels": {"app.kubernetes.io/name": "web"},
+                "managedFields": [
+                    {
+                        "manager": "argocd-controller",
+                        "operation": "Apply",
+                        "fieldsType": "FieldsV1",
+                        "fieldsV1": {
+                            "f:metadata": {"f:labels": {"f:app.kubernetes.io/name": {}}},
+                            "f:spec": {"f:template": {"f:spec": {"f:containers": {
+                                "k:{\"name\":\"web\"}": {".": {}, "f:name": {}, "f:image": {}},
+                            }}}},
+                        },
+                    },
+                    {
+                        "manager": "kube-controller-manager",
+                        "operation": "Update",
+                        "fieldsType": "FieldsV1",
+                        "fieldsV1": {"f:spec": {"f:replicas": {}}},
+                    },
+                    {
+                        "manager": "kubectl-edit",
+                        "operation": "Update",
+                        "fieldsType": "FieldsV1",
+                        "fieldsV1": {"f:spec": {"f:template": {"f:spec": {"f:containers": {
+                            "k:{\"name\":\"web\"}": {"f:image": {}},
+                        }}}}},
+                    },
+                ],
+            },
+            "spec": {
+                "replicas": replicas,
+                "template": {"spec": {"containers": [
+                    {"name": "sidecar", "image": "proxy:1"},
+                    {"name": "web", "image": image},
+                ]}},
+            },
+        })
+    }
+
+    #[test]
+    fn ownership() {
+        let object = deployment(8, "web:1.3");
+        assert_eq!(
+            managers(&object, ".spec.replicas"),
+            ["kube-controller-manager"]
+        );
+        assert_eq!(
+            managers(&object, ".spec.template.spec.containers[1].image"),
+            ["argocd-controller", "kubectl-edit"]
+        );
+        assert_eq!(
+            managers(&object, ".spec.template.spec.containers[1]"),
+            ["argocd-controller", "kubectl-edit"]
+        );
+        assert!(managers(&object, ".spec.template.spec.containers[0].image").is_empty());
+        assert_eq!(
+            managers(&object, ".metadata.labels.app.kubernetes.io/name"),
+            ["argocd-controller"]
+        );
+        assert!(managers(&json!({"spec": {}}), ".spec").is_empty());
+
+        let old = deployment(3, "web:1.2");
+        let changes = crate::diff::diff(&old, &object);
+        let managers = attribute(Some(&old), Some(&object), &changes);
+        assert_eq!(changes[0].path(), ".spec.replicas");
+        assert_eq!(managers[0], ["kube-controller-manager"]);
+        assert_eq!(managers[1], ["argocd-controller", "kubectl-edit"]);
+    }
+}
@@ -0,0 +1,97 @@
+//! A history of the errors, warnings and notices raised during a session.
+
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// How many messages are kept before the oldest are dropped, so that a file
+/// full of junk can't eat all the memory.
+const CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warn",
+            Severity::Error => "error",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub time: DateTime<Local>,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}",
+            self.time.format("%H:%M:%S"),
+            self.severity,
+            self.text
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MessageLog {
+    messages: VecDeque<Message>,
+    /// How many messages have been dropped off the front.
+    dropped: usize,
+}
+
+impl MessageLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, severity: Severity, text: impl Into<String>) {
+        if self.messages.len() == CAPACITY {
+            self.messages.pop_front();
+            self.dropped += 1;
+        }
+
+        self.messages.push_back(Message {
+            time: Local::now(),
+            severity,
+            text: text.into(),
+        });
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Severity::Info, text);
+    }
+
+    pub fn warning(&mut self, text: impl Into<String>) {
+        self.push(Severity::Warning, text);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Severity::Error, text);
+    }
+
+    /// The messages, oldest first.
+    pub fn messages(&self) -> &VecDeque<Message> {
+        &self.messages
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn last(&self) -> Option<&Message> {
+        self.messages.back()
+    }
+}
@@ -0,0 +1,182 @@
+//! Counters of the events read, served for Prometheus to scrape, so that
+//! patterns in them can be alerted on while they're being explored too.
+
+use crate::kube::EventV1;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tiny_http::{Header, Response, Server};
+
+#[derive(Debug, Default)]
+struct Counts {
+    by_verb: BTreeMap<String, u64>,
+    /// By response status code, or none for events without a response.
+    by_code: BTreeMap<String, u64>,
+    /// By namespace, or none for cluster scoped resources.
+    by_namespace: BTreeMap<String, u64>,
+    skipped: u64,
+    sampled: u64,
+    /// How long after the apiserver was done with it the last event was
+    /// read.
+    lag: f64,
+}
+
+/// The counters, shared between the app counting and the server serving them.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics(Arc<Mutex<Counts>>);
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an event read, whether or not it's kept.
+    pub fn record_event(&self, event: &EventV1) {
+        let mut counts = self.0.lock().unwrap();
+        *counts.by_verb.entry(event.verb.clone()).or_default() += 1;
+        let code = event.response_status.as_ref().map(|status| status.code);
+        let code = code.map(|code| code.to_string()).unwrap_or_default();
+        *counts.by_code.entry(code).or_default() += 1;
+        let object = event.object_ref.as_ref();
+        let namespace = object.and_then(|object| object.namespace.clone());
+        *counts
+            .by_namespace
+            .entry(namespace.unwrap_or_default())
+            .or_default() += 1;
+        let lag = Utc::now() - event.stage_timestamp;
+        counts.lag = lag.num_milliseconds() as f64 / 1000.0;
+    }
+
+    /// Count a document that couldn't be made sense of.
+    pub fn record_skipped(&self) {
+        self.0.lock().unwrap().skipped += 1;
+    }
+
+    /// Count an event dropped by sampling.
+    pub fn record_sampled(&self) {
+        self.0.lock().unwrap().sampled += 1;
+    }
+
+    /// The counters in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let counts = self.0.lock().unwrap();
+        let mut text = String::new();
+        let by = [
+            ("verb", &counts.by_verb),
+            ("code", &counts.by_code),
+            ("namespace", &counts.by_namespace),
+        ];
+        for (label, values) in by {
+            let name = format!("kale_events_by_{}_total", label);
+            header(
+                &mut text,
+                &name,
+                &format!("Audit events read, by {}.", label),
+                "counter",
+            );
+            for (value, count) in values {
+                let _ = writeln!(
+                    text,
+                    "{}{{{}=\"{}\"}} {}",
+                    name,
+                    label,
+                    escape(value),
+                    count
+                );
+            }
+        }
+
+        let single = [
+            (
+                "kale_skipped_documents_total",
+                "Documents read that couldn't be parsed as audit events.",
+                "counter",
+                counts.skipped.to_string(),
+            ),
+            (
+                "kale_sampled_events_total",
+                "Audit events dropped by sampling.",
+                "counter",
+                counts.sampled.to_string(),
+            ),
+            (
+                "kale_ingest_lag_seconds",
+                "How long after its stage timestamp the last event was read.",
+                "gauge",
+                counts.lag.to_string(),
+            ),
+        ];
+        for (name, help, kind, value) in single {
+            header(&mut text, name, help, kind);
+            let _ = writeln!(text, "{} {}", name, value);
+        }
+        text
+    }
+}
+
+fn header(text: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value as the text format needs.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve the metrics at `/metrics` on `addr` in the background.
+pub fn serve(addr: SocketAddr, metrics: Metrics) -> anyhow::Result<()> {
+    let server = Server::http(addr)
+        .map_err(|err| anyhow::anyhow!("failed to serve metrics on {}: {}", addr, err))?;
+    let content_type =
+        Header::from_bytes("Content-Type", "text/plain; version=0.0.4").expect("valid header");
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            // there's no one to tell if the scraper has gone away
+            let _ = match request.url() {
+                "/metrics" => request.respond(
+                    Response::from_string(metrics.render()).with_header(content_type.clone()),
+                ),
+                _ => request.respond(Response::empty(404)),
+            };
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(verb: &str, namespace: Option<&str>, code: i32) -> EventV1 {
+        test_event()
+            .verb(verb)
+            .object_ref(serde_json::json!({"resource": "pods", "namespace": namespace}))
+            .code(code)
+            .build()
+    }
+
+    #[test]
+    fn rendering() {
+        let metrics = Metrics::new();
+        metrics.record_event(&event("get", Some("default"), 200));
+        metrics.record_event(&event("get", None, 403));
+        metrics.record_event(&event("delete", Some("we\"ird"), 200));
+        metrics.record_skipped();
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE kale_events_by_verb_total counter\n"));
+        assert!(text.contains("kale_events_by_verb_total{verb=\"get\"} 2\n"));
+        assert!(text.contains("kale_events_by_code_total{code=\"403\"} 1\n"));
+        assert!(text.contains("kale_events_by_namespace_total{namespace=\"\"} 1\n"));
+        assert!(text.contains("kale_events_by_namespace_total{namespace=\"we\\\"ird\"} 1\n"));
+        assert!(text.contains("kale_skipped_documents_total 1\n"));
+        assert!(text.contains("# TYPE kale_ingest_lag_seconds gauge\n"));
+    }
+}
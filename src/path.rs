@@ -0,0 +1,279 @@
+//! jq style paths into events, like `.requestObject.spec.replicas`, for
+//! filtering on and showing fields that have no name of their own.
+//!
+//! A path is a series of `.key`, `[index]` and `[]` (every element) steps from
+//! the event as it appears in the audit log. Paths into the request and
+//! response objects, which are where most of them go, are looked up directly;
+//! anything else is looked up in the event serialised back to JSON.
+
+use crate::kube::EventV1;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    /// The path as written, for display.
+    source: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Each,
+}
+
+impl JsonPath {
+    /// The values at this path in the event, empty where it leads nowhere.
+    ///
+    /// Strings are given as they are and anything else as JSON.
+    pub fn values<'a>(&self, event: &'a EventV1) -> Vec<Cow<'a, str>> {
+        let (root, steps) = match self.steps.split_first() {
+            Some((Step::Key(key), rest)) if key == "requestObject" => {
+                (event.request_object.as_ref(), rest)
+            }
+            Some((Step::Key(key), rest)) if key == "responseObject" => {
+                (event.response_object.as_ref(), rest)
+            }
+            _ => {
+                let Ok(value) = serde_json::to_value(event) else {
+                    return Vec::new();
+                };
+                let mut found = Vec::new();
+                walk(&value, &self.steps, &mut found);
+                return found
+                    .into_iter()
+                    .filter_map(display)
+                    .map(|value| Cow::from(value.into_owned()))
+                    .collect();
+            }
+        };
+
+        let mut found = Vec::new();
+        if let Some(root) = root {
+            walk(root, steps, &mut found);
+        }
+        found.into_iter().filter_map(display).collect()
+    }
+
+    /// Whether the path leads into the request or response object.
+    pub fn is_in_body(&self) -> bool {
+        matches!(
+            self.steps.first(),
+            Some(Step::Key(key)) if key == "requestObject" || key == "responseObject"
+        )
+    }
+
+    /// Change every value this path leads to in one of the event's bodies,
+    /// given as `root`, like `requestObject`. Paths elsewhere change nothing.
+    pub fn update(&self, root: &str, body: &mut Value, f: &mut impl FnMut(&mut Value)) {
+        if let Some((Step::Key(key), steps)) = self.steps.split_first() {
+            if key == root {
+                walk_mut(body, steps, f);
+            }
+        }
+    }
+}
+
+/// Collect every value reached by following `steps` from `value`.
+fn walk<'v>(value: &'v Value, steps: &[Step], found: &mut Vec<&'v Value>) {
+    let Some((step, rest)) = steps.split_first() else {
+        found.push(value);
+        return;
+    };
+
+    match (step, value) {
+        (Step::Key(key), Value::Object(object)) => {
+            if let Some(value) = object.get(key) {
+                walk(value, rest, found);
+            }
+        }
+        (Step::Index(index), Value::Array(array)) => {
+            if let Some(value) = array.get(*index) {
+                walk(value, rest, found);
+            }
+        }
+        (Step::Each, Value::Array(array)) => {
+            for value in array {
+                walk(value, rest, found);
+            }
+        }
+        (Step::Each, Value::Object(object)) => {
+            for value in object.values() {
+                walk(value, rest, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Change every value reached by following `steps` from `value`.
+fn walk_mut(value: &mut Value, steps: &[Step], f: &mut impl FnMut(&mut Value)) {
+    let Some((step, rest)) = steps.split_first() else {
+        f(value);
+        return;
+    };
+
+    match (step, value) {
+        (Step::Key(key), Value::Object(object)) => {
+            if let Some(value) = object.get_mut(key) {
+                walk_mut(value, rest, f);
+            }
+        }
+        (Step::Index(index), Value::Array(array)) => {
+            if let Some(value) = array.get_mut(*index) {
+                walk_mut(value, rest, f);
+            }
+        }
+        (Step::Each, Value::Array(array)) => {
+            for value in array {
+                walk_mut(value, rest, f);
+            }
+        }
+        (Step::Each, Value::Object(object)) => {
+            for value in object.values_mut() {
+                walk_mut(value, rest, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn display(value: &Value) -> Option<Cow<'_, str>> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(Cow::from(s)),
+        value => Some(Cow::from(value.to_string())),
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if !s.starts_with('.') {
+            anyhow::bail!("path {:?} doesn't start with '.'", s);
+        }
+
+        let mut steps = Vec::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    let mut key = String::new();
+                    while let Some(c) = chars.next_if(|c| *c != '.' && *c != '[') {
+                        key.push(c);
+                    }
+                    // A `.` without a key is allowed before an index or on its
+                    // own, for the whole event, as in jq
+                    if !key.is_empty() {
+                        steps.push(Step::Key(key));
+                    } else if chars.peek().is_some_and(|c| *c != '[') {
+                        anyhow::bail!("empty key in path {:?}", s);
+                    }
+                }
+                '[' => {
+                    let mut index = String::new();
+                    while let Some(c) = chars.next_if(|c| *c != ']') {
+                        index.push(c);
+                    }
+                    if chars.next().is_none() {
+                        anyhow::bail!("missing ']' in path {:?}", s);
+                    }
+                    steps.push(match index.as_str() {
+                        "" => Step::Each,
+                        index => Step::Index(index.parse().map_err(|_| {
+                            anyhow::anyhow!("invalid index [{}] in path {:?}", index, s)
+                        })?),
+                    });
+                }
+                c => anyhow::bail!("unexpected '{}' in path {:?}", c, s),
+            }
+        }
+
+        Ok(Self {
+            source: s.to_string(),
+            steps,
+        })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event() -> EventV1 {
+        test_event()
+            .level("RequestResponse")
+            .uri("/apis/apps/v1/namespaces/default/deployments/web")
+            .verb("patch")
+            .request_object(serde_json::json!({"spec": {"replicas": 3, "paused": null}}))
+            .response_object(
+                serde_json::json!({"spec": {"template": {"spec": {"containers": [
+                    {"name": "web", "image": "nginx:1.27"},
+                    {"name": "sidecar", "image": "envoy:1.30"},
+                ]}}}}),
+            )
+            .build()
+    }
+
+    fn values(path: &str) -> Vec<String> {
+        let path = path.parse::<JsonPath>().expect("valid path");
+        path.values(&event())
+            .into_iter()
+            .map(Cow::into_owned)
+            .collect()
+    }
+
+    #[test]
+    fn lookups() {
+        assert_eq!(values(".requestObject.spec.replicas"), ["3"]);
+        assert!(values(".requestObject.spec.paused").is_empty());
+        assert!(values(".requestObject.metadata.name").is_empty());
+        assert_eq!(
+            values(".responseObject.spec.template.spec.containers[].image"),
+            ["nginx:1.27", "envoy:1.30"]
+        );
+        assert_eq!(
+            values(".responseObject.spec.template.spec.containers[1].name"),
+            ["sidecar"]
+        );
+        assert_eq!(values(".user.username"), ["alice"]);
+    }
+
+    #[test]
+    fn updates() {
+        let path = ".responseObject.spec.template.spec.containers[].image"
+            .parse::<JsonPath>()
+            .unwrap();
+        let mut event = event();
+        let body = event.response_object.as_mut().unwrap();
+        path.update("requestObject", body, &mut |value| *value = Value::Null);
+        path.update("responseObject", body, &mut |value| {
+            *value = Value::String(value.as_str().unwrap().to_uppercase())
+        });
+        assert_eq!(
+            path.values(&event),
+            [Cow::from("NGINX:1.27"), Cow::from("ENVOY:1.30")]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!("requestObject".parse::<JsonPath>().is_err());
+        assert!(".a..b".parse::<JsonPath>().is_err());
+        assert!(".a[x]".parse::<JsonPath>().is_err());
+        assert!(".a[0".parse::<JsonPath>().is_err());
+        assert!(".[0]".parse::<JsonPath>().is_ok());
+    }
+}
@@ -0,0 +1,183 @@
+//! Popup lists to choose from: a [`Picker`] for a single value to filter on,
+//! narrowed down by fuzzy matching, and a [`Checklist`] to toggle several.
+
+use crate::filter::Field;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+/// What picking an item from a [`Picker`] does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerKind {
+    /// Filter to the picked value of the field.
+    Value(Field),
+    /// Apply the saved search with the picked name.
+    Search,
+    /// Run the picked action on the marked events.
+    Action,
+    /// Run the picked command, as if by its key.
+    Command,
+}
+
+/// Picks an item to filter on.
+#[derive(Debug)]
+pub struct Picker {
+    pub title: String,
+    pub kind: PickerKind,
+    /// Every item to choose from, with a note shown beside it, like how many
+    /// events it matches.
+    items: Vec<(String, String)>,
+    query: String,
+    /// Indices into `items` matching the query, best match first.
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(
+        title: impl Into<String>,
+        kind: PickerKind,
+        items: impl IntoIterator<Item = (String, impl ToString)>,
+    ) -> Self {
+        let mut picker = Self {
+            title: title.into(),
+            kind,
+            items: items
+                .into_iter()
+                .map(|(value, note)| (value, note.to_string()))
+                .collect(),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+        };
+        picker.rematch();
+        picker
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.rematch();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.rematch();
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1));
+    }
+
+    /// The items matching the query, best first.
+    pub fn matches(&self) -> impl Iterator<Item = &(String, String)> {
+        self.matches.iter().map(|i| &self.items[*i])
+    }
+
+    /// The selected position among the matches.
+    pub fn selected(&self) -> Option<usize> {
+        (!self.matches.is_empty()).then_some(self.selected)
+    }
+
+    pub fn selected_value(&self) -> Option<&str> {
+        let i = self.matches.get(self.selected)?;
+        Some(&self.items[*i].0)
+    }
+
+    fn rematch(&mut self) {
+        if self.query.is_empty() {
+            self.matches = (0..self.items.len()).collect();
+        } else {
+            let matcher = SkimMatcherV2::default();
+            let mut scored = self
+                .items
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (value, _))| {
+                    matcher
+                        .fuzzy_match(value, &self.query)
+                        .map(|score| (score, i))
+                })
+                .collect::<Vec<_>>();
+            // Best score first, keeping the original order between equals
+            scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+            self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        }
+        self.selected = 0;
+    }
+}
+
+/// What a [`Checklist`] toggles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecklistKind {
+    /// Events with the unchecked values of the field are hidden.
+    Values(Field),
+    /// The checked columns are shown in the table.
+    Columns,
+}
+
+/// Toggles which of several things are shown.
+#[derive(Debug)]
+pub struct Checklist {
+    pub title: String,
+    pub kind: ChecklistKind,
+    /// Every value, and whether events with it are shown.
+    items: Vec<(String, bool)>,
+    selected: usize,
+}
+
+impl Checklist {
+    pub fn new(title: impl Into<String>, kind: ChecklistKind, items: Vec<(String, bool)>) -> Self {
+        Self {
+            title: title.into(),
+            kind,
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn items(&self) -> &[(String, bool)] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.items.len().saturating_sub(1));
+    }
+
+    /// Flip whether the selected value is shown.
+    pub fn toggle(&mut self) {
+        if let Some((_, checked)) = self.items.get_mut(self.selected) {
+            *checked = !*checked;
+        }
+    }
+
+    /// The values that are checked.
+    pub fn checked(&self) -> impl Iterator<Item = &str> {
+        self.items
+            .iter()
+            .filter(|(_, checked)| *checked)
+            .map(|(value, _)| value.as_str())
+    }
+
+    /// The values that have been unchecked.
+    pub fn excluded(&self) -> impl Iterator<Item = &str> {
+        self.items
+            .iter()
+            .filter(|(_, checked)| !checked)
+            .map(|(value, _)| value.as_str())
+    }
+}
@@ -0,0 +1,67 @@
+//! Piping events to a command of the user's, like jq, a script or something
+//! to send a notification with, as newline delimited JSON on its stdin.
+
+use crate::export;
+use crate::kube::EventV1;
+use anyhow::Context;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::thread::JoinHandle;
+
+/// A command events were piped to, running in the background.
+#[derive(Debug)]
+pub struct Pipe {
+    pub command: String,
+    /// How many events were piped to it.
+    pub count: usize,
+    running: JoinHandle<anyhow::Result<Output>>,
+}
+
+impl Pipe {
+    /// Start `command` with the shell, writing the events to its stdin and
+    /// collecting what it writes.
+    pub fn spawn<'a>(
+        command: &str,
+        events: impl IntoIterator<Item = &'a EventV1>,
+    ) -> anyhow::Result<Self> {
+        let mut input = Vec::new();
+        let count = export::write_json_lines(&mut input, events)?;
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("running {}", command))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let running = std::thread::spawn(move || {
+            // written from another thread, so that a command writing lots
+            // before it has read everything can't leave both stuck
+            let writing = std::thread::spawn(move || {
+                // commands like head stop reading early, which is fine
+                let _ = stdin.write_all(&input);
+            });
+            let output = child.wait_with_output()?;
+            let _ = writing.join();
+            Ok(output)
+        });
+        Ok(Self {
+            command: command.to_string(),
+            count,
+            running,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.running.is_finished()
+    }
+
+    /// Wait for the command to finish, and what it wrote.
+    pub fn output(self) -> anyhow::Result<Output> {
+        match self.running.join() {
+            Ok(output) => output.with_context(|| format!("running {}", self.command)),
+            Err(_) => anyhow::bail!("running {} panicked", self.command),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Users making more requests than the rate limits allow in any window of
+//! time, and what they were doing at their busiest, to find the clients that
+//! need rate limiting on their side.
+
+use crate::config::RateLimit;
+use crate::detail::Section;
+use crate::kube::EventV1;
+use crate::timestamp::Timestamps;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// The limits used when the config file gives none: 100 requests in any 10
+/// seconds, or 300 in any minute.
+pub const DEFAULT_LIMITS: [RateLimit; 2] = [
+    RateLimit {
+        seconds: 10,
+        requests: 100,
+    },
+    RateLimit {
+        seconds: 60,
+        requests: 300,
+    },
+];
+
+/// How many of what a user was doing at their busiest are listed.
+const TOP: usize = 5;
+
+/// The most requests a user made in a window, over a limit.
+#[derive(Debug)]
+pub struct Excess<'a> {
+    pub limit: RateLimit,
+    pub requests: usize,
+    /// When the window the requests were made in started.
+    pub from: DateTime<Utc>,
+    /// The requests made in the window.
+    pub events: Vec<&'a EventV1>,
+}
+
+impl Excess<'_> {
+    /// How many times the limit the requests are.
+    fn ratio(&self) -> f64 {
+        self.requests as f64 / self.limit.requests as f64
+    }
+}
+
+/// The most requests in any window of the limit's length among `events`,
+/// which are in time order, if more than it allows.
+fn excess<'a>(events: &[&'a EventV1], limit: RateLimit) -> Option<Excess<'a>> {
+    let window = Duration::seconds(limit.seconds.into());
+    let mut start = 0;
+    let mut busiest = (0, 0);
+    for (end, event) in events.iter().enumerate() {
+        let time = event.request_received_timestamp;
+        while start < end && time - events[start].request_received_timestamp >= window {
+            start += 1;
+        }
+        if end + 1 - start > busiest.1 - busiest.0 {
+            busiest = (start, end + 1);
+        }
+    }
+    let requests = busiest.1 - busiest.0;
+    if requests <= limit.requests {
+        return None;
+    }
+    Some(Excess {
+        limit,
+        requests,
+        from: events[busiest.0].request_received_timestamp,
+        events: events[busiest.0..busiest.1].to_vec(),
+    })
+}
+
+/// The users among the events, which are in time order, going over any of
+/// the limits, or the default ones if there are none, with how far over
+/// each, the furthest over first.
+pub fn excesses<'a>(
+    events: &[&'a EventV1],
+    limits: &[RateLimit],
+) -> Vec<(&'a str, Vec<Excess<'a>>)> {
+    let limits = match limits.is_empty() {
+        true => &DEFAULT_LIMITS[..],
+        false => limits,
+    };
+    let mut users = HashMap::<&str, Vec<&EventV1>>::new();
+    for event in events {
+        users.entry(&event.user.username).or_default().push(event);
+    }
+
+    let mut excesses = users
+        .into_iter()
+        .filter_map(|(user, events)| {
+            let mut excesses = limits
+                .iter()
+                .filter_map(|limit| excess(&events, *limit))
+                .collect::<Vec<_>>();
+            excesses.sort_by(|a, b| b.ratio().total_cmp(&a.ratio()));
+            (!excesses.is_empty()).then_some((user, excesses))
+        })
+        .collect::<Vec<_>>();
+    excesses.sort_by(|a, b| b.1[0].ratio().total_cmp(&a.1[0].ratio()).then(a.0.cmp(b.0)));
+    excesses
+}
+
+/// What a request was, as its verb and resource, or its path without one.
+fn action(event: &EventV1) -> String {
+    let resource = event.object_ref.as_ref().and_then(|object| {
+        let resource = object.resource.as_deref()?;
+        Some(match &object.subresource {
+            Some(subresource) => format!("{}/{}", resource, subresource),
+            None => resource.to_string(),
+        })
+    });
+    format!(
+        "{} {}",
+        event.verb,
+        resource.as_deref().unwrap_or(event.path())
+    )
+}
+
+/// A section for each user going over the limits, with how far over each
+/// and what they were doing most in the window furthest over.
+pub fn sections(
+    events: &[&EventV1],
+    limits: &[RateLimit],
+    timestamps: &Timestamps,
+) -> Vec<Section> {
+    excesses(events, limits)
+        .into_iter()
+        .map(|(user, excesses)| {
+            let mut section = Section::new(user);
+            section.alert = true;
+            for excess in &excesses {
+                section.row(
+                    format!(
+                        "Over {} in {}s",
+                        excess.limit.requests, excess.limit.seconds
+                    ),
+                    format!(
+                        "{} requests from {}",
+                        excess.requests,
+                        timestamps.format(excess.from)
+                    ),
+                );
+            }
+
+            let busiest = &excesses[0];
+            let mut actions = HashMap::<String, usize>::new();
+            for event in &busiest.events {
+                *actions.entry(action(event)).or_default() += 1;
+            }
+            let mut actions = actions.into_iter().collect::<Vec<_>>();
+            actions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            for (action, count) in actions.into_iter().take(TOP) {
+                let share = count as f64 * 100.0 / busiest.requests as f64;
+                section.row(action, format!("{} ({:.0}%)", count, share));
+            }
+            section
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, millis: i64, uri: &str, resource: &str) -> EventV1 {
+        let time = DateTime::parse_from_rfc3339("2024-07-01T10:00:00Z").unwrap()
+            + Duration::milliseconds(millis);
+        test_event()
+            .uri(uri)
+            .user(user)
+            .object_ref(serde_json::json!({"resource": resource}))
+            .at(time.to_rfc3339())
+            .build()
+    }
+
+    #[test]
+    fn over_the_limits() {
+        let limits = [RateLimit {
+            seconds: 1,
+            requests: 5,
+        }];
+        // a burst of 8 lists in under a second, then one a second
+        let mut events = (0..8)
+            .map(|i| match i % 4 {
+                0 => event("operator", i * 100, "/api/v1/secrets", "secrets"),
+                _ => event("operator", i * 100, "/api/v1/pods", "pods"),
+            })
+            .collect::<Vec<_>>();
+        events.extend((2..10).map(|i| event("operator", i * 1000, "/api/v1/pods", "pods")));
+        events.extend((0..5).map(|i| event("alice", i * 100, "/api/v1/pods", "pods")));
+        events.sort_by_key(|event| event.request_received_timestamp);
+        let events = events.iter().collect::<Vec<_>>();
+
+        let excesses = excesses(&events, &limits);
+        assert_eq!(excesses.len(), 1);
+        let (user, over) = &excesses[0];
+        assert_eq!(*user, "operator");
+        assert_eq!(over[0].requests, 8);
+
+        let sections = sections(&events, &limits, &Timestamps::default());
+        assert_eq!(
+            sections[0].rows,
+            [
+                (
+                    "Over 5 in 1s".to_string(),
+                    "8 requests from 2024-07-01 10:00:00 UTC".to_string()
+                ),
+                ("list pods".to_string(), "6 (75%)".to_string()),
+                ("list secrets".to_string(), "2 (25%)".to_string()),
+            ]
+        );
+        assert!(super::excesses(&events, &[]).is_empty());
+    }
+
+    #[test]
+    fn several_limits() {
+        let limits = [
+            RateLimit {
+                seconds: 1,
+                requests: 5,
+            },
+            RateLimit {
+                seconds: 10,
+                requests: 8,
+            },
+            RateLimit {
+                seconds: 60,
+                requests: 100,
+            },
+        ];
+        // 6 in the first second, then one a second for 6 seconds
+        let mut events = (0..6)
+            .map(|i| event("operator", i * 100, "/api/v1/pods", "pods"))
+            .collect::<Vec<_>>();
+        events.extend((1..7).map(|i| event("operator", i * 1000, "/api/v1/pods", "pods")));
+        let events = events.iter().collect::<Vec<_>>();
+
+        let excesses = excesses(&events, &limits);
+        let over = &excesses[0].1;
+        assert_eq!(over.len(), 2);
+        // 12 requests against 8 is further over than 6 against 5
+        assert_eq!((over[0].limit, over[0].requests), (limits[1], 12));
+        assert_eq!((over[1].limit, over[1].requests), (limits[0], 6));
+    }
+
+    #[test]
+    fn zero_length_window() {
+        let events = (0..3)
+            .map(|i| event("operator", i * 100, "/api/v1/pods", "pods"))
+            .collect::<Vec<_>>();
+        let events = events.iter().collect::<Vec<_>>();
+        let limit = RateLimit {
+            seconds: 0,
+            requests: 0,
+        };
+        let excess = excess(&events, limit).unwrap();
+        assert_eq!(excess.requests, 1);
+    }
+}
@@ -0,0 +1,335 @@
+//! A standalone HTML report of the events read, with tables of who did the
+//! most, what was denied, who touched Secrets and what used deprecated APIs,
+//! and the events themselves to filter, for sharing with people who will
+//! never run a TUI.
+
+use crate::kube::EventV1;
+use crate::secret;
+use chrono::SecondsFormat;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// How many rows each of the summary tables has at most.
+const TOP: usize = 20;
+
+/// How many events the report's table holds at most, the latest ones, to
+/// keep the file a size browsers can cope with.
+pub const MAX_EVENTS: usize = 100_000;
+
+/// A table summarising the events, as a row of cells for each distinct value
+/// with how many events had it, most first.
+#[derive(Debug, PartialEq)]
+pub struct Tally {
+    pub title: &'static str,
+    pub headings: &'static [&'static str],
+    pub rows: Vec<(Vec<String>, usize)>,
+}
+
+impl Tally {
+    fn new(
+        title: &'static str,
+        headings: &'static [&'static str],
+        keys: impl Iterator<Item = Vec<String>>,
+    ) -> Self {
+        let mut counts = HashMap::<Vec<String>, usize>::new();
+        for key in keys {
+            *counts.entry(key).or_default() += 1;
+        }
+        let mut rows = counts.into_iter().collect::<Vec<_>>();
+        rows.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        rows.truncate(TOP);
+        Self {
+            title,
+            headings,
+            rows,
+        }
+    }
+}
+
+/// Whether the request was refused, by authentication or authorization.
+fn is_denied(event: &EventV1) -> bool {
+    event
+        .response_status
+        .as_ref()
+        .is_some_and(|status| matches!(status.code, 401 | 403))
+        || event
+            .annotations
+            .get("authorization.k8s.io/decision")
+            .is_some_and(|decision| decision == "forbid")
+}
+
+fn resource(event: &EventV1) -> String {
+    let object = event.object_ref.as_ref();
+    object
+        .and_then(|object| object.resource.clone())
+        .unwrap_or_default()
+}
+
+/// The summary tables of the report.
+pub fn tallies(events: &[&EventV1]) -> Vec<Tally> {
+    let events = || events.iter().copied();
+    vec![
+        Tally::new(
+            "Top users",
+            &["User"],
+            events().map(|event| vec![event.user.username.clone()]),
+        ),
+        Tally::new(
+            "Denied requests",
+            &["User", "Verb", "Resource"],
+            events().filter(|event| is_denied(event)).map(|event| {
+                let user = event.user.username.clone();
+                vec![user, event.verb.clone(), resource(event)]
+            }),
+        ),
+        Tally::new(
+            "Secrets access",
+            &["User", "Verb", "Secret"],
+            events()
+                .filter(|event| secret::is_secret(event))
+                .map(|event| {
+                    let object = event.object_ref.as_ref();
+                    let namespace = object.and_then(|object| object.namespace.as_deref());
+                    let name = object.and_then(|object| object.name.as_deref());
+                    let secret = format!("{}/{}", namespace.unwrap_or("*"), name.unwrap_or("*"));
+                    vec![event.user.username.clone(), event.verb.clone(), secret]
+                }),
+        ),
+        Tally::new(
+            "Deprecated APIs",
+            &["API", "User agent", "Removed in"],
+            events()
+                .filter(|event| {
+                    event
+                        .annotations
+                        .get("k8s.io/deprecated")
+                        .is_some_and(|deprecated| deprecated == "true")
+                })
+                .map(|event| {
+                    let object = event.object_ref.as_ref();
+                    let group = object.and_then(|object| object.api_group.as_deref());
+                    let version = object.and_then(|object| object.api_version.as_deref());
+                    let api = match group.filter(|group| !group.is_empty()) {
+                        Some(group) => format!("{}/{}", group, version.unwrap_or_default()),
+                        None => version.unwrap_or_default().to_string(),
+                    };
+                    let removed = event.annotations.get("k8s.io/removed-release");
+                    vec![
+                        format!("{} {}", api, resource(event)),
+                        event.user_agent.clone().unwrap_or_default(),
+                        removed.cloned().unwrap_or_default(),
+                    ]
+                }),
+        ),
+    ]
+}
+
+/// Escape text for putting in HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+th { background: #eee; }
+td.count { text-align: right; }
+section { display: inline-block; vertical-align: top; margin-right: 2em; }
+#events td { font-family: monospace; white-space: nowrap; }
+input { width: 40em; padding: 0.3em; }
+";
+
+/// Shows the events whose cells contain every word typed in the filter box,
+/// only drawing the first thousand to stay quick.
+const SCRIPT: &str = "
+const body = document.querySelector('#events tbody');
+const shown = document.getElementById('shown');
+function draw() {
+  const words = document.getElementById('filter').value.toLowerCase().split(/\\s+/).filter(w => w);
+  const matching = EVENTS.filter(row => {
+    const text = row.join(' ').toLowerCase();
+    return words.every(word => text.includes(word));
+  });
+  body.replaceChildren(...matching.slice(0, 1000).map(row => {
+    const tr = document.createElement('tr');
+    for (const cell of row) {
+      const td = document.createElement('td');
+      td.textContent = cell;
+      tr.appendChild(td);
+    }
+    return tr;
+  }));
+  shown.textContent = matching.length + ' matching' + (matching.length > 1000 ? ', showing the first 1000' : '');
+}
+document.getElementById('filter').addEventListener('input', draw);
+draw();
+";
+
+/// The report of `events`, which are in time order, as a standalone HTML
+/// page.
+pub fn html(events: &[&EventV1]) -> String {
+    let mut html = String::new();
+    let _ = write_html(&mut html, events);
+    html
+}
+
+fn write_html(html: &mut String, events: &[&EventV1]) -> std::fmt::Result {
+    let time = |event: &EventV1| {
+        event
+            .request_received_timestamp
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+    };
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Kubernetes audit log report</title>")?;
+    writeln!(html, "<style>{}</style></head><body>", STYLE)?;
+    writeln!(html, "<h1>Kubernetes audit log report</h1>")?;
+    match (events.first(), events.last()) {
+        (Some(first), Some(last)) => writeln!(
+            html,
+            "<p>{} events from {} to {}</p>",
+            events.len(),
+            time(first),
+            time(last)
+        )?,
+        _ => writeln!(html, "<p>No events</p>")?,
+    }
+
+    for tally in tallies(events) {
+        writeln!(html, "<section><h2>{}</h2>", tally.title)?;
+        if tally.rows.is_empty() {
+            writeln!(html, "<p>None</p></section>")?;
+            continue;
+        }
+        write!(html, "<table><tr>")?;
+        for heading in tally.headings {
+            write!(html, "<th>{}</th>", heading)?;
+        }
+        writeln!(html, "<th>Events</th></tr>")?;
+        for (cells, count) in &tally.rows {
+            write!(html, "<tr>")?;
+            for cell in cells {
+                write!(html, "<td>{}</td>", escape(cell))?;
+            }
+            writeln!(html, "<td class=\"count\">{}</td></tr>", count)?;
+        }
+        writeln!(html, "</table></section>")?;
+    }
+
+    writeln!(html, "<h2>Events</h2>")?;
+    let skipped = events.len().saturating_sub(MAX_EVENTS);
+    if skipped > 0 {
+        writeln!(
+            html,
+            "<p>Only the latest {} events are included, {} earlier ones are left out.</p>",
+            MAX_EVENTS, skipped
+        )?;
+    }
+    writeln!(
+        html,
+        "<p><input id=\"filter\" placeholder=\"Filter by words in any column\"> <span id=\"shown\"></span></p>"
+    )?;
+    writeln!(html, "<table id=\"events\"><thead><tr>")?;
+    writeln!(
+        html,
+        "<th>Time</th><th>User</th><th>Verb</th><th>Code</th><th>URI</th>"
+    )?;
+    writeln!(html, "</tr></thead><tbody></tbody></table>")?;
+    let rows = events[skipped..]
+        .iter()
+        .map(|event| {
+            let code = event.response_status.as_ref();
+            [
+                time(event),
+                event.user.username.clone(),
+                event.verb.clone(),
+                code.map(|status| status.code.to_string())
+                    .unwrap_or_default(),
+                event.request_uri.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    // a closing tag in the data would end the script early
+    let rows = serde_json::to_string(&rows)
+        .unwrap_or_default()
+        .replace("</", "<\\/");
+    writeln!(html, "<script>const EVENTS = {};{}</script>", rows, SCRIPT)?;
+    writeln!(html, "</body></html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, verb: &str, resource: &str, code: i32) -> EventV1 {
+        test_event()
+            .uri(format!(
+                "/apis/extensions/v1beta1/namespaces/default/{}/web",
+                resource
+            ))
+            .verb(verb)
+            .user(user)
+            .user_agent("kubectl/v1.20.0")
+            .object_ref(serde_json::json!({
+                "resource": resource,
+                "namespace": "default",
+                "name": "web",
+                "apiGroup": "extensions",
+                "apiVersion": "v1beta1",
+            }))
+            .code(code)
+            .annotations(
+                serde_json::json!({"k8s.io/deprecated": "true", "k8s.io/removed-release": "1.22"}),
+            )
+            .build()
+    }
+
+    #[test]
+    fn reporting() {
+        let events = [
+            event("alice", "get", "secrets", 200),
+            event("bob", "get", "secrets", 403),
+            event("alice", "get", "secrets", 200),
+            event("</script>", "list", "ingresses", 200),
+        ];
+        let events = events.iter().collect::<Vec<_>>();
+        let tallies = tallies(&events);
+        let row = |cells: &[&str], count| (cells.iter().map(|c| c.to_string()).collect(), count);
+        assert_eq!(
+            tallies[0].rows,
+            [row(&["alice"], 2), row(&["</script>"], 1), row(&["bob"], 1)]
+        );
+        assert_eq!(tallies[1].rows, [row(&["bob", "get", "secrets"], 1)]);
+        assert_eq!(
+            tallies[2].rows,
+            [
+                row(&["alice", "get", "default/web"], 2),
+                row(&["bob", "get", "default/web"], 1)
+            ]
+        );
+        assert_eq!(
+            tallies[3].rows[1],
+            row(
+                &["extensions/v1beta1 ingresses", "kubectl/v1.20.0", "1.22"],
+                1
+            )
+        );
+
+        let html = html(&events);
+        assert!(html.contains("<p>4 events from 2024-07-01T10:00:00.000Z"));
+        assert!(html.contains("<td>&lt;/script&gt;</td>"));
+        assert!(html.contains("\"<\\/script>\""));
+    }
+}
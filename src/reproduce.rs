@@ -0,0 +1,473 @@
+//! Commands that make the request an event records again, with kubectl or
+//! curl, for reproducing what a controller did against a test cluster, or
+//! writing it down.
+
+use crate::kube::{EventV1, ObjectReference};
+use serde_json::Value;
+
+/// Quote an argument for the shell, if it needs it.
+pub fn quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// The body, as a heredoc to give to `-f -`, for the command to be followed
+/// by.
+fn heredoc(body: &Value) -> String {
+    let json = serde_json::to_string_pretty(body).unwrap_or_default();
+    format!(" <<'EOF'\n{}\nEOF", json)
+}
+
+/// The resource as kubectl takes it, qualified by its API group if it has
+/// one, so that `deployments.apps` isn't mistaken for another group's.
+fn resource(object: &ObjectReference) -> Option<String> {
+    let resource = object.resource.as_deref()?;
+    match object
+        .api_group
+        .as_deref()
+        .filter(|group| !group.is_empty())
+    {
+        Some(group) => Some(format!("{}.{}", resource, group)),
+        None => Some(resource.to_string()),
+    }
+}
+
+/// An approximate `kubectl` command making the same request as the event,
+/// or why there isn't one.
+///
+/// Writes need the request body, which is only logged at the `Request` level
+/// and above. Patches are assumed to be merge patches unless they're a list
+/// of operations, as the event doesn't say which kind they were.
+pub fn kubectl(event: &EventV1) -> Result<String, String> {
+    let object = event
+        .object_ref
+        .as_ref()
+        .filter(|_| event.is_resource_request())
+        .ok_or("kubectl only makes requests for resources")?;
+    let resource = resource(object).ok_or("the event doesn't say what resource it was for")?;
+    let name = object.name.as_deref();
+    let query = event.query();
+    let param = |key: &str| {
+        query
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_ref())
+            .collect::<Vec<_>>()
+    };
+    let body = event.request_object.as_ref();
+    let needs_body = || {
+        body.ok_or(format!(
+            "the request body for {} wasn't logged, which needs the Request audit level",
+            event.verb
+        ))
+    };
+    let needs_name = || name.ok_or("the event doesn't say what object it was for");
+
+    let mut args = vec!["kubectl".to_string()];
+    // what goes after `--`, for the command to run in a container
+    let mut trailing = Vec::new();
+    let mut stdin = None;
+    let subresource = object.subresource.as_deref();
+    match (event.verb.as_str(), subresource) {
+        ("get", Some("log")) => {
+            args.extend(["logs".into(), needs_name()?.into()]);
+            for container in param("container") {
+                args.extend(["-c".into(), container.into()]);
+            }
+            if param("follow") == ["true"] {
+                args.push("-f".into());
+            }
+        }
+        ("create" | "get", Some(subresource @ ("exec" | "attach"))) => {
+            args.extend([subresource.into(), needs_name()?.into()]);
+            for container in param("container") {
+                args.extend(["-c".into(), container.into()]);
+            }
+            if param("stdin") == ["true"] {
+                args.push("-i".into());
+            }
+            if param("tty") == ["true"] {
+                args.push("-t".into());
+            }
+            trailing.extend(param("command").into_iter().map(String::from));
+        }
+        ("create" | "get", Some("portforward")) => {
+            args.extend(["port-forward".into(), needs_name()?.into()]);
+            args.extend(param("ports").into_iter().map(String::from));
+        }
+        ("update" | "patch", Some("scale")) => {
+            let replicas = needs_body()?.pointer("/spec/replicas");
+            let replicas = replicas.ok_or("the scale request doesn't say how many replicas")?;
+            args.extend([
+                "scale".into(),
+                resource,
+                needs_name()?.into(),
+                format!("--replicas={}", replicas),
+            ]);
+        }
+        ("get" | "list" | "watch", subresource) => {
+            args.extend(["get".into(), resource]);
+            args.extend(name.map(String::from));
+            if event.verb == "get" {
+                args.extend(["-o".into(), "yaml".into()]);
+            }
+            if let Some(subresource) = subresource {
+                args.push(format!("--subresource={}", subresource));
+            }
+            if event.verb == "watch" || param("watch") == ["true"] {
+                args.push("--watch".into());
+            }
+        }
+        ("delete", None) => args.extend(["delete".into(), resource, needs_name()?.into()]),
+        ("deletecollection", None) => args.extend(["delete".into(), resource, "--all".into()]),
+        ("create", None) => {
+            args.extend(["create".into(), "-f".into(), "-".into()]);
+            stdin = Some(needs_body()?);
+        }
+        ("update", subresource) => {
+            args.extend(["replace".into(), "-f".into(), "-".into()]);
+            args.extend(subresource.map(|subresource| format!("--subresource={}", subresource)));
+            stdin = Some(needs_body()?);
+        }
+        ("patch", subresource) => {
+            let body = needs_body()?;
+            let complete = body.get("apiVersion").is_some() && body.get("kind").is_some();
+            if complete && !param("fieldManager").is_empty() && subresource.is_none() {
+                // server-side apply, which sends the whole object
+                args.extend(["apply".into(), "--server-side".into()]);
+                for manager in param("fieldManager") {
+                    args.push(format!("--field-manager={}", manager));
+                }
+                if param("force") == ["true"] {
+                    args.push("--force-conflicts".into());
+                }
+                args.extend(["-f".into(), "-".into()]);
+                stdin = Some(body);
+            } else {
+                let kind = if body.is_array() { "json" } else { "merge" };
+                args.extend(["patch".into(), resource, needs_name()?.into()]);
+                args.extend(
+                    subresource.map(|subresource| format!("--subresource={}", subresource)),
+                );
+                args.extend([format!("--type={}", kind), "-p".into(), body.to_string()]);
+            }
+        }
+        (verb, Some(subresource)) => {
+            return Err(format!(
+                "kubectl has no command to {} {}/{}",
+                verb, resource, subresource
+            ))
+        }
+        (verb, None) => return Err(format!("kubectl has no command to {} {}", verb, resource)),
+    }
+
+    if matches!(event.verb.as_str(), "list" | "watch" | "deletecollection") {
+        for selector in param("labelSelector") {
+            args.extend(["-l".into(), selector.into()]);
+        }
+        for selector in param("fieldSelector") {
+            args.push(format!("--field-selector={}", selector));
+        }
+    }
+    match &object.namespace {
+        Some(namespace) => args.extend(["-n".into(), namespace.clone()]),
+        // listing everything of a namespaced kind, or of a cluster scoped one,
+        // where it makes no difference
+        None if matches!(event.verb.as_str(), "list" | "watch") => args.push("-A".into()),
+        None => {}
+    }
+    if param("dryRun") == ["All"] {
+        args.push("--dry-run=server".into());
+    }
+    impersonate(event, &mut args);
+
+    if !trailing.is_empty() {
+        args.push("--".into());
+        args.extend(trailing);
+    }
+    let mut command = join(&args);
+    if let Some(body) = stdin {
+        command.push_str(&heredoc(body));
+    }
+    Ok(command)
+}
+
+/// Have kubectl make the request as the user the event's was impersonating,
+/// if any.
+fn impersonate(event: &EventV1, args: &mut Vec<String>) {
+    if let Some(user) = &event.impersonated_user {
+        args.extend(["--as".into(), user.username.clone()]);
+        for group in &user.groups {
+            args.extend(["--as-group".into(), group.clone()]);
+        }
+    }
+}
+
+fn join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The HTTP method of the request, or why there isn't one, as for those
+/// that upgrade to a stream, which plain HTTP clients can't make.
+fn method(event: &EventV1) -> Result<&'static str, String> {
+    let subresource = event.object_ref.as_ref();
+    let subresource = subresource.and_then(|object| object.subresource.as_deref());
+    if let Some(subresource @ ("exec" | "attach" | "portforward")) = subresource {
+        return Err(format!(
+            "{} streams over a websocket, which the kubectl command (K) can make",
+            subresource
+        ));
+    }
+    match event.verb.as_str() {
+        "get" | "list" | "watch" => Ok("GET"),
+        "create" => Ok("POST"),
+        "update" => Ok("PUT"),
+        "patch" => Ok("PATCH"),
+        "delete" | "deletecollection" => Ok("DELETE"),
+        verb => Err(format!("there's no HTTP method for {}", verb)),
+    }
+}
+
+/// The request body of a write, or why it's missing.
+fn body<'a>(event: &'a EventV1, method: &str) -> Result<Option<&'a Value>, String> {
+    match (method, &event.request_object) {
+        ("POST" | "PUT" | "PATCH", None) => Err(format!(
+            "the request body for {} wasn't logged, which needs the Request audit level",
+            event.verb
+        )),
+        (_, body) => Ok(body.as_ref()),
+    }
+}
+
+/// A `curl` command making exactly the request the event records again, to
+/// the apiserver at `$APISERVER` with the bearer token in `$TOKEN`.
+///
+/// Patches are sent as merge patches unless they're a list of operations, or
+/// a whole object sent by a field manager, which is taken to be an apply.
+pub fn curl(event: &EventV1) -> Result<String, String> {
+    let method = method(event)?;
+    let body = body(event, method)?;
+    // each word is quoted as it's added, as the variables need double
+    // quotes for the shell to fill them in
+    let mut words = vec!["curl".to_string()];
+    if method != "GET" {
+        words.push(format!("-X {}", method));
+    }
+    words.push("-H \"Authorization: Bearer $TOKEN\" --cacert \"$CA\"".into());
+    let header = |header: String| format!("-H {}", quote(&header));
+    if let Some(user) = &event.impersonated_user {
+        words.push(header(format!("Impersonate-User: {}", user.username)));
+        for group in &user.groups {
+            words.push(header(format!("Impersonate-Group: {}", group)));
+        }
+    }
+    if let Some(body) = body {
+        let content_type = match method {
+            "PATCH" if body.is_array() => "application/json-patch+json",
+            "PATCH" if event.applied_by().is_some() => "application/apply-patch+yaml",
+            "PATCH" => "application/merge-patch+json",
+            _ => "application/json",
+        };
+        words.push(header(format!("Content-Type: {}", content_type)));
+        words.push("--data-binary @-".into());
+    }
+    words.push(format!("\"$APISERVER\"{}", quote(&event.request_uri)));
+    let mut command = words.join(" ");
+    if let Some(body) = body {
+        command.push_str(&heredoc(body));
+    }
+    Ok(command)
+}
+
+/// A `kubectl --raw` command making the same request to the same URI as the
+/// event, with kubectl's credentials, or why there isn't one: it can't
+/// patch.
+pub fn kubectl_raw(event: &EventV1) -> Result<String, String> {
+    let method = method(event)?;
+    let body = body(event, method)?;
+    let command = match method {
+        "GET" => "get",
+        "POST" => "create",
+        "PUT" => "replace",
+        "DELETE" => "delete",
+        _ => return Err("kubectl --raw can't patch, but the curl command (y) can".into()),
+    };
+    let mut args = vec![
+        "kubectl".to_string(),
+        command.into(),
+        "--raw".into(),
+        event.request_uri.clone(),
+    ];
+    if body.is_some() {
+        args.extend(["-f".into(), "-".into()]);
+    }
+    impersonate(event, &mut args);
+    let mut command = join(&args);
+    if let Some(body) = body {
+        command.push_str(&heredoc(body));
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use serde_json::json;
+
+    fn event(verb: &str, uri: &str, object: Value, body: Option<Value>) -> EventV1 {
+        test_event()
+            .level("Request")
+            .uri(uri)
+            .verb(verb)
+            .user("system:serviceaccount:kube-system:deployment-controller")
+            .object_ref(object)
+            .request_object(body)
+            .build()
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("deployments.apps"), "deployments.apps");
+        assert_eq!(quote("app in (web)"), "'app in (web)'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn kubectl_commands() {
+        let get = event(
+            "get",
+            "/apis/apps/v1/namespaces/default/deployments/web",
+            json!({"resource": "deployments", "namespace": "default", "name": "web", "apiGroup": "apps"}),
+            None,
+        );
+        assert_eq!(
+            kubectl(&get).unwrap(),
+            "kubectl get deployments.apps web -o yaml -n default"
+        );
+
+        let list = event(
+            "list",
+            "/api/v1/pods?labelSelector=app%20in%20(web)&limit=500",
+            json!({"resource": "pods"}),
+            None,
+        );
+        assert_eq!(
+            kubectl(&list).unwrap(),
+            "kubectl get pods -l 'app in (web)' -A"
+        );
+
+        let exec = event(
+            "create",
+            "/api/v1/namespaces/default/pods/web/exec?command=sh&command=-c&command=id&container=app&stdin=true&tty=true",
+            json!({"resource": "pods", "namespace": "default", "name": "web", "subresource": "exec"}),
+            None,
+        );
+        assert_eq!(
+            kubectl(&exec).unwrap(),
+            "kubectl exec web -c app -i -t -n default -- sh -c id"
+        );
+
+        let patch = event(
+            "patch",
+            "/apis/apps/v1/namespaces/default/deployments/web",
+            json!({"resource": "deployments", "namespace": "default", "name": "web", "apiGroup": "apps"}),
+            Some(json!({"spec": {"paused": true}})),
+        );
+        assert_eq!(
+            kubectl(&patch).unwrap(),
+            r#"kubectl patch deployments.apps web --type=merge -p '{"spec":{"paused":true}}' -n default"#
+        );
+
+        let create = event(
+            "create",
+            "/api/v1/namespaces/default/configmaps",
+            json!({"resource": "configmaps", "namespace": "default"}),
+            Some(json!({"apiVersion": "v1", "kind": "ConfigMap"})),
+        );
+        assert_eq!(
+            kubectl(&create).unwrap(),
+            "kubectl create -f - -n default <<'EOF'\n{\n  \"apiVersion\": \"v1\",\n  \"kind\": \"ConfigMap\"\n}\nEOF"
+        );
+
+        let update = event(
+            "update",
+            "/api/v1/namespaces/default/configmaps/settings",
+            json!({"resource": "configmaps", "namespace": "default", "name": "settings"}),
+            None,
+        );
+        assert!(kubectl(&update).unwrap_err().contains("wasn't logged"));
+
+        let health = event("get", "/healthz", Value::Null, None);
+        assert!(kubectl(&health).is_err());
+    }
+
+    #[test]
+    fn raw_requests() {
+        let list = event(
+            "list",
+            "/api/v1/namespaces/default/pods?limit=500",
+            json!({"resource": "pods", "namespace": "default"}),
+            None,
+        );
+        assert_eq!(
+            curl(&list).unwrap(),
+            r#"curl -H "Authorization: Bearer $TOKEN" --cacert "$CA" "$APISERVER"'/api/v1/namespaces/default/pods?limit=500'"#
+        );
+        assert_eq!(
+            kubectl_raw(&list).unwrap(),
+            "kubectl get --raw '/api/v1/namespaces/default/pods?limit=500'"
+        );
+
+        let patch = event(
+            "patch",
+            "/apis/apps/v1/namespaces/default/deployments/web?fieldManager=kubectl-edit",
+            json!({"resource": "deployments", "namespace": "default", "name": "web", "apiGroup": "apps"}),
+            Some(json!([{"op": "remove", "path": "/spec/paused"}])),
+        );
+        let command = curl(&patch).unwrap();
+        assert!(command.starts_with("curl -X PATCH "));
+        assert!(command.contains(
+            "-H 'Content-Type: application/json-patch+json' --data-binary @- \"$APISERVER\"'/apis/apps/"
+        ));
+        assert!(command.ends_with(
+            "<<'EOF'\n[\n  {\n    \"op\": \"remove\",\n    \"path\": \"/spec/paused\"\n  }\n]\nEOF"
+        ));
+        assert!(kubectl_raw(&patch).is_err());
+
+        let mut create = event(
+            "create",
+            "/api/v1/namespaces/default/configmaps",
+            json!({"resource": "configmaps", "namespace": "default"}),
+            Some(json!({"apiVersion": "v1", "kind": "ConfigMap"})),
+        );
+        create.impersonated_user = Some(
+            serde_json::from_value(json!({
+                "username": "alice",
+                "groups": ["dev"],
+            }))
+            .unwrap(),
+        );
+        assert!(curl(&create)
+            .unwrap()
+            .contains("-H 'Impersonate-User: alice' -H 'Impersonate-Group: dev' -H 'Content-Type: application/json'"));
+        assert!(kubectl_raw(&create).unwrap().starts_with(
+            "kubectl create --raw /api/v1/namespaces/default/configmaps -f - --as alice --as-group dev <<'EOF'"
+        ));
+
+        let exec = event(
+            "create",
+            "/api/v1/namespaces/default/pods/web/exec?command=sh",
+            json!({"resource": "pods", "namespace": "default", "name": "web", "subresource": "exec"}),
+            None,
+        );
+        assert!(curl(&exec).unwrap_err().contains("websocket"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Runs of the same user doing the same thing to the same object over and
+//! over, like lease renewals and watches being re-established, collapsed into
+//! a row each.
+
+use crate::kube::EventV1;
+use crate::store::{self, EventStore};
+use std::collections::BTreeSet;
+
+/// A row of the table while collapsing runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunRow {
+    /// A run of events, by the positions among the visible events of its
+    /// first and last. An expanded run is drawn as its first event, followed
+    /// by the rest of them as events of their own.
+    Run {
+        first: usize,
+        last: usize,
+        expanded: bool,
+    },
+    /// An event, by its position among the visible events.
+    Event(usize),
+}
+
+impl RunRow {
+    /// The position among the visible events of the event the row is drawn
+    /// as, and selects.
+    pub fn position(&self) -> usize {
+        match self {
+            RunRow::Run { first, .. } => *first,
+            RunRow::Event(position) => *position,
+        }
+    }
+
+    /// Whether the event at `position` is shown by the row, including the
+    /// rest of a collapsed run.
+    pub fn contains(&self, position: usize) -> bool {
+        match self {
+            RunRow::Run {
+                first,
+                last,
+                expanded: false,
+            } => (*first..=*last).contains(&position),
+            row => row.position() == position,
+        }
+    }
+}
+
+/// Whether two events are the same user doing the same thing to the same
+/// object.
+fn repeats(a: &EventV1, b: &EventV1) -> bool {
+    a.user.username == b.user.username && a.verb == b.verb && a.path() == b.path()
+}
+
+/// Which runs are expanded, while collapsing runs.
+#[derive(Debug, Default)]
+pub struct Runs {
+    /// Indices into the store of the first events of the expanded runs.
+    expanded: BTreeSet<usize>,
+}
+
+impl Runs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A row for each run of two or more of the visible events, and for each
+    /// event on its own.
+    pub fn rows(&self, store: &EventStore, visible: &[usize]) -> Vec<RunRow> {
+        let mut rows = Vec::new();
+        let mut first = 0;
+        while first < visible.len() {
+            let mut last = first;
+            while last + 1 < visible.len()
+                && repeats(&store[visible[first]], &store[visible[last + 1]])
+            {
+                last += 1;
+            }
+            if last == first {
+                rows.push(RunRow::Event(first));
+            } else {
+                let expanded = self.expanded.contains(&visible[first]);
+                rows.push(RunRow::Run {
+                    first,
+                    last,
+                    expanded,
+                });
+                if expanded {
+                    rows.extend((first + 1..=last).map(RunRow::Event));
+                }
+            }
+            first = last + 1;
+        }
+        rows
+    }
+
+    /// Show each event of the run starting at `index` in the store.
+    pub fn expand(&mut self, index: usize) {
+        self.expanded.insert(index);
+    }
+
+    /// Collapse the run starting at `index` in the store back into a row.
+    pub fn collapse(&mut self, index: usize) {
+        self.expanded.remove(&index);
+    }
+
+    /// Account for an event having been inserted into the store at `index`.
+    pub fn inserted(&mut self, index: usize) {
+        store::shift_indices(&mut self.expanded, index);
+    }
+}
+
+/// The run, if any, an event is part of, by the index among `rows` of its row.
+pub fn run_of(rows: &[RunRow], position: usize) -> Option<usize> {
+    rows.iter().rposition(|row| match row {
+        RunRow::Run { first, last, .. } => (*first..=*last).contains(&position),
+        RunRow::Event(_) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(verb: &str, uri: &str, timestamp: &str) -> EventV1 {
+        test_event()
+            .uri(uri)
+            .verb(verb)
+            .user("system:kube-scheduler")
+            .at(timestamp)
+            .build()
+    }
+
+    #[test]
+    fn collapsing() {
+        let lease = "/apis/coordination.k8s.io/v1/namespaces/kube-system/leases/kube-scheduler";
+        let mut store = EventStore::new();
+        store.insert(event("update", lease, "2024-07-01T10:00:00Z"));
+        store.insert(event("update", lease, "2024-07-01T10:00:02Z"));
+        store.insert(event("update", lease, "2024-07-01T10:00:04Z"));
+        store.insert(event("get", lease, "2024-07-01T10:00:05Z"));
+        store.insert(event("update", lease, "2024-07-01T10:00:06Z"));
+        let visible = (0..store.len()).collect::<Vec<_>>();
+
+        let mut runs = Runs::new();
+        let rows = runs.rows(&store, &visible);
+        let run = RunRow::Run {
+            first: 0,
+            last: 2,
+            expanded: false,
+        };
+        assert_eq!(rows, [run, RunRow::Event(3), RunRow::Event(4)]);
+        assert!(rows[0].contains(2));
+        assert_eq!(run_of(&rows, 2), Some(0));
+        assert_eq!(run_of(&rows, 3), None);
+
+        runs.expand(0);
+        let rows = runs.rows(&store, &visible);
+        assert_eq!(rows.len(), 5);
+        assert!(!rows[0].contains(2));
+        assert_eq!(run_of(&rows, 2), Some(0));
+
+        // the expanded run follows its first event about
+        store.insert(event("list", "/api/v1/pods", "2024-07-01T09:00:00Z"));
+        runs.inserted(0);
+        let visible = (0..store.len()).collect::<Vec<_>>();
+        assert_eq!(runs.rows(&store, &visible).len(), 6);
+        runs.collapse(1);
+        assert_eq!(runs.rows(&store, &visible).len(), 4);
+    }
+}
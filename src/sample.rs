@@ -0,0 +1,90 @@
+//! Sampling the noisiest events while they arrive faster than can be kept up
+//! with, like from a busy apiserver, while keeping every write and failure.
+
+use crate::kube::EventV1;
+use std::collections::HashMap;
+
+/// Keep one in this many of each noisy pattern while sampling, unless
+/// configured otherwise.
+pub const DEFAULT_ONE_IN: u32 = 10;
+
+/// The verbs of events that are sampled; the rest change something, so are
+/// always kept.
+const SAMPLED_VERBS: [&str; 3] = ["get", "list", "watch"];
+
+/// Decides which events to keep once they arrive faster than a threshold.
+#[derive(Debug)]
+pub struct Sampler {
+    /// Events per second above which to sample.
+    threshold: f64,
+    one_in: u64,
+    /// How many events of each user, verb and resource have been seen while
+    /// sampling.
+    seen: HashMap<(String, String, String), u64>,
+}
+
+impl Sampler {
+    pub fn new(threshold: u32, one_in: u32) -> Self {
+        Self {
+            threshold: threshold.into(),
+            one_in: one_in.max(1).into(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Whether to keep an event arriving while they're arriving at `rate`
+    /// a second: always under the threshold, and over it, every write and
+    /// failure, and the first of every `one_in` of the same user reading the
+    /// same resource the same way.
+    pub fn keep(&mut self, event: &EventV1, rate: f64) -> bool {
+        if rate <= self.threshold
+            || !SAMPLED_VERBS.contains(&event.verb.as_str())
+            || event
+                .response_status
+                .as_ref()
+                .is_some_and(|status| status.is_failure())
+        {
+            return true;
+        }
+
+        let resource = event
+            .object_ref
+            .as_ref()
+            .and_then(|object| object.resource.clone())
+            .unwrap_or_default();
+        let key = (event.user.username.clone(), event.verb.clone(), resource);
+        let seen = self.seen.entry(key).or_default();
+        *seen += 1;
+        (*seen - 1).is_multiple_of(self.one_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(verb: &str, code: u16) -> EventV1 {
+        test_event()
+            .uri("/api/v1/namespaces/default/pods")
+            .verb(verb)
+            .object_ref(serde_json::json!({"resource": "pods", "namespace": "default"}))
+            .code(code.into())
+            .build()
+    }
+
+    #[test]
+    fn sampling() {
+        let mut sampler = Sampler::new(1000, 3);
+        let kept = |sampler: &mut Sampler, verb, code, rate| {
+            (0..6)
+                .filter(|_| sampler.keep(&event(verb, code), rate))
+                .count()
+        };
+        assert_eq!(kept(&mut sampler, "list", 200, 500.0), 6);
+        assert_eq!(kept(&mut sampler, "list", 200, 5000.0), 2);
+        assert_eq!(kept(&mut sampler, "create", 201, 5000.0), 6);
+        assert_eq!(kept(&mut sampler, "get", 403, 5000.0), 6);
+        assert_eq!(kept(&mut sampler, "get", 200, 5000.0), 2);
+    }
+}
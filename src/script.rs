@@ -0,0 +1,148 @@
+//! Functions over events written in Rhai in the config file, shown as columns
+//! and filtered on as `script[name]`, for anything the built in fields can't
+//! say without recompiling.
+//!
+//! Each script is run with the event as `event`, a map like its JSON, and
+//! what it ends with is its value: a bool to filter on, a string or number to
+//! show, an array for several values, or `()` for none.
+
+use crate::config::NamedScript;
+use crate::kube::EventV1;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::collections::BTreeMap;
+
+/// How many operations a script can take on one event before it's stopped,
+/// so that a loop that never ends can't hang the TUI.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// The scripts from the config file, compiled, with the engine they run on.
+pub struct Scripts {
+    engine: Engine,
+    /// The scripts by name.
+    scripts: BTreeMap<String, AST>,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scripts {
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        Self {
+            engine,
+            scripts: BTreeMap::new(),
+        }
+    }
+
+    /// Compile the scripts from the config file.
+    pub fn load(scripts: &[NamedScript]) -> anyhow::Result<Self> {
+        let mut loaded = Self::new();
+        for named in scripts {
+            loaded.register(&named.name, &named.script)?;
+        }
+        Ok(loaded)
+    }
+
+    /// Compile a script and make it available as `script[name]`, replacing
+    /// any called that already.
+    pub fn register(&mut self, name: &str, source: &str) -> anyhow::Result<()> {
+        let ast = self
+            .engine
+            .compile(source)
+            .map_err(|err| anyhow::anyhow!("compiling script {:?}: {}", name, err))?;
+        self.scripts.insert(name.to_string(), ast);
+        Ok(())
+    }
+
+    /// The names of the scripts, in order.
+    pub fn names(&self) -> Vec<String> {
+        self.scripts.keys().cloned().collect()
+    }
+
+    /// The values of the script called `name` for the event.
+    pub fn values(&self, name: &str, event: &EventV1) -> anyhow::Result<Vec<String>> {
+        let ast = self
+            .scripts
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("no script called {:?}", name))?;
+        let mut scope = Scope::new();
+        scope.push_constant("event", rhai::serde::to_dynamic(event)?);
+        let value = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+            .map_err(|err| anyhow::anyhow!("{}", err))?;
+        if value.is_array() {
+            let values = value.into_array().unwrap_or_default();
+            return Ok(values.into_iter().filter_map(text).collect());
+        }
+        Ok(text(value).into_iter().collect())
+    }
+}
+
+/// A value of a script as text.
+fn text(value: Dynamic) -> Option<String> {
+    if value.is_unit() {
+        return None;
+    }
+    if value.is_map() {
+        let json = rhai::serde::from_dynamic::<serde_json::Value>(&value).ok()?;
+        return Some(json.to_string());
+    }
+    Some(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    #[test]
+    fn running() {
+        let event = test_event()
+            .uri("/api/v1/namespaces/default/pods")
+            .verb("create")
+            .user("system:serviceaccount:ci:deployer")
+            .request_object(serde_json::json!({
+                "spec": {"containers": [{"image": "nginx"}, {"image": "envoy"}]},
+            }))
+            .build();
+
+        let mut scripts = Scripts::new();
+        scripts
+            .register(
+                "bot",
+                r#"event.user.username.starts_with("system:serviceaccount:")"#,
+            )
+            .unwrap();
+        assert_eq!(scripts.values("bot", &event).unwrap(), ["true"]);
+
+        scripts
+            .register(
+                "images",
+                "event.requestObject?.spec?.containers?.map(|c| c.image)",
+            )
+            .unwrap();
+        assert_eq!(
+            scripts.values("images", &event).unwrap(),
+            ["nginx", "envoy"]
+        );
+
+        scripts
+            .register("nothing", "if event.verb == \"get\" { 1 }")
+            .unwrap();
+        assert!(scripts.values("nothing", &event).unwrap().is_empty());
+
+        scripts.register("forever", "loop {}").unwrap();
+        assert!(scripts.values("forever", &event).is_err());
+        assert!(scripts.values("missing", &event).is_err());
+        assert!(scripts.register("broken", "event.").is_err());
+        assert_eq!(scripts.names(), ["bot", "forever", "images", "nothing"]);
+
+        // each set of scripts is its own
+        assert!(Scripts::new().values("bot", &event).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Keeping the values in Secrets off the screen unless they're asked for, so
+//! that sharing it doesn't leak credentials.
+
+use crate::kube::EventV1;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::Value;
+
+/// How much of a Secret's values to show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reveal {
+    #[default]
+    Hidden,
+    Shown,
+    /// Shown with `data`'s values base64 decoded.
+    Decoded,
+}
+
+impl Reveal {
+    pub fn next(self) -> Self {
+        match self {
+            Reveal::Hidden => Reveal::Shown,
+            Reveal::Shown => Reveal::Decoded,
+            Reveal::Decoded => Reveal::Hidden,
+        }
+    }
+}
+
+/// Whether the event is about Secrets, so that its bodies may hold their
+/// values even without saying what kind of object they are, as in patches.
+pub fn is_secret(event: &EventV1) -> bool {
+    event
+        .object_ref
+        .as_ref()
+        .is_some_and(|object| object.resource.as_deref() == Some("secrets"))
+}
+
+/// A body with the `data` and `stringData` values of any Secret in it hidden
+/// or decoded. Bodies are taken to be Secrets if `secret` says so or their
+/// kind does, and the items of lists are looked at the same way.
+pub fn reveal(mut value: Value, secret: bool, reveal: Reveal) -> Value {
+    if reveal == Reveal::Shown {
+        return value;
+    }
+
+    if secret || value["kind"] == "Secret" {
+        reveal_object(&mut value, reveal);
+    }
+    let secrets = secret || value["kind"] == "SecretList";
+    if let Some(Value::Array(items)) = value.get_mut("items") {
+        for item in items {
+            if secrets || item["kind"] == "Secret" {
+                reveal_object(item, reveal);
+            }
+        }
+    }
+    value
+}
+
+fn reveal_object(value: &mut Value, reveal: Reveal) {
+    for key in ["data", "stringData"] {
+        let Some(Value::Object(values)) = value.get_mut(key) else {
+            continue;
+        };
+        for value in values.values_mut() {
+            match reveal {
+                Reveal::Hidden => *value = Value::String("<hidden, R to reveal>".to_string()),
+                Reveal::Shown => {}
+                Reveal::Decoded if key == "data" => {
+                    if let Some(decoded) = value.as_str().and_then(decode) {
+                        *value = Value::String(decoded);
+                    }
+                }
+                Reveal::Decoded => {}
+            }
+        }
+    }
+}
+
+/// A base64 value decoded to text, or described if it isn't text.
+pub fn decode(encoded: &str) -> Option<String> {
+    let bytes = BASE64_STANDARD.decode(encoded.trim()).ok()?;
+    Some(match String::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && !c.is_whitespace()) => text,
+        Ok(text) => format!("<{} bytes of binary>", text.len()),
+        Err(err) => format!("<{} bytes of binary>", err.as_bytes().len()),
+    })
+}
+
+/// Whether a path into an event, as given by [`crate::diff`], leads into
+/// the values of a Secret in one of its bodies.
+pub fn is_secret_path(path: &str) -> bool {
+    let first = path.split(['.', '[']).find(|s| !s.is_empty());
+    matches!(first, Some("requestObject" | "responseObject")) && is_secret_object_path(path)
+}
+
+/// Whether a path into an object, as given by [`crate::diff`], leads into the
+/// values of a Secret.
+pub fn is_secret_object_path(path: &str) -> bool {
+    path.split(['.', '['])
+        .any(|segment| segment == "data" || segment == "stringData")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn revealing() {
+        let secret = json!({
+            "kind": "Secret",
+            "metadata": {"name": "token"},
+            "data": {"password": "aHVudGVyMg==", "key": "AAEC"},
+            "stringData": {"user": "admin"},
+        });
+        let hidden = reveal(secret.clone(), false, Reveal::Hidden);
+        assert_eq!(hidden["data"]["password"], "<hidden, R to reveal>");
+        assert_eq!(hidden["stringData"]["user"], "<hidden, R to reveal>");
+        assert_eq!(hidden["metadata"]["name"], "token");
+
+        assert_eq!(reveal(secret.clone(), false, Reveal::Shown), secret);
+
+        let decoded = reveal(secret.clone(), false, Reveal::Decoded);
+        assert_eq!(decoded["data"]["password"], "hunter2");
+        assert_eq!(decoded["data"]["key"], "<3 bytes of binary>");
+        assert_eq!(decoded["stringData"]["user"], "admin");
+
+        let patch = json!({"data": {"password": "aHVudGVyMg=="}});
+        assert_eq!(reveal(patch.clone(), false, Reveal::Hidden), patch);
+        assert_eq!(
+            reveal(patch, true, Reveal::Hidden)["data"]["password"],
+            "<hidden, R to reveal>"
+        );
+
+        let list = json!({"kind": "SecretList", "items": [{"data": {"key": "AAEC"}}]});
+        assert_eq!(
+            reveal(list, false, Reveal::Hidden)["items"][0]["data"]["key"],
+            "<hidden, R to reveal>"
+        );
+    }
+
+    #[test]
+    fn secret_paths() {
+        assert!(is_secret_path(".requestObject.data.password"));
+        assert!(is_secret_path(".responseObject.items[0].stringData.user"));
+        assert!(!is_secret_path(".responseObject.metadata.name"));
+        assert!(!is_secret_path(".annotations.data"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Which lists and watches narrow what they ask for with label or field
+//! selectors, and which clients ask for every object in a namespace or the
+//! whole cluster, the usual cause of apiserver memory spikes.
+
+use crate::detail::Section;
+use crate::kube::EventV1;
+use std::collections::{HashMap, HashSet};
+
+/// How many rows each list has at most.
+const TOP: usize = 20;
+
+const SELECTORS: [&str; 2] = ["labelSelector", "fieldSelector"];
+
+/// How much of the cluster a list or watch without selectors covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Namespace,
+    Cluster,
+}
+
+/// The selectors a list or watch gave, like `labelSelector=app=web`.
+pub fn selectors(event: &EventV1) -> Vec<String> {
+    event
+        .query()
+        .into_iter()
+        .filter(|(key, value)| SELECTORS.contains(&key.as_ref()) && !value.is_empty())
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect()
+}
+
+/// How much a list or watch without selectors covers, or `None` if it isn't
+/// one, or is of a single object.
+pub fn unselective(event: &EventV1) -> Option<Scope> {
+    if !matches!(event.verb.as_str(), "list" | "watch") || !selectors(event).is_empty() {
+        return None;
+    }
+    let object = event.object_ref.as_ref()?;
+    if object.name.is_some() {
+        return None;
+    }
+    match object.namespace {
+        Some(_) => Some(Scope::Namespace),
+        None => Some(Scope::Cluster),
+    }
+}
+
+/// Unselective lists and watches by client, the most cluster-wide first,
+/// with what they list most, then the selectors given most.
+pub fn sections(events: &[&EventV1]) -> Vec<Section> {
+    #[derive(Default)]
+    struct Client<'a> {
+        cluster: usize,
+        namespace: usize,
+        resources: HashMap<&'a str, usize>,
+    }
+
+    // watches are logged when they start and again when they end
+    let mut seen = HashSet::new();
+    let mut clients = HashMap::<&str, Client>::new();
+    let mut selected = HashMap::<String, HashSet<&str>>::new();
+    for event in events {
+        if !matches!(event.verb.as_str(), "list" | "watch") || !seen.insert(event.audit_id.as_str())
+        {
+            continue;
+        }
+        let user = event.user.username.as_str();
+        for selector in selectors(event) {
+            selected.entry(selector).or_default().insert(user);
+        }
+        let Some(scope) = unselective(event) else {
+            continue;
+        };
+        let client = clients.entry(user).or_default();
+        match scope {
+            Scope::Cluster => client.cluster += 1,
+            Scope::Namespace => client.namespace += 1,
+        }
+        let resource = event
+            .object_ref
+            .as_ref()
+            .and_then(|object| object.resource.as_deref())
+            .unwrap_or_default();
+        *client.resources.entry(resource).or_default() += 1;
+    }
+
+    let mut clients = clients.into_iter().collect::<Vec<_>>();
+    clients.sort_by(|a, b| {
+        let total = |client: &Client| client.cluster + client.namespace;
+        (b.1.cluster, total(&b.1))
+            .cmp(&(a.1.cluster, total(&a.1)))
+            .then(a.0.cmp(b.0))
+    });
+    let mut unselective = Section::new("Unselective lists and watches");
+    for (user, client) in clients.into_iter().take(TOP) {
+        unselective.alert |= client.cluster > 0;
+        let mostly = client
+            .resources
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)));
+        let mut text = format!(
+            "{} cluster-wide, {} namespace-wide",
+            client.cluster, client.namespace
+        );
+        if let Some((resource, count)) = mostly {
+            text.push_str(&format!(", mostly {} ({})", resource, count));
+        }
+        unselective.row(user, text);
+    }
+
+    let mut selected = selected.into_iter().collect::<Vec<_>>();
+    selected.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+    let mut selectors = Section::new("Selectors");
+    for (selector, users) in selected.into_iter().take(TOP) {
+        let clients = match users.len() {
+            1 => "1 client".to_string(),
+            count => format!("{} clients", count),
+        };
+        selectors.row(selector, clients);
+    }
+
+    [unselective, selectors]
+        .into_iter()
+        .filter(|section| !section.rows.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use serde_json::json;
+
+    fn event(id: &str, user: &str, verb: &str, uri: &str, namespace: Option<&str>) -> EventV1 {
+        test_event()
+            .audit_id(id)
+            .uri(uri)
+            .verb(verb)
+            .user(user)
+            .object_ref(json!({"resource": "pods", "namespace": namespace}))
+            .build()
+    }
+
+    #[test]
+    fn unselective_lists() {
+        let everything = event("1", "operator", "list", "/api/v1/pods?limit=500", None);
+        let watch = event(
+            "2",
+            "operator",
+            "watch",
+            "/api/v1/namespaces/web/pods?watch=true",
+            Some("web"),
+        );
+        let selective = event(
+            "3",
+            "kubelet",
+            "watch",
+            "/api/v1/pods?fieldSelector=spec.nodeName%3Dworker-1&watch=true",
+            None,
+        );
+        let labelled = event(
+            "4",
+            "alice",
+            "list",
+            "/api/v1/namespaces/web/pods?labelSelector=app%3Dweb",
+            Some("web"),
+        );
+        assert_eq!(unselective(&everything), Some(Scope::Cluster));
+        assert_eq!(unselective(&watch), Some(Scope::Namespace));
+        assert_eq!(unselective(&selective), None);
+        assert_eq!(selectors(&labelled), ["labelSelector=app=web"]);
+
+        let mut ended = watch.clone();
+        ended.stage_timestamp += chrono::Duration::minutes(5);
+        let events = [&everything, &watch, &selective, &labelled, &ended];
+        let sections = sections(&events);
+        assert!(sections[0].alert);
+        assert_eq!(
+            sections[0].rows,
+            [(
+                "operator".to_string(),
+                "1 cluster-wide, 1 namespace-wide, mostly pods (2)".to_string()
+            )]
+        );
+        assert_eq!(
+            sections[1].rows,
+            [
+                (
+                    "fieldSelector=spec.nodeName=worker-1".to_string(),
+                    "1 client".to_string()
+                ),
+                ("labelSelector=app=web".to_string(), "1 client".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! What was being looked at when quitting, saved so that a long investigation
+//! can be picked up again with `--resume` where it was left.
+
+use crate::filter::Field;
+use crate::kube::EventV1;
+use crate::view::Column;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// An event, identified well enough to find it again among the events read
+/// the next time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct EventId {
+    pub audit_id: String,
+    /// An audit ID is shared by each stage of a request, but they're reached
+    /// at different times.
+    pub stage_timestamp: DateTime<Utc>,
+}
+
+impl EventId {
+    pub fn of(event: &EventV1) -> Self {
+        Self {
+            audit_id: event.audit_id.clone(),
+            stage_timestamp: event.stage_timestamp,
+        }
+    }
+}
+
+/// What a tab was showing.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Tab {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// The label of the only input shown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_filter: Option<String>,
+    /// The titles of the optional columns shown, or their paths.
+    pub columns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<EventId>,
+    /// The object whose changes the tab follows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watching: Option<String>,
+    pub timeline_hidden: bool,
+}
+
+/// Everything saved of the session.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Session {
+    pub tabs: Vec<Tab>,
+    /// The tab being shown.
+    pub tab: usize,
+    pub bookmarks: Vec<EventId>,
+}
+
+impl Session {
+    /// Where the session is saved by default, if the platform has somewhere
+    /// for state like it.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("kale").join("session.json"))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading the session saved in {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Write the session, creating its directory if need be.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let text = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+/// The column with the title or path saved for it.
+pub fn column(saved: &str) -> anyhow::Result<Column> {
+    match Column::ALL
+        .into_iter()
+        .find(|column| column.title() == saved)
+    {
+        Some(column) => Ok(column),
+        None => match saved.parse()? {
+            Field::Script(name) => Ok(Column::Script(name)),
+            Field::Enriched(key) => Ok(Column::Enriched(key)),
+            Field::Path(path) => Ok(Column::Path(path)),
+            field => anyhow::bail!("there's no column for {}", field),
+        },
+    }
+}
+
+/// The parts of a session being resumed that wait on events yet to be read.
+#[derive(Debug, Default)]
+pub struct Resuming {
+    /// The events to bookmark once they're read.
+    pub bookmarks: HashSet<EventId>,
+    /// The event to select in each tab once it's read, until something else is
+    /// selected.
+    pub selections: Vec<Option<EventId>>,
+}
+
+impl Resuming {
+    pub fn is_done(&self) -> bool {
+        self.bookmarks.is_empty() && self.selections.iter().all(Option::is_none)
+    }
+}
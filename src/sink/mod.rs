@@ -0,0 +1,130 @@
+//! Where events are sent on to as they're read, like an observability backend.
+//!
+//! Every output implements [`Sink`], and is run in the background by a
+//! [`Forwarder`], which batches events up for it so that a slow or
+//! unreachable output never holds up exploring them.
+
+pub mod elasticsearch;
+pub mod otlp;
+
+pub use self::elasticsearch::ElasticsearchSink;
+pub use self::otlp::OtlpSink;
+
+use crate::filter::Filter;
+use crate::kube::EventV1;
+use crate::script::Scripts;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How many events to send on at once at most.
+const BATCH: usize = 500;
+
+/// How long to wait for a batch to fill before sending what there is.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A consumer of audit events.
+pub trait Sink: Send + 'static {
+    /// Send a batch of events on.
+    fn write(&mut self, events: &[EventV1]) -> anyhow::Result<()>;
+}
+
+/// Runs a sink in the background, sending it the events given to it.
+#[derive(Debug)]
+pub struct Forwarder {
+    pub name: String,
+    /// Only the events matching this are sent on, if given.
+    filter: Option<Filter>,
+    send: mpsc::Sender<EventV1>,
+    /// What went wrong sending events on, and when it came right again.
+    reports: mpsc::Receiver<Result<(), String>>,
+    running: JoinHandle<()>,
+}
+
+impl Forwarder {
+    pub fn spawn(name: impl Into<String>, mut sink: impl Sink, filter: Option<Filter>) -> Self {
+        let name = name.into();
+        let (send, recv) = mpsc::channel::<EventV1>();
+        let (report, reports) = mpsc::channel();
+        let running = std::thread::spawn(move || {
+            let mut batch = Vec::new();
+            let mut started = Instant::now();
+            let mut failing = false;
+            loop {
+                let wait = FLUSH_INTERVAL.saturating_sub(started.elapsed());
+                let closed = match recv.recv_timeout(wait) {
+                    Ok(event) => {
+                        if batch.is_empty() {
+                            started = Instant::now();
+                        }
+                        batch.push(event);
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+                let due = batch.len() >= BATCH || started.elapsed() >= FLUSH_INTERVAL;
+                if !batch.is_empty() && (due || closed) {
+                    // only tell of it going wrong and coming right again,
+                    // rather than of every batch while it's down
+                    match sink.write(&batch) {
+                        Ok(()) if failing => {
+                            failing = false;
+                            let _ = report.send(Ok(()));
+                        }
+                        Ok(()) => {}
+                        Err(err) if !failing => {
+                            failing = true;
+                            let _ = report.send(Err(format!("{:#}", err)));
+                        }
+                        Err(_) => {}
+                    }
+                    batch.clear();
+                }
+                if batch.is_empty() {
+                    started = Instant::now();
+                }
+                if closed {
+                    return;
+                }
+            }
+        });
+        Self {
+            name,
+            filter,
+            send,
+            reports,
+            running,
+        }
+    }
+
+    /// Send an event on, if it matches the filter.
+    pub fn forward(&self, event: &EventV1, scripts: &Scripts) {
+        if self
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter.eval(event, scripts))
+        {
+            // the sink only goes away if it panicked, see is_finished
+            let _ = self.send.send(event.clone());
+        }
+    }
+
+    /// Each time since last asked that sending events on started failing,
+    /// with why, or started working again.
+    pub fn reports(&self) -> Vec<Result<(), String>> {
+        self.reports.try_iter().collect()
+    }
+
+    /// Whether the sink has stopped, which it only does if it panicked
+    /// before being finished.
+    pub fn is_finished(&self) -> bool {
+        self.running.is_finished()
+    }
+
+    /// Send on whatever events are still waiting, and stop.
+    pub fn finish(self) {
+        drop(self.send);
+        let _ = self.running.join();
+    }
+}
@@ -0,0 +1,157 @@
+//! Sends events to an OpenTelemetry collector as log records, over OTLP/HTTP
+//! with the JSON encoding, so they end up wherever the rest of the cluster's
+//! telemetry goes.
+
+use crate::kube::EventV1;
+use crate::sink::Sink;
+use anyhow::Context;
+use serde_json::{json, Value};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long to give the collector to take a batch.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `INFO` and `WARN` in the OpenTelemetry log data model.
+const SEVERITY_INFO: u8 = 9;
+const SEVERITY_WARN: u8 = 13;
+
+#[derive(Debug)]
+pub struct OtlpSink {
+    /// Where to post log records to.
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl OtlpSink {
+    /// Send to the collector at `endpoint`, like `http://localhost:4318`.
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            url: format!("{}/v1/logs", endpoint.trim_end_matches('/')),
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+}
+
+impl Sink for OtlpSink {
+    fn write(&mut self, events: &[EventV1]) -> anyhow::Result<()> {
+        self.agent
+            .post(&self.url)
+            .send_json(logs(events))
+            .with_context(|| format!("sending events to {}", self.url))?;
+        Ok(())
+    }
+}
+
+fn string(key: &str, value: impl Into<String>) -> Value {
+    json!({"key": key, "value": {"stringValue": value.into()}})
+}
+
+/// A log record for the event, with the event itself as its body and the
+/// fields it's most likely to be searched by as attributes.
+fn record(event: &EventV1) -> Value {
+    let mut attributes = vec![
+        string("k8s.audit.id", &event.audit_id),
+        string("k8s.audit.stage", event.stage.to_string()),
+        string("k8s.audit.verb", &event.verb),
+        string("k8s.audit.user", &event.user.username),
+        string("url.path", event.path()),
+    ];
+    if let Some(object) = &event.object_ref {
+        let fields = [
+            ("k8s.namespace.name", &object.namespace),
+            ("k8s.audit.resource", &object.resource),
+            ("k8s.audit.subresource", &object.subresource),
+            ("k8s.audit.name", &object.name),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                attributes.push(string(key, value));
+            }
+        }
+    }
+    if let Some(user_agent) = &event.user_agent {
+        attributes.push(string("user_agent.original", user_agent));
+    }
+    if let Some(source) = &event.source {
+        attributes.push(string("k8s.cluster.name", source));
+    }
+    let status = event.response_status.as_ref();
+    if let Some(status) = status {
+        attributes.push(json!({
+            "key": "http.response.status_code",
+            "value": {"intValue": status.code.to_string()},
+        }));
+    }
+    let severity = match status.is_some_and(|status| status.is_failure()) {
+        true => (SEVERITY_WARN, "WARN"),
+        false => (SEVERITY_INFO, "INFO"),
+    };
+    let nanos = |time: chrono::DateTime<chrono::Utc>| {
+        time.timestamp_nanos_opt().unwrap_or_default().to_string()
+    };
+    let mut record = json!({
+        "timeUnixNano": nanos(event.request_received_timestamp),
+        "observedTimeUnixNano": nanos(event.stage_timestamp),
+        "severityNumber": severity.0,
+        "severityText": severity.1,
+        "body": {"stringValue": serde_json::to_string(event).unwrap_or_default()},
+        "attributes": attributes,
+    });
+    // the stages of a request share a trace, so they can be found together
+    if let Ok(id) = Uuid::parse_str(&event.audit_id) {
+        record["traceId"] = id.simple().to_string().into();
+    }
+    record
+}
+
+/// The export request for the events.
+pub fn logs(events: &[EventV1]) -> Value {
+    json!({
+        "resourceLogs": [{
+            "resource": {
+                "attributes": [string("service.name", "kube-apiserver")],
+            },
+            "scopeLogs": [{
+                "scope": {"name": "kale", "version": env!("CARGO_PKG_VERSION")},
+                "logRecords": events.iter().map(record).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    #[test]
+    fn encoding() {
+        let event = test_event()
+            .uri("/api/v1/namespaces/default/secrets/token?timeout=5s")
+            .verb("get")
+            .object_ref(json!({"resource": "secrets", "namespace": "default", "name": "token"}))
+            .code(403)
+            .set("stageTimestamp", "2024-07-01T10:00:00.5Z")
+            .build();
+
+        let logs = logs(&[event]);
+        let record = &logs["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["timeUnixNano"], "1719828000000000000");
+        assert_eq!(record["observedTimeUnixNano"], "1719828000500000000");
+        assert_eq!(record["severityText"], "WARN");
+        assert_eq!(record["traceId"], "ec95c2ca00d440b993b478a6eb1242c7");
+        let attributes = record["attributes"].as_array().unwrap();
+        assert!(attributes.contains(&string(
+            "url.path",
+            "/api/v1/namespaces/default/secrets/token"
+        )));
+        assert!(attributes.contains(&string("k8s.namespace.name", "default")));
+        assert!(attributes.contains(&json!({
+            "key": "http.response.status_code",
+            "value": {"intValue": "403"},
+        })));
+        let body = record["body"]["stringValue"].as_str().unwrap();
+        assert!(body.contains("\"auditID\":\"ec95c2ca-00d4-40b9-93b4-78a6eb1242c7\""));
+    }
+}
@@ -0,0 +1,165 @@
+//! How big events' request and response bodies are, serialised, to find the
+//! objects and clients weighing on etcd and the audit log.
+
+use crate::detail::Section;
+use crate::kube::EventV1;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How many rows each list has at most.
+const TOP: usize = 20;
+
+fn serialised(body: Option<&Value>) -> Option<usize> {
+    serde_json::to_vec(body?).ok().map(|bytes| bytes.len())
+}
+
+/// How many bytes the request body is, serialised, if it was logged.
+pub fn request_bytes(event: &EventV1) -> Option<usize> {
+    serialised(event.request_object.as_ref())
+}
+
+/// How many bytes the response body is, serialised, if it was logged.
+pub fn response_bytes(event: &EventV1) -> Option<usize> {
+    serialised(event.response_object.as_ref())
+}
+
+/// A number of bytes to read, like `512 B` or `1.5 KiB`.
+pub fn human(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// What an event was about, as `namespace/resource/name`, or its path
+/// without one.
+fn object(event: &EventV1) -> String {
+    let Some(object) = &event.object_ref else {
+        return event.path().to_string();
+    };
+    if object.name.is_none() {
+        return event.path().to_string();
+    }
+    [&object.namespace, &object.resource, &object.name]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The largest objects among the events, by the larger of each event's
+/// bodies, then the clients sending and receiving the most bytes in them.
+pub fn sections(events: &[&EventV1]) -> Vec<Section> {
+    let mut objects = HashMap::<String, (usize, &EventV1)>::new();
+    let mut clients = HashMap::<&str, (usize, usize, usize)>::new();
+    for event in events {
+        let sent = request_bytes(event).unwrap_or_default();
+        let received = response_bytes(event).unwrap_or_default();
+        if sent == 0 && received == 0 {
+            continue;
+        }
+        let largest = objects.entry(object(event)).or_insert((0, event));
+        if sent.max(received) > largest.0 {
+            *largest = (sent.max(received), event);
+        }
+        let client = clients.entry(&event.user.username).or_default();
+        client.0 += sent;
+        client.1 += received;
+        client.2 += 1;
+    }
+
+    let mut objects = objects.into_iter().collect::<Vec<_>>();
+    objects.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(&b.0)));
+    let mut largest = Section::new("Largest objects");
+    for (object, (bytes, event)) in objects.into_iter().take(TOP) {
+        let by = format!("{} by {}", event.verb, event.user.username);
+        largest.row(object, format!("{} ({})", human(bytes), by));
+    }
+
+    let mut clients = clients.into_iter().collect::<Vec<_>>();
+    clients.sort_by(|a, b| (b.1 .0 + b.1 .1).cmp(&(a.1 .0 + a.1 .1)).then(a.0.cmp(b.0)));
+    let mut chattiest = Section::new("Clients by bytes");
+    for (user, (sent, received, count)) in clients.into_iter().take(TOP) {
+        let requests = match count {
+            1 => "1 request".to_string(),
+            count => format!("{} requests", count),
+        };
+        chattiest.row(
+            user,
+            format!(
+                "{} in {} (sent {}, received {})",
+                human(sent + received),
+                requests,
+                human(sent),
+                human(received)
+            ),
+        );
+    }
+
+    [largest, chattiest]
+        .into_iter()
+        .filter(|section| !section.rows.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use serde_json::json;
+
+    fn event(user: &str, name: &str, data: usize) -> EventV1 {
+        test_event()
+            .level("RequestResponse")
+            .uri(format!("/api/v1/namespaces/web/configmaps/{}", name))
+            .verb("update")
+            .user(user)
+            .object_ref(json!({"resource": "configmaps", "namespace": "web", "name": name}))
+            .request_object(json!({"data": {"big": "x".repeat(data)}}))
+            .response_object(json!({"data": {"big": "x".repeat(data)}, "metadata": {}}))
+            .build()
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(human(512), "512 B");
+        assert_eq!(human(1536), "1.5 KiB");
+        assert_eq!(human(3 * 1024 * 1024), "3.0 MiB");
+
+        let small = event("alice", "settings", 10);
+        assert_eq!(request_bytes(&small), Some(29));
+        assert_eq!(response_bytes(&small), Some(43));
+        let big = event("bob", "bundle", 2048);
+        let events = [&small, &big, &small];
+
+        let sections = sections(&events);
+        assert_eq!(
+            sections[0].rows,
+            [
+                (
+                    "web/configmaps/bundle".to_string(),
+                    "2.0 KiB (update by bob)".to_string()
+                ),
+                (
+                    "web/configmaps/settings".to_string(),
+                    "43 B (update by alice)".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            sections[1].rows[1],
+            (
+                "alice".to_string(),
+                "144 B in 2 requests (sent 58 B, received 86 B)".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! How far each followed file has been read, kept in a state file so that
+//! following it again after a restart carries on from there rather than
+//! reading everything again.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Where a file was read up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Checkpoint {
+    /// The file's inode, to tell a file that has since been replaced, like
+    /// by log rotation, from the one that was read.
+    pub inode: u64,
+    /// How many bytes had been read, up to the end of the last whole line.
+    pub offset: u64,
+}
+
+/// The checkpoints of every followed file, shared between the sources
+/// following them.
+#[derive(Debug, Clone)]
+pub struct Checkpoints {
+    path: PathBuf,
+    /// By the absolute path of each file.
+    files: Arc<Mutex<BTreeMap<String, Checkpoint>>>,
+}
+
+impl Checkpoints {
+    /// Read the state file, or start afresh if it doesn't exist yet.
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let files = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parsing {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+        };
+        Ok(Self {
+            path,
+            files: Arc::new(Mutex::new(files)),
+        })
+    }
+
+    /// The checkpoint of the file with the given [`key`].
+    pub fn get(&self, key: &str) -> Option<Checkpoint> {
+        self.files.lock().unwrap().get(key).copied()
+    }
+
+    pub fn set(&self, key: &str, checkpoint: Checkpoint) {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), checkpoint);
+    }
+
+    /// Write the state file, by way of a temporary file beside it so that
+    /// quitting part way through can't leave it half written.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let text = serde_json::to_string_pretty(&*self.files.lock().unwrap())?;
+        let temporary = self.path.with_extension("tmp");
+        std::fs::write(&temporary, text)
+            .with_context(|| format!("writing {}", temporary.display()))?;
+        std::fs::rename(&temporary, &self.path)
+            .with_context(|| format!("writing {}", self.path.display()))
+    }
+}
+
+/// What a file's checkpoint is kept under, so that the same file is found
+/// again whichever directory it's followed from.
+pub fn key(file: &Path) -> String {
+    std::fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .display()
+        .to_string()
+}
@@ -0,0 +1,253 @@
+//! Read audit logs straight out of a cluster using `kubectl` and the current
+//! kubeconfig.
+//!
+//! Two layouts are supported:
+//!
+//! - the apiserver writes audit events to stdout (`--audit-log-path=-`), in
+//!   which case they are streamed from the `kube-apiserver` static pod logs.
+//! - the apiserver writes audit events to a file under `/var/log` on the
+//!   control plane nodes (the kubeadm default), in which case the file is
+//!   fetched through the kubelet's `/logs/` endpoint on every control plane node.
+
+use super::{send_document, EventSender, EventSource};
+use crate::kube;
+use anyhow::Context;
+use async_trait::async_trait;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+
+#[derive(Debug, Clone, Default)]
+pub struct ClusterOptions {
+    /// The kubeconfig context to use, defaulting to the current context.
+    pub context: Option<String>,
+    /// The path of the audit log on the control plane nodes. When unset the
+    /// audit events are read from the apiserver pod logs instead.
+    pub audit_log_path: Option<String>,
+}
+
+/// Fetches audit events from the cluster with `kubectl`.
+pub struct ClusterSource {
+    options: ClusterOptions,
+}
+
+impl ClusterSource {
+    pub fn new(options: ClusterOptions) -> Self {
+        Self { options }
+    }
+}
+
+#[async_trait]
+impl EventSource for ClusterSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || stream(self.options, &send)).await?
+    }
+}
+
+/// Fetch audit events from the cluster, forwarding each one to `send`.
+fn stream(options: ClusterOptions, send: &EventSender) -> anyhow::Result<()> {
+    match &options.audit_log_path {
+        Some(path) => {
+            // The kubelet only serves files under /var/log
+            let relative = path
+                .strip_prefix("/var/log/")
+                .with_context(|| format!("{} is not under /var/log", path))?;
+            for node in control_plane_nodes(&options)? {
+                send.notice(format!("reading {} from node {}", path, node))?;
+                let uri = format!("/api/v1/nodes/{}/proxy/logs/{}", node, relative);
+                let mut command = kubectl(&options);
+                command.args(["get", "--raw", &uri]);
+                stream_lines(command, send)
+                    .with_context(|| format!("reading {} from node {}", path, node))?;
+            }
+        }
+        None => {
+            if apiserver_pods(&options)?.is_empty() {
+                anyhow::bail!(
+                    "there are no kube-apiserver pods in kube-system to read the logs of, as \
+                     there aren't where the apiserver is built into another process, like on \
+                     k3s; give --audit-log-path to read the log file on the nodes instead"
+                );
+            }
+            send.notice("following the kube-apiserver pod logs")?;
+            let mut command = kubectl(&options);
+            command.args([
+                "logs",
+                "--namespace=kube-system",
+                "--selector=component=kube-apiserver",
+                "--follow",
+                "--tail=-1",
+                "--max-log-requests=10",
+            ]);
+            stream_lines(command, send).context("following the kube-apiserver pod logs")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `command`, sending on the audit events it writes, and fail with what
+/// it wrote to stderr if it fails.
+fn stream_lines(mut command: Command, send: &EventSender) -> anyhow::Result<()> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run kubectl")?;
+    // read alongside stdout, so that neither pipe can fill up and stall it
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = std::thread::spawn(move || {
+        let mut errors = Vec::new();
+        let _ = stderr.read_to_end(&mut errors);
+        errors
+    });
+    if let Err(err) = read_lines(child.stdout.take().expect("stdout is piped"), send) {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(err);
+    }
+    let status = child.wait()?;
+    check(status, &errors.join().unwrap_or_default())
+}
+
+/// An error saying what kubectl wrote to stderr, if it exited with `status`
+/// because it failed.
+fn check(status: ExitStatus, stderr: &[u8]) -> anyhow::Result<()> {
+    if status.success() {
+        return Ok(());
+    }
+    match String::from_utf8_lossy(stderr).trim() {
+        "" => anyhow::bail!("kubectl failed ({})", status),
+        stderr => anyhow::bail!("kubectl failed ({}): {}", status, stderr),
+    }
+}
+
+fn kubectl(options: &ClusterOptions) -> Command {
+    let mut command = Command::new("kubectl");
+    if let Some(context) = &options.context {
+        command.arg(format!("--context={}", context));
+    }
+    command
+}
+
+/// The workloads in every namespace whose pods can use service accounts, as a
+/// `List` like `kubectl get --output=json` gives.
+pub fn workloads(options: &ClusterOptions) -> anyhow::Result<serde_json::Value> {
+    let output = kubectl(options)
+        .args([
+            "get",
+            "deployments,daemonsets,statefulsets,cronjobs,jobs",
+            "--all-namespaces",
+            "--output=json",
+        ])
+        .output()
+        .context("failed to run kubectl")?;
+    check(output.status, &output.stderr).context("listing the workloads")?;
+    serde_json::from_slice(&output.stdout).context("parsing the workloads kubectl listed")
+}
+
+fn control_plane_nodes(options: &ClusterOptions) -> anyhow::Result<Vec<String>> {
+    let nodes = names(
+        options,
+        &["nodes", "--selector=node-role.kubernetes.io/control-plane"],
+    )
+    .context("listing the control plane nodes")?;
+    if nodes.is_empty() {
+        anyhow::bail!(
+            "there are no nodes labelled node-role.kubernetes.io/control-plane to read the \
+             audit log from"
+        );
+    }
+    Ok(nodes)
+}
+
+fn apiserver_pods(options: &ClusterOptions) -> anyhow::Result<Vec<String>> {
+    names(
+        options,
+        &[
+            "pods",
+            "--namespace=kube-system",
+            "--selector=component=kube-apiserver",
+        ],
+    )
+    .context("listing the kube-apiserver pods")
+}
+
+/// The names of the objects `kubectl get` lists with `args`.
+fn names(options: &ClusterOptions, args: &[&str]) -> anyhow::Result<Vec<String>> {
+    let output = kubectl(options)
+        .arg("get")
+        .args(args)
+        .arg("--output=jsonpath={.items[*].metadata.name}")
+        .output()
+        .context("failed to run kubectl")?;
+    check(output.status, &output.stderr)?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Pod logs interleave the apiserver's own log lines with the audit events, so
+/// anything that doesn't deserialise is skipped rather than treated as fatal.
+fn read_lines(reader: impl Read, send: &EventSender) -> anyhow::Result<()> {
+    for line in BufReader::new(reader).lines() {
+        // Lines that aren't even JSON are the apiserver's own logs
+        if let Ok(document) = serde_json::from_str(&line?) {
+            send_document(kube::events_from_value(document), send)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::source::{channel, Ingested};
+
+    #[test]
+    fn pod_log_lines() {
+        let text = format!(
+            "{}\nI0701 10:00:00.000000       1 server.go:42] serving\n{{}}\n{}\n",
+            test_event().audit_id("a").json(),
+            test_event().audit_id("b").json(),
+        );
+        let (send, mut recv) = channel();
+        read_lines(text.as_bytes(), &send).unwrap();
+        drop(send);
+        let mut ids = Vec::new();
+        let mut skipped = 0;
+        while let Some(ingested) = recv.blocking_recv() {
+            match ingested {
+                Ingested::Event(event) => ids.push(event.audit_id),
+                Ingested::Skipped(_) => skipped += 1,
+                _ => {}
+            }
+        }
+        // the apiserver's own log line is dropped quietly, but JSON that
+        // isn't an event is told of
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn failing() {
+        let (send, _recv) = channel();
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "echo 'error: context \"prod\" does not exist' >&2; exit 1",
+        ]);
+        let err = stream_lines(command, &send).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "kubectl failed (exit status: 1): error: context \"prod\" does not exist"
+        );
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 0"]);
+        assert!(stream_lines(command, &send).is_ok());
+    }
+}
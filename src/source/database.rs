@@ -0,0 +1,32 @@
+//! A source reading back the events saved to a SQLite file with `--save`.
+
+use super::{EventSender, EventSource};
+use crate::database;
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+/// Reads every event saved in a SQLite file.
+pub struct DatabaseSource {
+    path: PathBuf,
+}
+
+impl DatabaseSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl EventSource for DatabaseSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || {
+            let count = database::read(&self.path, |event| send.send(event))?;
+            send.notice(format!(
+                "finished reading {} events from {}",
+                count,
+                self.path.display()
+            ))
+        })
+        .await?
+    }
+}
@@ -0,0 +1,367 @@
+//! A minimal listener for the Fluentd/Fluent Bit forward protocol.
+//!
+//! Supports the Message, Forward, PackedForward and CompressedPackedForward
+//! modes, and acknowledges chunks when the client asks for it. Shared-key
+//! authentication (the handshake phase) is not supported.
+//!
+//! See <https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1>
+
+use super::{send_document, EventSender, EventSource};
+use crate::kube;
+use anyhow::Context;
+use async_trait::async_trait;
+use rmpv::Value as MsgValue;
+use serde_json::{Map, Number, Value};
+use std::io::{BufReader, Cursor, ErrorKind, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+/// Accepts forward protocol connections from Fluentd and Fluent Bit.
+pub struct ForwardSource {
+    addr: SocketAddr,
+}
+
+impl ForwardSource {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+}
+
+#[async_trait]
+impl EventSource for ForwardSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || listen(self.addr, send)).await?
+    }
+}
+
+/// Accept connections on `addr` forever, forwarding every decoded event to `send`.
+fn listen(addr: SocketAddr, send: EventSender) -> anyhow::Result<()> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("failed to listen on {}", addr))?;
+    send.notice(format!("listening for forward connections on {}", addr))?;
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let send = send.clone();
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr();
+            if let Err(err) = handle_connection(stream, send.clone()) {
+                let peer = peer.map_or_else(|_| "unknown".to_string(), |peer| peer.to_string());
+                let _ = send.notice(format!("dropped connection from {}: {:#}", peer, err));
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, send: EventSender) -> anyhow::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let message = match rmpv::decode::read_value(&mut reader) {
+            Ok(message) => message,
+            Err(err) if is_eof(&err) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let (records, chunk) = decode_message(message)?;
+        for record in records {
+            // Records that aren't audit events are dropped rather than
+            // tearing down the whole connection
+            send_document(kube::events_from_value(record), &send)?;
+        }
+
+        if let Some(chunk) = chunk {
+            let ack = MsgValue::Map(vec![(MsgValue::from("ack"), MsgValue::from(chunk))]);
+            rmpv::encode::write_value(&mut writer, &ack)?;
+        }
+    }
+}
+
+fn is_eof(err: &rmpv::decode::Error) -> bool {
+    match err {
+        rmpv::decode::Error::InvalidMarkerRead(err) => err.kind() == ErrorKind::UnexpectedEof,
+        _ => false,
+    }
+}
+
+/// Split a single forward protocol message into its records, and the chunk id
+/// to acknowledge if the client requested one.
+fn decode_message(message: MsgValue) -> anyhow::Result<(Vec<Value>, Option<String>)> {
+    let MsgValue::Array(mut parts) = message else {
+        anyhow::bail!("forward message was not an array");
+    };
+    if parts.len() < 2 {
+        anyhow::bail!("forward message had {} elements", parts.len());
+    }
+
+    let entries = parts.remove(1);
+    let options = match entries {
+        // Message: [tag, time, record, option?]
+        MsgValue::Integer(_) | MsgValue::Ext(..) => parts.get(2).cloned(),
+        _ => parts.get(1).cloned(),
+    };
+    let compressed = option(&options, "compressed").is_some_and(|c| c == "gzip");
+    let chunk = option(&options, "chunk");
+
+    let records = match entries {
+        // Forward: [tag, [[time, record], ...], option?]
+        MsgValue::Array(entries) => entries.into_iter().filter_map(entry_record).collect(),
+        // PackedForward: [tag, <concatenated [time, record] entries>, option?]
+        MsgValue::Binary(packed) => unpack_entries(packed, compressed)?,
+        MsgValue::String(packed) => unpack_entries(packed.into_bytes(), compressed)?,
+        // Message: [tag, time, record, option?]
+        _ => parts.into_iter().nth(1).map(to_json).into_iter().collect(),
+    };
+
+    Ok((records, chunk))
+}
+
+fn option(options: &Option<MsgValue>, key: &str) -> Option<String> {
+    let MsgValue::Map(options) = options.as_ref()? else {
+        return None;
+    };
+    options
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .and_then(|(_, v)| v.as_str())
+        .map(str::to_string)
+}
+
+fn unpack_entries(packed: Vec<u8>, compressed: bool) -> anyhow::Result<Vec<Value>> {
+    let packed = if compressed {
+        let mut decompressed = Vec::new();
+        // Compressed chunks may be several gzip members back to back
+        flate2::read::MultiGzDecoder::new(Cursor::new(packed))
+            .read_to_end(&mut decompressed)
+            .context("failed to decompress forward chunk")?;
+        decompressed
+    } else {
+        packed
+    };
+
+    let len = packed.len() as u64;
+    let mut cursor = Cursor::new(packed);
+    let mut records = Vec::new();
+    while cursor.position() < len {
+        let entry = rmpv::decode::read_value(&mut cursor)?;
+        records.extend(entry_record(entry));
+    }
+
+    Ok(records)
+}
+
+fn entry_record(entry: MsgValue) -> Option<Value> {
+    match entry {
+        MsgValue::Array(parts) => parts.into_iter().nth(1).map(to_json),
+        _ => None,
+    }
+}
+
+/// Convert a msgpack value into the equivalent JSON value.
+///
+/// Binary data is treated as (lossy) UTF-8, and extension types have no JSON
+/// equivalent so become `null`.
+fn to_json(value: MsgValue) -> Value {
+    match value {
+        MsgValue::Nil | MsgValue::Ext(..) => Value::Null,
+        MsgValue::Boolean(b) => Value::Bool(b),
+        MsgValue::Integer(i) => i
+            .as_i64()
+            .map(Number::from)
+            .or_else(|| i.as_u64().map(Number::from))
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        MsgValue::F32(f) => Number::from_f64(f.into()).map_or(Value::Null, Value::Number),
+        MsgValue::F64(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        MsgValue::String(s) => Value::String(s.into_str().unwrap_or_default()),
+        MsgValue::Binary(b) => Value::String(String::from_utf8_lossy(&b).into_owned()),
+        MsgValue::Array(values) => Value::Array(values.into_iter().map(to_json).collect()),
+        MsgValue::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        MsgValue::String(s) => s.into_str().unwrap_or_default(),
+                        MsgValue::Binary(b) => String::from_utf8_lossy(&b).into_owned(),
+                        other => other.to_string(),
+                    };
+                    (key, to_json(v))
+                })
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::source::{channel, Ingested};
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn record(id: &str) -> Value {
+        test_event()
+            .audit_id(id)
+            .uri("/api/v1/namespaces/default/pods")
+            .json()
+    }
+
+    /// The msgpack equivalent of a JSON value, as Fluent Bit would send it.
+    fn msgpack(value: &Value) -> MsgValue {
+        match value {
+            Value::Null => MsgValue::Nil,
+            Value::Bool(b) => MsgValue::Boolean(*b),
+            Value::Number(n) => MsgValue::from(n.as_i64().unwrap()),
+            Value::String(s) => MsgValue::from(s.as_str()),
+            Value::Array(values) => MsgValue::Array(values.iter().map(msgpack).collect()),
+            Value::Object(entries) => MsgValue::Map(
+                entries
+                    .iter()
+                    .map(|(k, v)| (MsgValue::from(k.as_str()), msgpack(v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn encode(value: &MsgValue) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, value).unwrap();
+        bytes
+    }
+
+    /// A frame as it comes off the wire.
+    fn frame(parts: Vec<MsgValue>) -> MsgValue {
+        let bytes = encode(&MsgValue::Array(parts));
+        rmpv::decode::read_value(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    fn entry(id: &str) -> MsgValue {
+        MsgValue::Array(vec![MsgValue::from(1719828000), msgpack(&record(id))])
+    }
+
+    fn options(entries: &[(&str, &str)]) -> MsgValue {
+        MsgValue::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (MsgValue::from(*k), MsgValue::from(*v)))
+                .collect(),
+        )
+    }
+
+    fn ids(records: &[Value]) -> Vec<&str> {
+        records
+            .iter()
+            .map(|record| record["auditID"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn message_mode() {
+        let message = frame(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::from(1719828000),
+            msgpack(&record("a")),
+            options(&[("chunk", "c1")]),
+        ]);
+        let (records, chunk) = decode_message(message).unwrap();
+        assert_eq!(records, [record("a")]);
+        assert_eq!(chunk.as_deref(), Some("c1"));
+    }
+
+    #[test]
+    fn forward_mode() {
+        let message = frame(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::Array(vec![entry("a"), entry("b"), MsgValue::from("junk")]),
+        ]);
+        let (records, chunk) = decode_message(message).unwrap();
+        assert_eq!(ids(&records), ["a", "b"]);
+        assert_eq!(chunk, None);
+    }
+
+    #[test]
+    fn packed_forward_modes() {
+        let packed = [entry("a"), entry("b")]
+            .iter()
+            .flat_map(encode)
+            .collect::<Vec<_>>();
+        let message = frame(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::Binary(packed.clone()),
+            options(&[("size", "2"), ("chunk", "c2")]),
+        ]);
+        let (records, chunk) = decode_message(message).unwrap();
+        assert_eq!(ids(&records), ["a", "b"]);
+        assert_eq!(chunk.as_deref(), Some("c2"));
+
+        // compressed as two gzip members back to back
+        let mut gzipped = Vec::new();
+        for half in packed.chunks(packed.len() / 2 + 1) {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(half).unwrap();
+            gzipped.extend(encoder.finish().unwrap());
+        }
+        let message = frame(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::Binary(gzipped),
+            options(&[("compressed", "gzip"), ("chunk", "c3")]),
+        ]);
+        let (records, chunk) = decode_message(message).unwrap();
+        assert_eq!(ids(&records), ["a", "b"]);
+        assert_eq!(chunk.as_deref(), Some("c3"));
+    }
+
+    #[test]
+    fn malformed_frames() {
+        assert!(decode_message(MsgValue::from("kube.audit")).is_err());
+        assert!(decode_message(frame(vec![MsgValue::from("kube.audit")])).is_err());
+
+        let packed = encode(&entry("a"));
+        let truncated = frame(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::Binary(packed[..packed.len() - 4].to_vec()),
+        ]);
+        assert!(decode_message(truncated).is_err());
+        let not_gzip = frame(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::Binary(packed),
+            options(&[("compressed", "gzip")]),
+        ]);
+        assert!(decode_message(not_gzip).is_err());
+    }
+
+    #[test]
+    fn acknowledging_chunks() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let (send, mut recv) = channel();
+        let handler = std::thread::spawn(move || handle_connection(server, send));
+
+        let message = MsgValue::Array(vec![
+            MsgValue::from("kube.audit"),
+            MsgValue::Array(vec![entry("a")]),
+            options(&[("chunk", "p8n9gmxTQVC8/nh2wlKKeQ==")]),
+        ]);
+        client.write_all(&encode(&message)).unwrap();
+        let ack = rmpv::decode::read_value(&mut client).unwrap();
+        assert_eq!(
+            ack,
+            MsgValue::Map(vec![(
+                MsgValue::from("ack"),
+                MsgValue::from("p8n9gmxTQVC8/nh2wlKKeQ==")
+            )])
+        );
+        match recv.try_recv() {
+            Ok(Ingested::Event(event)) => assert_eq!(event.audit_id, "a"),
+            _ => panic!("expected an event"),
+        }
+
+        // a frame that isn't msgpack ends the connection with an error
+        client.write_all(&[0xc1]).unwrap();
+        assert!(handler.join().unwrap().is_err());
+    }
+}
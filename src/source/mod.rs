@@ -0,0 +1,165 @@
+//! Where audit events come from.
+//!
+//! Every input implements [`EventSource`], and the binary composes whichever
+//! ones were asked for on the command line with [`spawn`]. Downstream users of
+//! the library can implement the trait to feed events in from anywhere else.
+
+pub mod checkpoint;
+pub mod cluster;
+pub mod database;
+pub mod forward;
+pub mod reader;
+pub mod replay;
+pub mod webhook;
+
+pub use self::checkpoint::Checkpoints;
+pub use self::cluster::ClusterSource;
+pub use self::database::DatabaseSource;
+pub use self::forward::ForwardSource;
+pub use self::reader::{FileSource, FollowSource, LoadProgress, ReaderSource};
+pub use self::replay::{ReplaySource, Speed};
+pub use self::webhook::WebhookSource;
+
+use crate::kube::EventV1;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+/// What a source produces.
+#[derive(Debug)]
+pub enum Ingested {
+    Event(Box<EventV1>),
+    /// A document that was read but couldn't be made sense of, and why.
+    Skipped(String),
+    /// Something worth telling the user about, like a listener starting.
+    Notice(String),
+    /// A source stopped because of an error.
+    Failed(String),
+}
+
+pub type EventReceiver = mpsc::UnboundedReceiver<Ingested>;
+
+/// The sending half of the channel sources feed.
+#[derive(Debug, Clone)]
+pub struct EventSender(mpsc::UnboundedSender<Ingested>);
+
+impl EventSender {
+    pub fn send(&self, event: EventV1) -> anyhow::Result<()> {
+        self.0
+            .send(Ingested::Event(Box::new(event)))
+            .map_err(|_| anyhow::anyhow!("event receiver has gone away"))
+    }
+
+    /// Report a document that couldn't be deserialised and was dropped.
+    pub fn skipped(&self, reason: impl Into<String>) -> anyhow::Result<()> {
+        self.0
+            .send(Ingested::Skipped(reason.into()))
+            .map_err(|_| anyhow::anyhow!("event receiver has gone away"))
+    }
+
+    /// Tell the user something about the source.
+    pub fn notice(&self, text: impl Into<String>) -> anyhow::Result<()> {
+        self.0
+            .send(Ingested::Notice(text.into()))
+            .map_err(|_| anyhow::anyhow!("event receiver has gone away"))
+    }
+
+    /// Whether nothing is listening any more, so the source can stop.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+/// A new channel for sources to send to.
+pub fn channel() -> (EventSender, EventReceiver) {
+    let (send, recv) = mpsc::unbounded_channel();
+    (EventSender(send), recv)
+}
+
+/// A producer of audit events.
+#[async_trait]
+pub trait EventSource: Send + 'static {
+    /// Read events until the source is exhausted, sending each to `send`.
+    ///
+    /// Sources that never run dry, like listeners, only return on error.
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()>;
+}
+
+/// Wraps another source, tagging each of its events with a label so that
+/// inputs from several clusters can be told apart.
+pub struct Labelled {
+    label: String,
+    inner: Box<dyn EventSource>,
+}
+
+impl Labelled {
+    pub fn new(label: impl Into<String>, inner: Box<dyn EventSource>) -> Self {
+        Self {
+            label: label.into(),
+            inner,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSource for Labelled {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        let (inner_send, mut inner_recv) = channel();
+        let inner = tokio::spawn(self.inner.run(inner_send));
+
+        while let Some(ingested) = inner_recv.recv().await {
+            match ingested {
+                Ingested::Event(mut event) => {
+                    event.source = Some(self.label.clone());
+                    send.send(*event)?;
+                }
+                Ingested::Skipped(reason) => send.skipped(format!("{}: {}", self.label, reason))?,
+                Ingested::Notice(text) => send.notice(format!("{}: {}", self.label, text))?,
+                Ingested::Failed(err) => send
+                    .0
+                    .send(Ingested::Failed(format!("{}: {}", self.label, err)))
+                    .map_err(|_| anyhow::anyhow!("event receiver has gone away"))?,
+            }
+        }
+
+        inner.await?.map_err(|err| err.context(self.label.clone()))
+    }
+}
+
+/// Run every source in the background, returning a channel of all their
+/// events combined.
+///
+/// A source returning an error is reported on the channel as
+/// [`Ingested::Failed`].
+pub fn spawn(sources: Vec<Box<dyn EventSource>>) -> EventReceiver {
+    let (send, recv) = channel();
+    for source in sources {
+        let send = send.clone();
+        tokio::spawn(async move {
+            if let Err(err) = source.run(send.clone()).await {
+                let _ = send.0.send(Ingested::Failed(format!("{:#}", err)));
+            }
+        });
+    }
+
+    recv
+}
+
+/// Send on the events of a deserialised document, or report the document as
+/// skipped. Returns how many events were sent.
+fn send_document(
+    document: serde_json::Result<Vec<EventV1>>,
+    send: &EventSender,
+) -> anyhow::Result<usize> {
+    let mut sent = 0;
+    match document {
+        Ok(events) => {
+            for event in events {
+                send.send(event)?;
+                sent += 1;
+            }
+        }
+        Err(err) => send.skipped(err.to_string())?,
+    }
+
+    Ok(sent)
+}
@@ -0,0 +1,454 @@
+//! Sources reading concatenated JSON documents from stdin or files.
+
+use super::checkpoint::{self, Checkpoint, Checkpoints};
+use super::{send_document, EventSender, EventSource};
+use crate::kube;
+use anyhow::Context;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait for more data when following a file.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often to save how far a followed file has been read while catching up
+/// on it, rather than after every line. It's saved when the source stops too.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reads every document from a reader, such as stdin, until it is exhausted.
+pub struct ReaderSource {
+    name: String,
+    reader: Box<dyn Read + Send>,
+}
+
+impl ReaderSource {
+    pub fn new(name: impl Into<String>, reader: impl Read + Send + 'static) -> Self {
+        Self {
+            name: name.into(),
+            reader: Box::new(reader),
+        }
+    }
+
+    pub fn stdin() -> Self {
+        Self::new("stdin", std::io::stdin())
+    }
+}
+
+#[async_trait]
+impl EventSource for ReaderSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || read_documents(&self.name, self.reader, &send, None))
+            .await?
+    }
+}
+
+/// How far through a file a [`FileSource`] is, for showing while it loads and
+/// for cancelling it by.
+#[derive(Debug)]
+pub struct LoadProgress {
+    pub name: String,
+    /// The size of the file, once it's open.
+    total: AtomicU64,
+    read: AtomicU64,
+    events: AtomicU64,
+    started: Instant,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl LoadProgress {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            total: AtomicU64::new(0),
+            read: AtomicU64::new(0),
+            events: AtomicU64::new(0),
+            started: Instant::now(),
+            cancelled: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// How many bytes have been read, and of how many.
+    pub fn bytes(&self) -> (u64, u64) {
+        let total = self.total.load(Ordering::Relaxed);
+        (self.read.load(Ordering::Relaxed).min(total), total)
+    }
+
+    /// How many events have been read so far.
+    pub fn events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// How long the rest of the file should take to read, going by how long
+    /// what has been read took.
+    pub fn remaining(&self) -> Option<Duration> {
+        let (read, total) = self.bytes();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        (read > 0).then(|| Duration::from_secs_f64(elapsed * (total - read) as f64 / read as f64))
+    }
+
+    /// Stop reading the file after the document being read.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the file has been read to the end, or given up on.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// A reader counting the bytes read through it towards a load's progress.
+struct Counting<R> {
+    inner: R,
+    progress: Arc<LoadProgress>,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.read.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Reads every document from a file.
+pub struct FileSource {
+    path: PathBuf,
+    progress: Arc<LoadProgress>,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let progress = Arc::new(LoadProgress::new(path.display().to_string()));
+        Self { path, progress }
+    }
+
+    /// How far through the file the source is, once it's running.
+    pub fn progress(&self) -> Arc<LoadProgress> {
+        self.progress.clone()
+    }
+}
+
+#[async_trait]
+impl EventSource for FileSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        let progress = self.progress;
+        let result = async {
+            let name = self.path.display().to_string();
+            let file =
+                File::open(&self.path).with_context(|| format!("failed to open {}", name))?;
+            progress
+                .total
+                .store(file.metadata()?.len(), Ordering::Relaxed);
+            let reader = BufReader::new(Counting {
+                inner: file,
+                progress: progress.clone(),
+            });
+            let read_progress = progress.clone();
+            tokio::task::spawn_blocking(move || {
+                read_documents(&name, reader, &send, Some(&read_progress))
+            })
+            .await?
+        }
+        .await;
+        progress.finished.store(true, Ordering::Relaxed);
+        result
+    }
+}
+
+/// Reads a file of newline delimited documents like `tail -F`, waiting for
+/// more to be written and starting again from the top if it is truncated.
+pub struct FollowSource {
+    path: PathBuf,
+    checkpoints: Option<Checkpoints>,
+}
+
+impl FollowSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            checkpoints: None,
+        }
+    }
+
+    /// Carry on from where the file was last read up to, if it's still the
+    /// same file, and keep track of how far it has been read since.
+    pub fn checkpointed(mut self, checkpoints: Checkpoints) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+}
+
+#[async_trait]
+impl EventSource for FollowSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || follow(&self.path, self.checkpoints.as_ref(), &send))
+            .await?
+    }
+}
+
+fn read_documents(
+    name: &str,
+    reader: impl Read,
+    send: &EventSender,
+    progress: Option<&LoadProgress>,
+) -> anyhow::Result<()> {
+    let stream = serde_json::Deserializer::from_reader(reader).into_iter::<Value>();
+
+    // Malformed JSON can't be recovered from, but documents that aren't audit
+    // events can be skipped over
+    for maybe_document in stream {
+        let document = maybe_document.with_context(|| format!("{} is not valid JSON", name))?;
+        let sent = send_document(kube::events_from_value(document), send)?;
+        if let Some(progress) = progress {
+            progress.events.fetch_add(sent as u64, Ordering::Relaxed);
+            if progress.is_cancelled() {
+                send.notice(format!("stopped reading {}", name))?;
+                return Ok(());
+            }
+        }
+    }
+
+    send.notice(format!("finished reading {}", name))?;
+    Ok(())
+}
+
+fn follow(
+    path: &PathBuf,
+    checkpoints: Option<&Checkpoints>,
+    send: &EventSender,
+) -> anyhow::Result<()> {
+    let name = path.display();
+    let file = File::open(path).with_context(|| format!("failed to open {}", name))?;
+    let metadata = file.metadata()?;
+    let mut opened = (metadata.dev(), metadata.ino());
+    let mut reader = BufReader::new(file);
+    let mut position = 0;
+    let mut line = String::new();
+
+    // Looked up by the file's absolute path, worked out just the once
+    let checkpoints = checkpoints.map(|checkpoints| (checkpoints, checkpoint::key(path)));
+    let mut saved = Instant::now();
+    let mut checkpoint = Checkpoint {
+        inode: metadata.ino(),
+        offset: 0,
+    };
+    match checkpoints
+        .as_ref()
+        .and_then(|(checkpoints, key)| checkpoints.get(key))
+    {
+        Some(last) if last.inode == checkpoint.inode && last.offset <= metadata.len() => {
+            send.notice(format!("resuming {} from byte {}", name, last.offset))?;
+            reader.seek(SeekFrom::Start(last.offset))?;
+            position = last.offset;
+            checkpoint = last;
+        }
+        Some(_) => send.notice(format!(
+            "{} has been replaced since it was last read, reading from the start",
+            name
+        ))?,
+        None => {}
+    }
+
+    loop {
+        if let Some((checkpoints, key)) = &checkpoints {
+            // kept current for saving on quitting, written out now and then
+            let current = Checkpoint {
+                inode: opened.1,
+                offset: position,
+            };
+            if current != checkpoint {
+                checkpoint = current;
+                checkpoints.set(key, checkpoint);
+                if saved.elapsed() >= CHECKPOINT_INTERVAL {
+                    checkpoints.save()?;
+                    saved = Instant::now();
+                }
+            }
+        }
+
+        let read = reader.read_line(&mut line)?;
+        if read == 0 || !line.ends_with('\n') {
+            // Either nothing new, or a partially written line, so wait for more
+            std::thread::sleep(FOLLOW_INTERVAL);
+            match std::fs::metadata(path) {
+                // Rotated, and the old file has been read to its end, so carry
+                // on with the new one
+                Ok(current) if (current.dev(), current.ino()) != opened => {
+                    if let Some(file) = reopen(path)? {
+                        let metadata = file.metadata()?;
+                        opened = (metadata.dev(), metadata.ino());
+                        send.notice(format!("{} was rotated, reading the new file", name))?;
+                        reader = BufReader::new(file);
+                        position = 0;
+                        line.clear();
+                    }
+                }
+                Ok(current) if current.len() < position => {
+                    send.notice(format!("{} was truncated, reading from the start", name))?;
+                    reader.seek(SeekFrom::Start(0))?;
+                    position = 0;
+                    line.clear();
+                }
+                Ok(_) => {}
+                // Moved away, and the new file not made yet
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to read {}", name));
+                }
+            }
+            if send.is_closed() {
+                return match checkpoints {
+                    Some((checkpoints, _)) => checkpoints.save(),
+                    None => Ok(()),
+                };
+            }
+            continue;
+        }
+
+        position += line.len() as u64;
+        if line.trim().is_empty() {
+            line.clear();
+            continue;
+        }
+        let document = serde_json::from_str(line.trim()).and_then(kube::events_from_value);
+        line.clear();
+        send_document(document, send)?;
+    }
+}
+
+/// Opens a followed file again after it has been replaced, or nothing if it
+/// hasn't been made again yet.
+fn reopen(path: &PathBuf) -> anyhow::Result<Option<File>> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("failed to open {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::source::{channel, Ingested};
+
+    fn line(id: &str) -> String {
+        let event = test_event().audit_id(id).uri("/healthz").verb("get");
+        format!("{}\n", event.json())
+    }
+
+    #[test]
+    fn checkpoint_on_stopping() {
+        let dir = std::env::temp_dir().join(format!("kale-follow-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (log, state) = (dir.join("audit.log"), dir.join("state.json"));
+        let text = [line("a"), line("b")].concat();
+        std::fs::write(&log, &text).unwrap();
+
+        let checkpoints = Checkpoints::load(&state).unwrap();
+        let (send, mut recv) = channel();
+        let following = {
+            let (log, checkpoints) = (log.clone(), checkpoints.clone());
+            std::thread::spawn(move || follow(&log, Some(&checkpoints), &send))
+        };
+        for _ in 0..2 {
+            assert!(recv.blocking_recv().is_some());
+        }
+        // quitting, well within the interval checkpoints are saved at
+        drop(recv);
+        following.join().unwrap().unwrap();
+
+        let saved = Checkpoints::load(&state).unwrap();
+        assert_eq!(
+            saved.get(&checkpoint::key(&log)).unwrap().offset,
+            text.len() as u64
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn checkpoint_after_rotation() {
+        let dir = std::env::temp_dir().join(format!("kale-rotated-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (log, state) = (dir.join("audit.log"), dir.join("state.json"));
+        std::fs::write(&log, line("a")).unwrap();
+
+        let checkpoints = Checkpoints::load(&state).unwrap();
+        let (send, mut recv) = channel();
+        let following = {
+            let (log, checkpoints) = (log.clone(), checkpoints.clone());
+            std::thread::spawn(move || follow(&log, Some(&checkpoints), &send))
+        };
+        assert!(matches!(recv.blocking_recv(), Some(Ingested::Event(_))));
+        std::fs::rename(&log, dir.join("audit.log.1")).unwrap();
+        std::fs::write(&log, line("b")).unwrap();
+        assert!(matches!(recv.blocking_recv(), Some(Ingested::Notice(_))));
+        assert!(matches!(recv.blocking_recv(), Some(Ingested::Event(_))));
+        drop(recv);
+        following.join().unwrap().unwrap();
+
+        // the new file, so that it's this one that's carried on with
+        let saved = Checkpoints::load(&state).unwrap();
+        let expected = Checkpoint {
+            inode: std::fs::metadata(&log).unwrap().ino(),
+            offset: line("b").len() as u64,
+        };
+        assert_eq!(saved.get(&checkpoint::key(&log)), Some(expected));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_and_truncation() {
+        let dir = std::env::temp_dir().join(format!("kale-rotate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("audit.log");
+        std::fs::write(&log, line("a")).unwrap();
+
+        let (send, mut recv) = channel();
+        let following = {
+            let log = log.clone();
+            std::thread::spawn(move || follow(&log, None, &send))
+        };
+        let mut next = || match recv.blocking_recv().unwrap() {
+            Ingested::Event(event) => event.audit_id,
+            Ingested::Notice(text) => text,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(next(), "a");
+
+        std::fs::rename(&log, dir.join("audit.log.1")).unwrap();
+        std::fs::write(&log, [line("b"), line("c")].concat()).unwrap();
+        assert_eq!(
+            next(),
+            format!("{} was rotated, reading the new file", log.display())
+        );
+        assert_eq!(next(), "b");
+        assert_eq!(next(), "c");
+
+        // shorter than what had been read of it, but the same file
+        std::fs::write(&log, line("d")).unwrap();
+        assert_eq!(
+            next(),
+            format!("{} was truncated, reading from the start", log.display())
+        );
+        assert_eq!(next(), "d");
+
+        drop(recv);
+        following.join().unwrap().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
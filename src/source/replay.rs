@@ -0,0 +1,170 @@
+//! Replaying other sources' events at the pace they happened, for `kale
+//! replay`.
+
+use super::{channel, EventSender, EventSource, Ingested};
+use crate::kube::EventV1;
+use async_trait::async_trait;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The speeds replaying can go at, slow enough to step through a burst and
+/// fast enough to skim a day's capture.
+const SPEEDS: std::ops::RangeInclusive<f64> = 0.001..=1_000_000.0;
+
+/// How much faster than they happened to replay events, like `10x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speed(f64);
+
+impl FromStr for Speed {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let factor = s.strip_suffix('x').unwrap_or(s);
+        let factor = factor
+            .parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("expected a speed like 10x or 0.5x"))?;
+        anyhow::ensure!(
+            SPEEDS.contains(&factor),
+            "the speed has to be from {}x to {}x",
+            SPEEDS.start(),
+            SPEEDS.end()
+        );
+        Ok(Self(factor))
+    }
+}
+
+/// Wraps other sources, reading all of their events and then sending them
+/// on in time order, as far apart as they happened divided by the speed, to
+/// watch a capture unfold as it did rather than all at once.
+///
+/// The wrapped sources have to run dry before replaying starts, so this is
+/// for files rather than listeners.
+pub struct ReplaySource {
+    speed: Speed,
+    inner: Vec<Box<dyn EventSource>>,
+}
+
+impl ReplaySource {
+    pub fn new(speed: Speed, inner: Vec<Box<dyn EventSource>>) -> Self {
+        Self { speed, inner }
+    }
+}
+
+#[async_trait]
+impl EventSource for ReplaySource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        let (inner_send, mut inner_recv) = channel();
+        let mut running = Vec::new();
+        for source in self.inner {
+            let inner_send = inner_send.clone();
+            running.push(tokio::spawn(async move {
+                if let Err(err) = source.run(inner_send.clone()).await {
+                    let _ = inner_send.0.send(Ingested::Failed(format!("{:#}", err)));
+                }
+            }));
+        }
+        drop(inner_send);
+
+        let mut events: Vec<EventV1> = Vec::new();
+        while let Some(ingested) = inner_recv.recv().await {
+            match ingested {
+                Ingested::Event(event) => events.push(*event),
+                ingested => send
+                    .0
+                    .send(ingested)
+                    .map_err(|_| anyhow::anyhow!("event receiver has gone away"))?,
+            }
+        }
+        for source in running {
+            source.await?;
+        }
+
+        events.sort_by_key(|event| event.request_received_timestamp);
+        let (Some(first), Some(last)) = (events.first(), events.last()) else {
+            return Ok(());
+        };
+        let start = first.request_received_timestamp;
+        let length = (last.request_received_timestamp - start).to_std()?;
+        send.notice(format!(
+            "replaying {} events at {}x, over {}s",
+            events.len(),
+            self.speed.0,
+            (length.as_secs_f64() / self.speed.0).ceil()
+        ))?;
+
+        let began = Instant::now();
+        for event in events {
+            let since = (event.request_received_timestamp - start).to_std()?;
+            let due = Duration::try_from_secs_f64(since.as_secs_f64() / self.speed.0)
+                .ok()
+                .and_then(|after| began.checked_add(after))
+                .ok_or_else(|| anyhow::anyhow!("{} is too late to replay", event.audit_id))?;
+            tokio::time::sleep_until(due).await;
+            send.send(event)?;
+        }
+        send.notice("finished replaying")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::source::ReaderSource;
+    use std::io::Cursor;
+
+    #[test]
+    fn speeds() {
+        assert_eq!("10x".parse::<Speed>().unwrap(), Speed(10.0));
+        assert_eq!("0.5".parse::<Speed>().unwrap(), Speed(0.5));
+        assert_eq!("1e6x".parse::<Speed>().unwrap(), Speed(1e6));
+        for speed in ["0x", "-2x", "NaNx", "infx", "1e300x", "0.0001x"] {
+            assert_eq!(
+                speed.parse::<Speed>().unwrap_err().to_string(),
+                "the speed has to be from 0.001x to 1000000x",
+                "{}",
+                speed
+            );
+        }
+        assert!("fast".parse::<Speed>().is_err());
+    }
+
+    #[test]
+    fn in_time_order_at_pace() {
+        // out of order, and a second apart, so 50ms apart at 20x
+        let text: String = [("a", 0), ("c", 2), ("b", 1)]
+            .iter()
+            .map(|(id, second)| {
+                let at = format!("2024-07-01T10:00:0{}Z", second);
+                test_event().audit_id(id).at(at.as_str()).json().to_string()
+            })
+            .collect();
+        let inner: Vec<Box<dyn EventSource>> =
+            vec![Box::new(ReaderSource::new("test", Cursor::new(text)))];
+        let replay = Box::new(ReplaySource::new(Speed(20.0), inner));
+
+        let (send, mut recv) = channel();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let sent = runtime.block_on(async {
+            let replaying = tokio::spawn(replay.run(send));
+            let mut sent = Vec::new();
+            while let Some(ingested) = recv.recv().await {
+                if let Ingested::Event(event) = ingested {
+                    sent.push((event.audit_id, std::time::Instant::now()));
+                }
+            }
+            replaying.await.unwrap().unwrap();
+            sent
+        });
+
+        let ids: Vec<_> = sent.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        for pair in sent.windows(2) {
+            let apart = pair[1].1 - pair[0].1;
+            assert!(apart >= Duration::from_millis(40), "{:?}", apart);
+            assert!(apart < Duration::from_millis(500), "{:?}", apart);
+        }
+    }
+}
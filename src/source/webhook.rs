@@ -0,0 +1,60 @@
+//! A receiver for the apiserver's audit webhook backend.
+//!
+//! The apiserver POSTs batches of events as `EventList` documents to the URL in
+//! its `--audit-webhook-config-file` kubeconfig; point that at this listener.
+
+use super::{send_document, EventSender, EventSource};
+use crate::kube;
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use tiny_http::{Method, Response, Server};
+
+/// Accepts audit webhook requests on a plain HTTP listener.
+pub struct WebhookSource {
+    addr: SocketAddr,
+}
+
+impl WebhookSource {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+}
+
+#[async_trait]
+impl EventSource for WebhookSource {
+    async fn run(self: Box<Self>, send: EventSender) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || listen(self.addr, &send)).await?
+    }
+}
+
+fn listen(addr: SocketAddr, send: &EventSender) -> anyhow::Result<()> {
+    let server = Server::http(addr)
+        .map_err(|err| anyhow::anyhow!("failed to listen on {}: {}", addr, err))?;
+    send.notice(format!("listening for audit webhook requests on {}", addr))?;
+
+    for mut request in server.incoming_requests() {
+        let response = if *request.method() != Method::Post {
+            Response::empty(405).boxed()
+        } else {
+            match serde_json::from_reader(request.as_reader()) {
+                Ok(document) => {
+                    send_document(kube::events_from_value(document), send)?;
+                    Response::empty(200).boxed()
+                }
+                Err(err) => Response::from_string(err.to_string())
+                    .with_status_code(400)
+                    .boxed(),
+            }
+        };
+
+        // A client going away before it's answered shouldn't stop the listener
+        if let Err(err) = request.respond(response) {
+            send.notice(format!(
+                "failed to answer an audit webhook request: {}",
+                err
+            ))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Counters about ingestion and the process, for the status bar.
+
+use std::time::{Duration, Instant};
+
+/// How often the ingest rate is recalculated.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct IngestStats {
+    /// Documents that were read but dropped as they couldn't be deserialised.
+    pub skipped: usize,
+    /// Events that were read but dropped by sampling.
+    pub sampled: usize,
+    window_start: Instant,
+    window_count: usize,
+    rate: f64,
+}
+
+impl Default for IngestStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IngestStats {
+    pub fn new() -> Self {
+        Self {
+            skipped: 0,
+            sampled: 0,
+            window_start: Instant::now(),
+            window_count: 0,
+            rate: 0.0,
+        }
+    }
+
+    /// Count an event towards the ingest rate.
+    pub fn record_event(&mut self) {
+        self.roll_window();
+        self.window_count += 1;
+    }
+
+    /// Events ingested per second, over the last complete window.
+    pub fn rate(&mut self) -> f64 {
+        self.roll_window();
+        self.rate
+    }
+
+    fn roll_window(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= RATE_WINDOW {
+            self.rate = self.window_count as f64 / elapsed.as_secs_f64();
+            self.window_start = Instant::now();
+            self.window_count = 0;
+        }
+    }
+}
+
+/// The resident memory of this process in bytes, where the platform tells us.
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kib * 1024)
+}
+
+/// Format a number of bytes with a binary unit, e.g. `12.3 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
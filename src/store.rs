@@ -0,0 +1,178 @@
+//! Every event read so far, independent of how it is being viewed.
+
+use crate::filter::Field;
+use crate::kube::EventV1;
+use crate::script::Scripts;
+use std::collections::{BTreeSet, HashMap};
+
+/// The events read from all sources, kept in order of when they were received
+/// by the apiserver.
+#[derive(Debug, Default)]
+pub struct EventStore {
+    events: Vec<EventV1>,
+    /// The distinct source labels seen so far, in order of first appearance.
+    sources: Vec<String>,
+    /// Indices of the bookmarked events.
+    bookmarks: BTreeSet<usize>,
+    /// Indices of the events that matched an alert rule as they arrived.
+    alerted: BTreeSet<usize>,
+}
+
+impl EventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event, returning the index it was inserted at.
+    ///
+    /// Events are kept ordered by time, so that inputs read side by side are
+    /// merged rather than interleaved. Events at or after the index shift along
+    /// by one.
+    pub fn insert(&mut self, event: EventV1) -> usize {
+        if let Some(source) = &event.source {
+            if !self.sources.contains(source) {
+                self.sources.push(source.clone());
+            }
+        }
+
+        let index = self.events.partition_point(|existing| {
+            existing.request_received_timestamp <= event.request_received_timestamp
+        });
+        self.events.insert(index, event);
+        shift_indices(&mut self.bookmarks, index);
+        shift_indices(&mut self.alerted, index);
+        index
+    }
+
+    pub fn get(&self, index: usize) -> Option<&EventV1> {
+        self.events.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EventV1> {
+        self.events.iter()
+    }
+
+    /// Every distinct value of `field` across the events, with how many
+    /// events have it, most common first.
+    pub fn value_counts(&self, field: &Field, scripts: &Scripts) -> Vec<(String, usize)> {
+        let mut counts = HashMap::<String, usize>::new();
+        for event in &self.events {
+            for value in field.values(event, scripts) {
+                *counts.entry(value.into_owned()).or_default() += 1;
+            }
+        }
+
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        counts
+    }
+
+    /// Bookmark the event at `index`, or remove its bookmark, returning
+    /// whether it is now bookmarked.
+    pub fn toggle_bookmark(&mut self, index: usize) -> bool {
+        if self.bookmarks.remove(&index) {
+            false
+        } else {
+            self.bookmarks.insert(index)
+        }
+    }
+
+    pub fn is_bookmarked(&self, index: usize) -> bool {
+        self.bookmarks.contains(&index)
+    }
+
+    /// Indices of the bookmarked events, in order.
+    pub fn bookmarks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bookmarks.iter().copied()
+    }
+
+    /// Flag the event at `index` as having matched an alert rule.
+    pub fn flag_alerted(&mut self, index: usize) {
+        self.alerted.insert(index);
+    }
+
+    pub fn is_alerted(&self, index: usize) -> bool {
+        self.alerted.contains(&index)
+    }
+
+    /// The labels of every source seen so far, empty if none were labelled.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+}
+
+/// Account for an event having been inserted at `index` in a set of indices
+/// into the store, moving those at or after it along by one.
+pub(crate) fn shift_indices(indices: &mut BTreeSet<usize>, index: usize) {
+    if indices.range(index..).next().is_some() {
+        *indices = indices
+            .iter()
+            .map(|i| if *i >= index { i + 1 } else { *i })
+            .collect();
+    }
+}
+
+impl std::ops::Index<usize> for EventStore {
+    type Output = EventV1;
+
+    fn index(&self, index: usize) -> &EventV1 {
+        &self.events[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+    use crate::kube::test_event;
+
+    fn event(source: &str, id: &str, second: u32) -> EventV1 {
+        let mut event = test_event()
+            .audit_id(id)
+            .at(format!("2024-07-01T10:00:{:02}Z", second))
+            .build();
+        event.source = Some(source.to_string());
+        event
+    }
+
+    #[test]
+    fn labelled_inputs() {
+        let mut store = EventStore::new();
+        // each input in order, but read side by side, one ahead of the other
+        for (source, id, second) in [
+            ("prod", "p1", 1),
+            ("prod", "p2", 4),
+            ("staging", "s1", 2),
+            ("prod", "p3", 6),
+            ("staging", "s2", 5),
+            ("staging", "s3", 7),
+        ] {
+            store.insert(event(source, id, second));
+        }
+        let ids = store.iter().map(|event| event.audit_id.as_str());
+        assert_eq!(
+            ids.collect::<Vec<_>>(),
+            ["p1", "s1", "p2", "s2", "p3", "s3"]
+        );
+        assert_eq!(store.sources(), ["prod", "staging"]);
+        assert_eq!(
+            store.value_counts(&Field::Cluster, &Scripts::new()),
+            [("prod".to_string(), 3), ("staging".to_string(), 3)]
+        );
+
+        let staging = "cluster=staging".parse::<Filter>().unwrap();
+        let ids = store
+            .iter()
+            .filter(|event| staging.eval(event, &Scripts::new()))
+            .map(|event| event.audit_id.as_str());
+        assert_eq!(ids.collect::<Vec<_>>(), ["s1", "s2", "s3"]);
+    }
+}
@@ -0,0 +1,147 @@
+//! The styles everything is drawn with, chosen with `--theme` or in the
+//! config file.
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// The themes there are to choose from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Light text on a black background
+    #[default]
+    Dark,
+    /// Dark text on a white background
+    Light,
+    /// The terminal's own colours, with only bold, underlined and reversed
+    /// text and markers beside the selection and errors, as when NO_COLOR is
+    /// set
+    Plain,
+}
+
+/// A palette of styles, one for each kind of thing drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Tables, lists, bodies and anything else without a style of its own.
+    pub text: Style,
+    /// The selected row, prompts, the status bar and the active tab.
+    pub highlight: Style,
+    /// Rows of bookmarked events.
+    pub bookmarked: Style,
+    /// Rows of events that matched an alert rule, and the status bar while
+    /// it flashes for one.
+    pub alerted: Style,
+    /// Rows of events marked for a batch action, on top of any other style.
+    pub marked: Style,
+    /// Failed requests and error messages.
+    pub error: Style,
+    pub warning: Style,
+    /// Requests turned away for too many requests, as on the timeline.
+    pub throttled: Style,
+    /// Text found in the bodies.
+    pub found: Style,
+    /// Labels beside values, as in the detail view.
+    pub label: Style,
+    /// Section headings and table headers.
+    pub heading: Style,
+    pub added: Style,
+    pub removed: Style,
+    pub changed: Style,
+    /// Whether to mark the selection and errors with symbols too, for when
+    /// styles alone can't be told apart.
+    pub markers: bool,
+}
+
+impl Theme {
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Self::dark(),
+            ThemeName::Light => Self::light(),
+            ThemeName::Plain => Self::plain(),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            text: Style::new().fg(Color::White).bg(Color::Black),
+            highlight: Style::new().fg(Color::Black).bg(Color::Gray),
+            bookmarked: Style::new().fg(Color::Yellow),
+            alerted: Style::new().fg(Color::White).bg(Color::Red),
+            marked: Style::new().bg(Color::DarkGray),
+            error: Style::new().fg(Color::Red),
+            warning: Style::new().fg(Color::Yellow),
+            throttled: Style::new().fg(Color::Magenta),
+            found: Style::new().fg(Color::Black).bg(Color::Yellow),
+            label: Style::new().fg(Color::Gray),
+            heading: Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            added: Style::new().fg(Color::Green),
+            removed: Style::new().fg(Color::Red),
+            changed: Style::new().fg(Color::Yellow),
+            markers: false,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            text: Style::new().fg(Color::Black).bg(Color::White),
+            highlight: Style::new().fg(Color::White).bg(Color::Blue),
+            bookmarked: Style::new().fg(Color::Magenta),
+            alerted: Style::new().fg(Color::White).bg(Color::Red),
+            marked: Style::new().bg(Color::Gray),
+            error: Style::new().fg(Color::Red),
+            warning: Style::new().fg(Color::Magenta),
+            throttled: Style::new().fg(Color::Cyan),
+            found: Style::new().fg(Color::Black).bg(Color::Yellow),
+            label: Style::new().fg(Color::DarkGray),
+            heading: Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            added: Style::new().fg(Color::Green),
+            removed: Style::new().fg(Color::Red),
+            changed: Style::new().fg(Color::Blue),
+            markers: false,
+        }
+    }
+
+    /// No colours at all, leaving the changes in diffs to their `+`, `-` and
+    /// `~` to tell apart and the selection and errors to their markers.
+    pub fn plain() -> Self {
+        Self {
+            text: Style::new(),
+            highlight: Style::new().add_modifier(Modifier::REVERSED),
+            bookmarked: Style::new().add_modifier(Modifier::UNDERLINED),
+            alerted: Style::new().add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            marked: Style::new().add_modifier(Modifier::BOLD),
+            error: Style::new().add_modifier(Modifier::BOLD),
+            warning: Style::new(),
+            throttled: Style::new().add_modifier(Modifier::ITALIC),
+            found: Style::new().add_modifier(Modifier::REVERSED),
+            label: Style::new(),
+            heading: Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            added: Style::new(),
+            removed: Style::new(),
+            changed: Style::new(),
+            markers: true,
+        }
+    }
+
+    /// What goes before the selected row of tables and lists.
+    pub fn selected_marker(&self) -> &'static str {
+        match self.markers {
+            true => "> ",
+            false => "",
+        }
+    }
+
+    /// What goes before anything telling of an error.
+    pub fn error_marker(&self) -> &'static str {
+        match self.markers {
+            true => "! ",
+            false => "",
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
@@ -0,0 +1,199 @@
+//! Which API Priority and Fairness priority levels and flow schemas are
+//! turning requests away with 429s, and whose requests, from the annotations
+//! the apiserver leaves on audit events.
+
+use crate::detail::Section;
+use crate::kube::EventV1;
+use std::collections::HashMap;
+
+const FLOW_SCHEMA: &str = "apf.flowschema";
+const PRIORITY_LEVEL: &str = "apf.prioritylevel";
+
+/// Whether the request was turned away for there being too many.
+pub fn throttled(event: &EventV1) -> bool {
+    event
+        .response_status
+        .as_ref()
+        .is_some_and(|status| status.code == 429)
+}
+
+/// How many requests went through a priority level or flow schema, and how
+/// many of them were throttled.
+#[derive(Debug, Default)]
+struct Tally {
+    requests: usize,
+    throttled: usize,
+}
+
+fn requests(count: usize) -> String {
+    match count {
+        1 => "1 request".to_string(),
+        count => format!("{} requests", count),
+    }
+}
+
+/// A section of a row for each name, the most throttled first, with how much
+/// of what went through it was throttled and anything else `more` says.
+fn section(
+    title: &str,
+    tallies: HashMap<&str, Tally>,
+    more: impl Fn(&str) -> Option<String>,
+) -> Section {
+    let mut tallies = tallies.into_iter().collect::<Vec<_>>();
+    tallies.sort_by(|a, b| b.1.throttled.cmp(&a.1.throttled).then(a.0.cmp(b.0)));
+    let mut section = Section::new(title);
+    for (name, tally) in tallies {
+        section.alert |= tally.throttled > 0;
+        let share = tally.throttled as f64 * 100.0 / tally.requests as f64;
+        let mut text = format!(
+            "{} of {} throttled ({:.0}%)",
+            tally.throttled,
+            requests(tally.requests),
+            share
+        );
+        if let Some(more) = more(name) {
+            text.push_str(&format!(", {}", more));
+        }
+        section.row(name, text);
+    }
+    section
+}
+
+/// The priority levels, then the flow schemas, the events went through with
+/// how many of each were throttled, then whose requests were throttled and
+/// under which flow schema most of them were.
+pub fn sections(events: &[&EventV1]) -> Vec<Section> {
+    let mut levels = HashMap::<&str, Tally>::new();
+    let mut schemas = HashMap::<&str, Tally>::new();
+    let mut schema_levels = HashMap::<&str, &str>::new();
+    let mut clients = HashMap::<&str, HashMap<Option<&str>, usize>>::new();
+    for event in events {
+        let throttled = throttled(event);
+        let level = event.annotations.get(PRIORITY_LEVEL).map(String::as_str);
+        let schema = event.annotations.get(FLOW_SCHEMA).map(String::as_str);
+        for (tallies, name) in [(&mut levels, level), (&mut schemas, schema)] {
+            if let Some(name) = name {
+                let tally = tallies.entry(name).or_default();
+                tally.requests += 1;
+                tally.throttled += throttled as usize;
+            }
+        }
+        if let (Some(schema), Some(level)) = (schema, level) {
+            schema_levels.insert(schema, level);
+        }
+        if throttled {
+            let user = event.user.username.as_str();
+            *clients.entry(user).or_default().entry(schema).or_default() += 1;
+        }
+    }
+
+    let mut sections = Vec::new();
+    if !levels.is_empty() {
+        sections.push(section("Priority levels", levels, |_| None));
+    }
+    if !schemas.is_empty() {
+        let at = |schema: &str| {
+            let level = schema_levels.get(schema)?;
+            Some(format!("at {}", level))
+        };
+        sections.push(section("Flow schemas", schemas, at));
+    }
+
+    let mut clients = clients
+        .into_iter()
+        .map(|(user, schemas)| (user, schemas.values().sum::<usize>(), schemas))
+        .collect::<Vec<_>>();
+    clients.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let mut throttled = Section::new("Throttled clients");
+    for (user, count, schemas) in clients {
+        let mostly = schemas
+            .into_iter()
+            .filter_map(|(schema, count)| Some((schema?, count)))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)));
+        let mut text = requests(count);
+        if let Some((schema, _)) = mostly {
+            text.push_str(&format!(", mostly under {}", schema));
+        }
+        throttled.row(user, text);
+    }
+    if !throttled.rows.is_empty() {
+        throttled.alert = true;
+        sections.push(throttled);
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(user: &str, schema: &str, level: &str, code: i32) -> EventV1 {
+        test_event()
+            .user(user)
+            .code(code)
+            .annotations(serde_json::json!({FLOW_SCHEMA: schema, PRIORITY_LEVEL: level}))
+            .build()
+    }
+
+    #[test]
+    fn throttling() {
+        let mut events = vec![
+            event("alice", "global-default", "global-default", 429),
+            event("alice", "global-default", "global-default", 429),
+            event("alice", "global-default", "global-default", 200),
+            event("bob", "global-default", "global-default", 429),
+            event("bob", "service-accounts", "workload-low", 200),
+            event(
+                "system:kube-scheduler",
+                "system-leader-election",
+                "leader-election",
+                200,
+            ),
+        ];
+        let mut unclassified = event("carol", "", "", 429);
+        unclassified.annotations.clear();
+        events.push(unclassified);
+        let events = events.iter().collect::<Vec<_>>();
+
+        let sections = sections(&events);
+        let titles = sections
+            .iter()
+            .map(|s| s.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            ["Priority levels", "Flow schemas", "Throttled clients"]
+        );
+        assert!(sections[0].alert);
+        assert_eq!(
+            sections[0].rows[0],
+            (
+                "global-default".to_string(),
+                "3 of 4 requests throttled (75%)".to_string()
+            )
+        );
+        assert_eq!(sections[0].rows[1].0, "leader-election");
+        assert_eq!(
+            sections[1].rows[1],
+            (
+                "service-accounts".to_string(),
+                "0 of 1 request throttled (0%), at workload-low".to_string()
+            )
+        );
+        assert_eq!(
+            sections[2].rows,
+            [
+                (
+                    "alice".to_string(),
+                    "2 requests, mostly under global-default".to_string()
+                ),
+                (
+                    "bob".to_string(),
+                    "1 request, mostly under global-default".to_string()
+                ),
+                ("carol".to_string(), "1 request".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! How many events there are over time, as a strip to see bursts of activity
+//! and failures in and jump to them by.
+
+use crate::kube::EventV1;
+use crate::store::EventStore;
+use chrono::{DateTime, Utc};
+
+/// How few of a slice's requests, one in this many, can have an outcome and
+/// still colour the slice, so that the odd 404 doesn't colour every slice.
+const NOTABLE: u64 = 10;
+
+/// How a request was answered, as far as the timeline goes, from least to
+/// most worrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    /// Anything but a failure, including requests not answered yet.
+    Success,
+    ClientError,
+    /// 429s, from API Priority and Fairness or a webhook turning requests
+    /// away.
+    Throttled,
+    ServerError,
+}
+
+impl Outcome {
+    /// Every outcome, the most worrying first.
+    pub const ALL: [Outcome; 4] = [
+        Outcome::ServerError,
+        Outcome::Throttled,
+        Outcome::ClientError,
+        Outcome::Success,
+    ];
+
+    pub fn of(event: &EventV1) -> Self {
+        match event.response_status.as_ref().map(|status| status.code) {
+            Some(429) => Outcome::Throttled,
+            Some(500..) => Outcome::ServerError,
+            Some(400..) => Outcome::ClientError,
+            _ => Outcome::Success,
+        }
+    }
+}
+
+/// Counts of events in equal slices of the time between the first and last.
+#[derive(Debug, PartialEq)]
+pub struct Timeline {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    pub counts: Vec<u64>,
+    /// How many of each slice's events had each [`Outcome`], in its order.
+    pub outcomes: Vec<[u64; 4]>,
+}
+
+impl Timeline {
+    /// Count the events at `indices`, which are in time order, into `slices`
+    /// slices, if there are any events to count.
+    pub fn new(store: &EventStore, indices: &[usize], slices: usize) -> Option<Self> {
+        if slices == 0 {
+            return None;
+        }
+        let mut timeline = Self {
+            start: store[*indices.first()?].request_received_timestamp,
+            end: store[*indices.last()?].request_received_timestamp,
+            counts: vec![0; slices],
+            outcomes: vec![[0; 4]; slices],
+        };
+        for i in indices {
+            let event = &store[*i];
+            let slice = timeline.slice(event.request_received_timestamp);
+            timeline.counts[slice] += 1;
+            timeline.outcomes[slice][Outcome::of(event) as usize] += 1;
+        }
+        Some(timeline)
+    }
+
+    /// Which slice a time falls in.
+    pub fn slice(&self, time: DateTime<Utc>) -> usize {
+        // one more than the span, so that the last event is in the last slice
+        let span = (self.end - self.start).num_milliseconds() + 1;
+        let offset = (time - self.start).num_milliseconds().clamp(0, span - 1);
+        (offset as i128 * self.counts.len() as i128 / span as i128) as usize
+    }
+
+    /// The slice with the most events in it, the earliest of any ties.
+    pub fn busiest(&self) -> Option<usize> {
+        let most = self.counts.iter().max()?;
+        self.counts.iter().position(|count| count == most)
+    }
+
+    /// What share of the slice's events had the outcome, from 0 to 1.
+    pub fn share(&self, slice: usize, outcome: Outcome) -> f64 {
+        match self.counts[slice] {
+            0 => 0.0,
+            count => self.outcomes[slice][outcome as usize] as f64 / count as f64,
+        }
+    }
+
+    /// The most worrying outcome of at least a tenth of the slice's events.
+    pub fn outcome(&self, slice: usize) -> Outcome {
+        let outcomes = [
+            Outcome::ServerError,
+            Outcome::Throttled,
+            Outcome::ClientError,
+        ];
+        let counts = self.outcomes[slice];
+        outcomes
+            .into_iter()
+            .find(|outcome| {
+                let count = counts[*outcome as usize];
+                count > 0 && count * NOTABLE >= self.counts[slice]
+            })
+            .unwrap_or(Outcome::Success)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+    use crate::kube::EventV1;
+
+    fn event(timestamp: &str, code: i32) -> EventV1 {
+        test_event().code(code).at(timestamp).build()
+    }
+
+    #[test]
+    fn counting() {
+        let mut store = EventStore::new();
+        for (timestamp, code) in [
+            ("2024-07-01T10:00:00Z", 200),
+            ("2024-07-01T10:00:01Z", 404),
+            ("2024-07-01T10:00:05Z", 200),
+            ("2024-07-01T10:00:06Z", 429),
+            ("2024-07-01T10:00:06Z", 500),
+            ("2024-07-01T10:00:09Z", 200),
+        ] {
+            store.insert(event(timestamp, code));
+        }
+        let indices = (0..store.len()).collect::<Vec<_>>();
+        let timeline = Timeline::new(&store, &indices, 5).unwrap();
+        assert_eq!(timeline.counts, [2, 0, 1, 2, 1]);
+        assert_eq!(timeline.busiest(), Some(0));
+        assert_eq!(timeline.slice(store[2].request_received_timestamp), 2);
+        assert_eq!(timeline.outcomes[3], [0, 0, 1, 1]);
+        let outcomes = (0..5).map(|slice| timeline.outcome(slice));
+        assert_eq!(
+            outcomes.collect::<Vec<_>>(),
+            [
+                Outcome::ClientError,
+                Outcome::Success,
+                Outcome::Success,
+                Outcome::ServerError,
+                Outcome::Success
+            ]
+        );
+
+        assert_eq!(timeline.share(3, Outcome::Throttled), 0.5);
+        assert_eq!(timeline.share(0, Outcome::Success), 0.5);
+        assert_eq!(timeline.share(1, Outcome::ServerError), 0.0);
+
+        assert_eq!(Timeline::new(&store, &[], 5), None);
+        let single = Timeline::new(&store, &[3], 5).unwrap();
+        assert_eq!(single.counts, [1, 0, 0, 0, 0]);
+    }
+}
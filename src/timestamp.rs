@@ -0,0 +1,197 @@
+//! How timestamps are shown, in which time zone and format, chosen with
+//! `--timezone` and `--time-format` or in the config file.
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The time zone to show timestamps in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Zone {
+    #[default]
+    Utc,
+    /// The system's time zone, or the one named by the `TZ` variable.
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utc" | "z" => Ok(Zone::Utc),
+            "local" => Ok(Zone::Local),
+            _ => s
+                .parse()
+                .map(Zone::Fixed)
+                .map_err(|_| format!("expected utc, local or an offset like +02:00, not {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Zone {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Utc => f.write_str("utc"),
+            Zone::Local => f.write_str("local"),
+            Zone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl From<Zone> for String {
+    fn from(zone: Zone) -> Self {
+        zone.to_string()
+    }
+}
+
+/// The formats timestamps can be shown in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// The date, time and offset, like 2024-07-01 10:00:00.250 UTC
+    #[default]
+    Default,
+    /// Like 2024-07-01T10:00:00.250Z
+    Rfc3339,
+    /// Only the time of day, to the millisecond
+    Time,
+    /// How long ago, like 12s ago
+    Relative,
+}
+
+/// A time zone and format together, to show timestamps with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamps {
+    pub zone: Zone,
+    pub format: TimeFormat,
+}
+
+impl Timestamps {
+    pub fn new(zone: Zone, format: TimeFormat) -> Self {
+        Self { zone, format }
+    }
+
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        self.format_at(time, Utc::now())
+    }
+
+    /// Format `time`, taking it to be `now` for relative timestamps.
+    pub fn format_at(&self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        match self.zone {
+            Zone::Utc => self.format_in(time, now),
+            Zone::Local => self.format_in(time.with_timezone(&Local), now),
+            Zone::Fixed(offset) => self.format_in(time.with_timezone(&offset), now),
+        }
+    }
+
+    fn format_in<Tz: TimeZone>(&self, time: DateTime<Tz>, now: DateTime<Utc>) -> String
+    where
+        Tz::Offset: fmt::Display,
+    {
+        match self.format {
+            TimeFormat::Default => time.to_string(),
+            TimeFormat::Rfc3339 => time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            TimeFormat::Time => time.format("%H:%M:%S%.3f").to_string(),
+            TimeFormat::Relative => relative(now.signed_duration_since(time)),
+        }
+    }
+
+    /// How wide a column of timestamps needs to be.
+    pub fn width(&self) -> u16 {
+        match self.format {
+            TimeFormat::Default => 30,
+            TimeFormat::Rfc3339 => 32,
+            TimeFormat::Time => 12,
+            TimeFormat::Relative => 10,
+        }
+    }
+}
+
+/// The time between two events, precise enough to tell bursts from gaps,
+/// like +250ms, +12.500s or +3m12s.
+pub fn elapsed(duration: chrono::Duration) -> String {
+    let millis = duration.num_milliseconds();
+    let sign = match millis < 0 {
+        true => "-",
+        false => "+",
+    };
+    match millis.abs() {
+        ms if ms < 1000 => format!("{}{}ms", sign, ms),
+        ms if ms < 60 * 1000 => format!("{}{}.{:03}s", sign, ms / 1000, ms % 1000),
+        ms if ms < 60 * 60 * 1000 => format!("{}{}m{:02}s", sign, ms / 60_000, ms / 1000 % 60),
+        ms => format!("{}{}h{:02}m", sign, ms / 3_600_000, ms / 60_000 % 60),
+    }
+}
+
+/// A duration before now in the largest whole unit it has.
+fn relative(ago: chrono::Duration) -> String {
+    let seconds = ago.num_seconds();
+    let amount = match seconds.abs() {
+        s if s < 60 => format!("{}s", s),
+        s if s < 60 * 60 => format!("{}m", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h", s / (60 * 60)),
+        s => format!("{}d", s / (24 * 60 * 60)),
+    };
+    match seconds < 0 {
+        true => format!("in {}", amount),
+        false => format!("{} ago", amount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn formatting() {
+        let received = time("2024-07-01T10:00:00.250Z");
+        let now = time("2024-07-01T10:03:12Z");
+        let format = |zone: &str, format| {
+            Timestamps::new(zone.parse().unwrap(), format).format_at(received, now)
+        };
+
+        assert_eq!(
+            format("utc", TimeFormat::Default),
+            "2024-07-01 10:00:00.250 UTC"
+        );
+        assert_eq!(
+            format("+02:00", TimeFormat::Default),
+            "2024-07-01 12:00:00.250 +02:00"
+        );
+        assert_eq!(
+            format("utc", TimeFormat::Rfc3339),
+            "2024-07-01T10:00:00.250Z"
+        );
+        assert_eq!(
+            format("-05:30", TimeFormat::Rfc3339),
+            "2024-07-01T04:30:00.250-05:30"
+        );
+        assert_eq!(format("+02:00", TimeFormat::Time), "12:00:00.250");
+        assert_eq!(format("utc", TimeFormat::Relative), "3m ago");
+        assert_eq!(relative(chrono::Duration::seconds(-5)), "in 5s");
+        assert_eq!(relative(chrono::Duration::hours(50)), "2d ago");
+
+        assert_eq!(elapsed(chrono::Duration::milliseconds(250)), "+250ms");
+        assert_eq!(elapsed(chrono::Duration::milliseconds(12_500)), "+12.500s");
+        assert_eq!(elapsed(chrono::Duration::seconds(192)), "+3m12s");
+        assert_eq!(elapsed(chrono::Duration::minutes(125)), "+2h05m");
+
+        assert!("Europe/Paris".parse::<Zone>().is_err());
+        assert_eq!("LOCAL".parse::<Zone>(), Ok(Zone::Local));
+    }
+}
@@ -0,0 +1,212 @@
+//! The namespaces, resources and objects the events are about, as a tree to
+//! browse them by in the sidebar.
+
+use crate::kube::EventV1;
+use crate::store::EventStore;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What cluster scoped objects are listed under in place of a namespace.
+pub const CLUSTER_SCOPED: &str = "(cluster)";
+
+/// How many events there are at a point in the tree, and the branches below.
+#[derive(Debug, Default)]
+struct Branch {
+    count: usize,
+    children: BTreeMap<String, Branch>,
+}
+
+/// A row of the tree as shown, one per expanded or top level branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    /// The namespace, then the resource and name, as far down as the node is.
+    pub path: Vec<String>,
+    pub count: usize,
+    pub expanded: bool,
+    pub leaf: bool,
+}
+
+impl Node {
+    pub fn label(&self) -> &str {
+        self.path.last().map(String::as_str).unwrap_or_default()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+}
+
+/// A tree of namespace, resource and object name, with which of its branches
+/// are expanded and which row is selected.
+#[derive(Debug, Default)]
+pub struct Sidebar {
+    root: Branch,
+    expanded: BTreeSet<Vec<String>>,
+    selected: usize,
+    /// Whether keys go to the sidebar rather than the table.
+    pub focused: bool,
+}
+
+impl Sidebar {
+    pub fn new(store: &EventStore) -> Self {
+        let mut sidebar = Self {
+            focused: true,
+            ..Self::default()
+        };
+        for event in store.iter() {
+            sidebar.add(event);
+        }
+        sidebar
+    }
+
+    /// Count an event in the tree, if it's about an object.
+    pub fn add(&mut self, event: &EventV1) {
+        let Some(object) = &event.object_ref else {
+            return;
+        };
+        let namespace = object.namespace.as_deref().unwrap_or(CLUSTER_SCOPED);
+        let path = [
+            Some(namespace),
+            object.resource.as_deref(),
+            object.name.as_deref(),
+        ];
+
+        let mut branch = &mut self.root;
+        for segment in path.into_iter().map_while(|segment| segment) {
+            branch = branch.children.entry(segment.to_string()).or_default();
+            branch.count += 1;
+        }
+    }
+
+    /// The rows shown: every namespace, and the branches below those that are
+    /// expanded.
+    pub fn nodes(&self) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        self.flatten(&self.root, &mut Vec::new(), &mut nodes);
+        nodes
+    }
+
+    fn flatten(&self, branch: &Branch, path: &mut Vec<String>, nodes: &mut Vec<Node>) {
+        for (segment, child) in &branch.children {
+            path.push(segment.clone());
+            let expanded = self.expanded.contains(path);
+            nodes.push(Node {
+                path: path.clone(),
+                count: child.count,
+                expanded,
+                leaf: child.children.is_empty(),
+            });
+            if expanded {
+                self.flatten(child, path, nodes);
+            }
+            path.pop();
+        }
+    }
+
+    /// The selected row.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_node(&self) -> Option<Node> {
+        self.nodes().into_iter().nth(self.selected)
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.nodes().len().saturating_sub(1));
+    }
+
+    /// Show what's below the selected node.
+    pub fn expand(&mut self) {
+        if let Some(node) = self.selected_node().filter(|node| !node.leaf) {
+            self.expanded.insert(node.path);
+        }
+    }
+
+    /// Hide what's below the selected node, or if it's already hidden, select
+    /// the node above it.
+    pub fn collapse(&mut self) {
+        let Some(node) = self.selected_node() else {
+            return;
+        };
+        if node.expanded {
+            self.expanded.remove(&node.path);
+        } else if node.depth() > 0 {
+            let parent = &node.path[..node.depth()];
+            if let Some(position) = self.nodes().iter().position(|n| n.path == parent) {
+                self.selected = position;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kube::test_event;
+
+    fn event(namespace: Option<&str>, resource: &str, name: Option<&str>) -> EventV1 {
+        test_event()
+            .uri("/")
+            .verb("get")
+            .object_ref(
+                serde_json::json!({"namespace": namespace, "resource": resource, "name": name}),
+            )
+            .build()
+    }
+
+    fn labels(sidebar: &Sidebar) -> Vec<(usize, String, usize)> {
+        sidebar
+            .nodes()
+            .iter()
+            .map(|node| (node.depth(), node.label().to_string(), node.count))
+            .collect()
+    }
+
+    #[test]
+    fn browsing() {
+        let mut store = EventStore::new();
+        store.insert(event(Some("default"), "pods", Some("web")));
+        store.insert(event(Some("default"), "pods", Some("web")));
+        store.insert(event(Some("default"), "pods", None));
+        store.insert(event(None, "nodes", Some("node-1")));
+        let mut sidebar = Sidebar::new(&store);
+        sidebar.add(&event(Some("kube-system"), "leases", Some("scheduler")));
+
+        assert_eq!(
+            labels(&sidebar),
+            [
+                (0, "(cluster)".to_string(), 1),
+                (0, "default".to_string(), 3),
+                (0, "kube-system".to_string(), 1),
+            ]
+        );
+
+        sidebar.next();
+        sidebar.expand();
+        sidebar.next();
+        sidebar.expand();
+        sidebar.next();
+        assert_eq!(
+            labels(&sidebar),
+            [
+                (0, "(cluster)".to_string(), 1),
+                (0, "default".to_string(), 3),
+                (1, "pods".to_string(), 3),
+                (2, "web".to_string(), 2),
+                (0, "kube-system".to_string(), 1),
+            ]
+        );
+        let node = sidebar.selected_node().unwrap();
+        assert_eq!(node.path, ["default", "pods", "web"]);
+        assert!(node.leaf);
+
+        sidebar.collapse();
+        assert_eq!(sidebar.selected_node().unwrap().path, ["default", "pods"]);
+        sidebar.collapse();
+        assert_eq!(sidebar.nodes().len(), 4);
+    }
+}
@@ -0,0 +1,1500 @@
+//! Drawing the [`EventStore`] as seen through a [`ViewState`].
+
+use crate::admission;
+use crate::agent;
+use crate::cascade;
+use crate::clients;
+use crate::config::Config;
+use crate::credential;
+use crate::detail;
+use crate::diff::Change;
+use crate::drift;
+use crate::fights;
+use crate::fold;
+use crate::group::GroupRow;
+use crate::kube::EventV1;
+use crate::managed;
+use crate::messages::{MessageLog, Severity};
+use crate::picker::{Checklist, Picker};
+use crate::rates;
+use crate::runs::RunRow;
+use crate::script::Scripts;
+use crate::secret::{self, Reveal};
+use crate::selectors;
+use crate::size;
+use crate::source::LoadProgress;
+use crate::stats::{self, IngestStats};
+use crate::store::EventStore;
+use crate::theme::Theme;
+use crate::throttling;
+use crate::timeline::Outcome;
+use crate::timestamp::{self, Timestamps};
+use crate::tree::Sidebar;
+use crate::view::{self, Column, Detail, Overlay, Pane, ViewState};
+use ratatui::{
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        block::Title, Block, BorderType, Borders, Clear, List, ListState, Padding, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Table, TableState, Wrap,
+    },
+    Frame,
+};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// The smallest terminal anything useful fits in, below which only a note
+/// saying so is drawn.
+const MIN_WIDTH: u16 = 50;
+const MIN_HEIGHT: u16 = 12;
+
+/// The height the table and request info are given when there is room for
+/// them and a good part of the pane below.
+const TABLE_HEIGHT: u16 = 12 + 1;
+const INFO_HEIGHT: u16 = 9 + 1;
+const PANE_HEIGHT: u16 = 10;
+
+/// The width of the column of groups, while grouping the table.
+const GROUP_WIDTH: u16 = 24;
+
+/// The width of the sidebar, borders and all.
+const SIDEBAR_WIDTH: u16 = 32;
+
+/// Draw the whole screen, with the tabs' titles across the top when there is
+/// more than one.
+#[allow(clippy::too_many_arguments)]
+pub fn draw(
+    frame: &mut Frame,
+    store: &EventStore,
+    scripts: &Scripts,
+    view: &mut ViewState,
+    tabs: &[String],
+    active_tab: usize,
+    stats: &mut IngestStats,
+    messages: &MessageLog,
+    theme: &Theme,
+    timestamps: &Timestamps,
+    alert: Option<&str>,
+    loads: &[Arc<LoadProgress>],
+    config: &Config,
+) {
+    // frame
+    let frame_area = frame.size();
+    if frame_area.width < MIN_WIDTH || frame_area.height < MIN_HEIGHT {
+        draw_too_small(frame, frame_area, theme);
+        return;
+    }
+    let mut title = "Kubernetes Audit Log Explorer (KALE)".to_string();
+    if let Some(source) = view.source_filter() {
+        title += &format!(" [{}]", source);
+    }
+    if let Some(filter) = view.filter() {
+        title += &format!(" [{}]", filter);
+    }
+    let mut frame_block = Block::new()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(theme.text);
+    if tabs.len() > 1 {
+        let spans = tabs.iter().enumerate().map(|(i, tab)| {
+            let span = match (i == active_tab, theme.markers) {
+                (true, true) => Span::from(format!("[{}:{}]", i + 1, tab)),
+                _ => Span::from(format!(" {}:{} ", i + 1, tab)),
+            };
+            match i == active_tab {
+                true => span.style(theme.highlight),
+                false => span,
+            }
+        });
+        frame_block =
+            frame_block.title(Title::from(Line::from_iter(spans)).alignment(Alignment::Right));
+    }
+    let frame_inner = frame_block.inner(frame_area);
+    frame.render_widget(frame_block, frame_area);
+
+    // the sidebar, while open, runs down the left of everything else
+    let frame_inner = match &view.sidebar {
+        Some(sidebar) => {
+            let hor_layout =
+                Layout::horizontal([Constraint::Length(SIDEBAR_WIDTH), Constraint::Fill(1)])
+                    .split(frame_inner);
+            draw_sidebar(frame, hor_layout[0], sidebar, theme);
+            hor_layout[1]
+        }
+        None => frame_inner,
+    };
+
+    // layout
+    let prompting = view.filter_input.is_some()
+        || view.search_name_input.is_some()
+        || view.column_input.is_some()
+        || view.find_input.is_some()
+        || view.decode_input.is_some()
+        || view.pipe_input.is_some();
+    let prompt_height = if prompting { 1 } else { 0 };
+    let timeline_height = match (view.timeline_hidden, view.timeline_outcomes) {
+        (true, _) => 0,
+        (false, true) => 1 + Outcome::ALL.len() as u16,
+        (false, false) => 1,
+    };
+    let status_height = 1;
+    // short terminals split what there is between the table and the pane,
+    // leaving the request info to the detail view
+    let roomy = frame_inner.height
+        >= TABLE_HEIGHT
+            + INFO_HEIGHT
+            + PANE_HEIGHT
+            + timeline_height
+            + prompt_height
+            + status_height;
+    let (table_height, info_height) = match roomy {
+        true => (
+            Constraint::Length(TABLE_HEIGHT),
+            Constraint::Length(INFO_HEIGHT),
+        ),
+        false => (Constraint::Fill(1), Constraint::Length(0)),
+    };
+    let vert_layout = Layout::vertical([
+        table_height,
+        info_height,
+        Constraint::Fill(1),
+        Constraint::Length(timeline_height),
+        Constraint::Length(prompt_height),
+        Constraint::Length(status_height),
+    ])
+    .split(frame_inner);
+
+    draw_table(
+        frame,
+        vert_layout[0],
+        store,
+        scripts,
+        view,
+        theme,
+        timestamps,
+    );
+    let event = view.selected_event(store);
+    draw_info(frame, vert_layout[1], event, theme);
+    let pane_area = vert_layout[2];
+    (view.scroll_limit, view.horizontal_limit) = match view.pane {
+        Pane::Bodies => match view.pinned() {
+            Some(pinned) => draw_comparison(
+                frame,
+                pane_area,
+                &store[pinned],
+                event,
+                view,
+                theme,
+                timestamps,
+            ),
+            None => draw_bodies(frame, pane_area, event, view, theme),
+        },
+        Pane::Messages => {
+            draw_messages(frame, pane_area, messages, view.message_scroll, theme);
+            (0, 0)
+        }
+        Pane::Bookmarks => {
+            draw_bookmarks(frame, pane_area, store, view, theme, timestamps);
+            (0, 0)
+        }
+        Pane::Diff => {
+            let (title, changes) = &view.diff;
+            let title = format!("Diff: {} ({} changes)", title, changes.len());
+            let scroll = view.scroll_position;
+            (
+                draw_diff(frame, pane_area, title, changes, &[], scroll, theme),
+                0,
+            )
+        }
+        Pane::Changes => {
+            let (title, managers, changes) = match view.object_changes(store) {
+                Some((old, new, changes)) => (
+                    format!(
+                        "Changes: {} {} -> {} {} ({} changes)",
+                        timestamps.format(old.request_received_timestamp),
+                        old.verb,
+                        timestamps.format(new.request_received_timestamp),
+                        new.verb,
+                        changes.len()
+                    ),
+                    managed::attribute(view::object(old), view::object(new), &changes),
+                    changes,
+                ),
+                None => (
+                    "Changes: no earlier version with a body to compare to".to_string(),
+                    Vec::new(),
+                    Vec::new(),
+                ),
+            };
+            let scroll = view.scroll_position;
+            (
+                draw_diff(frame, pane_area, title, &changes, &managers, scroll, theme),
+                0,
+            )
+        }
+        Pane::Drift => {
+            let (title, managers, changes) = match event.zip(event.and_then(drift::drift)) {
+                Some((event, changes)) => (
+                    format!(
+                        "Drift: last applied -> {} by {} ({} changes)",
+                        event.verb,
+                        event.user.username,
+                        changes.len()
+                    ),
+                    managed::attribute(None, view::object(event), &changes),
+                    changes,
+                ),
+                None => (
+                    "Drift: not a write of an object applied with kubectl apply".to_string(),
+                    Vec::new(),
+                    Vec::new(),
+                ),
+            };
+            let scroll = view.scroll_position;
+            (
+                draw_diff(frame, pane_area, title, &changes, &managers, scroll, theme),
+                0,
+            )
+        }
+    };
+    view.scroll_position = view.scroll_position.min(view.scroll_limit);
+    view.horizontal_scroll = view.horizontal_scroll.min(view.horizontal_limit);
+    let mut indicator = String::new();
+    if let (Some(find), Pane::Bodies) = (&view.find, view.pane) {
+        let lines = &view.find_lines;
+        indicator += &match lines.is_empty() {
+            true => format!(" {:?} not found ", find),
+            false => format!(" {:?} {} of {} ", find, view.find_match + 1, lines.len()),
+        };
+    }
+    if let Some(percent) = view.scroll_percent() {
+        indicator += &format!(" {}% ", percent);
+    }
+    if !indicator.is_empty() {
+        // over the right end of the pane's top border
+        let indicator = Paragraph::new(indicator).alignment(Alignment::Right);
+        frame.render_widget(
+            indicator,
+            Rect {
+                height: 1,
+                ..pane_area
+            },
+        );
+    }
+    draw_timeline(frame, vert_layout[3], store, view, theme);
+    draw_prompt(frame, vert_layout[4], view, theme);
+    draw_status(
+        frame,
+        vert_layout[5],
+        store,
+        view,
+        stats,
+        theme,
+        alert,
+        loads,
+    );
+
+    if let Some(picker) = &view.picker {
+        draw_picker(frame, frame_area, picker, theme);
+    }
+    if let Some(checklist) = &view.checklist {
+        draw_checklist(frame, frame_area, checklist, theme);
+    }
+    if let Some(detail) = &view.detail {
+        let area = match view.query_input {
+            Some(_) => {
+                let [area, prompt] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+                    .areas(frame_area);
+                draw_prompt(frame, prompt, view, theme);
+                area
+            }
+            None => frame_area,
+        };
+        draw_detail(frame, area, store, detail, view, theme, timestamps);
+    }
+    if let Some((overlay, scroll)) = view.overlay {
+        if !matches!(&view.overlay_sections, Some((events, _)) if *events == store.len()) {
+            let sections = overlay_sections(overlay, store, view, config, timestamps);
+            view.overlay_sections = Some((store.len(), sections));
+        }
+        let title = format!("{} (esc to close)", overlay.title());
+        if let Some((_, sections)) = &view.overlay_sections {
+            draw_sections(frame, frame_area, title, sections, scroll, theme);
+        }
+    }
+}
+
+/// A rectangle of the given percentage size in the middle of `area`.
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::vertical([Constraint::Percentage(percent_y)])
+        .flex(Flex::Center)
+        .split(area);
+    Layout::horizontal([Constraint::Percentage(percent_x)])
+        .flex(Flex::Center)
+        .split(vertical[0])[0]
+}
+
+/// In place of everything else, when the terminal is too small for it.
+fn draw_too_small(frame: &mut Frame, area: Rect, theme: &Theme) {
+    let text = format!(
+        "terminal too small (need {}x{}, have {}x{})",
+        MIN_WIDTH, MIN_HEIGHT, area.width, area.height
+    );
+    let lines = Paragraph::new(text.as_str())
+        .wrap(Wrap { trim: true })
+        .line_count(area.width) as u16;
+    let area = Layout::vertical([Constraint::Length(lines)])
+        .flex(Flex::Center)
+        .split(area)[0];
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .style(theme.text);
+    frame.render_widget(Block::new().style(theme.text), frame.size());
+    frame.render_widget(paragraph, area);
+}
+
+/// A popup listing the picker's matches, with the query in the title.
+fn draw_picker(frame: &mut Frame, area: Rect, picker: &Picker, theme: &Theme) {
+    let area = centered(area, 60, 60);
+    let block = Block::new()
+        .title(format!("{}: {}", picker.title, picker.query()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let items = picker
+        .matches()
+        .map(|(value, note)| format!("{} ({})", value, note));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(theme.selected_marker());
+    let mut state = ListState::default().with_selected(picker.selected());
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// The tree of namespaces, resources and objects, with how many events are
+/// about each, and the selected node highlighted while the sidebar has focus.
+fn draw_sidebar(frame: &mut Frame, area: Rect, sidebar: &Sidebar, theme: &Theme) {
+    let block = Block::new()
+        .title("Objects")
+        .borders(Borders::RIGHT)
+        .border_type(BorderType::Rounded);
+    let items = sidebar.nodes().into_iter().map(|node| {
+        let marker = match (node.leaf, node.expanded) {
+            (true, _) => " ",
+            (false, true) => "▾",
+            (false, false) => "▸",
+        };
+        format!(
+            "{}{} {} ({})",
+            "  ".repeat(node.depth()),
+            marker,
+            node.label(),
+            node.count
+        )
+    });
+    let mut list = List::new(items).block(block).style(theme.text);
+    if sidebar.focused {
+        list = list
+            .highlight_style(theme.highlight)
+            .highlight_symbol(theme.selected_marker());
+    }
+    let mut state = ListState::default().with_selected(Some(sidebar.selected()));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// A strip of how many events there are over time, with the selected event's
+/// time marked, and under it a row for each outcome when asked for.
+fn draw_timeline(
+    frame: &mut Frame,
+    area: Rect,
+    store: &EventStore,
+    view: &mut ViewState,
+    theme: &Theme,
+) {
+    let (area, outcomes_area) = match area.height {
+        0 => (area, Rect::default()),
+        _ => {
+            let [strip, outcomes] =
+                Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+            (strip, outcomes)
+        }
+    };
+    view.timeline_area = area;
+    let Some(timeline) = view.timeline(store) else {
+        return;
+    };
+    frame.render_widget(
+        Sparkline::default()
+            .data(&timeline.counts)
+            .style(theme.text),
+        area,
+    );
+    // colour the slices by how their requests went, like a heatmap
+    for slice in (0..timeline.counts.len()).filter(|_| area.height > 0) {
+        let style = match timeline.outcome(slice) {
+            Outcome::Success => continue,
+            Outcome::ClientError => theme.warning,
+            Outcome::Throttled => theme.throttled,
+            Outcome::ServerError => theme.error,
+        };
+        frame
+            .buffer_mut()
+            .get_mut(area.x + slice as u16, area.y)
+            .set_style(style);
+    }
+    if let Some(event) = view.selected_event(store).filter(|_| area.height > 0) {
+        let x = area.x + timeline.slice(event.request_received_timestamp) as u16;
+        frame
+            .buffer_mut()
+            .get_mut(x, area.y)
+            .set_style(theme.highlight);
+    }
+
+    // a heatmap of each outcome's share of each slice, the worst on top
+    let rows = Outcome::ALL.into_iter().zip(outcomes_area.rows());
+    for (outcome, row) in rows {
+        let style = match outcome {
+            Outcome::Success => theme.text,
+            Outcome::ClientError => theme.warning,
+            Outcome::Throttled => theme.throttled,
+            Outcome::ServerError => theme.error,
+        };
+        for slice in 0..timeline.counts.len().min(row.width as usize) {
+            let share = timeline.share(slice, outcome);
+            let shade = match share {
+                0.0 => continue,
+                ..=0.25 => "░",
+                ..=0.5 => "▒",
+                ..=0.75 => "▓",
+                _ => "█",
+            };
+            frame
+                .buffer_mut()
+                .get_mut(row.x + slice as u16, row.y)
+                .set_symbol(shade)
+                .set_style(style);
+        }
+    }
+}
+
+/// The table of events, with the cluster column only once there are several,
+/// a row for each group ahead of its events while grouping, and a row for each
+/// run of repeated events while collapsing them.
+///
+/// Only the rows that fit are built, so that drawing stays cheap however many
+/// events there are.
+fn draw_table(
+    frame: &mut Frame,
+    area: Rect,
+    store: &EventStore,
+    scripts: &Scripts,
+    view: &mut ViewState,
+    theme: &Theme,
+    timestamps: &Timestamps,
+) {
+    let groups = view
+        .grouping
+        .as_ref()
+        .map(|grouping| grouping.rows(store, scripts, view.visible()));
+    let runs = match (&view.grouping, &view.runs) {
+        (None, Some(runs)) => Some(runs.rows(store, view.visible())),
+        _ => None,
+    };
+    let (selected, offset) = match (&mut view.grouping, &groups) {
+        (Some(grouping), Some(groups)) => (grouping.selected(groups), &mut grouping.offset),
+        _ => {
+            let selected = view.selected();
+            let selected = match &runs {
+                Some(runs) => selected.and_then(|s| runs.iter().position(|row| row.contains(s))),
+                None => selected,
+            };
+            (selected, &mut view.offset)
+        }
+    };
+
+    // less one for the header
+    let height = area.height.saturating_sub(1) as usize;
+    if let Some(selected) = selected {
+        if selected < *offset {
+            *offset = selected;
+        } else if height > 0 && selected >= *offset + height {
+            *offset = selected + 1 - height;
+        }
+    }
+    let offset = *offset;
+
+    let show_cluster = !store.sources().is_empty();
+    let cluster_width = store.sources().iter().map(String::len).max().unwrap_or(0);
+    let visible = view.visible();
+    let total = match (&groups, &runs) {
+        (Some(groups), _) => groups.len(),
+        (None, Some(runs)) => runs.len(),
+        (None, None) => visible.len(),
+    };
+    let rows = (offset..total.min(offset + height)).map(|n| {
+        let (group, position) = match groups.as_ref().map(|groups| &groups[n]) {
+            Some(GroupRow::Group {
+                value,
+                count,
+                expanded,
+                ..
+            }) => {
+                let marker = if *expanded { "▾" } else { "▸" };
+                let label = format!("{} {} ({})", marker, value, count);
+                return Row::new([label]).style(theme.label);
+            }
+            Some(GroupRow::Event(position)) => (Some(String::new()), *position),
+            None => (None, runs.as_ref().map_or(n, |runs| runs[n].position())),
+        };
+        let i = visible[position];
+        // dry runs changed nothing, so fade into the background
+        let style = match store[i].is_dry_run() {
+            true => theme.label,
+            false => Style::new(),
+        };
+        let style = match store.is_bookmarked(i) {
+            true => style.patch(theme.bookmarked),
+            false => style,
+        };
+        let style = match store.is_alerted(i) {
+            true => style.patch(theme.alerted),
+            false => style,
+        };
+        let style = match view.is_marked(i) {
+            true => style.patch(theme.marked),
+            false => style,
+        };
+
+        let previous = position
+            .checked_sub(1)
+            .map(|position| &store[visible[position]]);
+        let mut cells = row(
+            &store[i],
+            previous,
+            show_cluster,
+            &view.columns,
+            scripts,
+            timestamps,
+        );
+        if store.is_alerted(i) {
+            let uri = cells.last_mut().expect("a row has a uri");
+            *uri = format!("{}{}", theme.error_marker(), uri);
+        }
+        if let Some(manager) = store[i].applied_by() {
+            let uri = cells.last_mut().expect("a row has a uri");
+            *uri = format!("{} (applied by {})", uri, manager);
+        }
+        if let Some(count) = view.dedupe().map(|dedupe| dedupe.count(i)) {
+            if count > 0 {
+                let uri = cells.last_mut().expect("a row has a uri");
+                *uri = format!("{} (+{} duplicates)", uri, count);
+            }
+        }
+        if let Some(RunRow::Run {
+            first,
+            last,
+            expanded,
+        }) = runs.as_ref().map(|runs| runs[n])
+        {
+            let uri = cells.last_mut().expect("a row has a uri");
+            *uri = match expanded {
+                true => format!("▾ {}", uri),
+                false => format!(
+                    "▸ {} ({} times in {})",
+                    uri,
+                    last + 1 - first,
+                    timestamp::elapsed(
+                        store[visible[last]].request_received_timestamp
+                            - store[i].request_received_timestamp
+                    )
+                    .trim_start_matches('+')
+                ),
+            };
+        }
+        Row::new(group.into_iter().chain(cells)).style(style)
+    });
+
+    let mut header = Vec::new();
+    let mut widths = Vec::new();
+    if let Some(grouping) = &view.grouping {
+        header.push(Cow::from(grouping.field.to_string()));
+        widths.push(Constraint::Length(GROUP_WIDTH));
+    }
+    if show_cluster {
+        header.push(Cow::from("cluster"));
+        widths.push(Constraint::Length(cluster_width.max(7) as u16));
+    }
+    header.extend([Cow::from("timestamp"), Cow::from("verb")]);
+    widths.extend([
+        Constraint::Length(timestamps.width()),
+        Constraint::Length(6),
+    ]);
+    for column in &view.columns {
+        header.push(Cow::from(column.title()));
+        widths.push(Constraint::Length(column.width()));
+    }
+    header.push(Cow::from("request uri"));
+    widths.push(Constraint::Fill(1));
+
+    let table = Table::default()
+        .style(theme.text)
+        .rows(rows)
+        .widths(widths)
+        .column_spacing(1)
+        .header(Row::new(header).style(theme.heading))
+        .highlight_style(theme.highlight)
+        .highlight_symbol(theme.selected_marker());
+    let mut state = TableState::new().with_selected(selected.map(|i| i - offset));
+    frame.render_stateful_widget(table, area, &mut state);
+
+    let below_header = Rect {
+        y: area.y + 1,
+        height: area.height.saturating_sub(1),
+        ..area
+    };
+    draw_scrollbar(frame, below_header, total, offset);
+}
+
+/// A scrollbar down the right of `area` when the `lines` don't all fit, with
+/// `position` being the first line shown.
+fn draw_scrollbar(frame: &mut Frame, area: Rect, lines: usize, position: usize) {
+    let height = area.height as usize;
+    if lines <= height || area.is_empty() {
+        return;
+    }
+    let mut state = ScrollbarState::new(lines - height + 1)
+        .position(position)
+        .viewport_content_length(height);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    frame.render_stateful_widget(scrollbar, area, &mut state);
+}
+
+/// The query parameters that change what a request does, and so are kept in
+/// the table's URIs where the likes of `limit` and `timeout` are dropped.
+const SHOWN_QUERY_PARAMETERS: &[&str] = &[
+    "watch",
+    "labelSelector",
+    "fieldSelector",
+    "command",
+    "container",
+    "dryRun",
+];
+
+/// The path of the request URI, followed by the parameters that matter.
+fn uri(event: &EventV1) -> String {
+    let shown = event
+        .query()
+        .into_iter()
+        .filter(|(key, _)| SHOWN_QUERY_PARAMETERS.contains(&key.as_ref()))
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>();
+    match shown.is_empty() {
+        true => event.path().to_string(),
+        false => format!("{}?{}", event.path(), shown.join("&")),
+    }
+}
+
+fn row(
+    event: &EventV1,
+    previous: Option<&EventV1>,
+    show_cluster: bool,
+    columns: &[Column],
+    scripts: &Scripts,
+    timestamps: &Timestamps,
+) -> Vec<String> {
+    let mut cells = Vec::new();
+    if show_cluster {
+        cells.push(event.source.clone().unwrap_or_default());
+    }
+    cells.push(timestamps.format(event.request_received_timestamp));
+    cells.push(event.verb.clone());
+    cells.extend(
+        columns
+            .iter()
+            .map(|column| column.cell(event, previous, scripts)),
+    );
+    cells.push(uri(event));
+    cells
+}
+
+fn draw_info(frame: &mut Frame, area: Rect, event: Option<&EventV1>, theme: &Theme) {
+    let info_block = Block::new()
+        .title("Request Info")
+        .borders(Borders::TOP)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::left(1));
+    let info_inner = info_block.inner(area);
+    frame.render_widget(info_block, area);
+    let info_text = match event {
+        Some(event) => format!(
+            "Request URI:       {}
+Audit ID:          {}
+Object Ref:        {}
+User:              {}
+Groups:            {}
+Impersonated User: {}
+User Agent:        {}
+Source IPs:        {}
+",
+            event.request_uri,
+            event.audit_id,
+            event
+                .object_ref
+                .as_ref()
+                .map(|ob| ob.to_string())
+                .unwrap_or_else(|| "N/A".to_string()),
+            event.user.username,
+            truncated_list(
+                &event.user.groups,
+                (info_inner.width as usize).saturating_sub(19)
+            ),
+            event
+                .impersonated_user
+                .as_ref()
+                .map(|imp_user| imp_user.username.as_str())
+                .unwrap_or("N/A"),
+            event.user_agent.as_deref().unwrap_or("N/A"),
+            event
+                .source_ips
+                .as_ref()
+                .map(|ips| ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>())
+                .map(|ips| ips.join(", "))
+                .unwrap_or("N/A".to_string())
+        ),
+        None => String::new(),
+    };
+    let mut lines = info_text
+        .lines()
+        .map(|line| Line::from(line.to_string()))
+        .collect::<Vec<_>>();
+    // failures are what is most often being looked for, so stand out
+    if let Some(event) = event {
+        let line = match &event.response_status {
+            Some(status) if status.is_failure() => Line::from(format!(
+                "Response Status:   {}{}",
+                theme.error_marker(),
+                status
+            ))
+            .style(theme.error)
+            .bold(),
+            Some(status) => Line::from(format!("Response Status:   {}", status)),
+            None => Line::from("Response Status:   N/A"),
+        };
+        lines.push(line);
+    }
+    frame.render_widget(Paragraph::new(lines), info_inner);
+}
+
+/// As many of the items as fit in `width`, followed by how many more there are.
+fn truncated_list(items: &[String], width: usize) -> String {
+    if items.is_empty() {
+        return "N/A".to_string();
+    }
+
+    let more = |count: usize| format!(", +{} more (enter for all)", count);
+    let mut text = items[0].clone();
+    for (i, item) in items.iter().enumerate().skip(1) {
+        let rest = items.len() - i - 1;
+        let suffix = if rest == 0 { 0 } else { more(rest).len() };
+        if text.len() + 2 + item.len() + suffix > width {
+            text += &more(items.len() - i);
+            break;
+        }
+        text += ", ";
+        text += item;
+    }
+    text
+}
+
+/// The request and response objects side by side, returning how far they can
+/// be scrolled down and right.
+fn draw_bodies(
+    frame: &mut Frame,
+    area: Rect,
+    event: Option<&EventV1>,
+    view: &mut ViewState,
+    theme: &Theme,
+) -> (u16, u16) {
+    let hor_layout =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).split(area);
+    let left = hor_layout[0];
+    let right = hor_layout[1];
+
+    let left_block = Block::new()
+        .title("Request")
+        .borders(Borders::TOP | Borders::RIGHT)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::left(1));
+    let left_inner = left_block.inner(left);
+    let right_block = Block::new()
+        .title("Response")
+        .borders(Borders::TOP)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::left(1));
+    let right_inner = right_block.inner(right);
+    frame.render_widget(left_block, left);
+    frame.render_widget(right_block, right);
+
+    let reveal = view
+        .selected_index()
+        .map(|index| view.reveal(index))
+        .unwrap_or_default();
+
+    // left
+    let left_text = event
+        .and_then(|event| Some((event, event.request_object.as_ref()?)))
+        .map(|(event, req)| body_text(event, "requestObject", req, view, reveal))
+        .unwrap_or_default();
+
+    // right
+    let right_text = event
+        .and_then(|event| Some((event, event.response_object.as_ref()?)))
+        .map(|(event, res)| body_text(event, "responseObject", res, view, reveal))
+        .unwrap_or_default();
+
+    draw_json_panes(
+        frame,
+        [(left_inner, left_text), (right_inner, right_text)],
+        view,
+        theme,
+    )
+}
+
+/// The event's request or response object, as named by `root`, pretty
+/// printed as [`prepare_body`] leaves it.
+fn body_text(
+    event: &EventV1,
+    root: &str,
+    body: &Value,
+    view: &ViewState,
+    reveal: Reveal,
+) -> String {
+    format!("{:#}", prepare_body(event, root, body, view, reveal))
+}
+
+/// The event's request or response object, folded unless the view says
+/// otherwise, with the values the view asks for decoded and any Secret values
+/// revealed only as far as `reveal`.
+fn prepare_body(
+    event: &EventV1,
+    root: &str,
+    body: &Value,
+    view: &ViewState,
+    reveal: Reveal,
+) -> Value {
+    let mut body = match view.unfolded {
+        true => body.clone(),
+        false => fold::fold(body, updates_status(event)),
+    };
+    for path in &view.decoded {
+        path.update(root, &mut body, &mut |value| {
+            if let Some(decoded) = value.as_str().and_then(secret::decode) {
+                *value = Value::String(decoded);
+            }
+        });
+    }
+    secret::reveal(body, secret::is_secret(event), reveal)
+}
+
+/// Whether the event is about an object's status, so folding it would hide
+/// the point.
+fn updates_status(event: &EventV1) -> bool {
+    event
+        .object_ref
+        .as_ref()
+        .is_some_and(|object| object.subresource.as_deref() == Some("status"))
+}
+
+/// Two JSON panes scrolled together, returning how far they can be scrolled
+/// down and right.
+///
+/// Where any found text is gets worked out first, so that a new find can
+/// scroll to it before anything is drawn.
+fn draw_json_panes(
+    frame: &mut Frame,
+    panes: [(Rect, String); 2],
+    view: &mut ViewState,
+    theme: &Theme,
+) -> (u16, u16) {
+    let mut lines = Vec::new();
+    if let Some(find) = &view.find {
+        for (area, text) in &panes {
+            // less the column for the scrollbar
+            let width = area.width.saturating_sub(1);
+            lines.extend(find_lines(text, find, width, !view.unwrapped));
+        }
+    }
+    lines.sort_unstable();
+    lines.dedup();
+    view.found(lines);
+
+    let mut limits = (0, 0);
+    for (area, text) in panes {
+        let (down, right) = draw_json(frame, area, text, view, theme);
+        limits = (limits.0.max(down), limits.1.max(right));
+    }
+    limits
+}
+
+/// The lines, as drawn, that `find` is on in `text`.
+fn find_lines(text: &str, find: &str, width: u16, wrapped: bool) -> Vec<u16> {
+    let find = find.to_ascii_lowercase();
+    let mut found = Vec::new();
+    let mut drawn = 0;
+    for line in text.lines() {
+        if line.to_ascii_lowercase().contains(&find) {
+            found.push(drawn as u16);
+        }
+        drawn += match wrapped {
+            true => Paragraph::new(line)
+                .wrap(Wrap { trim: false })
+                .line_count(width),
+            false => 1,
+        };
+    }
+    found
+}
+
+/// A line with each case insensitive occurrence of `find` highlighted.
+fn highlight<'a>(line: &'a str, find: &str, theme: &Theme) -> Line<'a> {
+    if find.is_empty() {
+        return Line::from(line);
+    }
+    // ASCII case folding keeps the byte offsets the same
+    let lower = line.to_ascii_lowercase();
+    let find = find.to_ascii_lowercase();
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for (i, _) in lower.match_indices(&find) {
+        if i < start {
+            continue;
+        }
+        spans.push(Span::from(&line[start..i]));
+        spans.push(Span::from(&line[i..i + find.len()]).style(theme.found));
+        start = i + find.len();
+    }
+    spans.push(Span::from(&line[start..]));
+    Line::from(spans)
+}
+
+/// Pretty printed JSON, scrolled and either wrapped or cut off at the right,
+/// with a scrollbar when it doesn't all fit. Returns how far it can be
+/// scrolled down and right.
+fn draw_json(
+    frame: &mut Frame,
+    area: Rect,
+    text: String,
+    view: &ViewState,
+    theme: &Theme,
+) -> (u16, u16) {
+    // leave a column for the scrollbar
+    let text_area = Rect {
+        width: area.width.saturating_sub(1),
+        ..area
+    };
+    let widest = text.lines().map(|line| line.chars().count()).max();
+    let lines = match &view.find {
+        Some(find) => text
+            .lines()
+            .map(|line| highlight(line, find, theme))
+            .collect(),
+        None => text.lines().map(Line::from).collect::<Vec<_>>(),
+    };
+    let mut paragraph = Paragraph::new(lines).style(theme.text);
+    let right_limit = match view.unwrapped {
+        true => {
+            paragraph = paragraph.scroll((view.scroll_position, view.horizontal_scroll));
+            widest.unwrap_or(0).saturating_sub(text_area.width as usize) as u16
+        }
+        false => {
+            paragraph = paragraph
+                .wrap(Wrap { trim: false })
+                .scroll((view.scroll_position, 0));
+            0
+        }
+    };
+    let lines = paragraph.line_count(text_area.width);
+    frame.render_widget(paragraph, text_area);
+    draw_scrollbar(frame, area, lines, view.scroll_position as usize);
+    (
+        lines.saturating_sub(area.height as usize) as u16,
+        right_limit,
+    )
+}
+
+/// A popup of checkboxes, toggled with space and applied with enter.
+fn draw_checklist(frame: &mut Frame, area: Rect, checklist: &Checklist, theme: &Theme) {
+    let height = checklist.items().len() as u16 + 2;
+    let area = centered(area, 40, 100).intersection(Rect {
+        height,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        ..area
+    });
+    let block = Block::new()
+        .title(format!("{} (space to toggle)", checklist.title))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let items = checklist.items().iter().map(|(value, checked)| {
+        let mark = if *checked { 'x' } else { ' ' };
+        format!("[{}] {}", mark, value)
+    });
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(theme.selected_marker());
+    let mut state = ListState::default().with_selected(Some(checklist.selected()));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// The whole of the pinned event beside the selected one, returning how far
+/// they can be scrolled down and right.
+fn draw_comparison(
+    frame: &mut Frame,
+    area: Rect,
+    pinned: &EventV1,
+    event: Option<&EventV1>,
+    view: &mut ViewState,
+    theme: &Theme,
+    timestamps: &Timestamps,
+) -> (u16, u16) {
+    let hor_layout =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).split(area);
+    let reveal = |index: Option<usize>| index.map(|i| view.reveal(i)).unwrap_or_default();
+    let sides = [
+        (
+            format!("Pinned: {}", summary(pinned, timestamps)),
+            Some(pinned),
+            reveal(view.pinned()),
+            hor_layout[0],
+            Borders::TOP | Borders::RIGHT,
+        ),
+        (
+            format!(
+                "Selected: {}",
+                event
+                    .map(|event| summary(event, timestamps))
+                    .unwrap_or_default()
+            ),
+            event,
+            reveal(view.selected_index()),
+            hor_layout[1],
+            Borders::TOP,
+        ),
+    ];
+
+    let panes = sides.map(|(title, event, reveal, area, borders)| {
+        let block = Block::new()
+            .title(title)
+            .borders(borders)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::left(1));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let text = event
+            .and_then(|event| {
+                let mut value = serde_json::to_value(event).ok()?;
+                for key in ["requestObject", "responseObject"] {
+                    if let Some(body) = value.get_mut(key) {
+                        *body = prepare_body(event, key, body, view, reveal);
+                    }
+                }
+                serde_json::to_string_pretty(&value).ok()
+            })
+            .unwrap_or_default();
+        (inner, text)
+    });
+    draw_json_panes(frame, panes, view, theme)
+}
+
+/// Every field of the event over the whole screen, a section at a time,
+/// with the timing of every stage of the request in the store.
+fn draw_detail(
+    frame: &mut Frame,
+    area: Rect,
+    store: &EventStore,
+    detail: &Detail,
+    view: &ViewState,
+    theme: &Theme,
+    timestamps: &Timestamps,
+) {
+    let event = &store[detail.index];
+    let title = format!("Event {} (esc to close)", event.audit_id);
+    let stages = detail
+        .stages
+        .iter()
+        .map(|&stage| &store[stage])
+        .collect::<Vec<_>>();
+    let mut sections = detail::sections(event, &stages, timestamps);
+    if let Some(path) = &view.query {
+        sections.insert(0, detail::query(event, path));
+    }
+    draw_sections(frame, area, title, &sections, detail.scroll, theme);
+}
+
+/// What an overlay says of the visible events, or a section saying there's
+/// nothing to tell where it would otherwise be empty.
+fn overlay_sections(
+    overlay: Overlay,
+    store: &EventStore,
+    view: &ViewState,
+    config: &Config,
+    timestamps: &Timestamps,
+) -> Vec<detail::Section> {
+    let events = view
+        .visible()
+        .iter()
+        .map(|&index| &store[index])
+        .collect::<Vec<_>>();
+    let (sections, none) = match overlay {
+        Overlay::Admission => (
+            admission::sections(events),
+            Some((
+                "Nothing would be blocked",
+                "no visible event breaks a Pod Security Standard or policy",
+            )),
+        ),
+        Overlay::Credentials => (
+            credential::sections(events, timestamps),
+            Some((
+                "No credentials handed out",
+                "no visible event asks for a certificate or token",
+            )),
+        ),
+        Overlay::Clients => (clients::sections(&events), None),
+        Overlay::Throttling => (throttling::sections(&events), None),
+        Overlay::Cascade => {
+            let root = view
+                .selected_index()
+                .and_then(|index| cascade::trace(store, index));
+            let sections = match &root {
+                Some(root) => cascade::sections(store, root),
+                None => Vec::new(),
+            };
+            (sections, None)
+        }
+        Overlay::Fights => (
+            fights::sections(&events),
+            Some((
+                "No fights",
+                "no object changed hands between users often enough",
+            )),
+        ),
+        Overlay::Sizes => (size::sections(&events), None),
+        Overlay::Selectors => (selectors::sections(&events), None),
+        Overlay::Agents => {
+            let skew = config.skew.unwrap_or(agent::DEFAULT_SKEW);
+            (agent::sections(&events, skew), None)
+        }
+        Overlay::Rates => (
+            rates::sections(&events, &config.rate_limits, timestamps),
+            Some((
+                "No one over the rate limits",
+                "no user made more requests than they allow",
+            )),
+        ),
+    };
+    match (sections.is_empty(), none) {
+        (true, Some((title, text))) => {
+            let mut section = detail::Section::new(title);
+            section.row("", text);
+            vec![section]
+        }
+        _ => sections,
+    }
+}
+
+/// Labelled sections over the whole screen, scrolled down `scroll` lines.
+fn draw_sections(
+    frame: &mut Frame,
+    area: Rect,
+    title: impl Into<String>,
+    sections: &[detail::Section],
+    scroll: u16,
+    theme: &Theme,
+) {
+    let block = Block::new()
+        .title(title.into())
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::horizontal(1));
+    let width = sections
+        .iter()
+        .flat_map(|section| &section.rows)
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines = Vec::new();
+    for section in sections {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(match section.alert {
+            true => Line::from(format!("{}{}", theme.error_marker(), section.title))
+                .style(theme.heading)
+                .patch_style(theme.error),
+            false => Line::from(section.title.as_str()).style(theme.heading),
+        });
+        for (label, value) in &section.rows {
+            let value = match section.alert {
+                true => Span::from(value.as_str()).style(theme.error),
+                false => Span::from(value.as_str()),
+            };
+            lines.push(Line::from(vec![
+                Span::from(format!("{:<width$}  ", label)).style(theme.label),
+                value,
+            ]));
+        }
+    }
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .style(theme.text);
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+/// When an event was received and what it did, in brief.
+fn summary(event: &EventV1, timestamps: &Timestamps) -> String {
+    format!(
+        "{} {}",
+        timestamps.format(event.request_received_timestamp),
+        event.verb
+    )
+}
+
+/// The message log, newest at the bottom, scrolled back `scroll` lines.
+fn draw_messages(
+    frame: &mut Frame,
+    area: Rect,
+    messages: &MessageLog,
+    scroll: usize,
+    theme: &Theme,
+) {
+    let block = Block::new()
+        .title(format!(
+            "Messages ({})",
+            messages.messages().len() + messages.dropped()
+        ))
+        .borders(Borders::TOP)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::left(1));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let height = inner.height as usize;
+    let end = messages.messages().len().saturating_sub(scroll);
+    let lines = messages
+        .messages()
+        .range(end.saturating_sub(height)..end)
+        .map(|message| {
+            let line = Line::from(message.to_string());
+            match message.severity {
+                Severity::Info => line,
+                Severity::Warning => line.style(theme.warning),
+                Severity::Error => line.style(theme.error),
+            }
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(Paragraph::new(lines).style(theme.text), inner);
+}
+
+/// Every bookmarked event, whether or not it passes the filters, with the
+/// selected event highlighted.
+fn draw_bookmarks(
+    frame: &mut Frame,
+    area: Rect,
+    store: &EventStore,
+    view: &ViewState,
+    theme: &Theme,
+    timestamps: &Timestamps,
+) {
+    let bookmarks = store.bookmarks().collect::<Vec<_>>();
+    let block = Block::new()
+        .title(format!("Bookmarks ({})", bookmarks.len()))
+        .borders(Borders::TOP)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::left(1));
+    let items = bookmarks.iter().map(|i| {
+        let event = &store[*i];
+        format!(
+            "{}  {:<6} {}",
+            timestamps.format(event.request_received_timestamp),
+            event.verb,
+            event.path()
+        )
+    });
+    let selected = view
+        .selected_index()
+        .and_then(|selected| bookmarks.iter().position(|i| *i == selected));
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(theme.selected_marker());
+    let mut state = ListState::default().with_selected(selected);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Differences between two events or objects, one per line, with the field
+/// managers owning each changed field when `managers` says, returning how far
+/// they can be scrolled.
+#[allow(clippy::too_many_arguments)]
+fn draw_diff(
+    frame: &mut Frame,
+    area: Rect,
+    title: String,
+    changes: &[Change],
+    managers: &[Vec<String>],
+    scroll: u16,
+    theme: &Theme,
+) -> u16 {
+    let block = Block::new()
+        .title(title)
+        .borders(Borders::TOP)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::left(1));
+    let lines = changes
+        .iter()
+        .enumerate()
+        .map(|(i, change)| {
+            let mut line = Line::from(change.to_string());
+            if let Some(managers) = managers.get(i).filter(|managers| !managers.is_empty()) {
+                let by = format!("  ({})", managers.join(", "));
+                line.push_span(Span::styled(by, theme.label));
+            }
+            match change {
+                Change::Added { .. } => line.style(theme.added),
+                Change::Removed { .. } => line.style(theme.removed),
+                Change::Changed { .. } => line.style(theme.changed),
+            }
+        })
+        .collect::<Vec<_>>();
+    let limit = lines
+        .len()
+        .saturating_sub(block.inner(area).height as usize) as u16;
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .scroll((scroll, 0))
+        .style(theme.text);
+    frame.render_widget(paragraph, area);
+    limit
+}
+
+fn draw_prompt(frame: &mut Frame, area: Rect, view: &ViewState, theme: &Theme) {
+    if let Some(input) = &view.filter_input {
+        let mut prompt = format!("/{}", input);
+        if let Some(err) = &view.prompt_error {
+            prompt += &format!("    ({})", err);
+        }
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    } else if let Some(name) = &view.search_name_input {
+        let prompt = format!("save search as: {}", name);
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    } else if let Some(path) = &view.column_input {
+        let mut prompt = format!("add column: {}", path);
+        if let Some(err) = &view.prompt_error {
+            prompt += &format!("    ({})", err);
+        }
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    } else if let Some(text) = &view.find_input {
+        let prompt = format!("find in bodies: {}", text);
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    } else if let Some(command) = &view.pipe_input {
+        let prompt = format!("pipe events to: {}", command);
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    } else if let Some(path) = &view.query_input {
+        let mut prompt = format!("query the event: {}", path);
+        if let Some(err) = &view.prompt_error {
+            prompt += &format!("    ({})", err);
+        }
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    } else if let Some(path) = &view.decode_input {
+        let mut prompt = format!("base64 decode: {}", path);
+        if let Some(err) = &view.prompt_error {
+            prompt += &format!("    ({})", err);
+        }
+        frame.render_widget(Paragraph::new(prompt).style(theme.highlight), area);
+    }
+}
+
+/// How far through loading a file is, as a bar and in numbers.
+fn loading(load: &LoadProgress) -> String {
+    const BAR_WIDTH: u64 = 10;
+    let (read, total) = load.bytes();
+    let filled = match total {
+        0 => 0,
+        total => read * BAR_WIDTH / total,
+    };
+    let mut text = format!(
+        "loading {} [{}{}] {} of {}, {} events",
+        load.name,
+        "█".repeat(filled as usize),
+        "░".repeat((BAR_WIDTH - filled) as usize),
+        stats::format_bytes(read),
+        stats::format_bytes(total),
+        load.events()
+    );
+    if let Some(remaining) = load.remaining() {
+        text += &format!(", {}s left", remaining.as_secs());
+    }
+    text + " (X to cancel)"
+}
+
+/// A single line of counters along the bottom.
+#[allow(clippy::too_many_arguments)]
+fn draw_status(
+    frame: &mut Frame,
+    area: Rect,
+    store: &EventStore,
+    view: &ViewState,
+    stats: &mut IngestStats,
+    theme: &Theme,
+    alert: Option<&str>,
+    loads: &[Arc<LoadProgress>],
+) {
+    let rate = stats.rate();
+    let memory = stats::resident_memory()
+        .map(stats::format_bytes)
+        .unwrap_or_else(|| "N/A".to_string());
+    // what's loading first, as the most likely to be cut off otherwise
+    let mut status = String::new();
+    for load in loads {
+        status += &format!("{} | ", loading(load));
+    }
+    if let Some(selected) = view.selected() {
+        status += &format!("{} of {} | ", selected + 1, view.visible().len());
+    }
+    status += &format!(
+        "{} events | {} matching | {} skipped | {:.0} events/s | {} memory",
+        store.len(),
+        view.visible().len(),
+        stats.skipped,
+        rate,
+        memory,
+    );
+    if stats.sampled > 0 {
+        status += &format!(" | {} sampled out", stats.sampled);
+    }
+    if let Some(dedupe) = view.dedupe() {
+        status += &format!(" | {} duplicates hidden", dedupe.hidden());
+    }
+    let style = match alert {
+        Some(name) => {
+            status = format!("{}alert: {} | {}", theme.error_marker(), name, status);
+            theme.alerted
+        }
+        None => theme.highlight,
+    };
+    frame.render_widget(Paragraph::new(status).style(style), area);
+}
@@ -0,0 +1,864 @@
+//! What is being looked at: the filters, which events pass them, and the
+//! selection and scroll positions.
+
+use crate::dedupe::Dedupe;
+use crate::detail::Section;
+use crate::diff::{self, Change};
+use crate::filter::{Field, Filter};
+use crate::group::Grouping;
+use crate::kube::EventV1;
+use crate::path::JsonPath;
+use crate::picker::{Checklist, Picker};
+use crate::runs::Runs;
+use crate::script::Scripts;
+use crate::secret::{self, Reveal};
+use crate::size;
+use crate::store::{self, EventStore};
+use crate::timeline::{Outcome, Timeline};
+use crate::timestamp;
+use crate::tree::Sidebar;
+use ratatui::layout::{Position, Rect};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// A column of the event table that can be shown or hidden.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Level,
+    Stage,
+    /// How long after the event shown above it the event was received.
+    SincePrevious,
+    RequestSize,
+    ResponseSize,
+    /// The resource, without any subresource.
+    Resource,
+    Subresource,
+    /// The API group, `core` for the legacy group.
+    ApiGroup,
+    /// The version of the API, without the group.
+    Version,
+    /// Whether the request was namespaced, cluster wide or non-resource.
+    Scope,
+    /// The values at a jq style path into the event.
+    Path(JsonPath),
+    /// The values of the script from the config file with this name.
+    Script(String),
+    /// A field worked out for events as they were read.
+    Enriched(String),
+}
+
+impl Column {
+    /// The columns that can always be chosen from.
+    pub const ALL: [Column; 10] = [
+        Column::Level,
+        Column::Stage,
+        Column::SincePrevious,
+        Column::RequestSize,
+        Column::ResponseSize,
+        Column::Resource,
+        Column::Subresource,
+        Column::ApiGroup,
+        Column::Version,
+        Column::Scope,
+    ];
+
+    pub fn title(&self) -> String {
+        match self {
+            Column::Level => "level".to_string(),
+            Column::Stage => "stage".to_string(),
+            Column::Resource => "resource".to_string(),
+            Column::Subresource => "subresource".to_string(),
+            Column::ApiGroup => "api group".to_string(),
+            Column::Version => "version".to_string(),
+            Column::Scope => "scope".to_string(),
+            Column::SincePrevious => "since previous".to_string(),
+            Column::RequestSize => "request size".to_string(),
+            Column::ResponseSize => "response size".to_string(),
+            Column::Path(path) => path.to_string(),
+            Column::Script(name) => Field::Script(name.clone()).to_string(),
+            Column::Enriched(key) => Field::Enriched(key.clone()).to_string(),
+        }
+    }
+
+    /// How wide the column is, enough for its longest value where it has one.
+    pub fn width(&self) -> u16 {
+        match self {
+            Column::Level => 15,
+            Column::Stage => 16,
+            Column::Resource => 24,
+            Column::Subresource => 12,
+            Column::ApiGroup => 24,
+            Column::Version => 12,
+            Column::Scope => 12,
+            Column::SincePrevious => 14,
+            Column::RequestSize | Column::ResponseSize => 13,
+            Column::Path(_) | Column::Script(_) | Column::Enriched(_) => 20,
+        }
+    }
+
+    /// The field the column shows, if it shows one.
+    pub fn field(&self) -> Option<Field> {
+        match self {
+            Column::Level => Some(Field::Level),
+            Column::Stage => Some(Field::Stage),
+            Column::Resource => Some(Field::Resource),
+            Column::Subresource => Some(Field::Subresource),
+            Column::ApiGroup => Some(Field::ApiGroup),
+            Column::Version => Some(Field::Version),
+            Column::Scope => Some(Field::Scope),
+            Column::SincePrevious => None,
+            Column::RequestSize => Some(Field::RequestSize),
+            Column::ResponseSize => Some(Field::ResponseSize),
+            Column::Path(path) => Some(Field::Path(path.clone())),
+            Column::Script(name) => Some(Field::Script(name.clone())),
+            Column::Enriched(key) => Some(Field::Enriched(key.clone())),
+        }
+    }
+
+    /// The column's value for `event`, given the event shown above it.
+    pub fn cell(&self, event: &EventV1, previous: Option<&EventV1>, scripts: &Scripts) -> String {
+        match self {
+            Column::SincePrevious => previous
+                .map(|previous| {
+                    timestamp::elapsed(
+                        event.request_received_timestamp - previous.request_received_timestamp,
+                    )
+                })
+                .unwrap_or_default(),
+            Column::RequestSize => size::request_bytes(event)
+                .map(size::human)
+                .unwrap_or_default(),
+            Column::ResponseSize => size::response_bytes(event)
+                .map(size::human)
+                .unwrap_or_default(),
+            Column::Path(path) => path.values(event).join(", "),
+            Column::Script(name) => match scripts.values(name, event) {
+                Ok(values) => values.join(", "),
+                Err(err) => format!("error: {}", err),
+            },
+            column => column
+                .field()
+                .map(|field| field.values(event, scripts).join(", "))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// What is shown below the request info.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pane {
+    /// The request and response objects.
+    #[default]
+    Bodies,
+    Messages,
+    Bookmarks,
+    /// The differences between two events.
+    Diff,
+    /// What the selected event changed about its object since the event
+    /// above it.
+    Changes,
+    /// Where the object the selected event wrote differs from how it was
+    /// last applied.
+    Drift,
+}
+
+/// A summary of the visible events, drawn over the whole screen as titled
+/// sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    /// What admission would block.
+    Admission,
+    /// The certificates and tokens handed out.
+    Credentials,
+    /// The events by the kind of client and by node.
+    Clients,
+    /// Throttling by priority level, flow schema and client.
+    Throttling,
+    /// What deleting the selected object took down with it, as a tree.
+    Cascade,
+    /// The objects users keep taking turns to change.
+    Fights,
+    /// The largest objects, and the clients sending and receiving the most
+    /// bytes.
+    Sizes,
+    /// Unselective lists and watches, and the selectors used.
+    Selectors,
+    /// The client binaries and versions, and who runs each.
+    Agents,
+    /// The users going over the rate limits.
+    Rates,
+}
+
+impl Overlay {
+    pub fn title(self) -> &'static str {
+        match self {
+            Overlay::Admission => "Admission",
+            Overlay::Credentials => "Credentials",
+            Overlay::Clients => "Clients",
+            Overlay::Throttling => "Throttling",
+            Overlay::Cascade => "Cascade",
+            Overlay::Fights => "Fights",
+            Overlay::Sizes => "Sizes",
+            Overlay::Selectors => "Selectors",
+            Overlay::Agents => "User agents",
+            Overlay::Rates => "Rates",
+        }
+    }
+}
+
+/// The event open in the detail view, with every stage of its request.
+#[derive(Debug)]
+pub struct Detail {
+    /// The index into the store of the event.
+    pub index: usize,
+    /// The indices into the store of the stages of the event's request, its
+    /// own among them, kept up to date as more arrive.
+    pub(crate) stages: BTreeSet<usize>,
+    /// How far the view is scrolled.
+    pub scroll: u16,
+}
+
+/// The version of its object an event's bodies tell of: the object returned,
+/// unless that was only a Status saying how it went, or else the one sent.
+pub fn object(event: &EventV1) -> Option<&Value> {
+    event
+        .response_object
+        .as_ref()
+        .filter(|object| object["kind"] != "Status")
+        .or(event.request_object.as_ref())
+}
+
+/// How many earlier filters are kept to undo back to.
+const UNDO_LIMIT: usize = 100;
+
+/// The filters entered at the prompt, to recall with up and down.
+#[derive(Debug, Default)]
+pub struct FilterHistory {
+    entries: Vec<String>,
+    /// The entry shown in the prompt, while recalling.
+    position: Option<usize>,
+    /// What was in the prompt before recalling started.
+    draft: String,
+}
+
+impl FilterHistory {
+    /// Remember an entered filter, unless it was also the last one.
+    pub fn push(&mut self, entry: String) {
+        if self.entries.last() != Some(&entry) {
+            self.entries.push(entry);
+        }
+        self.position = None;
+    }
+
+    /// Stop recalling, so the next recall starts from the newest entry.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// The entry before the one shown, starting from the newest. `input` is
+    /// kept to come back to.
+    pub fn older(&mut self, input: &str) -> Option<&str> {
+        let position = match self.position {
+            Some(position) => position.saturating_sub(1),
+            None => {
+                self.draft = input.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The entry after the one shown, or what was being typed after the newest.
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
+
+/// A filtered, navigable view over an [`EventStore`].
+#[derive(Debug, Default)]
+pub struct ViewState {
+    filter: Option<Filter>,
+    /// The filters replaced so far, most recent last.
+    undo: Vec<Option<Filter>>,
+    source_filter: Option<String>,
+    /// The events shown in place of exact duplicates, while hiding them.
+    dedupe: Option<Dedupe>,
+    /// Indices into the store of the events that pass the filters, in order.
+    visible: Vec<usize>,
+    /// The selected position in `visible`.
+    selected: Option<usize>,
+    /// Indices into the store of the events marked for a batch action.
+    marked: BTreeSet<usize>,
+    /// Indices into the store of the events hidden regardless of the filters.
+    hidden: BTreeSet<usize>,
+    /// Index into the store of the event shown beside the selected one.
+    pinned: Option<usize>,
+    /// Index into the store of the event whose Secret values are revealed,
+    /// and how far. Every other event's are hidden.
+    revealed: Option<(usize, Reveal)>,
+    /// The first position in `visible` drawn in the table.
+    pub(crate) offset: usize,
+    /// How far the request and response bodies are scrolled.
+    pub scroll_position: u16,
+    /// How far the bodies can be scrolled before running out, as of the last
+    /// time they were drawn.
+    pub(crate) scroll_limit: u16,
+    /// Whether long lines in the bodies run off to the right rather than
+    /// wrapping.
+    pub unwrapped: bool,
+    /// Paths to the values in the bodies shown base64 decoded.
+    pub decoded: Vec<JsonPath>,
+    /// The path being typed in, while choosing a value to decode.
+    pub decode_input: Option<String>,
+    /// Whether the bodies are shown in full, rather than with managed fields
+    /// and the like folded away.
+    pub unfolded: bool,
+    /// How far the bodies are scrolled to the right, while unwrapped.
+    pub horizontal_scroll: u16,
+    /// How far the bodies can be scrolled to the right, as of the last time
+    /// they were drawn.
+    pub(crate) horizontal_limit: u16,
+    /// The filter being typed in, while the filter prompt is open.
+    pub filter_input: Option<String>,
+    /// Why the input in the open prompt was rejected.
+    pub prompt_error: Option<String>,
+    pub filter_history: FilterHistory,
+    /// The name being typed in, while saving the filter as a search.
+    pub search_name_input: Option<String>,
+    /// The path being typed in, while adding a column.
+    pub column_input: Option<String>,
+    /// The command being typed in, while choosing what to pipe events to.
+    pub pipe_input: Option<String>,
+    /// The text being typed in, while finding text in the bodies.
+    pub find_input: Option<String>,
+    /// The text found and highlighted in the bodies.
+    pub find: Option<String>,
+    /// The lines of the bodies the found text is on, as of the last time they
+    /// were drawn.
+    pub(crate) find_lines: Vec<u16>,
+    /// Which of `find_lines` was last scrolled to.
+    pub(crate) find_match: usize,
+    /// Whether to scroll to the next match when the bodies are next drawn,
+    /// once where the matches are is known.
+    pub(crate) find_pending: bool,
+    /// What is shown below the request info.
+    pub pane: Pane,
+    /// The differences shown in the diff pane, with a title saying between what.
+    pub diff: (String, Vec<Change>),
+    /// How many lines the message log is scrolled back from the newest.
+    pub message_scroll: usize,
+    /// The event shown over the whole screen, while it is open.
+    pub detail: Option<Detail>,
+    /// The path being typed in, while querying the event in the detail view.
+    pub query_input: Option<String>,
+    /// The path whose values in the event are shown at the top of the detail
+    /// view.
+    pub query: Option<JsonPath>,
+    /// The summary of the visible events open over the whole screen, if
+    /// any, and how far it is scrolled.
+    pub overlay: Option<(Overlay, u16)>,
+    /// What the open overlay says, as of how many events there were when
+    /// it was worked out, so that it's only worked out again once more come.
+    pub(crate) overlay_sections: Option<(usize, Vec<Section>)>,
+    /// The popup picking a value to filter on, while it is open.
+    pub picker: Option<Picker>,
+    /// The popup toggling values to show, while it is open.
+    pub checklist: Option<Checklist>,
+    /// The optional columns shown in the table, between the verb and the URI.
+    pub columns: Vec<Column>,
+    /// The tree of namespaces, resources and objects beside the table, while
+    /// it is open.
+    pub sidebar: Option<Sidebar>,
+    /// Whether the timeline of activity is hidden.
+    pub timeline_hidden: bool,
+    /// Whether the timeline has a row under it for each outcome, shaded by
+    /// its share of each slice's requests.
+    pub timeline_outcomes: bool,
+    /// Where the timeline was drawn, one slice to a column, as of the last
+    /// time it was drawn.
+    pub(crate) timeline_area: Rect,
+    /// The field the table is grouped by, with the state of the groups, while
+    /// grouping.
+    pub grouping: Option<Grouping>,
+    /// Which runs of repeated events are expanded, while collapsing them.
+    pub runs: Option<Runs>,
+    /// The object whose changes the tab follows, as resource/name, keeping
+    /// the newest event selected as more arrive.
+    pub watching: Option<String>,
+}
+
+impl ViewState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    pub fn source_filter(&self) -> Option<&str> {
+        self.source_filter.as_deref()
+    }
+
+    /// Whether the event passes the filters.
+    pub fn matches(&self, event: &EventV1, scripts: &Scripts) -> bool {
+        (self.source_filter.is_none() || event.source == self.source_filter)
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.eval(event, scripts))
+    }
+
+    /// Show only the events matching `filter`, or every event if `None`.
+    pub fn set_filter(&mut self, store: &EventStore, scripts: &Scripts, filter: Option<Filter>) {
+        if filter != self.filter {
+            if self.undo.len() == UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+            self.undo.push(self.filter.take());
+        }
+        self.filter = filter;
+        self.refilter(store, scripts);
+    }
+
+    /// Go back to the filter before the last change, returning whether there
+    /// was one.
+    pub fn undo_filter(&mut self, store: &EventStore, scripts: &Scripts) -> bool {
+        match self.undo.pop() {
+            Some(filter) => {
+                self.filter = filter;
+                self.refilter(store, scripts);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cycle the source filter through each of the sources, then back to all.
+    pub fn cycle_source_filter(&mut self, store: &EventStore, scripts: &Scripts) {
+        let sources = store.sources();
+        let next = match &self.source_filter {
+            None => 0,
+            Some(current) => sources.iter().position(|s| s == current).unwrap_or(0) + 1,
+        };
+        self.source_filter = sources.get(next).cloned();
+        self.refilter(store, scripts);
+    }
+
+    /// Show only the events from the input labelled `source`, or from every
+    /// input.
+    pub fn set_source_filter(
+        &mut self,
+        store: &EventStore,
+        scripts: &Scripts,
+        source: Option<String>,
+    ) {
+        self.source_filter = source;
+        self.refilter(store, scripts);
+    }
+
+    pub fn dedupe(&self) -> Option<&Dedupe> {
+        self.dedupe.as_ref()
+    }
+
+    /// Hide exact duplicates of the events shown, or stop hiding them if
+    /// `None`.
+    pub fn set_dedupe(&mut self, store: &EventStore, scripts: &Scripts, dedupe: Option<Dedupe>) {
+        self.dedupe = dedupe;
+        self.refilter(store, scripts);
+    }
+
+    /// Recompute which events pass the filters, selecting the first.
+    pub fn refilter(&mut self, store: &EventStore, scripts: &Scripts) {
+        self.visible = (0..store.len())
+            .filter(|i| !self.hidden.contains(i) && self.matches(&store[*i], scripts))
+            .collect();
+        if let Some(dedupe) = &mut self.dedupe {
+            self.visible = dedupe.dedupe(store, std::mem::take(&mut self.visible));
+        }
+        self.selected = (!self.visible.is_empty()).then_some(0);
+        self.offset = 0;
+        self.scroll_position = 0;
+    }
+
+    /// Account for an event having been inserted into the store at `index`,
+    /// keeping the same event selected.
+    pub fn inserted(&mut self, store: &EventStore, scripts: &Scripts, index: usize) {
+        for visible in self.visible.iter_mut().filter(|i| **i >= index) {
+            *visible += 1;
+        }
+        store::shift_indices(&mut self.marked, index);
+        store::shift_indices(&mut self.hidden, index);
+        if let Some(pinned) = &mut self.pinned {
+            if *pinned >= index {
+                *pinned += 1;
+            }
+        }
+        if let Some((revealed, _)) = &mut self.revealed {
+            if *revealed >= index {
+                *revealed += 1;
+            }
+        }
+        if let Some(sidebar) = &mut self.sidebar {
+            sidebar.add(&store[index]);
+        }
+        if let Some(runs) = &mut self.runs {
+            runs.inserted(index);
+        }
+        if let Some(dedupe) = &mut self.dedupe {
+            dedupe.inserted(index);
+        }
+        if let Some(detail) = &mut self.detail {
+            if detail.index >= index {
+                detail.index += 1;
+            }
+            store::shift_indices(&mut detail.stages, index);
+            if store[index].audit_id == store[detail.index].audit_id {
+                detail.stages.insert(index);
+            }
+        }
+
+        let duplicate = |dedupe: &mut Dedupe| dedupe.duplicate(store, &self.visible, index);
+        if self.matches(&store[index], scripts) && !self.dedupe.as_mut().is_some_and(duplicate) {
+            let position = self.visible.partition_point(|i| *i < index);
+            self.visible.insert(position, index);
+            match self.selected {
+                Some(selected) if selected >= position => self.selected = Some(selected + 1),
+                Some(_) => {}
+                None => self.selected = Some(0),
+            }
+            if self.watching.is_some() {
+                self.select(self.visible.len() - 1);
+            }
+        }
+    }
+
+    /// Show the selected event over the whole screen, if there is one.
+    pub fn open_detail(&mut self, store: &EventStore) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let audit_id = &store[index].audit_id;
+        let stages = (0..store.len())
+            .filter(|&stage| store[stage].audit_id == *audit_id)
+            .collect();
+        self.detail = Some(Detail {
+            index,
+            stages,
+            scroll: 0,
+        });
+    }
+
+    /// Indices into the store of the events that pass the filters, in order.
+    pub fn visible(&self) -> &[usize] {
+        &self.visible
+    }
+
+    /// The selected position among the visible events.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// The index into the store of the selected event.
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected.map(|i| self.visible[i])
+    }
+
+    /// Select the event at a position among the visible events.
+    pub fn select(&mut self, position: usize) {
+        if position < self.visible.len() {
+            self.selected = Some(position);
+            self.scroll_position = 0;
+        }
+    }
+
+    /// Mark the event at `index` in the store, or unmark it.
+    pub fn toggle_mark(&mut self, index: usize) {
+        if !self.marked.remove(&index) {
+            self.marked.insert(index);
+        }
+    }
+
+    pub fn mark(&mut self, index: usize) {
+        self.marked.insert(index);
+    }
+
+    pub fn is_marked(&self, index: usize) -> bool {
+        self.marked.contains(&index)
+    }
+
+    /// Indices into the store of the marked events, in order.
+    pub fn marked(&self) -> impl Iterator<Item = usize> + '_ {
+        self.marked.iter().copied()
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    /// Hide the events at `indices` in the store, whatever the filters.
+    pub fn hide(
+        &mut self,
+        store: &EventStore,
+        scripts: &Scripts,
+        indices: impl IntoIterator<Item = usize>,
+    ) {
+        self.hidden.extend(indices);
+        self.refilter(store, scripts);
+    }
+
+    /// Show the hidden events again, returning how many there were.
+    pub fn unhide(&mut self, store: &EventStore, scripts: &Scripts) -> usize {
+        let count = self.hidden.len();
+        self.hidden.clear();
+        self.refilter(store, scripts);
+        count
+    }
+
+    pub fn hidden_count(&self) -> usize {
+        self.hidden.len()
+    }
+
+    /// Index into the store of the event pinned for comparison.
+    pub fn pinned(&self) -> Option<usize> {
+        self.pinned
+    }
+
+    /// Pin the selected event to compare others against, or unpin the pinned
+    /// one.
+    pub fn toggle_pin(&mut self) {
+        self.pinned = match self.pinned {
+            Some(_) => None,
+            None => self.selected_index(),
+        };
+        self.scroll_position = 0;
+    }
+
+    /// Show `pane`, or go back to the bodies if it is already shown.
+    pub fn toggle_pane(&mut self, pane: Pane) {
+        self.pane = if self.pane == pane {
+            Pane::Bodies
+        } else {
+            pane
+        };
+    }
+
+    pub fn selected_event<'a>(&self, store: &'a EventStore) -> Option<&'a EventV1> {
+        self.selected.map(|i| &store[self.visible[i]])
+    }
+
+    /// What the selected event changed about its object since the event above
+    /// it, going by the objects in their bodies, with the values in Secrets
+    /// hidden. Also the two events, or `None` without both bodies to go by.
+    pub fn object_changes<'a>(
+        &self,
+        store: &'a EventStore,
+    ) -> Option<(&'a EventV1, &'a EventV1, Vec<Change>)> {
+        let selected = self.selected?;
+        let new = &store[self.visible[selected]];
+        let old = &store[self.visible[selected.checked_sub(1)?]];
+        let mut changes = diff::diff(object(old)?, object(new)?);
+        if secret::is_secret(old) || secret::is_secret(new) {
+            for change in &mut changes {
+                if secret::is_secret_object_path(change.path()) {
+                    change.hide_values();
+                }
+            }
+        }
+        Some((old, new, changes))
+    }
+
+    /// The timeline of the visible events, as wide as it was last drawn.
+    pub fn timeline(&self, store: &EventStore) -> Option<Timeline> {
+        Timeline::new(store, &self.visible, self.timeline_area.width as usize)
+    }
+
+    /// Select the first event in the next slice of the timeline with any
+    /// events in it, or the previous one.
+    pub fn jump_slice(&mut self, store: &EventStore, forward: bool) {
+        let (Some(timeline), Some(selected)) = (self.timeline(store), self.selected) else {
+            return;
+        };
+        let slice = |i: &usize| timeline.slice(store[*i].request_received_timestamp);
+        let current = slice(&self.visible[selected]);
+        let position = match forward {
+            true => self.visible.partition_point(|i| slice(i) <= current),
+            false => {
+                let start = self.visible.partition_point(|i| slice(i) < current);
+                let Some(previous) = start.checked_sub(1) else {
+                    return;
+                };
+                let target = slice(&self.visible[previous]);
+                self.visible.partition_point(|i| slice(i) < target)
+            }
+        };
+        self.select(position);
+    }
+
+    /// Select the first event in the slice of the timeline with the most.
+    pub fn jump_busiest(&mut self, store: &EventStore) {
+        let Some(timeline) = self.timeline(store) else {
+            return;
+        };
+        if let Some(busiest) = timeline.busiest() {
+            self.jump_to_slice(store, &timeline, busiest);
+        }
+    }
+
+    /// Select the first event in the next slice of the timeline coloured for
+    /// its failures.
+    pub fn jump_failing(&mut self, store: &EventStore) {
+        let (Some(timeline), Some(selected)) = (self.timeline(store), self.selected) else {
+            return;
+        };
+        let current = timeline.slice(store[self.visible[selected]].request_received_timestamp);
+        let failing = (current + 1..timeline.counts.len())
+            .find(|slice| timeline.outcome(*slice) != Outcome::Success);
+        if let Some(failing) = failing {
+            self.jump_to_slice(store, &timeline, failing);
+        }
+    }
+
+    /// Select the first event in the slice of the timeline clicked on, if
+    /// the click was on it.
+    pub fn click_timeline(&mut self, store: &EventStore, column: u16, row: u16) {
+        if !self.timeline_area.contains(Position::new(column, row)) {
+            return;
+        }
+        if let Some(timeline) = self.timeline(store) {
+            let slice = (column - self.timeline_area.x) as usize;
+            self.jump_to_slice(store, &timeline, slice);
+        }
+    }
+
+    /// Select the first event in the slice, or after it if it has none.
+    fn jump_to_slice(&mut self, store: &EventStore, timeline: &Timeline, slice: usize) {
+        let position = self
+            .visible
+            .partition_point(|i| timeline.slice(store[*i].request_received_timestamp) < slice);
+        self.select(position);
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.map(|i| i.saturating_sub(1));
+        self.scroll_position = 0;
+    }
+
+    pub fn next(&mut self) {
+        self.selected = self
+            .selected
+            .map(|i| (i + 1).min(self.visible.len().saturating_sub(1)));
+        self.scroll_position = 0;
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll_position = self.scroll_position.saturating_sub(3);
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll_position = (self.scroll_position + 3).min(self.scroll_limit);
+    }
+
+    /// Show the values at `path` base64 decoded, or encoded again if they
+    /// already were.
+    pub fn toggle_decoded(&mut self, path: JsonPath) {
+        match self.decoded.iter().position(|decoded| *decoded == path) {
+            Some(i) => {
+                self.decoded.remove(i);
+            }
+            None => self.decoded.push(path),
+        }
+    }
+
+    /// How much of the Secret values in the event at `index` in the store to
+    /// show.
+    pub fn reveal(&self, index: usize) -> Reveal {
+        match self.revealed {
+            Some((revealed, reveal)) if revealed == index => reveal,
+            _ => Reveal::Hidden,
+        }
+    }
+
+    /// Go from hiding the selected event's Secret values to showing them, to
+    /// decoding them, and back, hiding any other event's.
+    pub fn cycle_reveal(&mut self) {
+        if let Some(index) = self.selected_index() {
+            self.revealed = Some((index, self.reveal(index).next()));
+        }
+    }
+
+    /// Find `text` in the bodies, scrolling to the first match from where
+    /// they are now, or stop finding anything if it's empty.
+    pub fn set_find(&mut self, text: String) {
+        self.find_lines.clear();
+        self.find_pending = !text.is_empty();
+        self.find = (!text.is_empty()).then_some(text);
+    }
+
+    /// Scroll to the next line with a match, going back to the first after
+    /// the last.
+    pub fn find_next(&mut self) {
+        if self.find_lines.is_empty() {
+            return;
+        }
+        self.find_match = (self.find_match + 1) % self.find_lines.len();
+        self.scroll_position = self.find_lines[self.find_match];
+    }
+
+    /// Note the lines the found text is on, scrolling to the first from
+    /// where the bodies are now if a find was just started.
+    pub(crate) fn found(&mut self, lines: Vec<u16>) {
+        self.find_lines = lines;
+        if std::mem::take(&mut self.find_pending) {
+            self.find_match = self
+                .find_lines
+                .iter()
+                .position(|line| *line >= self.scroll_position)
+                .unwrap_or(0);
+            if let Some(line) = self.find_lines.get(self.find_match) {
+                self.scroll_position = *line;
+            }
+        } else if self.find_match >= self.find_lines.len() {
+            self.find_match = 0;
+        }
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.horizontal_scroll = self.horizontal_scroll.saturating_sub(8);
+    }
+
+    pub fn scroll_right(&mut self) {
+        self.horizontal_scroll = (self.horizontal_scroll + 8).min(self.horizontal_limit);
+    }
+
+    /// Switch the bodies between wrapping long lines and cutting them off.
+    pub fn toggle_wrap(&mut self) {
+        self.unwrapped = !self.unwrapped;
+        self.horizontal_scroll = 0;
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll_position = 0;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_position = self.scroll_limit;
+    }
+
+    /// How far down the bodies are scrolled, as a percentage, if they don't
+    /// all fit.
+    pub fn scroll_percent(&self) -> Option<u16> {
+        (self.scroll_limit > 0).then(|| {
+            let percent = u32::from(self.scroll_position) * 100 / u32::from(self.scroll_limit);
+            percent.min(100) as u16
+        })
+    }
+}